@@ -0,0 +1,126 @@
+#!/usr/bin/env bash
+
+THISDIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+
+source "${THISDIR}/common.sh"
+
+# Default name of the daemon service set up by setup.sh --daemon-service
+DEFAULT_SERVICE="integ-daemon"
+
+# Number of times to check the daemon tasks before giving up, and the seconds between checks
+CHECK_ATTEMPTS=40
+CHECK_DELAY=15
+
+# Helper functions
+usage() {
+    cat >&2 <<EOF
+${0##*/}
+                 --cluster CLUSTER [--service ${DEFAULT_SERVICE}]
+
+Checks that every ACTIVE container instance in the cluster runs a healthy task of the daemon service,
+e.g. after the updater has drained, updated and rebooted the instances
+
+Required:
+   --cluster                          Cluster name to check
+
+Optional:
+   --service                          Name of the service with the DAEMON scheduling strategy (default ${DEFAULT_SERVICE})
+
+EOF
+}
+
+parse_args() {
+    while [ ${#} -gt 0 ]; do
+        case "${1}" in
+        --cluster)
+            shift
+            CLUSTER="${1}"
+            ;;
+        --service)
+            shift
+            SERVICE="${1}"
+            ;;
+
+        --help)
+            usage
+            exit 0
+            ;;
+        *)
+            log ERROR "Unknown argument: ${1}" >&2
+            usage
+            exit 2
+            ;;
+        esac
+        shift
+    done
+
+    SERVICE="${SERVICE:-$DEFAULT_SERVICE}"
+
+    # Required arguments
+    required_arg "--cluster" "${CLUSTER}"
+}
+
+# Prints the container instances that have no RUNNING and HEALTHY task of the daemon service
+missing_daemons() {
+    local instances
+    if ! instances=$(aws ecs list-container-instances \
+        --cluster "${CLUSTER}" \
+        --status ACTIVE \
+        --query 'containerInstanceArns[]' \
+        --output text); then
+        log ERROR "Failed to list container instances in cluster '${CLUSTER}'"
+        return 1
+    fi
+    if [ -z "${instances}" ]; then
+        log ERROR "No ACTIVE container instances in cluster '${CLUSTER}'"
+        return 1
+    fi
+
+    local instance tasks healthy
+    for instance in ${instances}; do
+        if ! tasks=$(aws ecs list-tasks \
+            --cluster "${CLUSTER}" \
+            --container-instance "${instance}" \
+            --service-name "${SERVICE}" \
+            --desired-status RUNNING \
+            --query 'taskArns[]' \
+            --output text); then
+            log ERROR "Failed to list tasks on container instance '${instance}'"
+            return 1
+        fi
+        healthy=0
+        if [ -n "${tasks}" ]; then
+            # shellcheck disable=SC2086
+            if ! healthy=$(aws ecs describe-tasks \
+                --cluster "${CLUSTER}" \
+                --tasks ${tasks} \
+                --query "length(tasks[?lastStatus=='RUNNING' && healthStatus=='HEALTHY'])" \
+                --output text); then
+                log ERROR "Failed to describe tasks on container instance '${instance}'"
+                return 1
+            fi
+        fi
+        if [ "${healthy}" -eq 0 ]; then
+            echo "${instance}"
+        fi
+    done
+}
+
+# Initial setup and checks
+parse_args "${@}"
+
+log INFO "Checking that every container instance in cluster '${CLUSTER}' runs a healthy task of service '${SERVICE}'"
+for ((attempt = 1; attempt <= CHECK_ATTEMPTS; attempt++)); do
+    if ! missing=$(missing_daemons); then
+        exit 1
+    fi
+    if [ -z "${missing}" ]; then
+        log INFO "Every container instance in cluster '${CLUSTER}' runs a healthy task of service '${SERVICE}'"
+        exit 0
+    fi
+    log INFO "Container instances without a healthy daemon task (attempt ${attempt} of ${CHECK_ATTEMPTS}): ${missing//$'\n'/ }"
+    sleep "${CHECK_DELAY}"
+done
+
+log ERROR "Container instances in cluster '${CLUSTER}' still have no healthy task of service '${SERVICE}': ${missing//$'\n'/ }"
+exit 1
@@ -0,0 +1,199 @@
+#!/usr/bin/env bash
+
+THISDIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+
+source "${THISDIR}/common.sh"
+
+# CloudWatch namespace of the updater's metrics
+NAMESPACE="Bottlerocket/ECSUpdater"
+
+# Default number of minutes before now to look for metrics
+DEFAULT_SINCE_MINUTES=60
+
+# Most seconds each phase of the run may take, as PHASE=SECONDS
+BUDGETS=()
+
+# Number of times to query the metrics before giving up, and the seconds between queries.
+# CloudWatch Logs takes a few minutes to extract metrics from the updater's log events.
+CHECK_ATTEMPTS=20
+CHECK_DELAY=30
+
+# Helper functions
+usage() {
+    cat >&2 <<EOF
+${0##*/}
+                 --cluster CLUSTER [--expected-version VERSION] [--expected-instances COUNT]
+                 [--since-minutes ${DEFAULT_SINCE_MINUTES}] [--budget PHASE=SECONDS ...]
+
+Checks the CloudWatch metrics published by an updater run on the cluster: the run published metrics,
+no update failed, and optionally the expected number of Bottlerocket instances run the expected version
+
+Required:
+   --cluster                          Cluster name the updater ran on
+
+Optional:
+   --expected-version                 Bottlerocket version instances are expected to run after the run, e.g. 1.20.0
+   --expected-instances               Number of instances expected to run --expected-version after the run
+                                      (default: at least one)
+   --since-minutes                    How many minutes back to look for metrics (default ${DEFAULT_SINCE_MINUTES})
+   --budget                           Most seconds a phase of the run may take, e.g. check=300; can be repeated.
+                                      Phases are discover, check, plan, drain, update and verify, see PhaseDuration
+
+EOF
+}
+
+parse_args() {
+    while [ ${#} -gt 0 ]; do
+        case "${1}" in
+        --cluster)
+            shift
+            CLUSTER="${1}"
+            ;;
+        --expected-version)
+            shift
+            EXPECTED_VERSION="${1}"
+            ;;
+        --expected-instances)
+            shift
+            EXPECTED_INSTANCES="${1}"
+            ;;
+        --since-minutes)
+            shift
+            SINCE_MINUTES="${1}"
+            ;;
+        --budget)
+            shift
+            if [[ ! "${1}" =~ ^[a-z]+=[0-9]+$ ]]; then
+                log ERROR "Budget '${1}' must have the form PHASE=SECONDS"
+                exit 2
+            fi
+            BUDGETS+=("${1}")
+            ;;
+
+        --help)
+            usage
+            exit 0
+            ;;
+        *)
+            log ERROR "Unknown argument: ${1}" >&2
+            usage
+            exit 2
+            ;;
+        esac
+        shift
+    done
+
+    SINCE_MINUTES="${SINCE_MINUTES:-$DEFAULT_SINCE_MINUTES}"
+
+    # Required arguments
+    required_arg "--cluster" "${CLUSTER}"
+}
+
+# Prints the value of a statistic of a metric over the checked period, or "None" when it has no data
+metric_statistic() {
+    local metric="${1:?}"
+    local statistic="${2:?}"
+    shift 2
+    local start end
+    start="$(date -u -d "${SINCE_MINUTES} minutes ago" +%Y-%m-%dT%H:%M:%SZ 2>/dev/null ||
+        date -u -v-"${SINCE_MINUTES}"M +%Y-%m-%dT%H:%M:%SZ)"
+    end="$(date -u +%Y-%m-%dT%H:%M:%SZ)"
+    aws cloudwatch get-metric-statistics \
+        --namespace "${NAMESPACE}" \
+        --metric-name "${metric}" \
+        --dimensions Name=Cluster,Value="${CLUSTER}" "${@}" \
+        --start-time "${start}" \
+        --end-time "${end}" \
+        --period $((SINCE_MINUTES * 60)) \
+        --statistics "${statistic}" \
+        --query "Datapoints[0].${statistic}" \
+        --output text
+}
+
+# Checks the metrics once; returns 1 when they are not published yet and 2 when they are wrong
+check_metrics() {
+    local failures bottlerocket updated
+    if ! failures=$(metric_statistic FailedUpdates Sum); then
+        log ERROR "Failed to get the FailedUpdates metric"
+        return 2
+    fi
+    if ! bottlerocket=$(metric_statistic BottlerocketInstances Maximum); then
+        log ERROR "Failed to get the BottlerocketInstances metric"
+        return 2
+    fi
+    if [ "${failures}" == "None" ] || [ "${bottlerocket}" == "None" ]; then
+        log INFO "The updater's metrics for cluster '${CLUSTER}' are not published yet"
+        return 1
+    fi
+    log INFO "FailedUpdates: ${failures}, BottlerocketInstances: ${bottlerocket}"
+    if [ "${failures%.*}" -ne 0 ]; then
+        log ERROR "The updater reported ${failures} failed update(s) on cluster '${CLUSTER}'"
+        return 2
+    fi
+    if [ "${bottlerocket%.*}" -eq 0 ]; then
+        log ERROR "The updater found no Bottlerocket instances in cluster '${CLUSTER}'"
+        return 2
+    fi
+
+    local budget phase seconds took
+    for budget in "${BUDGETS[@]}"; do
+        phase="${budget%%=*}"
+        seconds="${budget#*=}"
+        if ! took=$(metric_statistic PhaseDuration Maximum Name=Phase,Value="${phase}"); then
+            log ERROR "Failed to get the PhaseDuration metric"
+            return 2
+        fi
+        if [ "${took}" == "None" ]; then
+            log INFO "No duration of phase '${phase}' published yet"
+            return 1
+        fi
+        log INFO "Phase '${phase}' took ${took} second(s), budget ${seconds}"
+        if [ "${took%.*}" -ge "${seconds}" ]; then
+            log ERROR "Phase '${phase}' took ${took} second(s), over its budget of ${seconds}"
+            return 2
+        fi
+    done
+
+    if [ -n "${EXPECTED_VERSION}" ]; then
+        if ! updated=$(metric_statistic InstancesAfterPass Maximum Name=Version,Value="${EXPECTED_VERSION}"); then
+            log ERROR "Failed to get the InstancesAfterPass metric"
+            return 2
+        fi
+        if [ "${updated}" == "None" ]; then
+            log INFO "No instances on version '${EXPECTED_VERSION}' published yet"
+            return 1
+        fi
+        log INFO "InstancesAfterPass for version '${EXPECTED_VERSION}': ${updated}"
+        if [ -n "${EXPECTED_INSTANCES}" ] && [ "${updated%.*}" -ne "${EXPECTED_INSTANCES}" ]; then
+            log ERROR "Expected ${EXPECTED_INSTANCES} instance(s) on version '${EXPECTED_VERSION}', the updater reported ${updated}"
+            return 2
+        fi
+        if [ "${updated%.*}" -eq 0 ]; then
+            log ERROR "The updater reported no instances on version '${EXPECTED_VERSION}'"
+            return 2
+        fi
+    fi
+    return 0
+}
+
+# Initial setup and checks
+parse_args "${@}"
+
+log INFO "Checking the updater's metrics in namespace '${NAMESPACE}' for cluster '${CLUSTER}'"
+for ((attempt = 1; attempt <= CHECK_ATTEMPTS; attempt++)); do
+    check_metrics
+    case "${?}" in
+    0)
+        log INFO "The updater's metrics for cluster '${CLUSTER}' are as expected"
+        exit 0
+        ;;
+    2)
+        exit 1
+        ;;
+    esac
+    log INFO "Checking again in ${CHECK_DELAY} seconds (attempt ${attempt} of ${CHECK_ATTEMPTS})"
+    sleep "${CHECK_DELAY}"
+done
+
+log ERROR "The updater's metrics for cluster '${CLUSTER}' were not published"
+exit 1
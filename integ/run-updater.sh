@@ -8,7 +8,7 @@ source "${THISDIR}/common.sh"
 usage() {
     cat >&2 <<EOF
 ${0##*/}
-                 --cluster CLUSTER --updater-image UPDATER-IMAGE
+                 --cluster CLUSTER --updater-image UPDATER-IMAGE [--inject-faults RULES]
 
 Starts an ECS updater to manage Bottlerocket instances in a given cluster
 
@@ -16,6 +16,9 @@ Required:
    --cluster                          Cluster name to manage Bottlerocket instances in
    --updater-image                    Bottlerocket ECS updater image ECR location
 
+Optional:
+   --inject-faults                    AWS calls for the updater to make fail at random, e.g. SendCommand=0.05
+
 EOF
 }
 
@@ -30,6 +33,10 @@ parse_args() {
             shift
             UPDATER_IMAGE="${1}"
             ;;
+        --inject-faults)
+            shift
+            INJECT_FAULTS="${1}"
+            ;;
 
         --help)
             usage
@@ -100,7 +107,8 @@ if ! aws cloudformation deploy \
     Subnets="${subnets}" \
     UpdaterImage="${UPDATER_IMAGE}" \
     LogGroupName="${log_group}" \
-    ScheduleState="DISABLED"; then
+    ScheduleState="DISABLED" \
+    InjectFaults="${INJECT_FAULTS}"; then
     log ERROR "Failed to deploy Bottlerocket ECS updater"
     exit 1
 fi
@@ -18,9 +18,11 @@ usage() {
     cat >&2 <<EOF
 ${0##*/}
                  --ami-id AMI-ID
+                 [--scenario SCENARIO-FILE]
                  [--instance-type ${DEFAULT_INSTANCE_TYPE}]
                  [--instance-count ${DEFAULT_INSTANCE_COUNT}]
                  [--cluster ${DEFAULT_CLUSTER_NAME}]
+                 [--daemon-service]
 
 Deploys templates '${INTEG_STACK_TEMPLATE}' and '${CLUSTER_STACK_TEMPLATE}' to set up an ECS cluster.
 
@@ -28,13 +30,39 @@ Required:
    --ami-id                           Image ID for test instance in cluster (an aws-ecs-1 AMI ID)
 
 Optional:
+   --scenario                         Scenario file shared with the updater's -simulate mode. Its 'integ' section,
+                                      cluster name, and number of Bottlerocket instances are used for any of the
+                                      other options not given on the command line (requires yq)
    --instance-type                    Instance type for test instances (default ${DEFAULT_INSTANCE_TYPE})
    --instance-count                   Number of instances to launch in the cluster (default ${DEFAULT_INSTANCE_COUNT})
    --cluster                          Name of the cluster (default ${DEFAULT_CLUSTER_NAME}). New cluster is created if it does not exist.
+   --daemon-service                   Run a service with the DAEMON scheduling strategy on every instance; implied by
+                                      a scenario with 'daemon_services'. Check it after an update with check-daemons.sh
 
 EOF
 }
 
+# Reads settings from a scenario file for any options that were not given
+read_scenario() {
+    local scenario="${1:?}"
+    if ! command -v yq >/dev/null 2>&1; then
+        log ERROR "yq is required to read scenario '${scenario}'"
+        exit 2
+    fi
+    if [ ! -f "${scenario}" ]; then
+        log ERROR "Scenario '${scenario}' does not exist"
+        exit 2
+    fi
+    AMI_ID="${AMI_ID:-$(yq '.integ.ami_id // ""' "${scenario}")}"
+    INSTANCE_TYPE="${INSTANCE_TYPE:-$(yq '.integ.instance_type // ""' "${scenario}")}"
+    CLUSTER_STACK_NAME="${CLUSTER_STACK_NAME:-$(yq '.cluster // ""' "${scenario}")}"
+    INSTANCE_COUNT="${INSTANCE_COUNT:-$(yq '[.instances[] | select(.variant)] | length' "${scenario}")}"
+    if [ "$(yq '.daemon_services // [] | length' "${scenario}")" -gt 0 ]; then
+        DAEMON_SERVICE="true"
+    fi
+    log INFO "Using scenario '${scenario}'"
+}
+
 parse_args() {
     while [ ${#} -gt 0 ]; do
         case "${1}" in
@@ -54,6 +82,13 @@ parse_args() {
             shift
             CLUSTER_STACK_NAME="${1}"
             ;;
+        --scenario)
+            shift
+            SCENARIO_FILE="${1}"
+            ;;
+        --daemon-service)
+            DAEMON_SERVICE="true"
+            ;;
 
         --help)
             usage
@@ -68,9 +103,14 @@ parse_args() {
         shift
     done
 
+    if [ -n "${SCENARIO_FILE}" ]; then
+        read_scenario "${SCENARIO_FILE}"
+    fi
+
     INSTANCE_TYPE="${INSTANCE_TYPE:-$DEFAULT_INSTANCE_TYPE}"
     INSTANCE_COUNT="${INSTANCE_COUNT:-$DEFAULT_INSTANCE_COUNT}"
     CLUSTER_STACK_NAME="${CLUSTER_STACK_NAME:-$DEFAULT_CLUSTER_NAME}"
+    DAEMON_SERVICE="${DAEMON_SERVICE:-false}"
 
     # Required arguments
     required_arg "--ami-id" "${AMI_ID}"
@@ -100,7 +140,8 @@ if ! aws cloudformation deploy \
     IntegSharedResourceStack="${INTEG_STACK_NAME}" \
     InstanceCount="${INSTANCE_COUNT}" \
     ImageID="${AMI_ID}" \
-    InstanceType="${INSTANCE_TYPE}"; then
+    InstanceType="${INSTANCE_TYPE}" \
+    DaemonService="${DAEMON_SERVICE}"; then
     log ERROR "Failed to deploy stack '${CLUSTER_STACK_TEMPLATE}' stack template"
     exit 1
 fi
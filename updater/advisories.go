@@ -0,0 +1,132 @@
+package main
+
+import (
+	"bytes"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"sort"
+
+	"gopkg.in/yaml.v3"
+)
+
+// advisoryFeed is the file given with -advisories, which lists the security
+// advisories affecting Bottlerocket versions, e.g.:
+//
+//	advisories:
+//	  - id: BRSA-abcd1234
+//	    cves: [CVE-2024-1234]
+//	    severity: important
+//	    introduced: 1.15.0
+//	    fixed: 1.19.3
+type advisoryFeed struct {
+	Advisories []advisory `yaml:"advisories"`
+}
+
+// advisory is a security advisory affecting the Bottlerocket versions from
+// Introduced, or every version when it is empty, up to but not including Fixed.
+type advisory struct {
+	ID         string   `yaml:"id"`
+	CVEs       []string `yaml:"cves"`
+	Severity   string   `yaml:"severity"`
+	Introduced string   `yaml:"introduced"`
+	Fixed      string   `yaml:"fixed"`
+}
+
+// parseAdvisories parses and validates the advisories of a feed.
+func parseAdvisories(data []byte) ([]advisory, error) {
+	feed := advisoryFeed{}
+	decoder := yaml.NewDecoder(bytes.NewReader(data))
+	decoder.KnownFields(true)
+	if err := decoder.Decode(&feed); err != nil && !errors.Is(err, io.EOF) {
+		return nil, fmt.Errorf("failed to parse advisories: %w", err)
+	}
+	seen := make(map[string]bool)
+	for _, a := range feed.Advisories {
+		switch {
+		case a.ID == "":
+			return nil, errors.New("advisory without an id")
+		case seen[a.ID]:
+			return nil, fmt.Errorf("advisory %q is listed more than once", a.ID)
+		case !minimumVersionPattern.MatchString(a.Fixed):
+			return nil, fmt.Errorf("advisory %q must have a fixed version such as 1.19.3, not %q", a.ID, a.Fixed)
+		case a.Introduced != "" && !minimumVersionPattern.MatchString(a.Introduced):
+			return nil, fmt.Errorf("advisory %q has an invalid introduced version %q", a.ID, a.Introduced)
+		case a.Introduced != "" && compareVersions(a.Introduced, a.Fixed) >= 0:
+			return nil, fmt.Errorf("advisory %q is fixed in %s, which is not after %s where it was introduced", a.ID, a.Fixed, a.Introduced)
+		}
+		seen[a.ID] = true
+	}
+	return feed.Advisories, nil
+}
+
+// readAdvisories reads the advisories of the feed at path.
+func readAdvisories(path string) ([]advisory, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return nil, fmt.Errorf("failed to read advisories: %w", err)
+	}
+	advisories, err := parseAdvisories(data)
+	if err != nil {
+		return nil, fmt.Errorf("invalid advisories %q: %w", path, err)
+	}
+	return advisories, nil
+}
+
+// affects reports whether the advisory affects the Bottlerocket version;
+// unknown versions are never reported as affected.
+func (a advisory) affects(version string) bool {
+	if !minimumVersionPattern.MatchString(version) {
+		return false
+	}
+	return compareVersions(version, a.Fixed) < 0 && (a.Introduced == "" || compareVersions(version, a.Introduced) >= 0)
+}
+
+// advisoryCount is an advisory with the number of instances of a report
+// running a version it affects.
+type advisoryCount struct {
+	ID        string   `json:"id"`
+	CVEs      []string `json:"cves,omitempty"`
+	Severity  string   `json:"severity,omitempty"`
+	Fixed     string   `json:"fixed"`
+	Instances int      `json:"instances"`
+}
+
+// addAdvisories lists, for each instance of the report, the advisories
+// affecting the version it runs at the end of the pass, or ran before the
+// pass when that is unknown, and counts the instances each advisory affects,
+// most affected first. Advisories affecting no instance are left out.
+func (r *fleetReport) addAdvisories(advisories []advisory) {
+	counts := make(map[string]int)
+	for n := range r.Instances {
+		inst := &r.Instances[n]
+		version := inst.Version
+		if !minimumVersionPattern.MatchString(version) {
+			version = inst.VersionBefore
+		}
+		for _, a := range advisories {
+			if a.affects(version) {
+				inst.Advisories = append(inst.Advisories, a.ID)
+				counts[a.ID]++
+			}
+		}
+	}
+	for _, a := range advisories {
+		if counts[a.ID] > 0 {
+			r.Advisories = append(r.Advisories, advisoryCount{
+				ID:        a.ID,
+				CVEs:      a.CVEs,
+				Severity:  a.Severity,
+				Fixed:     a.Fixed,
+				Instances: counts[a.ID],
+			})
+		}
+	}
+	sort.SliceStable(r.Advisories, func(i, j int) bool {
+		if r.Advisories[i].Instances != r.Advisories[j].Instances {
+			return r.Advisories[i].Instances > r.Advisories[j].Instances
+		}
+		return r.Advisories[i].ID < r.Advisories[j].ID
+	})
+}
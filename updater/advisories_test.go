@@ -0,0 +1,96 @@
+package main
+
+import (
+	"os"
+	"path/filepath"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseAdvisories(t *testing.T) {
+	advisories, err := parseAdvisories([]byte(`
+advisories:
+  - id: BRSA-1
+    cves: [CVE-2024-1234, CVE-2024-5678]
+    severity: important
+    fixed: 1.19.3
+  - id: BRSA-2
+    introduced: 1.15.0
+    fixed: 1.16.1
+`))
+	require.NoError(t, err)
+	assert.Equal(t, []advisory{
+		{ID: "BRSA-1", CVEs: []string{"CVE-2024-1234", "CVE-2024-5678"}, Severity: "important", Fixed: "1.19.3"},
+		{ID: "BRSA-2", Introduced: "1.15.0", Fixed: "1.16.1"},
+	}, advisories)
+
+	advisories, err = parseAdvisories(nil)
+	require.NoError(t, err)
+	assert.Empty(t, advisories)
+
+	invalid := map[string]string{
+		"missing id":         "advisories:\n  - fixed: 1.19.3\n",
+		"duplicate id":       "advisories:\n  - {id: BRSA-1, fixed: 1.19.3}\n  - {id: BRSA-1, fixed: 1.20.0}\n",
+		"missing fixed":      "advisories:\n  - id: BRSA-1\n",
+		"invalid fixed":      "advisories:\n  - {id: BRSA-1, fixed: latest}\n",
+		"invalid introduced": "advisories:\n  - {id: BRSA-1, introduced: '1.15', fixed: 1.19.3}\n",
+		"fixed before":       "advisories:\n  - {id: BRSA-1, introduced: 1.19.3, fixed: 1.19.3}\n",
+		"unknown field":      "advisories:\n  - {id: BRSA-1, fixed: 1.19.3, affected: [1.19.0]}\n",
+	}
+	for name, data := range invalid {
+		_, err := parseAdvisories([]byte(data))
+		assert.Error(t, err, name)
+	}
+}
+
+func TestReadAdvisories(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "advisories.yaml")
+	_, err := readAdvisories(path)
+	assert.Error(t, err, "a missing file should fail")
+
+	require.NoError(t, os.WriteFile(path, []byte("advisories:\n  - {id: BRSA-1, fixed: 1.19.3}\n"), 0o644))
+	advisories, err := readAdvisories(path)
+	require.NoError(t, err)
+	assert.Equal(t, []advisory{{ID: "BRSA-1", Fixed: "1.19.3"}}, advisories)
+}
+
+func TestAdvisoryAffects(t *testing.T) {
+	a := advisory{ID: "BRSA-1", Introduced: "1.15.0", Fixed: "1.19.3"}
+	for version, expected := range map[string]bool{
+		"1.14.9":       false,
+		"1.15.0":       true,
+		"1.19.2":       true,
+		"v1.19.2":      true,
+		"1.19.3":       false,
+		"1.20.0":       false,
+		unknownVersion: false,
+		"":             false,
+	} {
+		assert.Equal(t, expected, a.affects(version), version)
+	}
+	assert.True(t, advisory{ID: "BRSA-2", Fixed: "1.19.3"}.affects("1.0.0"), "should affect every version before the fix without introduced")
+}
+
+func TestAddAdvisories(t *testing.T) {
+	report := fleetReport{Instances: []reportInstance{
+		{InstanceID: "inst-id-1", VersionBefore: "1.18.0", Version: "1.19.0"},
+		{InstanceID: "inst-id-2", VersionBefore: "1.18.0", Version: "1.20.0"},
+		{InstanceID: "inst-id-3", VersionBefore: "1.16.0", Version: unknownVersion},
+		{InstanceID: "arn-foreign"},
+	}}
+	report.addAdvisories([]advisory{
+		{ID: "BRSA-1", Severity: "moderate", Fixed: "1.17.0"},
+		{ID: "BRSA-2", CVEs: []string{"CVE-2024-1234"}, Severity: "important", Fixed: "1.19.3"},
+		{ID: "BRSA-3", Fixed: "1.10.0"},
+	})
+	assert.Equal(t, []string{"BRSA-2"}, report.Instances[0].Advisories)
+	assert.Empty(t, report.Instances[1].Advisories)
+	assert.Equal(t, []string{"BRSA-1", "BRSA-2"}, report.Instances[2].Advisories, "should fall back to the version before the pass")
+	assert.Empty(t, report.Instances[3].Advisories)
+	assert.Equal(t, []advisoryCount{
+		{ID: "BRSA-2", CVEs: []string{"CVE-2024-1234"}, Severity: "important", Fixed: "1.19.3", Instances: 2},
+		{ID: "BRSA-1", Severity: "moderate", Fixed: "1.17.0", Instances: 1},
+	}, report.Advisories)
+}
@@ -2,17 +2,27 @@ package main
 
 import (
 	"encoding/json"
+	"errors"
 	"fmt"
 	"log"
+	"regexp"
+	"strconv"
 	"strings"
-	"sync"
 	"time"
+	"unicode"
 
 	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/arn"
+	"github.com/aws/aws-sdk-go/aws/awserr"
 	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+	"github.com/aws/aws-sdk-go/service/cloudwatchlogs"
 	"github.com/aws/aws-sdk-go/service/ec2"
 	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/elbv2"
+	"github.com/aws/aws-sdk-go/service/secretsmanager"
 	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/aws/aws-sdk-go/service/sts"
 )
 
 const (
@@ -26,16 +36,61 @@ const (
 	waiterMaxAttempts    = 100
 	// If this time is reached and the ssm command has not already started running, it will not run.
 	deliveryTimeoutSeconds = 600
+	// The longest comment SSM accepts on a command
+	maxCommandCommentLength = 100
+	// Tag keys used to record a summary of the last run on the cluster
+	tagLastRun      = "bottlerocket-ecs-updater:last-run"
+	tagLastUpdated  = "bottlerocket-ecs-updater:last-run-updated"
+	tagLastFailed   = "bottlerocket-ecs-updater:last-run-failed"
+	tagLastSkipped  = "bottlerocket-ecs-updater:last-run-skipped"
+	tagLastWarnings = "bottlerocket-ecs-updater:last-run-warnings"
+	tagLastChange   = "bottlerocket-ecs-updater:last-run-change"
 )
 
 type instance struct {
 	instanceID          string
 	containerInstanceID string
 	bottlerocketVersion string
+	// variant is the instance's Bottlerocket variant, e.g. aws-ecs-2.
+	variant string
+	// taskCount is the number of running and pending tasks on the instance.
+	taskCount int64
+	// utilization is the highest of the instance's CPU and memory utilization.
+	utilization float64
+	// registeredCPU and registeredMemory are the CPU units and MiB of memory
+	// the instance registered with ECS.
+	registeredCPU, registeredMemory int64
+	// targetRelease is the release the instance updates to.
+	targetRelease release
+	// stage is the instance's value of the stage attribute.
+	stage string
+	// quarantine excludes the instance from updates; it is nil when the
+	// instance is not quarantined.
+	quarantine *quarantine
+	// scheduledReboot is set when EC2 has scheduled a reboot of the instance,
+	// which the update's reboot completes.
+	scheduledReboot bool
+	// consecutiveFailures is the number of passes in a row in which the update
+	// of the instance failed, as recorded on its container instance.
+	consecutiveFailures int
+	// unparseableOutput is the output of the instance's update check when it
+	// could not be parsed.
+	unparseableOutput string
+	// invalidTarget is set when SSM rejected the instance as a target of the
+	// update check, e.g. because it was terminated.
+	invalidTarget bool
+	// tags are the tags of the container instance.
+	tags map[string]string
+	// arch is the instance's CPU architecture, e.g. x86_64 or arm64.
+	arch string
+	// cachedCheck is set when the instance's update check was skipped because
+	// a recent check found it running the latest version.
+	cachedCheck bool
 }
 
 type checkOutput struct {
-	UpdateState     string `json:"update_state"`
+	UpdateState     string   `json:"update_state"`
+	ChosenUpdate    *release `json:"chosen_update"`
 	ActivePartition struct {
 		Image struct {
 			Version string `json:"version"`
@@ -43,26 +98,106 @@ type checkOutput struct {
 	} `json:"active_partition"`
 }
 
+// The AWS APIs the updater uses are split into the calls that only read and
+// the calls that change resources, so that read-only mode (see readonly.go)
+// can hold on to the read half of a client alone.
+
 type ECSAPI interface {
+	ECSReadAPI
+	ECSWriteAPI
+}
+
+type ECSReadAPI interface {
 	ListContainerInstancesPages(*ecs.ListContainerInstancesInput, func(*ecs.ListContainerInstancesOutput, bool) bool) error
 	DescribeContainerInstances(input *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error)
-	UpdateContainerInstancesState(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error)
 	ListTasks(input *ecs.ListTasksInput) (*ecs.ListTasksOutput, error)
 	DescribeTasks(input *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error)
+	DescribeServices(input *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error)
 	WaitUntilTasksStoppedWithContext(ctx aws.Context, input *ecs.DescribeTasksInput, opts ...request.WaiterOption) error
+	WaitUntilTasksRunningWithContext(ctx aws.Context, input *ecs.DescribeTasksInput, opts ...request.WaiterOption) error
+	DescribeClusters(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error)
+	DescribeCapacityProviders(input *ecs.DescribeCapacityProvidersInput) (*ecs.DescribeCapacityProvidersOutput, error)
+}
+
+type ECSWriteAPI interface {
+	UpdateContainerInstancesState(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error)
+	TagResource(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error)
+	UntagResource(input *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error)
+	StopTask(input *ecs.StopTaskInput) (*ecs.StopTaskOutput, error)
+	StartTask(input *ecs.StartTaskInput) (*ecs.StartTaskOutput, error)
 }
 
 type SSMAPI interface {
-	WaitUntilCommandExecutedWithContext(ctx aws.Context, input *ssm.GetCommandInvocationInput, opts ...request.WaiterOption) error
-	SendCommand(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error)
+	SSMReadAPI
+	SSMWriteAPI
+}
+
+type SSMReadAPI interface {
 	GetCommandInvocation(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error)
+	ListCommandInvocationsPages(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error
+	GetParameter(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error)
+	ListCommandsPages(input *ssm.ListCommandsInput, fn func(*ssm.ListCommandsOutput, bool) bool) error
+	GetMaintenanceWindow(input *ssm.GetMaintenanceWindowInput) (*ssm.GetMaintenanceWindowOutput, error)
+	DescribeMaintenanceWindowExecutions(input *ssm.DescribeMaintenanceWindowExecutionsInput) (*ssm.DescribeMaintenanceWindowExecutionsOutput, error)
+	DescribeAutomationExecutions(input *ssm.DescribeAutomationExecutionsInput) (*ssm.DescribeAutomationExecutionsOutput, error)
+}
+
+// SSMWriteAPI includes SendCommand, since the documents it runs may change
+// instances.
+type SSMWriteAPI interface {
+	SendCommand(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error)
+	PutParameter(input *ssm.PutParameterInput) (*ssm.PutParameterOutput, error)
+	DeleteParameter(input *ssm.DeleteParameterInput) (*ssm.DeleteParameterOutput, error)
+	AddTagsToResource(input *ssm.AddTagsToResourceInput) (*ssm.AddTagsToResourceOutput, error)
 }
 
 type EC2API interface {
-	WaitUntilInstanceStatusOk(input *ec2.DescribeInstanceStatusInput) error
+	WaitUntilInstanceStatusOkWithContext(ctx aws.Context, input *ec2.DescribeInstanceStatusInput, opts ...request.WaiterOption) error
+	DescribeInstancesPages(input *ec2.DescribeInstancesInput, fn func(*ec2.DescribeInstancesOutput, bool) bool) error
+	DescribeInstanceStatusPages(input *ec2.DescribeInstanceStatusInput, fn func(*ec2.DescribeInstanceStatusOutput, bool) bool) error
+}
+
+type AutoScalingAPI interface {
+	AutoScalingReadAPI
+	AutoScalingWriteAPI
+}
+
+type AutoScalingReadAPI interface {
+	DescribeAutoScalingGroups(input *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error)
+	DescribeAutoScalingInstances(input *autoscaling.DescribeAutoScalingInstancesInput) (*autoscaling.DescribeAutoScalingInstancesOutput, error)
+}
+
+type AutoScalingWriteAPI interface {
+	SuspendProcesses(input *autoscaling.ScalingProcessQuery) (*autoscaling.SuspendProcessesOutput, error)
+	ResumeProcesses(input *autoscaling.ScalingProcessQuery) (*autoscaling.ResumeProcessesOutput, error)
+	CreateOrUpdateTags(input *autoscaling.CreateOrUpdateTagsInput) (*autoscaling.CreateOrUpdateTagsOutput, error)
+	DeleteTags(input *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error)
+	SetDesiredCapacity(input *autoscaling.SetDesiredCapacityInput) (*autoscaling.SetDesiredCapacityOutput, error)
+}
+
+type ELBV2API interface {
+	DescribeTargetHealth(input *elbv2.DescribeTargetHealthInput) (*elbv2.DescribeTargetHealthOutput, error)
 }
 
-func (u *updater) alreadyRunning(family string) (bool, error) {
+type CloudWatchLogsAPI interface {
+	FilterLogEventsPages(input *cloudwatchlogs.FilterLogEventsInput, fn func(*cloudwatchlogs.FilterLogEventsOutput, bool) bool) error
+}
+
+type STSAPI interface {
+	GetCallerIdentity(input *sts.GetCallerIdentityInput) (*sts.GetCallerIdentityOutput, error)
+}
+
+type SecretsManagerAPI interface {
+	GetSecretValue(input *secretsmanager.GetSecretValueInput) (*secretsmanager.GetSecretValueOutput, error)
+}
+
+// alreadyRunning reports whether another updater task of the family leads the
+// cluster, in which case this task must not act on it. When sibling tasks run,
+// e.g. because of a misconfigured desired count, the oldest one leads (see
+// leaderTask); without its own task ARN, self, this task can't tell whether
+// it is the leader and defers to its siblings. Tasks of the stack's check
+// schedule only check, and are not siblings (see checkTaskGroup).
+func (u *updater) alreadyRunning(family string, self string) (bool, error) {
 	log.Print("Checking for running updater tasks")
 	list, err := u.ecs.ListTasks(&ecs.ListTasksInput{
 		Cluster: &u.cluster,
@@ -71,10 +206,29 @@ func (u *updater) alreadyRunning(family string) (bool, error) {
 	if err != nil {
 		return false, fmt.Errorf("failed to list running updater tasks: %w", err)
 	}
-	if len(list.TaskArns) > 1 {
+	if len(list.TaskArns) <= 1 {
+		log.Println("This is the only running updater.")
+		return false, nil
+	}
+	tasks, err := u.updaterTasks(list.TaskArns)
+	if err != nil {
+		return false, err
+	}
+	if len(tasks) <= 1 {
+		log.Println("This is the only running updater besides check-only tasks.")
+		return false, nil
+	}
+	siblings := len(tasks) - 1
+	if self == "" {
+		log.Printf("Warning: %d other updater task(s) are running and this task's ARN is unknown, therefore deferring to them", siblings)
+		return true, nil
+	}
+	if leader := leaderTask(tasks); leader != self {
+		log.Printf("Warning: %d other updater task(s) are running and task %q leads, therefore idling", siblings, leader)
 		return true, nil
 	}
-	log.Println("This is the only running updater.")
+	log.Printf("Warning: %d other updater task(s) are running; this task is the oldest, therefore leading", siblings)
+	u.warnings.add(warnSibling, "%d other updater task(s) of family %q are running", siblings, family)
 	return false, nil
 }
 
@@ -95,44 +249,201 @@ func (u *updater) listContainerInstances() ([]*string, error) {
 	return containerInstances, nil
 }
 
+// filterForeignInstances separates container instance ARNs that belong to the
+// updater's region and account from those that don't. SSM commands are only
+// ever sent to instances in the updater's own region and account, so a cluster
+// with external or mis-registered instances can't cause the updater to act on
+// an instance ID that means something else elsewhere. An ARN that can't be
+// parsed is treated as foreign.
+func (u *updater) filterForeignInstances(instances []*string) ([]*string, []instanceFailure) {
+	local := make([]*string, 0, len(instances))
+	var foreign []instanceFailure
+	for _, containerInstance := range instances {
+		parsed, err := arn.Parse(aws.StringValue(containerInstance))
+		var reason string
+		switch {
+		case err != nil:
+			reason = fmt.Sprintf("invalid container instance ARN: %v", err)
+		case u.region != "" && parsed.Region != u.region:
+			reason = fmt.Sprintf("container instance is in region %q, but the updater runs in region %q", parsed.Region, u.region)
+		case u.account != "" && parsed.AccountID != u.account:
+			reason = fmt.Sprintf("container instance is in account %q, but the updater runs in account %q", parsed.AccountID, u.account)
+		default:
+			local = append(local, containerInstance)
+			continue
+		}
+		foreign = append(foreign, instanceFailure{arn: aws.StringValue(containerInstance), reason: reason})
+	}
+	return local, foreign
+}
+
+// describedInstances is the result of describing a cluster's container instances.
+type describedInstances struct {
+	// bottlerocket lists the container instances running Bottlerocket OS.
+	bottlerocket []instance
+	// failures lists the container instances whose state is unknown.
+	failures []instanceFailure
+	// others counts the container instances that are not running Bottlerocket OS.
+	others int
+	// unsupported lists the Bottlerocket instances whose variant the updater
+	// is not configured to update.
+	unsupported []instance
+	// stale lists the container instances that are registered for the same
+	// EC2 instance as a Bottlerocket instance in bottlerocket, and should be
+	// deregistered.
+	stale []instance
+}
+
+// registration is what decides which of the container instances registered
+// for the same EC2 instance is current.
+type registration struct {
+	connected    bool
+	active       bool
+	registeredAt time.Time
+}
+
+// preferredTo returns whether the registration is more likely current than
+// the other: a registration with a connected agent wins, then an ACTIVE one,
+// then the later one.
+func (r registration) preferredTo(other registration) bool {
+	if r.connected != other.connected {
+		return r.connected
+	}
+	if r.active != other.active {
+		return r.active
+	}
+	return r.registeredAt.After(other.registeredAt)
+}
+
+// dedupeRegistrations keeps one container instance per EC2 instance, the one
+// whose registration is preferred, in the order the EC2 instances were first
+// seen. It returns the kept instances and the stale registrations.
+func dedupeRegistrations(instances []instance, registrations map[string]registration) ([]instance, []instance) {
+	kept := make([]instance, 0, len(instances))
+	stale := make([]instance, 0)
+	index := make(map[string]int)
+	for _, inst := range instances {
+		i, ok := index[inst.instanceID]
+		if inst.instanceID == "" || !ok {
+			index[inst.instanceID] = len(kept)
+			kept = append(kept, inst)
+			continue
+		}
+		if registrations[inst.containerInstanceID].preferredTo(registrations[kept[i].containerInstanceID]) {
+			stale = append(stale, kept[i])
+			kept[i] = inst
+		} else {
+			stale = append(stale, inst)
+		}
+	}
+	return kept, stale
+}
+
+// instanceFailure records why a container instance could not be described.
+type instanceFailure struct {
+	arn    string
+	reason string
+}
+
 // filterBottlerocketInstances filters container instances and returns list of
-// instances that are running Bottlerocket OS
-func (u *updater) filterBottlerocketInstances(instances []*string) ([]instance, error) {
+// instances that are running Bottlerocket OS. A page that fails to be described
+// is retried once; if it fails again, its instances are reported as failures
+// instead of failing the whole listing.
+func (u *updater) filterBottlerocketInstances(instances []*string) (describedInstances, error) {
 	log.Printf("Filtering container instances running Bottlerocket OS")
-	bottlerocketInstances := make([]instance, 0)
+	described := describedInstances{bottlerocket: make([]instance, 0)}
+	registrations := make(map[string]registration)
 	errCount := 0
 	var lastErr error
 	pageCount, err := eachPage(len(instances), ecsPageSize, func(start, stop int) error {
-		resp, err := u.ecs.DescribeContainerInstances(&ecs.DescribeContainerInstancesInput{
+		input := &ecs.DescribeContainerInstancesInput{
 			Cluster:            &u.cluster,
 			ContainerInstances: instances[start:stop],
-		})
+			Include:            aws.StringSlice([]string{ecs.ContainerInstanceFieldTags}),
+		}
+		resp, err := u.ecs.DescribeContainerInstances(input)
+		if err != nil {
+			log.Printf("Failed to describe container instances from %d to %d, retrying: %v", start, stop, err)
+			u.warnings.add(warnRetried, "describing container instances failed: %v", err)
+			resp, err = u.ecs.DescribeContainerInstances(input)
+		}
 		// count errors per page.
 		if err != nil {
 			log.Printf("Failed to describe container instances from %d to %d: %v", start, stop, err)
 			errCount++
 			lastErr = err
+			for _, arn := range instances[start:stop] {
+				described.failures = append(described.failures, instanceFailure{
+					arn:    aws.StringValue(arn),
+					reason: fmt.Sprintf("failed to describe container instance: %v", err),
+				})
+			}
 			return nil
 		}
+		// ECS reports instances it could not describe, e.g. ones that are no longer
+		// registered, as failures rather than as an error for the whole call.
+		failed := make(map[string]bool)
+		for _, failure := range resp.Failures {
+			arn := aws.StringValue(failure.Arn)
+			reason := aws.StringValue(failure.Reason)
+			if detail := aws.StringValue(failure.Detail); detail != "" {
+				reason = fmt.Sprintf("%s (%s)", reason, detail)
+			}
+			log.Printf("Warning: ECS failed to describe container instance %q: %s", arn, reason)
+			described.failures = append(described.failures, instanceFailure{arn: arn, reason: reason})
+			failed[arn] = true
+		}
 		for _, containerInstance := range resp.ContainerInstances {
+			if failed[aws.StringValue(containerInstance.ContainerInstanceArn)] {
+				continue
+			}
 			if containsAttribute(containerInstance.Attributes, "bottlerocket.variant") {
-				bottlerocketInstances = append(bottlerocketInstances, instance{
+				inst := instance{
 					instanceID:          aws.StringValue(containerInstance.Ec2InstanceId),
 					containerInstanceID: aws.StringValue(containerInstance.ContainerInstanceArn),
-				})
-				log.Printf("Bottlerocket instance %q detected.", aws.StringValue(containerInstance.Ec2InstanceId))
+					variant:             attributeValue(containerInstance.Attributes, "bottlerocket.variant"),
+					arch:                attributeValue(containerInstance.Attributes, "ecs.cpu-architecture"),
+					taskCount:           aws.Int64Value(containerInstance.RunningTasksCount) + aws.Int64Value(containerInstance.PendingTasksCount),
+					utilization:         utilization(containerInstance),
+					registeredCPU:       resourceValue(containerInstance.RegisteredResources, "CPU"),
+					registeredMemory:    resourceValue(containerInstance.RegisteredResources, "MEMORY"),
+					stage:               attributeValue(containerInstance.Attributes, u.stageAttribute),
+				}
+				q, err := quarantineFromTags(containerInstance.Tags)
+				if err != nil {
+					log.Printf("Bottlerocket instance %q has an invalid quarantine, therefore keeping it quarantined: %v", inst.instanceID, err)
+					u.warnings.add(warnSkipped, "instance %q has an invalid quarantine: %v", inst.instanceID, err)
+				}
+				inst.quarantine = q
+				inst.consecutiveFailures = consecutiveFailuresFromTags(containerInstance.Tags)
+				inst.tags = tagValues(containerInstance.Tags)
+				if !u.supportsVariant(inst.variant) {
+					log.Printf("Bottlerocket instance %q detected with unsupported variant %q.", inst.instanceID, inst.variant)
+					described.unsupported = append(described.unsupported, inst)
+					continue
+				}
+				described.bottlerocket = append(described.bottlerocket, inst)
+				registrations[inst.containerInstanceID] = registration{
+					connected:    aws.BoolValue(containerInstance.AgentConnected),
+					active:       aws.StringValue(containerInstance.Status) == ecs.ContainerInstanceStatusActive,
+					registeredAt: aws.TimeValue(containerInstance.RegisteredAt),
+				}
+				log.Printf("Bottlerocket instance %q detected.", inst.instanceID)
+			} else {
+				described.others++
 			}
 		}
 		return nil
 	})
 	if err != nil {
-		return nil, err
+		return describedInstances{}, err
 	}
 	// check if every page had an error; errors are only fatal if each page failed.
 	if errCount == pageCount {
-		return nil, fmt.Errorf("failed to describe any container instances: %w", lastErr)
+		return describedInstances{}, fmt.Errorf("failed to describe any container instances: %w", lastErr)
 	}
-	return bottlerocketInstances, nil
+	described.bottlerocket, described.stale = dedupeRegistrations(described.bottlerocket, registrations)
+	return described, nil
 }
 
 // containsAttribute checks if a slice of ECS Attributes struct contains a specified name.
@@ -145,6 +456,20 @@ func containsAttribute(attrs []*ecs.Attribute, searchString string) bool {
 	return false
 }
 
+// attributeValue returns the value of the named ECS attribute, or an empty
+// string when the attribute isn't set or no name is given.
+func attributeValue(attrs []*ecs.Attribute, name string) string {
+	if name == "" {
+		return ""
+	}
+	for _, attr := range attrs {
+		if aws.StringValue(attr.Name) == name {
+			return aws.StringValue(attr.Value)
+		}
+	}
+	return ""
+}
+
 // eachPage defines batch processing boundaries for handling paginated results of API calls.
 func eachPage(inputLen int, size int, fn func(start, stop int) error) (int, error) {
 	pageCount := 0
@@ -161,20 +486,51 @@ func eachPage(inputLen int, size int, fn func(start, stop int) error) (int, erro
 	return pageCount, nil
 }
 
-// filterAvailableUpdates returns a list of instances that have updates available
+// filterAvailableUpdates returns a list of instances that have updates available.
+// It also records the active version of every instance it checks in
+// bottlerocketInstances, so callers can tell which versions the fleet runs.
+// Instances with a cached check are not checked again. The check is sent to
+// every page of instances first, and all the commands are then awaited
+// together, so a large fleet waits for its slowest page rather than for each
+// page in turn.
 func (u *updater) filterAvailableUpdates(bottlerocketInstances []instance) ([]instance, error) {
 	log.Printf("Filtering instances with available updates")
-	// make slice of Bottlerocket instances to use with SendCommand and checkCommandOutput
+	// make slice of Bottlerocket instances to use with SendCommand and checkCommandOutput,
+	// and of their indexes in bottlerocketInstances
 	instances := make([]string, 0)
-	for _, inst := range bottlerocketInstances {
+	indexes := make([]int, 0)
+	for i, inst := range bottlerocketInstances {
+		if inst.cachedCheck {
+			continue
+		}
 		instances = append(instances, inst.instanceID)
+		indexes = append(indexes, i)
 	}
 
 	var lastErr error
 	errCount := 0
-	candidates := make([]instance, 0)
+	channel := u.channel()
+	// checkPage is a page of instances the check was sent to.
+	type checkPage struct {
+		commandID   string
+		instanceIDs []string
+		indexes     []int
+	}
+	pages := make([]checkPage, 0)
+	pending := make(map[string][]string)
 	pageCount, err := eachPage(len(instances), ssmPageSize, func(start, stop int) error {
-		commandID, err := u.sendCommand(instances[start:stop], u.checkDocument)
+		commandID, sent, invalid, err := u.sendExcludingInvalid(channel, instances[start:stop], u.checkDocument, nil)
+		rejected := make(map[string]bool, len(invalid))
+		for _, instanceID := range invalid {
+			rejected[instanceID] = true
+		}
+		for _, i := range indexes[start:stop] {
+			bottlerocketInstances[i].invalidTarget = rejected[bottlerocketInstances[i].instanceID]
+		}
+		if err != nil && len(invalid) == stop-start {
+			// every instance of the page was rejected, which is not a failure to check the others.
+			return nil
+		}
 		if err != nil {
 			// errors here are considered non-fatal.
 			log.Printf("Failed to send document %s: %v", u.checkDocument, err)
@@ -182,34 +538,82 @@ func (u *updater) filterAvailableUpdates(bottlerocketInstances []instance) ([]in
 			lastErr = err
 			return nil
 		}
-		for _, inst := range bottlerocketInstances[start:stop] {
+		pages = append(pages, checkPage{commandID: commandID, instanceIDs: sent, indexes: indexes[start:stop]})
+		pending[commandID] = append(pending[commandID], sent...)
+		return nil
+	})
+	if err != nil {
+		return nil, err
+	}
+	failed := make(map[invocation]error)
+	if len(pending) > 0 {
+		log.Printf("Waiting for %d update check command(s) to complete", len(pending))
+		failed = channel.wait(pending)
+	}
+
+	candidates := make([]instance, 0)
+	for _, page := range pages {
+		if err := commandFailures(channel, u.checkDocument, page.commandID, page.instanceIDs, failed); err != nil {
+			// errors here are considered non-fatal.
+			log.Printf("Failed to send document %s: %v", u.checkDocument, err)
+			errCount++
+			lastErr = err
+			continue
+		}
+		commandID := page.commandID
+		for _, i := range page.indexes {
+			inst := bottlerocketInstances[i]
+			if inst.invalidTarget {
+				continue
+			}
 			commandOutput, err := u.getCommandResult(commandID, inst.instanceID)
 			if err != nil {
 				// errors here are considered non-fatal
 				log.Printf("Failed to get output for command %s, document %s and instance %q: %v", commandID, u.checkDocument, inst, err)
+				u.warnings.add(warnSkipped, "update check of instance %q has no output: %v", inst.instanceID, err)
 				continue
 			}
 			output, err := parseCommandOutput(commandOutput)
+			if errors.Is(err, errTruncatedOutput) {
+				log.Printf("Output of command %s for instance %q was truncated, therefore skipping it", commandID, inst)
+				u.warnings.add(warnTruncated, "output of update check %s for instance %q was truncated", commandID, inst.instanceID)
+				continue
+			}
 			if err != nil {
 				log.Printf("Failed to parse command output %q for instance %q: %v", string(commandOutput), inst, err)
+				u.warnings.add(warnUnparseable, "update check output of instance %q is invalid: %v", inst.instanceID, err)
+				bottlerocketInstances[i].unparseableOutput = string(commandOutput)
 				continue
 			}
+			bottlerocketInstances[i].bottlerocketVersion = output.ActivePartition.Image.Version
 			if output.UpdateState == updateStateAvailable || output.UpdateState == updateStateReady {
 				inst.bottlerocketVersion = output.ActivePartition.Image.Version
+				if output.ChosenUpdate != nil {
+					inst.targetRelease = *output.ChosenUpdate
+				}
 				candidates = append(candidates, inst)
 			}
 		}
-		return nil
-	})
-	if err != nil {
-		return nil, err
 	}
-	if errCount == pageCount {
+	if pageCount > 0 && errCount == pageCount {
 		return nil, fmt.Errorf("all attempts to send SSM document %s failed: %w", u.checkDocument, lastErr)
 	}
 	return candidates, nil
 }
 
+// rebootCandidates returns the Bottlerocket instances whose update check
+// succeeded, as recorded by filterAvailableUpdates, to reboot them whether or
+// not they have an update available.
+func rebootCandidates(bottlerocketInstances []instance) []instance {
+	candidates := make([]instance, 0, len(bottlerocketInstances))
+	for _, inst := range bottlerocketInstances {
+		if inst.bottlerocketVersion != "" {
+			candidates = append(candidates, inst)
+		}
+	}
+	return candidates
+}
+
 // eligible checks the eligibility of container instance for update. It's eligible
 // if all the running tasks were started by a service.
 func (u *updater) eligible(containerInstance string) (bool, error) {
@@ -235,15 +639,22 @@ func (u *updater) eligible(containerInstance string) (bool, error) {
 	}
 	for _, listResult := range desc.Tasks {
 		startedBy := aws.StringValue(listResult.StartedBy)
-		if !strings.HasPrefix(startedBy, "ecs-svc/") {
+		if !strings.HasPrefix(startedBy, "ecs-svc/") && !u.isSingleton(listResult) {
 			log.Printf("Container instance %q has a non-service task running: %s", containerInstance, aws.StringValue(listResult.TaskArn))
 			return false, nil
 		}
+		if u.isSingleton(listResult) && usesAwsvpc(listResult) {
+			log.Printf("Container instance %q has a singleton task in awsvpc network mode running, which is not supported: %s", containerInstance, aws.StringValue(listResult.TaskArn))
+			return false, nil
+		}
 	}
 	return true, nil
 }
 
-func (u *updater) drainInstance(containerInstance string) error {
+// drainInstance drains the instance and waits for its tasks to stop, or for
+// the targets of tasks of services in targetGroups to drain from them.
+func (u *updater) drainInstance(inst instance, targetGroups map[string][]string) error {
+	containerInstance := inst.containerInstanceID
 	log.Printf("Starting drain on container instance %q", containerInstance)
 	resp, err := u.ecs.UpdateContainerInstancesState(&ecs.UpdateContainerInstancesStateInput{
 		Cluster:            &u.cluster,
@@ -263,8 +674,11 @@ func (u *updater) drainInstance(containerInstance string) error {
 		return fmt.Errorf("failures in API call: %v", resp.Failures)
 	}
 	log.Printf("Container instance state changed to DRAINING")
+	if u.stuckDrainPolicy != "" {
+		u.recordDrainStart(containerInstance, time.Now())
+	}
 
-	err = u.waitUntilDrained(containerInstance)
+	err = u.waitUntilDrained(inst, targetGroups)
 	if err != nil {
 		log.Printf("Container instance %q failed to drain, therefore attempting to re-activate", containerInstance)
 		err2 := u.activateInstance(containerInstance)
@@ -294,10 +708,17 @@ func (u *updater) activateInstance(containerInstance string) error {
 		return fmt.Errorf("API failures while activating: %v", resp.Failures)
 	}
 	log.Printf("Container instance %q state changed to ACTIVE successfully!", containerInstance)
+	if u.stuckDrainPolicy != "" {
+		u.clearDrainStart(containerInstance)
+	}
+	if u.intentRecords {
+		u.resolveIntent(containerInstance)
+	}
 	return nil
 }
 
-func (u *updater) waitUntilDrained(containerInstance string) error {
+func (u *updater) waitUntilDrained(inst instance, targetGroups map[string][]string) error {
+	containerInstance := inst.containerInstanceID
 	log.Printf("Waiting for container instance %q to drain", containerInstance)
 	list, err := u.ecs.ListTasks(&ecs.ListTasksInput{
 		Cluster:           &u.cluster,
@@ -312,8 +733,17 @@ func (u *updater) waitUntilDrained(containerInstance string) error {
 		log.Printf("No tasks to drain")
 		return nil
 	}
+	if len(targetGroups) > 0 {
+		taskARNs, err = u.waitUntilTargetsDrained(inst, taskARNs, targetGroups)
+		if err != nil {
+			return err
+		}
+		if len(taskARNs) == 0 {
+			return nil
+		}
+	}
 
-	return u.ecs.WaitUntilTasksStoppedWithContext(aws.BackgroundContext(), &ecs.DescribeTasksInput{
+	return u.ecs.WaitUntilTasksStoppedWithContext(u.shutdownContext(), &ecs.DescribeTasksInput{
 		Cluster: &u.cluster,
 		Tasks:   taskARNs,
 	},
@@ -359,25 +789,33 @@ func (u *updater) updateInstance(inst instance) error {
 		return fmt.Errorf("unknown update state %q", check.UpdateState)
 	}
 
+	return u.rebootInstance(inst)
+}
+
+// rebootInstance applies the settings, if any, to the instance and reboots it,
+// waiting until it reaches Ok status again.
+func (u *updater) rebootInstance(inst instance) error {
+	if err := u.applySettings(inst); err != nil {
+		return err
+	}
+
+	ec2IDs := []string{inst.instanceID}
 	// occasionally instance goes into reboot before reporting command output, therefore
 	// we do not poll for command output. Instead we rely on verifyUpdate to confirm update
 	// success or failure.
 	log.Printf("Sending SSM document %q on instance %q", u.rebootDocument, inst.instanceID)
-	// SendCommand is directly called here because we do not want to wait on command complete.
-	resp, err := u.ssm.SendCommand(&ssm.SendCommandInput{
-		DocumentName:    aws.String(u.rebootDocument),
-		DocumentVersion: aws.String("$DEFAULT"),
-		InstanceIds:     aws.StringSlice(ec2IDs),
-		TimeoutSeconds:  aws.Int64(deliveryTimeoutSeconds),
-	})
+	// The command is sent directly here because we do not want to wait on command complete.
+	rebootID, err := u.channel().send(ec2IDs, u.rebootDocument, nil)
 	if err != nil {
 		return fmt.Errorf("failed to send reboot command: %w", err)
 	}
-	rebootID := *resp.Command.CommandId
 	log.Printf("SSM document %q posted with command ID %q", u.rebootDocument, rebootID)
+	u.sent.record(rebootID, u.documentPurpose(u.rebootDocument), ec2IDs)
 
 	// added some sleep time for reboot to start before we check instance state
-	time.Sleep(15 * time.Second)
+	if err := sleepContext(u.shutdownContext(), 15*time.Second); err != nil {
+		return fmt.Errorf("stopped waiting for the reboot: %w", err)
+	}
 	err = u.waitUntilOk(inst.instanceID)
 	if err != nil {
 		return fmt.Errorf("failed to reach Ok status after reboot: %w", err)
@@ -385,125 +823,262 @@ func (u *updater) updateInstance(inst instance) error {
 	return nil
 }
 
-// verifyUpdate verifies if instance was properly updated
-func (u *updater) verifyUpdate(inst instance) (bool, error) {
+// verifyUpdate verifies if instance was properly updated. It returns the
+// version the instance runs after the update.
+func (u *updater) verifyUpdate(inst instance) (string, bool, error) {
 	log.Println("Verifying update by checking there is no new version available to update" +
 		" and validate the active version")
 	ec2IDs := []string{inst.instanceID}
 	updateStatus, err := u.sendCommand(ec2IDs, u.checkDocument)
 	if err != nil {
-		return false, fmt.Errorf("failed to send update check command: %w", err)
+		return "", false, fmt.Errorf("failed to send update check command: %w", err)
 	}
 
 	updateResult, err := u.getCommandResult(updateStatus, inst.instanceID)
 	if err != nil {
-		return false, fmt.Errorf("failed to get check command output: %w", err)
+		return "", false, fmt.Errorf("failed to get check command output: %w", err)
 	}
 	output, err := parseCommandOutput(updateResult)
 	if err != nil {
-		return false, fmt.Errorf("failed to parse command output %q, manual verification required: %w", string(updateResult), err)
+		return "", false, fmt.Errorf("failed to parse command output %q, manual verification required: %w", string(updateResult), err)
 	}
 	updatedVersion := output.ActivePartition.Image.Version
 	if updatedVersion == inst.bottlerocketVersion {
 		log.Printf("Container instance %q did not update, its current "+
 			"version %s and updated version %s are the same", inst.containerInstanceID, inst.bottlerocketVersion, updatedVersion)
-		return false, nil
+		return updatedVersion, false, nil
 	} else if output.UpdateState == updateStateAvailable {
 		log.Printf("Container instance %q was updated to version %q successfully, however another newer version was recently released;"+
 			" Instance will be updated to newer version in next iteration.", inst.containerInstanceID, updatedVersion)
-		return true, nil
+		return updatedVersion, true, nil
 	}
 	log.Printf("Container instance %q updated to version %q", inst.containerInstanceID, updatedVersion)
-	return true, nil
+	return updatedVersion, true, nil
+}
+
+// verifyReboot verifies that the instance is back after a reboot by checking
+// its update state. It returns the version the instance runs after the reboot.
+func (u *updater) verifyReboot(inst instance) (string, error) {
+	log.Printf("Verifying reboot of instance %q by checking its active version", inst.instanceID)
+	commandID, err := u.sendCommand([]string{inst.instanceID}, u.checkDocument)
+	if err != nil {
+		return "", fmt.Errorf("failed to send update check command: %w", err)
+	}
+	result, err := u.getCommandResult(commandID, inst.instanceID)
+	if err != nil {
+		return "", fmt.Errorf("failed to get check command output: %w", err)
+	}
+	output, err := parseCommandOutput(result)
+	if err != nil {
+		return "", fmt.Errorf("failed to parse command output %q, manual verification required: %w", string(result), err)
+	}
+	activeVersion := output.ActivePartition.Image.Version
+	if activeVersion != inst.bottlerocketVersion {
+		log.Printf("Container instance %q runs version %q after the reboot instead of %q; an update applied before the reboot took effect",
+			inst.containerInstanceID, activeVersion, inst.bottlerocketVersion)
+	}
+	return activeVersion, nil
+}
+
+// changeRefPattern matches the change references that fit in SSM command
+// comments and ECS tag values.
+var changeRefPattern = regexp.MustCompile(`^[A-Za-z0-9_.:/=+\-@]{1,64}$`)
+
+// commandComment returns the comment attached to SSM commands. SSM commands
+// cannot be tagged, so the comment carries the metadata needed to attribute a
+// command in the SSM command history to the updater pass that sent it, and
+// to the update of an instance when one is in progress. Two full IDs would
+// fill most of the 100 characters SSM allows, so the IDs are shortened and
+// come after the cluster and change reference, which must not be truncated.
+func (u *updater) commandComment() string {
+	parts := []string{"updater=" + version}
+	if u.cluster != "" {
+		parts = append(parts, "cluster="+u.clusterName())
+	}
+	if u.changeRef != "" {
+		parts = append(parts, "change="+u.changeRef)
+	}
+	if u.passID != "" {
+		parts = append(parts, "pass="+shortCorrelationID(u.passID))
+	}
+	if u.updateID != "" {
+		parts = append(parts, "update="+shortCorrelationID(u.updateID))
+	}
+	if u.ssmComment != "" {
+		parts = append(parts, u.ssmComment)
+	}
+	comment := []rune(strings.Join(parts, " "))
+	if len(comment) > maxCommandCommentLength {
+		comment = comment[:maxCommandCommentLength]
+	}
+	return string(comment)
 }
 
 func (u *updater) sendCommand(instanceIDs []string, ssmDocument string) (string, error) {
-	log.Printf("Sending SSM document %q", ssmDocument)
-	resp, err := u.ssm.SendCommand(&ssm.SendCommandInput{
-		DocumentName:    aws.String(ssmDocument),
-		DocumentVersion: aws.String("$DEFAULT"),
-		InstanceIds:     aws.StringSlice(instanceIDs),
-		TimeoutSeconds:  aws.Int64(deliveryTimeoutSeconds),
-	})
+	return u.sendCommandWithParameters(instanceIDs, ssmDocument, nil)
+}
+
+// sendCommandWithParameters sends an SSM document that takes parameters and
+// waits for it to complete.
+func (u *updater) sendCommandWithParameters(instanceIDs []string, ssmDocument string, parameters map[string][]string) (string, error) {
+	commandID, _, err := u.sendCommandExcludingInvalid(instanceIDs, ssmDocument, parameters)
+	return commandID, err
+}
+
+// sendCommandExcludingInvalid sends an SSM document that takes parameters and
+// waits for it to complete. When SSM rejects some of the instances as invalid
+// targets, e.g. because they were terminated since they were listed, the
+// document is sent again without them rather than failing for every instance.
+// It returns the rejected instances, which the command did not run on.
+func (u *updater) sendCommandExcludingInvalid(instanceIDs []string, ssmDocument string, parameters map[string][]string) (string, []string, error) {
+	channel := u.channel()
+	commandID, sent, invalid, err := u.sendExcludingInvalid(channel, instanceIDs, ssmDocument, parameters)
 	if err != nil {
-		return "", fmt.Errorf("send command failed: %w", err)
+		return "", invalid, err
+	}
+	log.Printf("Waiting for command %q to complete for %d instance(s)", commandID, len(sent))
+	failed := channel.wait(map[string][]string{commandID: sent})
+	if err := commandFailures(channel, ssmDocument, commandID, sent, failed); err != nil {
+		return "", invalid, err
+	}
+	return commandID, invalid, nil
+}
+
+// sendExcludingInvalid sends an SSM document without waiting for it, sending
+// it again without the instances SSM rejects as invalid targets. It returns
+// the command ID, the instances the command was sent to and the rejected
+// instances.
+func (u *updater) sendExcludingInvalid(channel commandChannel, instanceIDs []string, ssmDocument string, parameters map[string][]string) (string, []string, []string, error) {
+	log.Printf("Sending SSM document %q", ssmDocument)
+	invalid := make([]string, 0)
+	var commandID string
+	for {
+		var err error
+		commandID, err = channel.send(instanceIDs, ssmDocument, parameters)
+		if err == nil {
+			break
+		}
+		rejected := invalidInstanceIDs(err, instanceIDs)
+		if len(rejected) == 0 {
+			return "", nil, invalid, fmt.Errorf("send command failed: %w", err)
+		}
+		for _, instanceID := range rejected {
+			log.Printf("SSM rejected instance %q as a target of document %q, therefore skipping it: %v", instanceID, ssmDocument, err)
+			u.warnings.add(warnSkipped, "instance %q is not a valid target of document %q, e.g. because it was terminated", instanceID, ssmDocument)
+		}
+		invalid = append(invalid, rejected...)
+		instanceIDs = excludeInstances(instanceIDs, rejected)
+		if len(instanceIDs) == 0 {
+			return "", nil, invalid, fmt.Errorf("send command failed: no valid instances: %w", err)
+		}
 	}
-	commandID := *resp.Command.CommandId
 	log.Printf("SSM document %q posted with command id %q", ssmDocument, commandID)
+	u.sent.record(commandID, u.documentPurpose(ssmDocument), instanceIDs)
+	return commandID, instanceIDs, invalid, nil
+}
 
-	// Wait for the sent commands to complete.
-	wg := sync.WaitGroup{}
-	instanceCount := len(instanceIDs)
-	errChan := make(chan error, instanceCount)
-	for _, v := range instanceIDs {
-		log.Printf("Waiting for command %q to complete for instance %q", commandID, v)
-		wg.Add(1)
-		go func(instanceID string) {
-			defer wg.Done()
-			err = u.ssm.WaitUntilCommandExecutedWithContext(aws.BackgroundContext(), &ssm.GetCommandInvocationInput{
-				CommandId:  aws.String(commandID),
-				InstanceId: aws.String(instanceID),
-			},
-				request.WithWaiterMaxAttempts(waiterMaxAttempts),
-				request.WithWaiterDelay(request.ConstantWaiterDelay(waiterDelay)))
-			if err != nil {
-				errChan <- err
-				log.Printf("Error encountered while awaiting document %q execution for instance: %q: %s", ssmDocument, instanceID, err)
-				u.logCommmandOutput(commandID, instanceID)
-			}
-		}(aws.StringValue(&v))
+// commandFailures logs the invocations of the command that failed, among the
+// failures returned by waiting for it, and returns an error when the command
+// failed on every instance it was sent to.
+func commandFailures(channel commandChannel, ssmDocument string, commandID string, instanceIDs []string, failed map[invocation]error) error {
+	var lastErr error
+	failures := 0
+	for _, instanceID := range instanceIDs {
+		err, ok := failed[invocation{commandID, instanceID}]
+		if !ok {
+			continue
+		}
+		lastErr = err
+		failures++
+		log.Printf("Error encountered while awaiting document %q execution for instance: %q: %s", ssmDocument, instanceID, err)
+		logCommmandOutput(channel, commandID, instanceID)
+	}
+	if len(instanceIDs) > 0 && failures == len(instanceIDs) {
+		return fmt.Errorf("too many failures while awaiting document execution: %w", lastErr)
 	}
-	wg.Wait()
-	close(errChan)
+	return nil
+}
 
-	errCount := 0
-	for err = range errChan {
-		errCount++
-		if errCount == instanceCount {
-			return "", fmt.Errorf("too many failures while awaiting document execution: %w", err)
+// invalidInstanceIDs returns the instances among instanceIDs that SSM rejected
+// as invalid targets of a command with err, which lists them in its message,
+// e.g. "Instances [[i-0123456789abcdef0]] not in a valid state for account".
+// It returns none when err is another error or doesn't name the instances.
+func invalidInstanceIDs(err error, instanceIDs []string) []string {
+	var aerr awserr.Error
+	if !errors.As(err, &aerr) || aerr.Code() != ssm.ErrCodeInvalidInstanceId {
+		return nil
+	}
+	named := make(map[string]bool)
+	for _, word := range strings.FieldsFunc(aerr.Message(), func(r rune) bool {
+		return !unicode.IsLetter(r) && !unicode.IsDigit(r) && r != '-'
+	}) {
+		named[word] = true
+	}
+	rejected := make([]string, 0)
+	for _, instanceID := range instanceIDs {
+		if named[instanceID] {
+			rejected = append(rejected, instanceID)
 		}
 	}
-	return commandID, nil
+	return rejected
 }
 
-func (u *updater) getCommandResult(commandID string, instanceID string) ([]byte, error) {
-	resp, err := u.ssm.GetCommandInvocation(&ssm.GetCommandInvocationInput{
-		CommandId:  aws.String(commandID),
-		InstanceId: aws.String(instanceID),
-	})
-	if err != nil {
-		return nil, fmt.Errorf("failed to retrieve command invocation output: %w", err)
+// excludeInstances returns the instances that are not in excluded.
+func excludeInstances(instanceIDs []string, excluded []string) []string {
+	skip := make(map[string]bool, len(excluded))
+	for _, instanceID := range excluded {
+		skip[instanceID] = true
 	}
-	commandResults := []byte(aws.StringValue(resp.StandardOutputContent))
-	if aws.StringValue(resp.Status) != ssm.CommandInvocationStatusSuccess {
-		return nil, fmt.Errorf("command %s has not reached success status, current status %q", commandID, aws.StringValue(resp.Status))
+	remaining := make([]string, 0, len(instanceIDs))
+	for _, instanceID := range instanceIDs {
+		if !skip[instanceID] {
+			remaining = append(remaining, instanceID)
+		}
 	}
-	return commandResults, nil
+	return remaining
 }
 
-// logCommmandOutput logs the ssm command invocation response
-func (u *updater) logCommmandOutput(commandID string, instanceID string) {
-	resp, err := u.ssm.GetCommandInvocation(&ssm.GetCommandInvocationInput{
-		CommandId:  aws.String(commandID),
-		InstanceId: aws.String(instanceID),
-	})
+func (u *updater) getCommandResult(commandID string, instanceID string) ([]byte, error) {
+	return u.channel().output(commandID, instanceID)
+}
+
+// logCommmandOutput logs the command invocation response
+func logCommmandOutput(channel commandChannel, commandID string, instanceID string) {
+	description, err := channel.describe(commandID, instanceID)
 	if err != nil {
 		log.Printf("Failed to get invocation output for instance %q: %v", instanceID, err)
+		return
 	}
-	log.Printf("Invocation output for instance %q: %#q", instanceID, resp)
+	log.Printf("Invocation output for instance %q: %s", instanceID, description)
 }
 
 // waitUntilOk takes an EC2 ID as a parameter and waits until the specified EC2 instance is in an Ok status.
+// It stops waiting when the updater is asked to shut down.
 func (u *updater) waitUntilOk(ec2ID string) error {
 	log.Printf("Waiting for instance %q to reach Ok status", ec2ID)
-	return u.ec2.WaitUntilInstanceStatusOk(&ec2.DescribeInstanceStatusInput{
+	err := u.ec2.WaitUntilInstanceStatusOkWithContext(u.shutdownContext(), &ec2.DescribeInstanceStatusInput{
 		InstanceIds: []*string{aws.String(ec2ID)},
 	})
+	if err != nil && u.stopping() {
+		return errShutdown
+	}
+	return err
 }
 
+// truncatedOutputPattern matches the marker SSM appends to command output that
+// exceeds the size GetCommandInvocation returns, e.g. "---Output truncated---".
+var truncatedOutputPattern = regexp.MustCompile(`(?i)-+\s*output truncated\s*-+`)
+
+// errTruncatedOutput is returned when command output was cut off by SSM and can't
+// be parsed reliably.
+var errTruncatedOutput = errors.New("command output was truncated by SSM")
+
 // parseCommandOutput takes raw bytes of ssm command output and converts it into a struct
 func parseCommandOutput(commandOutput []byte) (checkOutput, error) {
 	output := checkOutput{}
+	if truncatedOutputPattern.Match(commandOutput) {
+		return output, errTruncatedOutput
+	}
 	err := json.Unmarshal(commandOutput, &output)
 	if err != nil {
 		return output, fmt.Errorf("failed to unmarshal json: %w", err)
@@ -513,3 +1088,110 @@ func parseCommandOutput(commandOutput []byte) (checkOutput, error) {
 	}
 	return output, nil
 }
+
+// describeCluster returns the description of the cluster managed by the updater.
+func (u *updater) describeCluster() (*ecs.Cluster, error) {
+	resp, err := u.ecs.DescribeClusters(&ecs.DescribeClustersInput{
+		Clusters: aws.StringSlice([]string{u.cluster}),
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe cluster: %w", err)
+	}
+	if len(resp.Clusters) == 0 {
+		return nil, fmt.Errorf("cluster %q not found: %v", u.cluster, resp.Failures)
+	}
+	return resp.Clusters[0], nil
+}
+
+// clusterARN returns the full ARN of the cluster, looking it up when the
+// updater was configured with the short cluster name.
+func (u *updater) clusterARN() (string, error) {
+	if arn.IsARN(u.cluster) {
+		return u.cluster, nil
+	}
+	cluster, err := u.describeCluster()
+	if err != nil {
+		return "", err
+	}
+	return aws.StringValue(cluster.ClusterArn), nil
+}
+
+// clusterName returns the short name of the cluster.
+func (u *updater) clusterName() string {
+	if arn.IsARN(u.cluster) {
+		return u.cluster[strings.LastIndex(u.cluster, "/")+1:]
+	}
+	return u.cluster
+}
+
+// managedDrainingProviders returns the names of the cluster's Auto Scaling group
+// capacity providers that have ECS managed instance draining enabled. It also warns
+// about providers whose managed scaling may terminate instances drained for update.
+func (u *updater) managedDrainingProviders() ([]string, error) {
+	cluster, err := u.describeCluster()
+	if err != nil {
+		return nil, err
+	}
+	if len(cluster.CapacityProviders) == 0 {
+		return nil, nil
+	}
+	resp, err := u.ecs.DescribeCapacityProviders(&ecs.DescribeCapacityProvidersInput{
+		CapacityProviders: cluster.CapacityProviders,
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe capacity providers: %w", err)
+	}
+	managed := make([]string, 0)
+	for _, provider := range resp.CapacityProviders {
+		asgProvider := provider.AutoScalingGroupProvider
+		if asgProvider == nil {
+			// Fargate capacity providers do not manage container instances.
+			continue
+		}
+		name := aws.StringValue(provider.Name)
+		if asgProvider.ManagedScaling != nil &&
+			aws.StringValue(asgProvider.ManagedScaling.Status) == ecs.ManagedScalingStatusEnabled &&
+			aws.StringValue(asgProvider.ManagedTerminationProtection) != ecs.ManagedTerminationProtectionEnabled {
+			log.Printf("Capacity provider %q uses managed scaling without managed termination protection;"+
+				" instances drained for update may be terminated by scale-in", name)
+		}
+		if aws.StringValue(asgProvider.ManagedDraining) == ecs.ManagedDrainingEnabled {
+			managed = append(managed, name)
+		}
+	}
+	return managed, nil
+}
+
+// tagClusterSummary records the outcome of the run as tags on the cluster so the
+// last updater activity is visible in the ECS console.
+func (u *updater) tagClusterSummary(summary *passSummary) error {
+	clusterARN, err := u.clusterARN()
+	if err != nil {
+		return err
+	}
+	now := time.Now()
+	tags := []*ecs.Tag{
+		{Key: aws.String(tagLastRun), Value: aws.String(now.UTC().Format(time.RFC3339))},
+		{Key: aws.String(tagLastUpdated), Value: aws.String(strconv.Itoa(summary.updated))},
+		{Key: aws.String(tagLastFailed), Value: aws.String(strconv.Itoa(summary.failed))},
+		{Key: aws.String(tagLastSkipped), Value: aws.String(strconv.Itoa(summary.skipped))},
+		{Key: aws.String(tagLastWarnings), Value: aws.String(strconv.Itoa(summary.warnings.total()))},
+		// Recorded even when empty so that a run without a change reference does
+		// not appear to belong to the change of an earlier run.
+		{Key: aws.String(tagLastChange), Value: aws.String(summary.changeRef)},
+	}
+	lkgTags, err := u.lastKnownGoodTags(clusterARN, summary, now)
+	if err != nil {
+		log.Printf("Failed to read the last known good version of cluster %q, therefore not recording it: %v", clusterARN, err)
+	}
+	tags = append(tags, lkgTags...)
+	_, err = u.ecs.TagResource(&ecs.TagResourceInput{
+		ResourceArn: aws.String(clusterARN),
+		Tags:        tags,
+	})
+	if err != nil {
+		return fmt.Errorf("failed to tag cluster: %w", err)
+	}
+	log.Printf("Recorded run summary in tags on cluster %q", clusterARN)
+	return nil
+}
@@ -4,8 +4,9 @@ import (
 	"errors"
 	"fmt"
 	"strconv"
-	"sync"
+	"strings"
 	"testing"
+	"time"
 
 	"github.com/aws/aws-sdk-go/aws"
 	"github.com/aws/aws-sdk-go/aws/request"
@@ -64,11 +65,8 @@ func TestFilterAvailableUpdates(t *testing.T) {
 		"inst-id-5": `{"update_state": "Available", "active_partition": { "image": { "version": "v1.0.5"}}}`,
 	}
 
-	// mutex needed to prevent race condition when incrementing counter in concurrent
-	// execution of WaitUntilCommandExecutedWithContextFn
-	var m sync.Mutex
 	sendCommandCalls := 0
-	commandWaiterCalls := 0
+	listInvocationsCalls := 0
 	getCommandInvocationCalls := 0
 	mockSSM := MockSSM{
 		GetCommandInvocationFn: func(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
@@ -87,11 +85,10 @@ func TestFilterAvailableUpdates(t *testing.T) {
 				},
 			}, nil
 		},
-		WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
-			m.Lock()
-			commandWaiterCalls++
-			m.Unlock()
+		ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+			listInvocationsCalls++
 			assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
+			fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, instanceIDs(instances)...), true)
 			return nil
 		},
 	}
@@ -100,8 +97,48 @@ func TestFilterAvailableUpdates(t *testing.T) {
 	require.NoError(t, err)
 	assert.Equal(t, expected, actual, "Should only contain instances in Aavailable or Ready update state")
 	assert.Equal(t, 1, sendCommandCalls, "should send commands for each page")
-	assert.Equal(t, 5, commandWaiterCalls, "should wait for each instance")
+	assert.Equal(t, 1, listInvocationsCalls, "should poll invocations once for each page")
 	assert.Equal(t, 5, getCommandInvocationCalls, "should collect output for each instance")
+	versions := make([]string, 0, len(instances))
+	for _, inst := range instances {
+		versions = append(versions, inst.bottlerocketVersion)
+	}
+	assert.Equal(t, []string{"v1.0.5", "v1.0.5", "v1.1.1", "v1.1.1", "v1.0.5"}, versions, "should record the version of every checked instance")
+}
+
+func TestFilterAvailableUpdatesUnparseable(t *testing.T) {
+	instances := []instance{
+		{instanceID: "inst-id-1", containerInstanceID: "cont-inst-1"},
+		{instanceID: "inst-id-2", containerInstanceID: "cont-inst-2"},
+	}
+	responses := map[string]string{
+		"inst-id-1": `{"update_state": "Available", "active_partition": { "image": { "version": "v1.0.5"}}}`,
+		"inst-id-2": `{"updateState": "Available", "activePartition": { "image": { "version": "v1.0.5"}}}`,
+	}
+	mockSSM := MockSSM{
+		GetCommandInvocationFn: func(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
+			return &ssm.GetCommandInvocationOutput{
+				Status:                aws.String("Success"),
+				StandardOutputContent: aws.String(responses[*input.InstanceId]),
+			}, nil
+		},
+		SendCommandFn: func(_ *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+			return &ssm.SendCommandOutput{Command: &ssm.Command{CommandId: aws.String("command-id")}}, nil
+		},
+		ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+			fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, instanceIDs(instances)...), true)
+			return nil
+		},
+	}
+	u := updater{ssm: mockSSM, checkDocument: "check-document", warnings: newPassWarnings()}
+	actual, err := u.filterAvailableUpdates(instances)
+	require.NoError(t, err)
+	require.Len(t, actual, 1)
+	assert.Equal(t, "inst-id-1", actual[0].instanceID)
+	assert.Empty(t, instances[0].unparseableOutput)
+	assert.Equal(t, responses["inst-id-2"], instances[1].unparseableOutput, "should keep the output that could not be parsed")
+	assert.Equal(t, 1, u.warnings.counts[warnUnparseable])
+	assert.Equal(t, 0, u.warnings.counts[warnSkipped])
 }
 
 func TestPaginatedFilterAvailableUpdatesSuccess(t *testing.T) {
@@ -127,11 +164,8 @@ func TestPaginatedFilterAvailableUpdatesSuccess(t *testing.T) {
 		})
 	}
 
-	// mutex needed to prevent race condition when incrementing counter in concurrent
-	// execution of WaitUntilCommandExecutedWithContextFn
-	var m sync.Mutex
 	sendCommandCalls := 0
-	commandWaiterCalls := 0
+	listInvocationsCalls := 0
 	getCommandInvocationCalls := 0
 	mockSSM := MockSSM{
 		GetCommandInvocationFn: func(_ *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
@@ -142,16 +176,16 @@ func TestPaginatedFilterAvailableUpdatesSuccess(t *testing.T) {
 			sendCommandCalls++
 			return &ssm.SendCommandOutput{
 				Command: &ssm.Command{
-					CommandId:    aws.String("command-id"),
+					CommandId:    aws.String(fmt.Sprintf("command-id-%d", sendCommandCalls)),
 					DocumentName: aws.String("check-document"),
 				},
 			}, nil
 		},
-		WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
-			m.Lock()
-			commandWaiterCalls++
-			m.Unlock()
-			assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
+		ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+			listInvocationsCalls++
+			assert.Contains(t, aws.StringValue(input.CommandId), "command-id-")
+			assert.Equal(t, 2, sendCommandCalls, "every page should be sent before the commands are polled")
+			fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, instanceIDs(instances)...), true)
 			return nil
 		},
 	}
@@ -160,7 +194,7 @@ func TestPaginatedFilterAvailableUpdatesSuccess(t *testing.T) {
 	require.NoError(t, err)
 	assert.EqualValues(t, expected, actual, "should contain all instances")
 	assert.Equal(t, 2, sendCommandCalls, "should send commands for each page")
-	assert.Equal(t, 100, commandWaiterCalls, "should wait for each instance")
+	assert.Equal(t, 2, listInvocationsCalls, "should poll the invocations of each page once, after sending every page")
 	assert.Equal(t, 100, getCommandInvocationCalls, "should collect output for each instance")
 }
 
@@ -203,11 +237,8 @@ func TestPaginatedFilterAvailableUpdatesInPageFailures(t *testing.T) {
 		})
 	}
 
-	// mutex needed to prevent race condition when incrementing counter in concurrent
-	// execution of WaitUntilCommandExecutedWithContextFn
-	var m sync.Mutex
 	sendCommandCalls := 0
-	commandWaiterCalls := 0
+	listInvocationsCalls := 0
 	getCommandInvocationCalls := 0
 	count := 0
 	mockSSM := MockSSM{
@@ -234,16 +265,15 @@ func TestPaginatedFilterAvailableUpdatesInPageFailures(t *testing.T) {
 			sendCommandCalls++
 			return &ssm.SendCommandOutput{
 				Command: &ssm.Command{
-					CommandId:    aws.String("command-id"),
+					CommandId:    aws.String(fmt.Sprintf("command-id-%d", sendCommandCalls)),
 					DocumentName: aws.String("check-document"),
 				},
 			}, nil
 		},
-		WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
-			assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-			m.Lock()
-			commandWaiterCalls++
-			m.Unlock()
+		ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+			listInvocationsCalls++
+			assert.Contains(t, aws.StringValue(input.CommandId), "command-id-")
+			fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, instanceIDs(instances)...), true)
 			return nil
 		},
 	}
@@ -252,7 +282,7 @@ func TestPaginatedFilterAvailableUpdatesInPageFailures(t *testing.T) {
 	require.NoError(t, err)
 	assert.EqualValues(t, 40, len(actual), "Every 3rd instance of 120 should succeed")
 	assert.Equal(t, 3, sendCommandCalls, "should send commands for each page")
-	assert.Equal(t, 120, commandWaiterCalls, "should wait for each instance")
+	assert.Equal(t, 3, listInvocationsCalls, "should poll the invocations of each page once, after sending every page")
 	assert.Equal(t, 120, getCommandInvocationCalls, "should collect output for each instance")
 }
 
@@ -281,11 +311,8 @@ func TestPaginatedFilterAvailableUpdatesSingleErr(t *testing.T) {
 
 	pageErrors := []error{errors.New("Failed to send document"), nil}
 
-	// mutex needed to prevent race condition when incrementing counter in concurrent
-	// execution of WaitUntilCommandExecutedWithContextFn
-	var m sync.Mutex
 	sendCommandCalls := 0
-	commandWaiterCalls := 0
+	listInvocationsCalls := 0
 	getCommandInvocationCalls := 0
 	callCount := 0
 	mockSSM := MockSSM{
@@ -305,11 +332,10 @@ func TestPaginatedFilterAvailableUpdatesSingleErr(t *testing.T) {
 				},
 			}, failErr
 		},
-		WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+		ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+			listInvocationsCalls++
 			assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-			m.Lock()
-			commandWaiterCalls++
-			m.Unlock()
+			fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, instanceIDs(instances)...), true)
 			return nil
 		},
 	}
@@ -319,10 +345,22 @@ func TestPaginatedFilterAvailableUpdatesSingleErr(t *testing.T) {
 	require.NoError(t, err)
 	assert.EqualValues(t, actual, expected[50:], "Should only contain instances from the 2nd page")
 	assert.Equal(t, 2, sendCommandCalls, "should send commands for each page")
-	assert.Equal(t, 50, commandWaiterCalls, "should wait for each instance")
+	assert.Equal(t, 1, listInvocationsCalls, "should only poll invocations for the page that was sent")
 	assert.Equal(t, 50, getCommandInvocationCalls, "should collect output for each instance")
 }
 
+func TestParseCommandOutputTruncated(t *testing.T) {
+	for _, output := range []string{
+		`{"update_state": "Available", "active_partition": { "image": {---Output truncated---`,
+		"{\"update_state\": \"Available\"\n--output truncated--",
+	} {
+		_, err := parseCommandOutput([]byte(output))
+		assert.ErrorIs(t, err, errTruncatedOutput)
+	}
+	_, err := parseCommandOutput([]byte(`{"update_state": "Available", "active_partition": { "image": { "version": "v1.0.5"}}}`))
+	assert.NoError(t, err)
+}
+
 func TestGetCommandResult(t *testing.T) {
 	cases := []struct {
 		name            string
@@ -380,30 +418,76 @@ func TestGetCommandResult(t *testing.T) {
 
 func TestSendCommandSuccess(t *testing.T) {
 	instances := []string{"inst-id-1", "inst-id-2"}
-	// mutex needed to prevent race condition when appending to instances slice in concurrent
-	// execution of WaitUntilCommandExecutedWithContextFn
-	var m sync.Mutex
-	waitInstanceIDs := []string{}
+	listInvocationsCalls := 0
 	mockSSM := MockSSM{
 		SendCommandFn: func(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
 			assert.Equal(t, "test-doc", aws.StringValue(input.DocumentName))
 			assert.Equal(t, "$DEFAULT", aws.StringValue(input.DocumentVersion))
 			assert.Equal(t, aws.StringSlice(instances), input.InstanceIds)
+			assert.Equal(t, "updater=dev cluster=test-cluster pass=pass-id", aws.StringValue(input.Comment))
 			return &ssm.SendCommandOutput{Command: &ssm.Command{CommandId: aws.String("command-id")}}, nil
 		},
-		WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+		ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+			listInvocationsCalls++
 			assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-			m.Lock()
-			waitInstanceIDs = append(waitInstanceIDs, aws.StringValue(input.InstanceId))
-			m.Unlock()
+			fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, instances...), true)
 			return nil
 		},
 	}
-	u := updater{ssm: mockSSM}
+	u := updater{ssm: mockSSM, cluster: "test-cluster", passID: "pass-id"}
 	commandID, err := u.sendCommand(instances, "test-doc")
 	require.NoError(t, err)
 	assert.EqualValues(t, "command-id", commandID)
-	assert.ElementsMatch(t, instances, waitInstanceIDs)
+	assert.Equal(t, 1, listInvocationsCalls, "should poll all invocations of the command at once")
+}
+
+func TestCommandComment(t *testing.T) {
+	cases := []struct {
+		name     string
+		u        updater
+		expected string
+	}{
+		{
+			name:     "defaults",
+			u:        updater{},
+			expected: "updater=dev",
+		},
+		{
+			name:     "cluster arn",
+			u:        updater{cluster: "arn:aws:ecs:us-west-2:000000000000:cluster/my-cluster", passID: "pass-id"},
+			expected: "updater=dev cluster=my-cluster pass=pass-id",
+		},
+		{
+			name:     "custom comment",
+			u:        updater{cluster: "my-cluster", passID: "pass-id", ssmComment: "change CHG-1234"},
+			expected: "updater=dev cluster=my-cluster pass=pass-id change CHG-1234",
+		},
+		{
+			name:     "change reference",
+			u:        updater{cluster: "my-cluster", passID: "pass-id", changeRef: "CHG-1234", ssmComment: "weekly"},
+			expected: "updater=dev cluster=my-cluster change=CHG-1234 pass=pass-id weekly",
+		},
+		{
+			name: "update in progress",
+			u: updater{
+				cluster:   "arn:aws:ecs:us-west-2:000000000000:cluster/production-web-cluster",
+				passID:    "6f1c9a52-8d0e-4b8e-9a51-0c2f8e6f4b1d",
+				updateID:  "1b9d6bcd-bbfd-4b2d-9b5d-ab8dfbbd4bed",
+				changeRef: "CHG-0012345",
+			},
+			expected: "updater=dev cluster=production-web-cluster change=CHG-0012345 pass=6f1c9a52 update=1b9d6bcd",
+		},
+		{
+			name:     "truncated",
+			u:        updater{cluster: "my-cluster", passID: "pass-id", ssmComment: strings.Repeat("x", 200)},
+			expected: ("updater=dev cluster=my-cluster pass=pass-id " + strings.Repeat("x", 200))[:maxCommandCommentLength],
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			assert.Equal(t, tc.expected, tc.u.commandComment())
+		})
+	}
 }
 
 func TestSendCommandErr(t *testing.T) {
@@ -441,7 +525,7 @@ func TestSendCommandWaitErr(t *testing.T) {
 	}
 	for _, tc := range cases {
 		t.Run(tc.name, func(t *testing.T) {
-			waitError := errors.New("exceeded max attempts")
+			waitError := errors.New("throttled")
 			failedInstanceIDs := []string{}
 			mockSSM := MockSSM{
 				SendCommandFn: func(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
@@ -451,7 +535,7 @@ func TestSendCommandWaitErr(t *testing.T) {
 						Command: &ssm.Command{CommandId: aws.String("command-id")},
 					}, nil
 				},
-				WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+				ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, _ func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
 					assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
 					return waitError
 				},
@@ -486,11 +570,10 @@ func TestSendCommandWaitSuccess(t *testing.T) {
 		failedInstanceIDs := []string{}
 		mockSSM := MockSSM{
 			SendCommandFn: mockSendCommand,
-			WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
-				if aws.StringValue(input.InstanceId) == commandSuccessInstance {
-					return nil
-				}
-				return errors.New("exceeded max attempts")
+			ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+				fn(invocationsOutput(ssm.CommandInvocationStatusFailed, expectedFailInstances...), false)
+				fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, commandSuccessInstance), true)
+				return nil
 			},
 			GetCommandInvocationFn: func(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
 				assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
@@ -506,17 +589,11 @@ func TestSendCommandWaitSuccess(t *testing.T) {
 	})
 	t.Run("wait all success", func(t *testing.T) {
 		instances := []string{"inst-id-1", "inst-id-2"}
-		// mutex needed to prevent race condition when appending to instances slice in concurrent
-		// execution of WaitUntilCommandExecutedWithContextFn
-		var m sync.Mutex
-		waitInstanceIDs := []string{}
 		mockSSM := MockSSM{
 			SendCommandFn: mockSendCommand,
-			WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+			ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
 				assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-				m.Lock()
-				waitInstanceIDs = append(waitInstanceIDs, aws.StringValue(input.InstanceId))
-				m.Unlock()
+				fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, instances...), true)
 				return nil
 			},
 		}
@@ -524,7 +601,6 @@ func TestSendCommandWaitSuccess(t *testing.T) {
 		commandID, err := u.sendCommand(instances, "test-doc")
 		require.NoError(t, err)
 		assert.Equal(t, "command-id", commandID)
-		assert.ElementsMatch(t, instances, waitInstanceIDs, "should match instances for which wait succeeds")
 	})
 
 }
@@ -609,6 +685,35 @@ func TestListContainerInstances(t *testing.T) {
 	}
 }
 
+func TestFilterForeignInstances(t *testing.T) {
+	instances := []*string{
+		aws.String("arn:aws:ecs:us-west-2:111111111111:container-instance/cluster/local"),
+		aws.String("arn:aws:ecs:us-east-1:111111111111:container-instance/cluster/other-region"),
+		aws.String("arn:aws:ecs:us-west-2:222222222222:container-instance/cluster/other-account"),
+		aws.String("not-an-arn"),
+	}
+
+	t.Run("checked", func(t *testing.T) {
+		u := updater{region: "us-west-2", account: "111111111111"}
+		local, foreign := u.filterForeignInstances(instances)
+		assert.Equal(t, instances[:1], local)
+		require.Len(t, foreign, 3)
+		assert.Equal(t, "arn:aws:ecs:us-east-1:111111111111:container-instance/cluster/other-region", foreign[0].arn)
+		assert.Contains(t, foreign[0].reason, `region "us-east-1"`)
+		assert.Equal(t, "arn:aws:ecs:us-west-2:222222222222:container-instance/cluster/other-account", foreign[1].arn)
+		assert.Contains(t, foreign[1].reason, `account "222222222222"`)
+		assert.Equal(t, "not-an-arn", foreign[2].arn)
+		assert.Contains(t, foreign[2].reason, "invalid container instance ARN")
+	})
+	t.Run("unchecked", func(t *testing.T) {
+		u := updater{}
+		local, foreign := u.filterForeignInstances(instances)
+		assert.Equal(t, instances[:3], local)
+		require.Len(t, foreign, 1)
+		assert.Equal(t, "not-an-arn", foreign[0].arn)
+	})
+}
+
 func TestFilterBottlerocketInstances(t *testing.T) {
 	output := &ecs.DescribeContainerInstancesOutput{
 		ContainerInstances: []*ecs.ContainerInstance{{
@@ -662,7 +767,109 @@ func TestFilterBottlerocketInstances(t *testing.T) {
 		aws.String("ec2-id-not2"),
 	})
 	require.NoError(t, err)
-	assert.EqualValues(t, expected, actual)
+	assert.EqualValues(t, expected, actual.bottlerocket)
+	assert.Empty(t, actual.failures)
+	assert.Equal(t, 2, actual.others)
+}
+
+func TestFilterBottlerocketInstancesUnsupportedVariant(t *testing.T) {
+	output := &ecs.DescribeContainerInstancesOutput{
+		ContainerInstances: []*ecs.ContainerInstance{{
+			Attributes:           []*ecs.Attribute{{Name: aws.String("bottlerocket.variant"), Value: aws.String("aws-ecs-2")}},
+			ContainerInstanceArn: aws.String("cont-inst-supported"),
+			Ec2InstanceId:        aws.String("ec2-id-supported"),
+		}, {
+			Attributes:           []*ecs.Attribute{{Name: aws.String("bottlerocket.variant"), Value: aws.String("aws-ecs-3")}},
+			ContainerInstanceArn: aws.String("cont-inst-unsupported"),
+			Ec2InstanceId:        aws.String("ec2-id-unsupported"),
+		}},
+	}
+	mockECS := MockECS{
+		DescribeContainerInstancesFn: func(_ *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+			return output, nil
+		},
+	}
+	u := updater{ecs: mockECS, variants: parseVariants(defaultSupportedVariants)}
+
+	actual, err := u.filterBottlerocketInstances(aws.StringSlice([]string{"cont-inst-supported", "cont-inst-unsupported"}))
+	require.NoError(t, err)
+	assert.Equal(t, []instance{{instanceID: "ec2-id-supported", containerInstanceID: "cont-inst-supported", variant: "aws-ecs-2"}}, actual.bottlerocket)
+	assert.Equal(t, []instance{{instanceID: "ec2-id-unsupported", containerInstanceID: "cont-inst-unsupported", variant: "aws-ecs-3"}}, actual.unsupported)
+	assert.Zero(t, actual.others, "should not count unsupported Bottlerocket instances as other instances")
+}
+
+func TestFilterBottlerocketInstancesDuplicates(t *testing.T) {
+	registered := time.Date(2024, 3, 1, 0, 0, 0, 0, time.UTC)
+	containerInstance := func(arn string, ec2ID string, connected bool, status string, registeredAt time.Time) *ecs.ContainerInstance {
+		return &ecs.ContainerInstance{
+			Attributes:           []*ecs.Attribute{{Name: aws.String("bottlerocket.variant"), Value: aws.String("aws-ecs-2")}},
+			ContainerInstanceArn: aws.String(arn),
+			Ec2InstanceId:        aws.String(ec2ID),
+			AgentConnected:       aws.Bool(connected),
+			Status:               aws.String(status),
+			RegisteredAt:         aws.Time(registeredAt),
+		}
+	}
+	mockECS := MockECS{
+		DescribeContainerInstancesFn: func(_ *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+			return &ecs.DescribeContainerInstancesOutput{
+				ContainerInstances: []*ecs.ContainerInstance{
+					containerInstance("cont-inst-disconnected", "ec2-id-1", false, ecs.ContainerInstanceStatusActive, registered.Add(time.Hour)),
+					containerInstance("cont-inst-unique", "ec2-id-2", true, ecs.ContainerInstanceStatusActive, registered),
+					containerInstance("cont-inst-connected", "ec2-id-1", true, ecs.ContainerInstanceStatusActive, registered),
+					containerInstance("cont-inst-older", "ec2-id-3", true, ecs.ContainerInstanceStatusActive, registered),
+					containerInstance("cont-inst-newer", "ec2-id-3", true, ecs.ContainerInstanceStatusActive, registered.Add(time.Hour)),
+				},
+			}, nil
+		},
+	}
+	u := updater{ecs: mockECS}
+	actual, err := u.filterBottlerocketInstances(aws.StringSlice([]string{"cont-inst-disconnected", "cont-inst-unique", "cont-inst-connected", "cont-inst-older", "cont-inst-newer"}))
+	require.NoError(t, err)
+	assert.Equal(t, []instance{
+		{instanceID: "ec2-id-1", containerInstanceID: "cont-inst-connected", variant: "aws-ecs-2"},
+		{instanceID: "ec2-id-2", containerInstanceID: "cont-inst-unique", variant: "aws-ecs-2"},
+		{instanceID: "ec2-id-3", containerInstanceID: "cont-inst-newer", variant: "aws-ecs-2"},
+	}, actual.bottlerocket, "should prefer connected, then newer registrations")
+	assert.Equal(t, []instance{
+		{instanceID: "ec2-id-1", containerInstanceID: "cont-inst-disconnected", variant: "aws-ecs-2"},
+		{instanceID: "ec2-id-3", containerInstanceID: "cont-inst-older", variant: "aws-ecs-2"},
+	}, actual.stale)
+}
+
+func TestFilterBottlerocketInstancesAPIFailures(t *testing.T) {
+	mockECS := MockECS{
+		DescribeContainerInstancesFn: func(_ *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+			return &ecs.DescribeContainerInstancesOutput{
+				ContainerInstances: []*ecs.ContainerInstance{{
+					Attributes:           []*ecs.Attribute{{Name: aws.String("bottlerocket.variant")}},
+					ContainerInstanceArn: aws.String("cont-inst-br1"),
+					Ec2InstanceId:        aws.String("ec2-id-br1"),
+				}, {
+					// Reported both as described and as a failure
+					Attributes:           []*ecs.Attribute{{Name: aws.String("bottlerocket.variant")}},
+					ContainerInstanceArn: aws.String("cont-inst-br2"),
+					Ec2InstanceId:        aws.String("ec2-id-br2"),
+				}},
+				Failures: []*ecs.Failure{{
+					Arn:    aws.String("cont-inst-br2"),
+					Reason: aws.String("MISSING"),
+				}, {
+					Arn:    aws.String("cont-inst-br3"),
+					Reason: aws.String("MISSING"),
+					Detail: aws.String("container instance is deregistered"),
+				}},
+			}, nil
+		},
+	}
+	u := updater{ecs: mockECS}
+	actual, err := u.filterBottlerocketInstances(aws.StringSlice([]string{"cont-inst-br1", "cont-inst-br2", "cont-inst-br3"}))
+	require.NoError(t, err)
+	assert.Equal(t, []instance{{instanceID: "ec2-id-br1", containerInstanceID: "cont-inst-br1"}}, actual.bottlerocket)
+	assert.Equal(t, []instanceFailure{
+		{arn: "cont-inst-br2", reason: "MISSING"},
+		{arn: "cont-inst-br3", reason: "MISSING (container instance is deregistered)"},
+	}, actual.failures)
 }
 
 func TestPaginatedFilterBottlerocketInstancesAllFail(t *testing.T) {
@@ -680,10 +887,18 @@ func TestPaginatedFilterBottlerocketInstancesAllFail(t *testing.T) {
 		100,
 		nil,
 		errors.New("Failed to describe container instances"),
+	}, {
+		100, // retry
+		nil,
+		errors.New("Failed to describe container instances"),
 	}, {
 		50,
 		nil,
 		errors.New("Failed to describe container instances"),
+	}, {
+		50, // retry
+		nil,
+		errors.New("Failed to describe container instances"),
 	}}
 
 	callCount := 0
@@ -700,8 +915,9 @@ func TestPaginatedFilterBottlerocketInstancesAllFail(t *testing.T) {
 	u := updater{ecs: mockECS}
 	actual, err := u.filterBottlerocketInstances(instances)
 	require.Error(t, err)
-	assert.Empty(t, actual)
+	assert.Empty(t, actual.bottlerocket)
 	assert.Contains(t, err.Error(), "Failed to describe container instances")
+	assert.Equal(t, 4, callCount, "should retry each page once")
 }
 
 func TestPaginatedFilterBottlerocketInstancesSingleFailure(t *testing.T) {
@@ -731,6 +947,67 @@ func TestPaginatedFilterBottlerocketInstancesSingleFailure(t *testing.T) {
 		100,
 		nil,
 		errors.New("Failed to describe container instances"),
+	}, {
+		100, // retry
+		nil,
+		errors.New("Failed to describe container instances"),
+	}, {
+		50,
+		descOut[100:],
+		nil,
+	}}
+
+	callCount := 0
+	mockECS := MockECS{
+		DescribeContainerInstancesFn: func(input *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+			require.Less(t, callCount, len(responses))
+			resp := responses[callCount]
+			callCount++
+			assert.Equal(t, resp.inputLen, len(input.ContainerInstances))
+			return &ecs.DescribeContainerInstancesOutput{ContainerInstances: resp.ContainerInstances}, resp.err
+		},
+	}
+
+	u := updater{ecs: mockECS}
+	actual, err := u.filterBottlerocketInstances(instances)
+	require.NoError(t, err)
+	assert.EqualValues(t, expected[100:], actual.bottlerocket, "should contain only the last 50 instnaces")
+	require.Len(t, actual.failures, 100, "should report the instances of the failed page")
+	assert.Equal(t, "ec2-id-br0", actual.failures[0].arn)
+	assert.Contains(t, actual.failures[0].reason, "Failed to describe container instances")
+}
+
+func TestPaginatedFilterBottlerocketInstancesRetry(t *testing.T) {
+	descOut := make([]*ecs.ContainerInstance, 0)
+	instances := make([]*string, 0)
+	expected := make([]instance, 0)
+	for i := 0; i < 150; i++ {
+		instanceARN := "cont-inst-br" + strconv.Itoa(i)
+		ec2ID := "ec2-id-br" + strconv.Itoa(i)
+		instances = append(instances, aws.String(ec2ID))
+		descOut = append(descOut, &ecs.ContainerInstance{
+			Attributes:           []*ecs.Attribute{{Name: aws.String("bottlerocket.variant")}},
+			ContainerInstanceArn: aws.String(instanceARN),
+			Ec2InstanceId:        aws.String(ec2ID),
+		})
+		expected = append(expected, instance{
+			instanceID:          ec2ID,
+			containerInstanceID: instanceARN,
+		})
+	}
+
+	responses := []struct {
+		inputLen           int
+		ContainerInstances []*ecs.ContainerInstance
+		err                error
+	}{{
+		100,
+		nil,
+		errors.New("Failed to describe container instances"),
+	}, {
+		100, // retry
+		descOut[:100],
+		nil,
 	}, {
 		50,
 		descOut[100:],
@@ -751,7 +1028,8 @@ func TestPaginatedFilterBottlerocketInstancesSingleFailure(t *testing.T) {
 	u := updater{ecs: mockECS}
 	actual, err := u.filterBottlerocketInstances(instances)
 	require.NoError(t, err)
-	assert.EqualValues(t, expected[100:], actual, "should contain only the last 50 instnaces")
+	assert.EqualValues(t, expected, actual.bottlerocket, "should contain all the instances")
+	assert.Empty(t, actual.failures)
 }
 
 func TestPaginatedFilterBottlerocketInstancesNoBR(t *testing.T) {
@@ -796,7 +1074,7 @@ func TestPaginatedFilterBottlerocketInstancesNoBR(t *testing.T) {
 	u := updater{ecs: mockECS}
 	actual, err := u.filterBottlerocketInstances(instances)
 	require.NoError(t, err)
-	assert.Empty(t, actual)
+	assert.Empty(t, actual.bottlerocket)
 }
 
 func TestPaginatedFilterBottlerocketInstancesAllBRInstances(t *testing.T) {
@@ -846,7 +1124,7 @@ func TestPaginatedFilterBottlerocketInstancesAllBRInstances(t *testing.T) {
 	u := updater{ecs: mockECS}
 	actual, err := u.filterBottlerocketInstances(instances)
 	require.NoError(t, err)
-	assert.EqualValues(t, expected, actual, "should contain all the instances")
+	assert.EqualValues(t, expected, actual.bottlerocket, "should contain all the instances")
 }
 
 func TestEligible(t *testing.T) {
@@ -1028,7 +1306,7 @@ func TestDrainInstance(t *testing.T) {
 			},
 		}
 		u := updater{ecs: mockECS, cluster: "test-cluster"}
-		err := u.drainInstance("cont-inst-id")
+		err := u.drainInstance(instance{containerInstanceID: "cont-inst-id"}, nil)
 		require.NoError(t, err)
 		assert.Equal(t, 1, listTaskCount)
 		assert.Equal(t, []string{"DRAINING"}, stateChangeCalls)
@@ -1050,7 +1328,7 @@ func TestDrainInstance(t *testing.T) {
 			},
 		}
 		u := updater{ecs: mockECS, cluster: "test-cluster"}
-		err := u.drainInstance("cont-inst-id")
+		err := u.drainInstance(instance{containerInstanceID: "cont-inst-id"}, nil)
 		require.NoError(t, err)
 		assert.Equal(t, []string{"DRAINING"}, stateChangeCalls)
 		assert.Equal(t, 1, waitCount)
@@ -1067,7 +1345,7 @@ func TestDrainInstance(t *testing.T) {
 			},
 		}
 		u := updater{ecs: mockECS, cluster: "test-cluster"}
-		err := u.drainInstance("cont-inst-id")
+		err := u.drainInstance(instance{containerInstanceID: "cont-inst-id"}, nil)
 		require.Error(t, err)
 		assert.ErrorIs(t, err, stateOutErr)
 	})
@@ -1090,7 +1368,7 @@ func TestDrainInstance(t *testing.T) {
 			},
 		}
 		u := updater{ecs: mockECS, cluster: "test-cluster"}
-		err := u.drainInstance("cont-inst-id")
+		err := u.drainInstance(instance{containerInstanceID: "cont-inst-id"}, nil)
 		require.Error(t, err)
 		assert.Contains(t, err.Error(), fmt.Sprintf("%v", stateOutAPIFailure.Failures))
 		assert.Equal(t, []string{"DRAINING", "ACTIVE"}, stateChangeCalls)
@@ -1108,7 +1386,7 @@ func TestDrainInstance(t *testing.T) {
 			},
 		}
 		u := updater{ecs: mockECS, cluster: "test-cluster"}
-		err := u.drainInstance("cont-inst-id")
+		err := u.drainInstance(instance{containerInstanceID: "cont-inst-id"}, nil)
 		require.Error(t, err)
 		assert.ErrorIs(t, err, listTaskErr)
 		assert.Equal(t, []string{"DRAINING", "ACTIVE"}, stateChangeCalls)
@@ -1129,7 +1407,7 @@ func TestDrainInstance(t *testing.T) {
 			},
 		}
 		u := updater{ecs: mockECS, cluster: "test-cluster"}
-		err := u.drainInstance("cont-inst-id")
+		err := u.drainInstance(instance{containerInstanceID: "cont-inst-id"}, nil)
 		require.Error(t, err)
 		assert.ErrorIs(t, err, waitTaskErr)
 		assert.Equal(t, []string{"DRAINING", "ACTIVE"}, stateChangeCalls)
@@ -1193,14 +1471,14 @@ func TestUpdateInstance(t *testing.T) {
 					assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
 					return tc.invocationOut, nil
 				},
-				WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+				ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
 					assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-					assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
+					fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, "instance-id"), true)
 					return nil
 				},
 			}
 			mockEC2 := MockEC2{
-				WaitUntilInstanceStatusOkFn: func(input *ec2.DescribeInstanceStatusInput) error {
+				WaitUntilInstanceStatusOkWithContextFn: func(_ aws.Context, input *ec2.DescribeInstanceStatusInput, _ ...request.WaiterOption) error {
 					assert.Equal(t, []*string{aws.String("instance-id")}, input.InstanceIds)
 					return nil
 				},
@@ -1240,9 +1518,9 @@ func TestUpdateInstanceErr(t *testing.T) {
 			StandardOutputContent: aws.String("{\"update_state\": \"Available\", \"active_partition\": { \"image\": { \"version\": \"0.0.0\"}}}"),
 		}, nil
 	}
-	mockWaitCommandExecution := func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+	mockListCommandInvocations := func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
 		assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-		assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
+		fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, "instance-id"), true)
 		return nil
 	}
 
@@ -1273,8 +1551,8 @@ func TestUpdateInstanceErr(t *testing.T) {
 				}
 				return commandOutput, nil
 			},
-			GetCommandInvocationFn:                mockGetCommandInvocation,
-			WaitUntilCommandExecutedWithContextFn: mockWaitCommandExecution,
+			GetCommandInvocationFn:        mockGetCommandInvocation,
+			ListCommandInvocationsPagesFn: mockListCommandInvocations,
 		}
 		u := updater{ssm: mockSSM, checkDocument: "check-document", applyDocument: "apply-document"}
 		err := u.updateInstance(instance{
@@ -1294,8 +1572,8 @@ func TestUpdateInstanceErr(t *testing.T) {
 				}
 				return commandOutput, nil
 			},
-			GetCommandInvocationFn:                mockGetCommandInvocation,
-			WaitUntilCommandExecutedWithContextFn: mockWaitCommandExecution,
+			GetCommandInvocationFn:        mockGetCommandInvocation,
+			ListCommandInvocationsPagesFn: mockListCommandInvocations,
 		}
 		u := updater{ssm: mockSSM, checkDocument: "check-document", applyDocument: "apply-document", rebootDocument: "reboot-document"}
 		err := u.updateInstance(instance{
@@ -1314,7 +1592,7 @@ func TestUpdateInstanceErr(t *testing.T) {
 				assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
 				return nil, ssmGetInvocationErr
 			},
-			WaitUntilCommandExecutedWithContextFn: mockWaitCommandExecution,
+			ListCommandInvocationsPagesFn: mockListCommandInvocations,
 		}
 		u := updater{ssm: mockSSM, checkDocument: "check-document"}
 		err := u.updateInstance(instance{
@@ -1328,9 +1606,8 @@ func TestUpdateInstanceErr(t *testing.T) {
 		waitExecErr := errors.New("failed to wait ssm execution complete")
 		mockSSM := MockSSM{
 			SendCommandFn: mockSendCommand,
-			WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+			ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, _ func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
 				assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-				assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
 				return waitExecErr
 			},
 			GetCommandInvocationFn: func(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
@@ -1350,13 +1627,13 @@ func TestUpdateInstanceErr(t *testing.T) {
 	t.Run("wait instance ok err", func(t *testing.T) {
 		waitErr := errors.New("failed to wait instance ok")
 		mockSSM := MockSSM{
-			SendCommandFn:                         mockSendCommand,
-			GetCommandInvocationFn:                mockGetCommandInvocation,
-			WaitUntilCommandExecutedWithContextFn: mockWaitCommandExecution,
+			SendCommandFn:                 mockSendCommand,
+			GetCommandInvocationFn:        mockGetCommandInvocation,
+			ListCommandInvocationsPagesFn: mockListCommandInvocations,
 		}
 
 		mockEC2 := MockEC2{
-			WaitUntilInstanceStatusOkFn: func(input *ec2.DescribeInstanceStatusInput) error {
+			WaitUntilInstanceStatusOkWithContextFn: func(_ aws.Context, input *ec2.DescribeInstanceStatusInput, _ ...request.WaiterOption) error {
 				assert.Equal(t, []*string{aws.String("instance-id")}, input.InstanceIds)
 				return waitErr
 			},
@@ -1374,9 +1651,10 @@ func TestUpdateInstanceErr(t *testing.T) {
 func TestVerifyUpdate(t *testing.T) {
 	checkPattern := "{\"update_state\": \"%s\", \"active_partition\": { \"image\": { \"version\": \"%s\"}}}"
 	cases := []struct {
-		name          string
-		invocationOut *ssm.GetCommandInvocationOutput
-		expectedOk    bool
+		name            string
+		invocationOut   *ssm.GetCommandInvocationOutput
+		expectedOk      bool
+		expectedVersion string
 	}{
 		{
 			name: "verify success",
@@ -1384,7 +1662,8 @@ func TestVerifyUpdate(t *testing.T) {
 				Status:                aws.String("Success"),
 				StandardOutputContent: aws.String(fmt.Sprintf(checkPattern, updateStateIdle, "0.0.1")),
 			},
-			expectedOk: true,
+			expectedOk:      true,
+			expectedVersion: "0.0.1",
 		},
 		{
 			name: "version is same",
@@ -1392,7 +1671,8 @@ func TestVerifyUpdate(t *testing.T) {
 				Status:                aws.String("Success"),
 				StandardOutputContent: aws.String(fmt.Sprintf(checkPattern, updateStateIdle, "0.0.0")),
 			},
-			expectedOk: false,
+			expectedOk:      false,
+			expectedVersion: "0.0.0",
 		},
 		{
 			name: "another version is available",
@@ -1400,7 +1680,8 @@ func TestVerifyUpdate(t *testing.T) {
 				Status:                aws.String("Success"),
 				StandardOutputContent: aws.String(fmt.Sprintf(checkPattern, updateStateAvailable, "0.0.1")),
 			},
-			expectedOk: true,
+			expectedOk:      true,
+			expectedVersion: "0.0.1",
 		},
 	}
 
@@ -1420,20 +1701,21 @@ func TestVerifyUpdate(t *testing.T) {
 					assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
 					return tc.invocationOut, nil
 				},
-				WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+				ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
 					assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-					assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
+					fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, "instance-id"), true)
 					return nil
 				},
 			}
 			u := updater{ssm: mockSSM, checkDocument: "check-document"}
-			ok, err := u.verifyUpdate(instance{
+			version, ok, err := u.verifyUpdate(instance{
 				instanceID:          "instance-id",
 				containerInstanceID: "cont-inst-id",
 				bottlerocketVersion: "0.0.0",
 			})
 			require.NoError(t, err)
 			assert.Equal(t, tc.expectedOk, ok)
+			assert.Equal(t, tc.expectedVersion, version)
 		})
 	}
 }
@@ -1449,9 +1731,9 @@ func TestVerifyUpdateErr(t *testing.T) {
 			},
 		}, nil
 	}
-	mockWaitCommandExecution := func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+	mockListCommandInvocations := func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
 		assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-		assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
+		fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, "instance-id"), true)
 		return nil
 	}
 	mockGetCommandInvocation := func(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
@@ -1472,7 +1754,7 @@ func TestVerifyUpdateErr(t *testing.T) {
 			},
 		}
 		u := updater{ssm: mockSSM, checkDocument: "check-document"}
-		ok, err := u.verifyUpdate(instance{
+		_, ok, err := u.verifyUpdate(instance{
 			instanceID:          "instance-id",
 			containerInstanceID: "cont-inst-id",
 			bottlerocketVersion: "0.0.0",
@@ -1485,9 +1767,8 @@ func TestVerifyUpdateErr(t *testing.T) {
 		waitExecErr := errors.New("failed to wait ssm execution complete")
 		mockSSM := MockSSM{
 			SendCommandFn: mockSSMCommandOut,
-			WaitUntilCommandExecutedWithContextFn: func(_ aws.Context, input *ssm.GetCommandInvocationInput, _ ...request.WaiterOption) error {
+			ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, _ func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
 				assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
-				assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
 				return waitExecErr
 			},
 			GetCommandInvocationFn: func(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
@@ -1497,7 +1778,7 @@ func TestVerifyUpdateErr(t *testing.T) {
 			},
 		}
 		u := updater{ssm: mockSSM, checkDocument: "check-document"}
-		ok, err := u.verifyUpdate(instance{
+		_, ok, err := u.verifyUpdate(instance{
 			instanceID:          "instance-id",
 			containerInstanceID: "cont-inst-id",
 			bottlerocketVersion: "0.0.0",
@@ -1509,8 +1790,8 @@ func TestVerifyUpdateErr(t *testing.T) {
 	t.Run("invocation err", func(t *testing.T) {
 		ssmGetInvocationErr := errors.New("failed to get command invocation")
 		mockSSM := MockSSM{
-			SendCommandFn:                         mockSSMCommandOut,
-			WaitUntilCommandExecutedWithContextFn: mockWaitCommandExecution,
+			SendCommandFn:                 mockSSMCommandOut,
+			ListCommandInvocationsPagesFn: mockListCommandInvocations,
 			GetCommandInvocationFn: func(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
 				assert.Equal(t, "command-id", aws.StringValue(input.CommandId))
 				assert.Equal(t, "instance-id", aws.StringValue(input.InstanceId))
@@ -1518,7 +1799,7 @@ func TestVerifyUpdateErr(t *testing.T) {
 			},
 		}
 		u := updater{ssm: mockSSM, checkDocument: "check-document"}
-		ok, err := u.verifyUpdate(instance{
+		_, ok, err := u.verifyUpdate(instance{
 			instanceID:          "instance-id",
 			containerInstanceID: "cont-inst-id",
 			bottlerocketVersion: "0.0.0",
@@ -1530,12 +1811,12 @@ func TestVerifyUpdateErr(t *testing.T) {
 
 	t.Run("parse output err", func(t *testing.T) {
 		mockSSM := MockSSM{
-			SendCommandFn:                         mockSSMCommandOut,
-			WaitUntilCommandExecutedWithContextFn: mockWaitCommandExecution,
-			GetCommandInvocationFn:                mockGetCommandInvocation,
+			SendCommandFn:                 mockSSMCommandOut,
+			ListCommandInvocationsPagesFn: mockListCommandInvocations,
+			GetCommandInvocationFn:        mockGetCommandInvocation,
 		}
 		u := updater{ssm: mockSSM, checkDocument: "check-document"}
-		ok, err := u.verifyUpdate(instance{
+		_, ok, err := u.verifyUpdate(instance{
 			instanceID:          "instance-id",
 			containerInstanceID: "cont-inst-id",
 			bottlerocketVersion: "0.0.0",
@@ -1603,23 +1884,48 @@ func TestActivateInstance(t *testing.T) {
 }
 
 func TestAlreadyRunning(t *testing.T) {
+	siblings := &ecs.ListTasksOutput{
+		TaskArns: []*string{
+			aws.String("task-arn-1"),
+			aws.String("task-arn-2"),
+		},
+	}
 	cases := []struct {
-		name        string
-		listOut     *ecs.ListTasksOutput
-		listErr     error
-		expectedOk  bool
-		expectedErr string
+		name             string
+		listOut          *ecs.ListTasksOutput
+		listErr          error
+		self             string
+		describeErr      error
+		expectedOk       bool
+		expectedErr      string
+		expectedWarnings int
 	}{
 		{
-			name: "success",
-			listOut: &ecs.ListTasksOutput{
-				TaskArns: []*string{
-					aws.String("task-arn-1"),
-					aws.String("task-arn-2"),
-				},
-			},
+			name:       "success",
+			listOut:    siblings,
+			expectedOk: true,
+		},
+		{
+			name:       "sibling leads",
+			listOut:    siblings,
+			self:       "task-arn-1",
 			expectedOk: true,
 		},
+		{
+			name:             "leader",
+			listOut:          siblings,
+			self:             "task-arn-2",
+			expectedOk:       false,
+			expectedWarnings: 1,
+		},
+		{
+			name:        "fail describe tasks",
+			listOut:     siblings,
+			self:        "task-arn-2",
+			describeErr: errors.New("failed to describe tasks"),
+			expectedOk:  false,
+			expectedErr: "failed to describe tasks",
+		},
 		{
 			name: "only one task",
 			listOut: &ecs.ListTasksOutput{
@@ -1642,9 +1948,17 @@ func TestAlreadyRunning(t *testing.T) {
 				ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
 					return tc.listOut, tc.listErr
 				},
+				DescribeTasksFn: func(input *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+					assert.Equal(t, siblings.TaskArns, input.Tasks)
+					// task-arn-2 was created first, so it leads.
+					return &ecs.DescribeTasksOutput{Tasks: []*ecs.Task{
+						{TaskArn: aws.String("task-arn-1"), CreatedAt: aws.Time(time.Unix(1700000600, 0))},
+						{TaskArn: aws.String("task-arn-2"), CreatedAt: aws.Time(time.Unix(1700000000, 0))},
+					}}, tc.describeErr
+				},
 			}
-			u := updater{ecs: mockECS, cluster: "ecs-cluster"}
-			ok, err := u.alreadyRunning("updater-family")
+			u := updater{ecs: mockECS, cluster: "ecs-cluster", warnings: newPassWarnings()}
+			ok, err := u.alreadyRunning("updater-family", tc.self)
 			if tc.expectedErr == "" {
 				require.NoError(t, err)
 			} else {
@@ -1652,6 +1966,178 @@ func TestAlreadyRunning(t *testing.T) {
 				assert.Contains(t, err.Error(), tc.expectedErr)
 			}
 			assert.Equal(t, tc.expectedOk, ok)
+			assert.Equal(t, tc.expectedWarnings, u.warnings.count(warnSibling))
+		})
+	}
+}
+
+func TestAlreadyRunningCheckOnlySibling(t *testing.T) {
+	mockECS := MockECS{
+		ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+			return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"check-task", "update-task"})}, nil
+		},
+		DescribeTasksFn: func(_ *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+			// The check-only task is older, so it would lead if it counted.
+			return &ecs.DescribeTasksOutput{Tasks: []*ecs.Task{
+				{TaskArn: aws.String("check-task"), Group: aws.String(checkTaskGroup), CreatedAt: aws.Time(time.Unix(1700000000, 0))},
+				{TaskArn: aws.String("update-task"), Group: aws.String("family:updater-family"), CreatedAt: aws.Time(time.Unix(1700000600, 0))},
+			}}, nil
+		},
+	}
+	for _, self := range []string{"update-task", ""} {
+		u := updater{ecs: mockECS, cluster: "ecs-cluster", warnings: newPassWarnings()}
+		ok, err := u.alreadyRunning("updater-family", self)
+		require.NoError(t, err)
+		assert.False(t, ok, "an older check-only task should not make the update run idle")
+		assert.Equal(t, 0, u.warnings.count(warnSibling))
+	}
+}
+
+func TestTagClusterSummary(t *testing.T) {
+	const clusterARN = "arn:aws:ecs:us-west-2:123456789012:cluster/test-cluster"
+	summary := newPassSummary()
+	summary.updatedInstance("inst-id-1", "Instance updated successfully")
+	summary.updatedInstance("inst-id-2", "Instance updated successfully")
+	summary.failedInstance("inst-id-3", "Update failed")
+	summary.warnings = newPassWarnings()
+	summary.warnings.add(warnRetried, "sending document failed")
+	summary.changeRef = "CHG-1234"
+
+	cases := []struct {
+		name        string
+		cluster     string
+		describeOut *ecs.DescribeClustersOutput
+		describeErr error
+		tagErr      error
+		expectedErr string
+	}{
+		{
+			name:    "cluster arn",
+			cluster: clusterARN,
+		},
+		{
+			name:    "cluster name",
+			cluster: "test-cluster",
+			describeOut: &ecs.DescribeClustersOutput{
+				Clusters: []*ecs.Cluster{{ClusterArn: aws.String(clusterARN)}},
+			},
+		},
+		{
+			name:        "cluster not found",
+			cluster:     "test-cluster",
+			describeOut: &ecs.DescribeClustersOutput{},
+			expectedErr: "cluster \"test-cluster\" not found",
+		},
+		{
+			name:        "describe fail",
+			cluster:     "test-cluster",
+			describeErr: errors.New("failed to describe clusters"),
+			expectedErr: "failed to describe clusters",
+		},
+		{
+			name:        "tag fail",
+			cluster:     clusterARN,
+			tagErr:      errors.New("failed to tag"),
+			expectedErr: "failed to tag",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			tags := map[string]string{}
+			mockECS := MockECS{
+				DescribeClustersFn: func(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+					assert.Equal(t, aws.StringSlice([]string{"test-cluster"}), input.Clusters)
+					return tc.describeOut, tc.describeErr
+				},
+				TagResourceFn: func(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+					assert.Equal(t, clusterARN, aws.StringValue(input.ResourceArn))
+					for _, tag := range input.Tags {
+						tags[aws.StringValue(tag.Key)] = aws.StringValue(tag.Value)
+					}
+					return &ecs.TagResourceOutput{}, tc.tagErr
+				},
+			}
+			u := updater{ecs: mockECS, cluster: tc.cluster}
+			err := u.tagClusterSummary(summary)
+			if tc.expectedErr != "" {
+				require.Error(t, err)
+				assert.Contains(t, err.Error(), tc.expectedErr)
+				return
+			}
+			require.NoError(t, err)
+			assert.Equal(t, "2", tags[tagLastUpdated])
+			assert.Equal(t, "1", tags[tagLastFailed])
+			assert.Equal(t, "0", tags[tagLastSkipped])
+			assert.Equal(t, "1", tags[tagLastWarnings])
+			assert.Equal(t, "CHG-1234", tags[tagLastChange])
+			assert.NotEmpty(t, tags[tagLastRun])
+		})
+	}
+}
+
+func TestManagedDrainingProviders(t *testing.T) {
+	cases := []struct {
+		name        string
+		cluster     *ecs.Cluster
+		providers   []*ecs.CapacityProvider
+		providerErr error
+		expected    []string
+		expectedErr string
+	}{
+		{
+			name:     "no capacity providers",
+			cluster:  &ecs.Cluster{},
+			expected: nil,
+		},
+		{
+			name:    "managed draining",
+			cluster: &ecs.Cluster{CapacityProviders: aws.StringSlice([]string{"cp-1", "cp-2", "FARGATE"})},
+			providers: []*ecs.CapacityProvider{
+				{
+					Name: aws.String("cp-1"),
+					AutoScalingGroupProvider: &ecs.AutoScalingGroupProvider{
+						ManagedDraining: aws.String(ecs.ManagedDrainingEnabled),
+					},
+				},
+				{
+					Name: aws.String("cp-2"),
+					AutoScalingGroupProvider: &ecs.AutoScalingGroupProvider{
+						ManagedDraining: aws.String(ecs.ManagedDrainingDisabled),
+					},
+				},
+				{
+					Name: aws.String("FARGATE"),
+				},
+			},
+			expected: []string{"cp-1"},
+		},
+		{
+			name:        "describe capacity providers fail",
+			cluster:     &ecs.Cluster{CapacityProviders: aws.StringSlice([]string{"cp-1"})},
+			providerErr: errors.New("failed to describe capacity providers"),
+			expectedErr: "failed to describe capacity providers",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			mockECS := MockECS{
+				DescribeClustersFn: func(_ *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+					return &ecs.DescribeClustersOutput{Clusters: []*ecs.Cluster{tc.cluster}}, nil
+				},
+				DescribeCapacityProvidersFn: func(input *ecs.DescribeCapacityProvidersInput) (*ecs.DescribeCapacityProvidersOutput, error) {
+					assert.Equal(t, tc.cluster.CapacityProviders, input.CapacityProviders)
+					return &ecs.DescribeCapacityProvidersOutput{CapacityProviders: tc.providers}, tc.providerErr
+				},
+			}
+			u := updater{ecs: mockECS, cluster: "test-cluster"}
+			actual, err := u.managedDrainingProviders()
+			if tc.expectedErr != "" {
+				require.Error(t, err)
+				assert.Contains(t, err.Error(), tc.expectedErr)
+				return
+			}
+			require.NoError(t, err)
+			assert.Equal(t, tc.expected, actual)
 		})
 	}
 }
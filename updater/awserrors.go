@@ -0,0 +1,73 @@
+package main
+
+import (
+	"errors"
+	"net"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/aws/request"
+)
+
+// Classes of the errors of failed AWS calls, which tell broken permissions or
+// configuration apart from AWS having a bad day.
+const (
+	errClassThrottling   = "throttling"
+	errClassAccessDenied = "access-denied"
+	errClassNotFound     = "not-found"
+	errClassTimeout      = "timeout"
+	errClassOther        = "other"
+)
+
+// errorClasses lists every class, so metrics report zero errors too.
+var errorClasses = []string{errClassThrottling, errClassAccessDenied, errClassNotFound, errClassTimeout, errClassOther}
+
+// accessDeniedCodes are the error codes AWS services return for calls the
+// caller is not allowed, or not authenticated, to make.
+var accessDeniedCodes = map[string]bool{
+	"AccessDenied":                true,
+	"AccessDeniedException":       true,
+	"UnauthorizedOperation":       true,
+	"UnauthorizedException":       true,
+	"AuthFailure":                 true,
+	"InvalidClientTokenId":        true,
+	"UnrecognizedClientException": true,
+	"ExpiredToken":                true,
+	"ExpiredTokenException":       true,
+	"SignatureDoesNotMatch":       true,
+}
+
+// timeoutCodes are the error codes of calls that timed out.
+var timeoutCodes = map[string]bool{
+	"RequestTimeout":          true,
+	"RequestTimeoutException": true,
+	"ResourceNotReady":        true,
+}
+
+// classifyAWSError returns the class of the error of an AWS call.
+func classifyAWSError(err error) string {
+	if request.IsErrorThrottle(err) {
+		return errClassThrottling
+	}
+	var aerr awserr.Error
+	if errors.As(err, &aerr) {
+		code := aerr.Code()
+		switch {
+		case accessDeniedCodes[code]:
+			return errClassAccessDenied
+		case strings.Contains(code, "NotFound"):
+			return errClassNotFound
+		case timeoutCodes[code]:
+			return errClassTimeout
+		}
+		// Network errors are wrapped in a RequestError.
+		if aerr.OrigErr() != nil {
+			err = aerr.OrigErr()
+		}
+	}
+	var netErr net.Error
+	if errors.As(err, &netErr) && netErr.Timeout() {
+		return errClassTimeout
+	}
+	return errClassOther
+}
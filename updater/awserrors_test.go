@@ -0,0 +1,34 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"net"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/stretchr/testify/assert"
+)
+
+func TestClassifyAWSError(t *testing.T) {
+	cases := []struct {
+		err      error
+		expected string
+	}{
+		{awserr.New("ThrottlingException", "rate exceeded", nil), errClassThrottling},
+		{awserr.New("AccessDeniedException", "not authorized to perform ecs:ListTasks", nil), errClassAccessDenied},
+		{fmt.Errorf("failed to list tasks: %w", awserr.New("UnauthorizedOperation", "not authorized", nil)), errClassAccessDenied},
+		{awserr.New("ParameterNotFound", "", nil), errClassNotFound},
+		{awserr.New("ClusterNotFoundException", "cluster not found", nil), errClassNotFound},
+		{awserr.New("InvalidInstanceID.NotFound", "", nil), errClassNotFound},
+		{awserr.New(request.ErrCodeRequestError, "send request failed", &net.DNSError{IsTimeout: true}), errClassTimeout},
+		{awserr.New("RequestTimeout", "", nil), errClassTimeout},
+		{awserr.New(request.ErrCodeRequestError, "send request failed", errors.New("connection reset")), errClassOther},
+		{awserr.New("InvalidParameterException", "bad input", nil), errClassOther},
+		{errors.New("refused in read-only mode"), errClassOther},
+	}
+	for _, tc := range cases {
+		assert.Equal(t, tc.expected, classifyAWSError(tc.err), tc.err.Error())
+	}
+}
@@ -0,0 +1,69 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"regexp"
+	"strconv"
+	"strings"
+	"time"
+)
+
+// instanceCountPattern matches a number of instances, or a percentage of
+// them, as accepted by -max-unavailable and -waves.
+var instanceCountPattern = regexp.MustCompile(`^([1-9][0-9]*|[1-9][0-9]?%|100%)$`)
+
+// instanceCount returns the number of instances out of total that spec, a
+// number or a percentage such as 10%, stands for. Percentages are rounded
+// down, but the count is at least 1, since the updater can't update an
+// instance without taking it out of service.
+func instanceCount(spec string, total int) int {
+	var count int
+	if strings.HasSuffix(spec, "%") {
+		percent, _ := strconv.Atoi(strings.TrimSuffix(spec, "%"))
+		count = total * percent / 100
+	} else {
+		count, _ = strconv.Atoi(spec)
+	}
+	if count < 1 {
+		return 1
+	}
+	return count
+}
+
+// waitForBudget waits until the instance can be drained without more of the
+// cluster's container instances being unavailable at once than -max-unavailable
+// allows. Instances are unavailable when clusterHealth reports them unhealthy:
+// draining, e.g. by other automation, registering, or with their ECS agent
+// disconnected, e.g. while rebooting. It returns an error when the budget stays
+// exhausted for as long as a drain may take.
+func (u *updater) waitForBudget(inst instance) error {
+	if u.maxUnavailable == "" {
+		return nil
+	}
+	for attempt := 1; ; attempt++ {
+		health, err := u.clusterHealth()
+		if err != nil {
+			return fmt.Errorf("cannot determine the unavailable container instances: %w", err)
+		}
+		unavailable := 0
+		for _, failure := range health.unhealthy {
+			if failure.arn != inst.containerInstanceID {
+				unavailable++
+			}
+		}
+		limit := instanceCount(u.maxUnavailable, health.total)
+		if unavailable < limit {
+			return nil
+		}
+		if attempt >= waiterMaxAttempts || u.stopping() {
+			return fmt.Errorf("%d of %d container instance(s) are still unavailable after %s, and -max-unavailable %s allows %d",
+				unavailable, health.total, waiterDelay*time.Duration(attempt-1), u.maxUnavailable, limit)
+		}
+		log.Printf("%d of %d container instance(s) are unavailable and -max-unavailable %s allows %d, therefore waiting to drain instance %#q",
+			unavailable, health.total, u.maxUnavailable, limit, inst)
+		if err := sleepContext(u.shutdownContext(), waiterDelay); err != nil {
+			return err
+		}
+	}
+}
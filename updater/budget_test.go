@@ -0,0 +1,138 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestInstanceCount(t *testing.T) {
+	cases := []struct {
+		spec     string
+		total    int
+		expected int
+	}{
+		{spec: "1", total: 10, expected: 1},
+		{spec: "3", total: 10, expected: 3},
+		{spec: "3", total: 2, expected: 3},
+		{spec: "10%", total: 10, expected: 1},
+		{spec: "25%", total: 10, expected: 2},
+		{spec: "100%", total: 10, expected: 10},
+		{spec: "10%", total: 5, expected: 1},
+		{spec: "50%", total: 0, expected: 1},
+	}
+	for _, tc := range cases {
+		assert.Equal(t, tc.expected, instanceCount(tc.spec, tc.total), "%s of %d", tc.spec, tc.total)
+	}
+	for _, spec := range []string{"1", "25", "1%", "50%", "99%", "100%"} {
+		assert.True(t, instanceCountPattern.MatchString(spec), spec)
+	}
+	for _, spec := range []string{"0", "0%", "05", "101%", "-1", "1.5", "%", "ten"} {
+		assert.False(t, instanceCountPattern.MatchString(spec), spec)
+	}
+}
+
+func TestWaitForBudget(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = func(time.Duration) {}
+
+	active := func(arn string) *ecs.ContainerInstance {
+		return &ecs.ContainerInstance{ContainerInstanceArn: aws.String(arn), Status: aws.String(ecs.ContainerInstanceStatusActive), AgentConnected: aws.Bool(true)}
+	}
+	draining := func(arn string) *ecs.ContainerInstance {
+		return &ecs.ContainerInstance{ContainerInstanceArn: aws.String(arn), Status: aws.String(ecs.ContainerInstanceStatusDraining), AgentConnected: aws.Bool(true)}
+	}
+	inst := instance{instanceID: "i-1", containerInstanceID: "arn-1"}
+
+	cases := []struct {
+		name          string
+		budget        string
+		responses     [][]*ecs.ContainerInstance
+		describeErr   error
+		expectedCalls int
+		expectedError string
+	}{
+		{
+			name:          "disabled",
+			responses:     [][]*ecs.ContainerInstance{{draining("arn-2"), draining("arn-3")}},
+			expectedCalls: 0,
+		},
+		{
+			name:          "within budget",
+			budget:        "2",
+			responses:     [][]*ecs.ContainerInstance{{active("arn-1"), draining("arn-2"), active("arn-3"), active("arn-4")}},
+			expectedCalls: 1,
+		},
+		{
+			name:          "instance itself not counted",
+			budget:        "1",
+			responses:     [][]*ecs.ContainerInstance{{draining("arn-1"), active("arn-2"), active("arn-3"), active("arn-4")}},
+			expectedCalls: 1,
+		},
+		{
+			name:   "waits for recovery",
+			budget: "50%",
+			responses: [][]*ecs.ContainerInstance{
+				{active("arn-1"), draining("arn-2"), draining("arn-3"), active("arn-4")},
+				{active("arn-1"), active("arn-2"), draining("arn-3"), active("arn-4")},
+			},
+			expectedCalls: 2,
+		},
+		{
+			name:          "budget exhausted",
+			budget:        "25%",
+			responses:     [][]*ecs.ContainerInstance{{active("arn-1"), draining("arn-2"), active("arn-3"), active("arn-4")}},
+			expectedCalls: waiterMaxAttempts,
+			expectedError: "1 of 4 container instance(s) are still unavailable",
+		},
+		{
+			name:          "describe fails",
+			budget:        "1",
+			describeErr:   errors.New("failed to describe"),
+			expectedCalls: 1,
+			expectedError: "cannot determine the unavailable container instances",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			calls := 0
+			mockECS := MockECS{
+				ListContainerInstancesPagesFn: func(_ *ecs.ListContainerInstancesInput, fn func(*ecs.ListContainerInstancesOutput, bool) bool) error {
+					fn(&ecs.ListContainerInstancesOutput{
+						ContainerInstanceArns: aws.StringSlice([]string{"arn-1", "arn-2", "arn-3", "arn-4"}),
+					}, true)
+					return nil
+				},
+				DescribeContainerInstancesFn: func(_ *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+					calls++
+					if tc.describeErr != nil {
+						return nil, tc.describeErr
+					}
+					response := tc.responses[len(tc.responses)-1]
+					if calls <= len(tc.responses) {
+						response = tc.responses[calls-1]
+					}
+					return &ecs.DescribeContainerInstancesOutput{ContainerInstances: response}, nil
+				},
+			}
+			u := updater{ecs: mockECS, cluster: "cluster", maxUnavailable: tc.budget}
+			err := u.waitForBudget(inst)
+			assert.Equal(t, tc.expectedCalls, calls)
+			if tc.expectedError == "" {
+				assert.NoError(t, err)
+				return
+			}
+			require.Error(t, err)
+			assert.Contains(t, err.Error(), tc.expectedError)
+			if tc.describeErr != nil {
+				assert.ErrorIs(t, err, tc.describeErr)
+			}
+		})
+	}
+}
@@ -0,0 +1,256 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"regexp"
+	"sort"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+const (
+	// sendAttempts is how many times a command is sent before giving up.
+	sendAttempts = 3
+	// sendRetryDelay is the delay before the first resend; it doubles with each attempt.
+	sendRetryDelay = 2 * time.Second
+	// commandLookupSkew allows for clock skew between the updater and SSM when
+	// looking for commands sent by a failed attempt. It is kept short so that an
+	// earlier command of the pass with the same document and instances, such as
+	// the update check before a reboot, is not mistaken for the lost one.
+	commandLookupSkew = 10 * time.Second
+)
+
+// ssmMaxConcurrencyPattern and ssmMaxErrorsPattern match the rate controls SSM
+// accepts for commands.
+var (
+	ssmMaxConcurrencyPattern = regexp.MustCompile(`^([1-9][0-9]*|[1-9][0-9]?%|100%)$`)
+	ssmMaxErrorsPattern      = regexp.MustCompile(`^([1-9][0-9]*|0|[0-9]%|[1-9][0-9]%|100%)$`)
+)
+
+// commandChannel runs documents on instances and reports their outcome. The
+// updater only reaches instances through a command channel, so transports
+// other than SSM Run Command can be plugged in without changing the update logic.
+type commandChannel interface {
+	// send starts the document on the instances and returns the command ID
+	// without waiting for the command to finish.
+	send(instanceIDs []string, document string, parameters map[string][]string) (string, error)
+	// wait blocks until the pending commands finish. pending maps each command
+	// ID to the instances the command was sent to. The result holds an error
+	// for every invocation that did not succeed.
+	wait(pending map[string][]string) map[invocation]error
+	// output returns the standard output of a command that succeeded on the instance.
+	output(commandID string, instanceID string) ([]byte, error)
+	// describe returns a description of the command's execution on the
+	// instance, to be logged when the command failed.
+	describe(commandID string, instanceID string) (string, error)
+}
+
+// channel returns the channel used to run commands on instances, which is SSM
+// Run Command unless another channel was configured.
+func (u *updater) channel() commandChannel {
+	if u.commands != nil {
+		return u.commands
+	}
+	return ssmChannel{
+		ssm:            u.ssm,
+		comment:        u.commandComment(),
+		poller:         u.commandPoller(),
+		warnings:       u.warnings,
+		maxConcurrency: u.ssmMaxConcurrency,
+		maxErrors:      u.ssmMaxErrors,
+	}
+}
+
+// ssmChannel runs documents with SSM Run Command.
+type ssmChannel struct {
+	ssm SSMAPI
+	// comment is attached to every command to attribute it to the updater pass.
+	comment  string
+	poller   commandPoller
+	warnings *passWarnings
+	// maxConcurrency and maxErrors are the rate controls of every command,
+	// e.g. "10" or "20%"; SSM's defaults apply when they are empty.
+	maxConcurrency string
+	maxErrors      string
+}
+
+var _ commandChannel = ssmChannel{}
+
+// send sends the command, retrying failed attempts. SendCommand has no client
+// token, and an attempt that fails in transit may still have created the
+// command, so the SDK does not retry it (see sendCommandRetryer). Instead,
+// before each resend, send looks for a command created by an earlier attempt
+// and returns it rather than running the document twice.
+func (c ssmChannel) send(instanceIDs []string, document string, parameters map[string][]string) (string, error) {
+	input := &ssm.SendCommandInput{
+		DocumentName:    aws.String(document),
+		DocumentVersion: aws.String("$DEFAULT"),
+		InstanceIds:     aws.StringSlice(instanceIDs),
+		TimeoutSeconds:  aws.Int64(deliveryTimeoutSeconds),
+		Comment:         aws.String(c.comment),
+	}
+	if c.maxConcurrency != "" {
+		input.MaxConcurrency = aws.String(c.maxConcurrency)
+	}
+	if c.maxErrors != "" {
+		input.MaxErrors = aws.String(c.maxErrors)
+	}
+	if len(parameters) > 0 {
+		input.Parameters = make(map[string][]*string, len(parameters))
+		for name, values := range parameters {
+			input.Parameters[name] = aws.StringSlice(values)
+		}
+	}
+	started := time.Now()
+	delay := sendRetryDelay
+	var err error
+	for attempt := 1; ; attempt++ {
+		var resp *ssm.SendCommandOutput
+		resp, err = c.ssm.SendCommand(input)
+		if err == nil {
+			return aws.StringValue(resp.Command.CommandId), nil
+		}
+		if attempt == sendAttempts || !isRetryable(err) {
+			break
+		}
+		sleep(delay)
+		delay *= 2
+		commandID, lookupErr := c.findSentCommand(input, started.Add(-commandLookupSkew))
+		if lookupErr != nil {
+			// Without knowing whether the command was sent, resending could run it twice.
+			return "", fmt.Errorf("%v; failed to check whether the command was sent anyway: %w", err, lookupErr)
+		}
+		if commandID != "" {
+			log.Printf("Sending document %q failed with %v, but command %q was created; using it", document, err, commandID)
+			c.warnings.add(warnRetried, "sending document %q failed, but command %q was created: %v", document, commandID, err)
+			return commandID, nil
+		}
+		log.Printf("Sending document %q failed, retrying: %v", document, err)
+		c.warnings.add(warnRetried, "sending document %q failed: %v", document, err)
+	}
+	return "", err
+}
+
+// findSentCommand returns the ID of a command sent since the given time that
+// matches the input's document, instances and comment. The comment identifies
+// the updater pass, so a match was sent by this pass. It returns an empty ID
+// when there is no such command.
+func (c ssmChannel) findSentCommand(input *ssm.SendCommandInput, since time.Time) (string, error) {
+	var commandID string
+	err := c.ssm.ListCommandsPages(&ssm.ListCommandsInput{
+		Filters: []*ssm.CommandFilter{
+			{Key: aws.String(ssm.CommandFilterKeyDocumentName), Value: input.DocumentName},
+			{Key: aws.String(ssm.CommandFilterKeyInvokedAfter), Value: aws.String(since.UTC().Format(time.RFC3339))},
+		},
+	}, func(page *ssm.ListCommandsOutput, _ bool) bool {
+		for _, command := range page.Commands {
+			if aws.StringValue(command.Comment) == aws.StringValue(input.Comment) &&
+				sameInstances(command.InstanceIds, input.InstanceIds) {
+				commandID = aws.StringValue(command.CommandId)
+				return false
+			}
+		}
+		return true
+	})
+	if err != nil {
+		return "", err
+	}
+	return commandID, nil
+}
+
+// sameInstances reports whether both lists hold the same instance IDs.
+func sameInstances(a []*string, b []*string) bool {
+	if len(a) != len(b) {
+		return false
+	}
+	ids := make(map[string]int)
+	for _, id := range a {
+		ids[aws.StringValue(id)]++
+	}
+	for _, id := range b {
+		ids[aws.StringValue(id)]--
+		if ids[aws.StringValue(id)] < 0 {
+			return false
+		}
+	}
+	return true
+}
+
+// isRetryable reports whether a failed request may succeed if it is retried.
+func isRetryable(err error) bool {
+	return request.IsErrorRetryable(err) || request.IsErrorThrottle(err)
+}
+
+// wait waits for the invocations of the pending commands, and logs the
+// aggregate status of commands sent with rate controls, which SSM stops once
+// they exceed maxErrors.
+func (c ssmChannel) wait(pending map[string][]string) map[invocation]error {
+	failed := c.poller.wait(pending)
+	if c.maxConcurrency != "" || c.maxErrors != "" {
+		commandIDs := make([]string, 0, len(pending))
+		for commandID := range pending {
+			commandIDs = append(commandIDs, commandID)
+		}
+		sort.Strings(commandIDs)
+		for _, commandID := range commandIDs {
+			c.logCommandStatus(commandID)
+		}
+	}
+	return failed
+}
+
+// logCommandStatus logs the aggregate status of a command, as ListCommands
+// reports it.
+func (c ssmChannel) logCommandStatus(commandID string) {
+	var command *ssm.Command
+	err := c.ssm.ListCommandsPages(&ssm.ListCommandsInput{CommandId: aws.String(commandID)}, func(page *ssm.ListCommandsOutput, _ bool) bool {
+		for _, cmd := range page.Commands {
+			if aws.StringValue(cmd.CommandId) == commandID {
+				command = cmd
+				return false
+			}
+		}
+		return true
+	})
+	if err != nil {
+		log.Printf("Failed to get the status of command %q: %v", commandID, err)
+		return
+	}
+	if command == nil || command.Status == nil {
+		return
+	}
+	log.Printf("Command %q finished with status %s: %d of %d invocation(s) completed, %d failed, %d timed out (max concurrency %s, max errors %s)",
+		commandID, aws.StringValue(command.Status), aws.Int64Value(command.CompletedCount), aws.Int64Value(command.TargetCount),
+		aws.Int64Value(command.ErrorCount), aws.Int64Value(command.DeliveryTimedOutCount),
+		aws.StringValue(command.MaxConcurrency), aws.StringValue(command.MaxErrors))
+}
+
+func (c ssmChannel) output(commandID string, instanceID string) ([]byte, error) {
+	resp, err := c.ssm.GetCommandInvocation(&ssm.GetCommandInvocationInput{
+		CommandId:  aws.String(commandID),
+		InstanceId: aws.String(instanceID),
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to retrieve command invocation output: %w", err)
+	}
+	commandResults := []byte(aws.StringValue(resp.StandardOutputContent))
+	if aws.StringValue(resp.Status) != ssm.CommandInvocationStatusSuccess {
+		return nil, fmt.Errorf("command %s has not reached success status, current status %q", commandID, aws.StringValue(resp.Status))
+	}
+	return commandResults, nil
+}
+
+func (c ssmChannel) describe(commandID string, instanceID string) (string, error) {
+	resp, err := c.ssm.GetCommandInvocation(&ssm.GetCommandInvocationInput{
+		CommandId:  aws.String(commandID),
+		InstanceId: aws.String(instanceID),
+	})
+	if err != nil {
+		return "", err
+	}
+	return fmt.Sprintf("%#q", resp), nil
+}
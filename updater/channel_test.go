@@ -0,0 +1,250 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+// fakeChannel is a commandChannel that records the documents it was asked to
+// run and returns canned outputs.
+type fakeChannel struct {
+	sent    []string
+	failed  map[string]error
+	outputs map[string]string
+	// invalid are the instances sends are rejected for as invalid targets.
+	invalid map[string]bool
+	targets [][]string
+}
+
+var _ commandChannel = (*fakeChannel)(nil)
+
+func (c *fakeChannel) send(instanceIDs []string, document string, _ map[string][]string) (string, error) {
+	c.targets = append(c.targets, instanceIDs)
+	for _, instanceID := range instanceIDs {
+		if c.invalid[instanceID] {
+			return "", awserr.New(ssm.ErrCodeInvalidInstanceId, fmt.Sprintf("Instances [[%s]] not in a valid state", instanceID), nil)
+		}
+	}
+	c.sent = append(c.sent, document)
+	return document, nil
+}
+
+func (c *fakeChannel) wait(pending map[string][]string) map[invocation]error {
+	failed := make(map[invocation]error)
+	for commandID, instanceIDs := range pending {
+		for _, instanceID := range instanceIDs {
+			if err, ok := c.failed[instanceID]; ok {
+				failed[invocation{commandID, instanceID}] = err
+			}
+		}
+	}
+	return failed
+}
+
+func (c *fakeChannel) output(commandID string, _ string) ([]byte, error) {
+	return []byte(c.outputs[commandID]), nil
+}
+
+func (c *fakeChannel) describe(_ string, _ string) (string, error) {
+	return "", nil
+}
+
+func TestCommandChannel(t *testing.T) {
+	t.Run("verify update", func(t *testing.T) {
+		channel := &fakeChannel{outputs: map[string]string{
+			"check-document": `{"update_state": "Idle", "active_partition": {"image": {"version": "1.12.0"}}}`,
+		}}
+		u := updater{commands: channel, checkDocument: "check-document"}
+		version, ok, err := u.verifyUpdate(instance{instanceID: "inst-id-1", bottlerocketVersion: "1.11.0"})
+		require.NoError(t, err)
+		assert.True(t, ok)
+		assert.Equal(t, "1.12.0", version)
+		assert.Equal(t, []string{"check-document"}, channel.sent)
+	})
+	t.Run("all instances fail", func(t *testing.T) {
+		waitErr := errors.New("unreachable")
+		channel := &fakeChannel{failed: map[string]error{"inst-id-1": waitErr, "inst-id-2": waitErr}}
+		u := updater{commands: channel}
+		_, err := u.sendCommand([]string{"inst-id-1", "inst-id-2"}, "check-document")
+		require.Error(t, err)
+		assert.ErrorIs(t, err, waitErr)
+	})
+	t.Run("some instances fail", func(t *testing.T) {
+		channel := &fakeChannel{failed: map[string]error{"inst-id-1": errors.New("unreachable")}}
+		u := updater{commands: channel}
+		commandID, err := u.sendCommand([]string{"inst-id-1", "inst-id-2"}, "check-document")
+		require.NoError(t, err)
+		assert.Equal(t, "check-document", commandID)
+	})
+	t.Run("invalid instances", func(t *testing.T) {
+		channel := &fakeChannel{invalid: map[string]bool{"inst-id-1": true, "inst-id-10": true}}
+		u := updater{commands: channel, warnings: newPassWarnings()}
+		commandID, invalid, err := u.sendCommandExcludingInvalid([]string{"inst-id-1", "inst-id-2", "inst-id-10"}, "check-document", nil)
+		require.NoError(t, err)
+		assert.Equal(t, "check-document", commandID)
+		assert.Equal(t, []string{"inst-id-1", "inst-id-10"}, invalid)
+		assert.Equal(t, [][]string{
+			{"inst-id-1", "inst-id-2", "inst-id-10"},
+			{"inst-id-2", "inst-id-10"},
+			{"inst-id-2"},
+		}, channel.targets)
+		assert.Equal(t, 2, u.warnings.count(warnSkipped))
+	})
+	t.Run("all instances invalid", func(t *testing.T) {
+		channel := &fakeChannel{invalid: map[string]bool{"inst-id-1": true}}
+		u := updater{commands: channel}
+		_, invalid, err := u.sendCommandExcludingInvalid([]string{"inst-id-1"}, "check-document", nil)
+		require.Error(t, err)
+		assert.Equal(t, []string{"inst-id-1"}, invalid)
+	})
+}
+
+func TestInvalidInstanceIDs(t *testing.T) {
+	instanceIDs := []string{"i-0123456789abcdef0", "i-0123456789abcdef1"}
+	err := awserr.New(ssm.ErrCodeInvalidInstanceId, "Instances [[i-0123456789abcdef1]] not in a valid state for account 123456789012", nil)
+	assert.Equal(t, []string{"i-0123456789abcdef1"}, invalidInstanceIDs(fmt.Errorf("wrapped: %w", err), instanceIDs))
+	assert.Empty(t, invalidInstanceIDs(awserr.New(ssm.ErrCodeInvalidInstanceId, "invalid instance", nil), instanceIDs))
+	assert.Empty(t, invalidInstanceIDs(errors.New("Instances [[i-0123456789abcdef1]] not in a valid state"), instanceIDs))
+}
+
+func TestSSMChannelSendRetry(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = func(time.Duration) {}
+
+	instances := []string{"inst-id-1", "inst-id-2"}
+	throttled := awserr.New("ThrottlingException", "rate exceeded", nil)
+	sentCommand := func(comment string, instanceIDs ...string) *ssm.ListCommandsOutput {
+		return &ssm.ListCommandsOutput{Commands: []*ssm.Command{{
+			CommandId:   aws.String("sent-command-id"),
+			Comment:     aws.String(comment),
+			InstanceIds: aws.StringSlice(instanceIDs),
+		}}}
+	}
+
+	t.Run("lost response", func(t *testing.T) {
+		sends := 0
+		channel := ssmChannel{comment: "pass=pass-id", ssm: MockSSM{
+			SendCommandFn: func(_ *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+				sends++
+				return nil, throttled
+			},
+			ListCommandsPagesFn: func(input *ssm.ListCommandsInput, fn func(*ssm.ListCommandsOutput, bool) bool) error {
+				assert.Equal(t, ssm.CommandFilterKeyDocumentName, aws.StringValue(input.Filters[0].Key))
+				assert.Equal(t, "test-doc", aws.StringValue(input.Filters[0].Value))
+				fn(sentCommand("pass=pass-id", "inst-id-2", "inst-id-1"), true)
+				return nil
+			},
+		}}
+		commandID, err := channel.send(instances, "test-doc", nil)
+		require.NoError(t, err)
+		assert.Equal(t, "sent-command-id", commandID)
+		assert.Equal(t, 1, sends, "should not resend a command that was created")
+	})
+	t.Run("resend", func(t *testing.T) {
+		sends := 0
+		channel := ssmChannel{comment: "pass=pass-id", ssm: MockSSM{
+			SendCommandFn: func(_ *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+				sends++
+				if sends == 1 {
+					return nil, throttled
+				}
+				return &ssm.SendCommandOutput{Command: &ssm.Command{CommandId: aws.String("command-id")}}, nil
+			},
+			ListCommandsPagesFn: func(_ *ssm.ListCommandsInput, fn func(*ssm.ListCommandsOutput, bool) bool) error {
+				// Commands of other passes or instances are not the lost command.
+				fn(sentCommand("pass=other-pass", instances...), false)
+				fn(sentCommand("pass=pass-id", "inst-id-1"), true)
+				return nil
+			},
+		}}
+		channel.warnings = newPassWarnings()
+		commandID, err := channel.send(instances, "test-doc", nil)
+		require.NoError(t, err)
+		assert.Equal(t, "command-id", commandID)
+		assert.Equal(t, 2, sends)
+		assert.Equal(t, 1, channel.warnings.count(warnRetried))
+	})
+	t.Run("gives up", func(t *testing.T) {
+		sends := 0
+		channel := ssmChannel{ssm: MockSSM{
+			SendCommandFn: func(_ *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+				sends++
+				return nil, throttled
+			},
+			ListCommandsPagesFn: func(_ *ssm.ListCommandsInput, fn func(*ssm.ListCommandsOutput, bool) bool) error {
+				fn(&ssm.ListCommandsOutput{}, true)
+				return nil
+			},
+		}}
+		_, err := channel.send(instances, "test-doc", nil)
+		assert.ErrorIs(t, err, throttled)
+		assert.Equal(t, sendAttempts, sends)
+	})
+	t.Run("lookup fails", func(t *testing.T) {
+		sends := 0
+		lookupErr := errors.New("access denied")
+		channel := ssmChannel{ssm: MockSSM{
+			SendCommandFn: func(_ *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+				sends++
+				return nil, throttled
+			},
+			ListCommandsPagesFn: func(_ *ssm.ListCommandsInput, _ func(*ssm.ListCommandsOutput, bool) bool) error {
+				return lookupErr
+			},
+		}}
+		_, err := channel.send(instances, "test-doc", nil)
+		assert.ErrorIs(t, err, lookupErr)
+		assert.Equal(t, 1, sends, "should not resend without knowing whether the command was created")
+	})
+}
+
+func TestSSMChannelRateControls(t *testing.T) {
+	for _, valid := range []string{"1", "50", "1%", "20%", "100%"} {
+		assert.True(t, ssmMaxConcurrencyPattern.MatchString(valid), valid)
+	}
+	for _, invalid := range []string{"0", "0%", "101%", "10 %", "-1"} {
+		assert.False(t, ssmMaxConcurrencyPattern.MatchString(invalid), invalid)
+	}
+	for _, valid := range []string{"0", "5", "0%", "10%", "100%"} {
+		assert.True(t, ssmMaxErrorsPattern.MatchString(valid), valid)
+	}
+	assert.False(t, ssmMaxErrorsPattern.MatchString("101%"))
+
+	var listed []string
+	instances := []string{"inst-id-1", "inst-id-2"}
+	mockSSM := MockSSM{
+		SendCommandFn: func(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+			assert.Equal(t, "10", aws.StringValue(input.MaxConcurrency))
+			assert.Equal(t, "5%", aws.StringValue(input.MaxErrors))
+			return &ssm.SendCommandOutput{Command: &ssm.Command{CommandId: aws.String("command-id")}}, nil
+		},
+		ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+			fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, instances...), true)
+			return nil
+		},
+		ListCommandsPagesFn: func(input *ssm.ListCommandsInput, fn func(*ssm.ListCommandsOutput, bool) bool) error {
+			listed = append(listed, aws.StringValue(input.CommandId))
+			fn(&ssm.ListCommandsOutput{Commands: []*ssm.Command{{
+				CommandId:      input.CommandId,
+				Status:         aws.String(ssm.CommandStatusSuccess),
+				TargetCount:    aws.Int64(2),
+				CompletedCount: aws.Int64(2),
+			}}}, true)
+			return nil
+		},
+	}
+	channel := ssmChannel{ssm: mockSSM, poller: commandPoller{ssm: mockSSM, maxPolls: 1}, maxConcurrency: "10", maxErrors: "5%"}
+	commandID, err := channel.send(instances, "test-doc", nil)
+	require.NoError(t, err)
+	assert.Empty(t, channel.wait(map[string][]string{commandID: instances}))
+	assert.Equal(t, []string{"command-id"}, listed, "should get the aggregate status of the command")
+}
@@ -0,0 +1,137 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+// tagChecked records on a container instance the version its last update
+// check found it running with no update available, and when, e.g.
+// "1.20.0@2024-05-01T12:00:00Z".
+const tagChecked = "bottlerocket-ecs-updater:checked"
+
+// checkedVersion is the result of an update check cached in tagChecked.
+type checkedVersion struct {
+	version string
+	at      time.Time
+}
+
+// checkedFromTags reads the cached update check from the tags of a container
+// instance; ok is false when there is none or it is invalid.
+func checkedFromTags(tags map[string]string) (checkedVersion, bool) {
+	value, ok := tags[tagChecked]
+	if !ok {
+		return checkedVersion{}, false
+	}
+	version, at, ok := strings.Cut(value, "@")
+	if !ok || version == "" {
+		return checkedVersion{}, false
+	}
+	t, err := time.Parse(time.RFC3339, at)
+	if err != nil {
+		return checkedVersion{}, false
+	}
+	return checkedVersion{version: version, at: t}, true
+}
+
+func (c checkedVersion) tagValue() string {
+	return c.version + "@" + c.at.UTC().Format(time.RFC3339)
+}
+
+// latestKey identifies the releases of a variant for an architecture.
+func latestKey(inst instance) string {
+	return inst.variant + "/" + inst.arch
+}
+
+// latestVersionParameter returns the name of the public SSM parameter holding
+// the latest version of the variant for the architecture.
+func latestVersionParameter(variant, arch string) string {
+	return fmt.Sprintf("/aws/service/bottlerocket/%s/%s/latest/image_version", variant, arch)
+}
+
+// latestVersions returns the latest published version of each variant and
+// architecture the instances run, keyed by latestKey. Versions that can't be
+// read are left out, so that the instances running them are checked.
+func (u *updater) latestVersions(instances []instance) map[string]string {
+	latest := make(map[string]string)
+	failed := make(map[string]bool)
+	for _, inst := range instances {
+		key := latestKey(inst)
+		if inst.arch == "" || latest[key] != "" || failed[key] {
+			continue
+		}
+		name := latestVersionParameter(inst.variant, inst.arch)
+		resp, err := u.ssm.GetParameter(&ssm.GetParameterInput{Name: aws.String(name)})
+		if err == nil && (resp.Parameter == nil || aws.StringValue(resp.Parameter.Value) == "") {
+			err = fmt.Errorf("parameter %q has no value", name)
+		}
+		if err != nil {
+			log.Printf("Failed to read the latest version of %s, therefore checking its instances: %v", key, err)
+			u.warnings.add(warnDegraded, "latest version of %s is unknown: %v", key, err)
+			failed[key] = true
+			continue
+		}
+		// The parameter holds the version and the commit of the image, e.g.
+		// 1.20.0-fcf15d3a.
+		version, _, _ := strings.Cut(strings.TrimPrefix(aws.StringValue(resp.Parameter.Value), "v"), "-")
+		latest[key] = version
+	}
+	return latest
+}
+
+// applyCheckCache marks the instances whose last update check found them
+// running the latest version of their variant less than checkCacheTTL before
+// now, so that filterAvailableUpdates doesn't check them again. It records
+// the cached version as their version.
+func (u *updater) applyCheckCache(instances []instance, latest map[string]string, now time.Time) {
+	cached := make(map[string]int)
+	for i, inst := range instances {
+		checked, ok := checkedFromTags(inst.tags)
+		if !ok || now.Sub(checked.at) >= u.checkCacheTTL || checked.version != latest[latestKey(inst)] {
+			continue
+		}
+		instances[i].bottlerocketVersion = checked.version
+		instances[i].cachedCheck = true
+		cached[checked.version]++
+	}
+	versions := make([]string, 0, len(cached))
+	for version := range cached {
+		versions = append(versions, version)
+	}
+	sort.Strings(versions)
+	for _, version := range versions {
+		log.Printf("Not checking %d instance(s) for updates, which ran the latest version %s when checked within %s", cached[version], version, u.checkCacheTTL)
+	}
+}
+
+// recordCheckCache caches the update checks of the pass that found instances
+// running the latest version of their variant with no update available, in
+// tags on their container instances.
+func (u *updater) recordCheckCache(instances []instance, candidates []instance, latest map[string]string, now time.Time) {
+	available := make(map[string]bool, len(candidates))
+	for _, inst := range candidates {
+		available[inst.instanceID] = true
+	}
+	for _, inst := range instances {
+		if inst.cachedCheck || inst.bottlerocketVersion == "" || available[inst.instanceID] ||
+			inst.bottlerocketVersion != latest[latestKey(inst)] {
+			continue
+		}
+		checked := checkedVersion{version: inst.bottlerocketVersion, at: now}
+		_, err := u.ecs.TagResource(&ecs.TagResourceInput{
+			ResourceArn: aws.String(inst.containerInstanceID),
+			Tags:        []*ecs.Tag{{Key: aws.String(tagChecked), Value: aws.String(checked.tagValue())}},
+		})
+		if err != nil {
+			log.Printf("Failed to cache the update check of instance %#q: %v", inst, err)
+			u.warnings.add(warnDegraded, "update check of instance %q was not cached: %v", inst.instanceID, err)
+		}
+	}
+}
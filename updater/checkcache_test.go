@@ -0,0 +1,93 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+)
+
+func TestCheckedFromTags(t *testing.T) {
+	at := time.Date(2024, 5, 1, 12, 0, 0, 0, time.UTC)
+	checked, ok := checkedFromTags(map[string]string{tagChecked: "1.20.0@2024-05-01T12:00:00Z"})
+	assert.True(t, ok)
+	assert.Equal(t, checkedVersion{version: "1.20.0", at: at}, checked)
+	assert.Equal(t, "1.20.0@2024-05-01T12:00:00Z", checked.tagValue())
+
+	for _, value := range []string{"1.20.0", "@2024-05-01T12:00:00Z", "1.20.0@yesterday"} {
+		_, ok = checkedFromTags(map[string]string{tagChecked: value})
+		assert.False(t, ok, value)
+	}
+	_, ok = checkedFromTags(nil)
+	assert.False(t, ok)
+}
+
+func TestLatestVersions(t *testing.T) {
+	var names []string
+	u := updater{warnings: newPassWarnings(), ssm: MockSSM{
+		GetParameterFn: func(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error) {
+			names = append(names, aws.StringValue(input.Name))
+			if aws.StringValue(input.Name) == latestVersionParameter("aws-ecs-1", "x86_64") {
+				return nil, errors.New("failed to get parameter")
+			}
+			return &ssm.GetParameterOutput{Parameter: &ssm.Parameter{Value: aws.String("1.20.0-fcf15d3a")}}, nil
+		},
+	}}
+	latest := u.latestVersions([]instance{
+		{instanceID: "inst-id-1", variant: "aws-ecs-2", arch: "x86_64"},
+		{instanceID: "inst-id-2", variant: "aws-ecs-2", arch: "x86_64"},
+		{instanceID: "inst-id-3", variant: "aws-ecs-2", arch: "arm64"},
+		{instanceID: "inst-id-4", variant: "aws-ecs-1", arch: "x86_64"},
+		{instanceID: "inst-id-5", variant: "aws-ecs-1", arch: "x86_64"},
+		{instanceID: "inst-id-6", variant: "aws-ecs-1"},
+	})
+	assert.Equal(t, map[string]string{"aws-ecs-2/x86_64": "1.20.0", "aws-ecs-2/arm64": "1.20.0"}, latest)
+	assert.Equal(t, []string{
+		"/aws/service/bottlerocket/aws-ecs-2/x86_64/latest/image_version",
+		"/aws/service/bottlerocket/aws-ecs-2/arm64/latest/image_version",
+		"/aws/service/bottlerocket/aws-ecs-1/x86_64/latest/image_version",
+	}, names, "should read each version once")
+	assert.Equal(t, 1, u.warnings.counts[warnDegraded])
+}
+
+func TestCheckCache(t *testing.T) {
+	now := time.Date(2024, 5, 1, 12, 0, 0, 0, time.UTC)
+	recent := checkedVersion{version: "1.20.0", at: now.Add(-time.Hour)}.tagValue()
+	expired := checkedVersion{version: "1.20.0", at: now.Add(-7 * time.Hour)}.tagValue()
+	outdated := checkedVersion{version: "1.19.0", at: now.Add(-time.Hour)}.tagValue()
+	instances := []instance{
+		{instanceID: "inst-id-1", containerInstanceID: "cont-inst-1", variant: "aws-ecs-2", arch: "x86_64", tags: map[string]string{tagChecked: recent}},
+		{instanceID: "inst-id-2", containerInstanceID: "cont-inst-2", variant: "aws-ecs-2", arch: "x86_64", tags: map[string]string{tagChecked: expired}},
+		{instanceID: "inst-id-3", containerInstanceID: "cont-inst-3", variant: "aws-ecs-2", arch: "x86_64", tags: map[string]string{tagChecked: outdated}},
+		{instanceID: "inst-id-4", containerInstanceID: "cont-inst-4", variant: "aws-ecs-2", arch: "x86_64"},
+	}
+	latest := map[string]string{"aws-ecs-2/x86_64": "1.20.0"}
+	var tagged []string
+	u := updater{checkCacheTTL: 6 * time.Hour, warnings: newPassWarnings(), ecs: MockECS{
+		TagResourceFn: func(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+			assert.Equal(t, tagChecked, aws.StringValue(input.Tags[0].Key))
+			assert.Equal(t, "1.20.0@2024-05-01T12:00:00Z", aws.StringValue(input.Tags[0].Value))
+			tagged = append(tagged, aws.StringValue(input.ResourceArn))
+			return &ecs.TagResourceOutput{}, nil
+		},
+	}}
+
+	u.applyCheckCache(instances, latest, now)
+	assert.True(t, instances[0].cachedCheck)
+	assert.Equal(t, "1.20.0", instances[0].bottlerocketVersion)
+	assert.False(t, instances[1].cachedCheck, "expired checks should not be used")
+	assert.False(t, instances[2].cachedCheck, "checks of versions that are not the latest should not be used")
+	assert.False(t, instances[3].cachedCheck)
+
+	// the pass checks the other instances; inst-id-3 has an update available.
+	instances[1].bottlerocketVersion = "1.20.0"
+	instances[2].bottlerocketVersion = "1.19.0"
+	instances[3].bottlerocketVersion = "1.20.0"
+	u.recordCheckCache(instances, []instance{instances[2]}, latest, now)
+	assert.Equal(t, []string{"cont-inst-2", "cont-inst-4"}, tagged)
+	assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+}
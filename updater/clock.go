@@ -0,0 +1,89 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"net/http"
+	"sync"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws/request"
+)
+
+// clockSkew measures how far the local clock is off from the clocks of AWS,
+// using the Date header of AWS responses. Release ages, soak times and
+// promotion between clusters are all judged by the local clock, so a skewed
+// clock could make the updater roll out a release early. A nil clockSkew
+// measures nothing.
+type clockSkew struct {
+	mu sync.Mutex
+	// now is the local clock; time.Now when nil.
+	now func() time.Time
+	// skew is the local time minus the AWS time of the latest response.
+	skew     time.Duration
+	measured bool
+}
+
+// install measures the skew of the local clock on every AWS response, for the
+// clients created with the handlers.
+func (c *clockSkew) install(handlers *request.Handlers) {
+	handlers.Complete.PushBack(func(r *request.Request) {
+		if r.HTTPResponse == nil {
+			return
+		}
+		c.observe(r.HTTPResponse.Header.Get("Date"))
+	})
+}
+
+// observe records the skew of the local clock from the Date header of a response.
+func (c *clockSkew) observe(date string) {
+	if c == nil || date == "" {
+		return
+	}
+	awsTime, err := http.ParseTime(date)
+	if err != nil {
+		return
+	}
+	now := time.Now
+	if c.now != nil {
+		now = c.now
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	c.skew = now().Sub(awsTime)
+	c.measured = true
+}
+
+// current returns the latest measured skew, and whether any was measured.
+func (c *clockSkew) current() (time.Duration, bool) {
+	if c == nil {
+		return 0, false
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	return c.skew, c.measured
+}
+
+// checkClockSkew returns an error when the local clock is off from AWS by more
+// than maxClockSkew. The Date header only has a resolution of a second, so
+// small limits are not meaningful.
+func (u *updater) checkClockSkew() error {
+	if u.maxClockSkew <= 0 {
+		return nil
+	}
+	skew, ok := u.clock.current()
+	if !ok {
+		log.Printf("Could not measure the skew of the local clock, therefore not checking it")
+		return nil
+	}
+	direction := "ahead of"
+	if skew < 0 {
+		skew = -skew
+		direction = "behind"
+	}
+	if skew > u.maxClockSkew {
+		return fmt.Errorf("the local clock is %s %s AWS, more than the %s allowed by -max-clock-skew; not updating instances,"+
+			" since release ages and soak times depend on the time", skew.Round(time.Second), direction, u.maxClockSkew)
+	}
+	return nil
+}
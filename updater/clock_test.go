@@ -0,0 +1,81 @@
+package main
+
+import (
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestClockSkew(t *testing.T) {
+	local := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	clock := &clockSkew{now: func() time.Time { return local }}
+	_, ok := clock.current()
+	assert.False(t, ok)
+
+	clock.observe("not a date")
+	_, ok = clock.current()
+	assert.False(t, ok, "should ignore invalid Date headers")
+
+	clock.observe("Fri, 01 Mar 2024 11:50:00 GMT")
+	skew, ok := clock.current()
+	require.True(t, ok)
+	assert.Equal(t, 10*time.Minute, skew)
+
+	var nilClock *clockSkew
+	nilClock.observe("Fri, 01 Mar 2024 11:50:00 GMT")
+	_, ok = nilClock.current()
+	assert.False(t, ok)
+}
+
+func TestCheckClockSkew(t *testing.T) {
+	local := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	cases := []struct {
+		name          string
+		maxClockSkew  time.Duration
+		date          string
+		expectedError string
+	}{
+		{
+			name:         "disabled",
+			maxClockSkew: 0,
+			date:         "Fri, 01 Mar 2024 11:00:00 GMT",
+		},
+		{
+			name:         "not measured",
+			maxClockSkew: 5 * time.Minute,
+		},
+		{
+			name:         "within limit",
+			maxClockSkew: 5 * time.Minute,
+			date:         "Fri, 01 Mar 2024 12:04:00 GMT",
+		},
+		{
+			name:          "ahead",
+			maxClockSkew:  5 * time.Minute,
+			date:          "Fri, 01 Mar 2024 11:50:00 GMT",
+			expectedError: "the local clock is 10m0s ahead of AWS",
+		},
+		{
+			name:          "behind",
+			maxClockSkew:  5 * time.Minute,
+			date:          "Fri, 01 Mar 2024 13:00:00 GMT",
+			expectedError: "the local clock is 1h0m0s behind AWS",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			clock := &clockSkew{now: func() time.Time { return local }}
+			clock.observe(tc.date)
+			u := updater{maxClockSkew: tc.maxClockSkew, clock: clock}
+			err := u.checkClockSkew()
+			if tc.expectedError == "" {
+				assert.NoError(t, err)
+				return
+			}
+			require.Error(t, err)
+			assert.Contains(t, err.Error(), tc.expectedError)
+		})
+	}
+}
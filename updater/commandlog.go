@@ -0,0 +1,88 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sync"
+)
+
+// sentCommand is an SSM command the updater sent during a pass.
+type sentCommand struct {
+	id string
+	// purpose is the role of the command's document: check, apply, reboot or settings.
+	purpose     string
+	instanceIDs []string
+}
+
+// String describes the command, e.g. "apply 4b2d... to 1 instance(s)".
+func (c sentCommand) String() string {
+	return fmt.Sprintf("%s %s to %d instance(s)", c.purpose, c.id, len(c.instanceIDs))
+}
+
+// commandLog records the commands sent during a pass, so that their
+// invocations can be looked up in SSM after the pass. A nil commandLog
+// records nothing.
+type commandLog struct {
+	mu       sync.Mutex
+	commands []sentCommand
+}
+
+// record adds a sent command to the log.
+func (l *commandLog) record(commandID string, purpose string, instanceIDs []string) {
+	if l == nil {
+		return
+	}
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	l.commands = append(l.commands, sentCommand{id: commandID, purpose: purpose, instanceIDs: instanceIDs})
+}
+
+// forInstance returns the commands sent to the instance, in the order they were sent.
+func (l *commandLog) forInstance(instanceID string) []sentCommand {
+	if l == nil {
+		return nil
+	}
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	sent := make([]sentCommand, 0)
+	for _, command := range l.commands {
+		for _, id := range command.instanceIDs {
+			if id == instanceID {
+				sent = append(sent, command)
+				break
+			}
+		}
+	}
+	return sent
+}
+
+// log writes the sent commands to the log.
+func (l *commandLog) log() {
+	if l == nil {
+		return
+	}
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	if len(l.commands) == 0 {
+		return
+	}
+	log.Printf("%d SSM command(s) sent:", len(l.commands))
+	for _, command := range l.commands {
+		log.Printf("Command %s: %q", command, command.instanceIDs)
+	}
+}
+
+// documentPurpose returns the role of the document in an update.
+func (u *updater) documentPurpose(document string) string {
+	switch document {
+	case u.checkDocument:
+		return "check"
+	case u.applyDocument:
+		return "apply"
+	case u.rebootDocument:
+		return "reboot"
+	case u.settingsDocument:
+		return "settings"
+	}
+	return document
+}
@@ -0,0 +1,26 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+)
+
+func TestCommandLog(t *testing.T) {
+	var none *commandLog
+	none.record("command-id", "check", []string{"inst-id-1"})
+	assert.Empty(t, none.forInstance("inst-id-1"))
+
+	u := updater{checkDocument: "check-doc", applyDocument: "apply-doc", rebootDocument: "reboot-doc", sent: &commandLog{}}
+	u.sent.record("check-id", u.documentPurpose("check-doc"), []string{"inst-id-1", "inst-id-2"})
+	u.sent.record("apply-id", u.documentPurpose("apply-doc"), []string{"inst-id-2"})
+	u.sent.record("other-id", u.documentPurpose("other-doc"), []string{"inst-id-2"})
+
+	assert.Equal(t, []sentCommand{
+		{id: "check-id", purpose: "check", instanceIDs: []string{"inst-id-1", "inst-id-2"}},
+	}, u.sent.forInstance("inst-id-1"))
+	sent := u.sent.forInstance("inst-id-2")
+	assert.Len(t, sent, 3)
+	assert.Equal(t, "apply apply-id to 1 instance(s)", sent[1].String())
+	assert.Equal(t, "other-doc", sent[2].purpose)
+}
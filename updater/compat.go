@@ -0,0 +1,87 @@
+package main
+
+import (
+	"strconv"
+	"strings"
+)
+
+// apiclientFeature is a capability of the apiclient on Bottlerocket hosts that
+// older releases lack. The updater learns each instance's version from its
+// update check, which apiclient supports since 1.0.5, and adapts the commands
+// it sends to hosts whose apiclient predates a feature.
+type apiclientFeature string
+
+const (
+	// featureSetJSON is `apiclient set --json`, which the settings document
+	// uses to apply settings. Older hosts apply them through the raw API.
+	featureSetJSON apiclientFeature = "set --json"
+)
+
+// apiclientSince is the compatibility matrix of apiclient: the first
+// Bottlerocket release that supports each feature.
+var apiclientSince = map[apiclientFeature]string{
+	featureSetJSON: "1.2.0",
+}
+
+const (
+	// settingsSyntaxParameter is the parameter of the settings document that
+	// selects the apiclient syntax used to apply the settings.
+	settingsSyntaxParameter = "syntax"
+	// settingsSyntaxRaw applies settings with PATCH /settings and a commit
+	// through the raw API, which every apiclient supports. The document
+	// defaults to apiclient set --json, so the parameter is only sent to hosts
+	// that need the raw syntax.
+	settingsSyntaxRaw = "raw"
+)
+
+// apiclientSupports returns whether the apiclient of a host running the
+// Bottlerocket version supports the feature. The apiclient of a host whose
+// version is unknown is assumed to be current.
+func apiclientSupports(version string, feature apiclientFeature) bool {
+	since, ok := apiclientSince[feature]
+	if !ok || version == "" || version == unknownVersion {
+		return true
+	}
+	return compareVersions(version, since) >= 0
+}
+
+// compareVersions compares two Bottlerocket versions such as 1.19.2 or
+// v1.0.5 by their numeric components, ignoring pre-release suffixes. It
+// returns -1, 0 or 1 when a is older than, the same as or newer than b.
+func compareVersions(a, b string) int {
+	pa, pb := versionParts(a), versionParts(b)
+	for i := 0; i < len(pa) || i < len(pb); i++ {
+		var x, y int
+		if i < len(pa) {
+			x = pa[i]
+		}
+		if i < len(pb) {
+			y = pb[i]
+		}
+		if x < y {
+			return -1
+		}
+		if x > y {
+			return 1
+		}
+	}
+	return 0
+}
+
+// versionParts returns the numeric components of the version; components that
+// are not numbers count as 0.
+func versionParts(version string) []int {
+	version = strings.TrimPrefix(version, "v")
+	if i := strings.IndexAny(version, "-+"); i >= 0 {
+		version = version[:i]
+	}
+	parts := make([]int, 0, 3)
+	for _, part := range strings.Split(version, ".") {
+		n, err := strconv.Atoi(part)
+		if err != nil {
+			n = 0
+		}
+		parts = append(parts, n)
+	}
+	return parts
+}
@@ -0,0 +1,33 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+)
+
+func TestCompareVersions(t *testing.T) {
+	cases := []struct {
+		a, b     string
+		expected int
+	}{
+		{"1.2.0", "1.2.0", 0},
+		{"v1.2.0", "1.2.0", 0},
+		{"1.10.0", "1.9.1", 1},
+		{"1.1.4", "1.2.0", -1},
+		{"1.2", "1.2.0", 0},
+		{"1.2.0-rc1", "1.2.0", 0},
+		{"2.0.0", "1.99.99", 1},
+	}
+	for _, tc := range cases {
+		assert.Equal(t, tc.expected, compareVersions(tc.a, tc.b), "%s vs %s", tc.a, tc.b)
+	}
+}
+
+func TestAPIClientSupports(t *testing.T) {
+	assert.True(t, apiclientSupports("1.19.0", featureSetJSON))
+	assert.True(t, apiclientSupports("1.2.0", featureSetJSON))
+	assert.False(t, apiclientSupports("1.1.4", featureSetJSON))
+	assert.True(t, apiclientSupports("", featureSetJSON), "should assume an unknown version is current")
+	assert.True(t, apiclientSupports(unknownVersion, featureSetJSON))
+}
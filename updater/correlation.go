@@ -0,0 +1,44 @@
+package main
+
+import (
+	"log"
+)
+
+// correlateLog prefixes log lines with the ID of the pass, and of the update
+// in progress, so that the logs of a rollout can be correlated with its SSM
+// commands, metrics, notifications and reports. It returns a function that
+// restores the log's prefix and flags.
+func (u *updater) correlateLog() func() {
+	prefix, flags := log.Prefix(), log.Flags()
+	log.SetFlags(flags | log.Lmsgprefix)
+	u.setLogPrefix()
+	return func() {
+		log.SetPrefix(prefix)
+		log.SetFlags(flags)
+	}
+}
+
+func (u *updater) setLogPrefix() {
+	prefix := "pass=" + u.passID + " "
+	if u.updateID != "" {
+		prefix += "update=" + u.updateID + " "
+	}
+	log.SetPrefix(prefix)
+}
+
+// beginUpdate identifies the update of an instance with a new update ID, which
+// its SSM commands and log lines carry until it ends, and records the ID in the
+// summary. It returns release extended to end the update.
+func (u *updater) beginUpdate(inst instance, summary *passSummary, release func()) func() {
+	u.updateID = newCorrelationID()
+	summary.updateIDs[inst.instanceID] = u.updateID
+	u.setLogPrefix()
+	log.Printf("Starting update %s of instance %#q", u.updateID, inst)
+	u.progressStep(inst, stepPreparing)
+	return func() {
+		release()
+		u.updateID = ""
+		u.setLogPrefix()
+		u.progressStep(inst, "")
+	}
+}
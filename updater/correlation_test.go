@@ -0,0 +1,36 @@
+package main
+
+import (
+	"bytes"
+	"log"
+	"os"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+)
+
+func TestCorrelateLog(t *testing.T) {
+	var out bytes.Buffer
+	log.SetOutput(&out)
+	defer log.SetOutput(os.Stderr)
+
+	u := updater{passID: "pass-1"}
+	restoreLog := u.correlateLog()
+	summary := newPassSummary()
+	released := false
+	release := u.beginUpdate(instance{instanceID: "inst-id-1"}, summary, func() { released = true })
+	updateID := summary.updateIDs["inst-id-1"]
+	assert.NotEmpty(t, updateID)
+	assert.Equal(t, updateID, u.updateID)
+	log.Print("draining")
+	release()
+	assert.True(t, released)
+	assert.Empty(t, u.updateID, "the update should end when the instance is released")
+	log.Print("done")
+	restoreLog()
+	log.Print("after")
+
+	assert.Contains(t, out.String(), "pass=pass-1 update="+updateID+" draining\n")
+	assert.Contains(t, out.String(), "pass=pass-1 done\n")
+	assert.NotContains(t, out.String(), "pass=pass-1 after")
+}
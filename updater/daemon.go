@@ -0,0 +1,132 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+const (
+	// daemonWaitTimeout is how long the tasks of daemon services may take to
+	// run again on an updated instance.
+	daemonWaitTimeout = 10 * time.Minute
+	// daemonPollInterval is how often the tasks on an updated instance are checked.
+	daemonPollInterval = 15 * time.Second
+)
+
+// daemonServices returns the services with tasks in the workloads that use
+// the DAEMON scheduling strategy.
+func (u *updater) daemonServices(workloads map[string]workload) (map[string]bool, error) {
+	services := make([]string, 0)
+	seen := make(map[string]bool)
+	for _, w := range workloads {
+		for _, service := range w.services() {
+			if !seen[service] {
+				seen[service] = true
+				services = append(services, service)
+			}
+		}
+	}
+	sort.Strings(services)
+	daemons := make(map[string]bool)
+	_, err := eachPage(len(services), describeServicesPageSize, func(start, stop int) error {
+		resp, err := u.ecs.DescribeServices(&ecs.DescribeServicesInput{
+			Cluster:  &u.cluster,
+			Services: aws.StringSlice(services[start:stop]),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to describe services: %w", err)
+		}
+		for _, service := range resp.Services {
+			if aws.StringValue(service.SchedulingStrategy) == ecs.SchedulingStrategyDaemon {
+				daemons[aws.StringValue(service.ServiceName)] = true
+			}
+		}
+		return nil
+	})
+	if err != nil {
+		return nil, err
+	}
+	return daemons, nil
+}
+
+// daemons returns the daemon services with tasks in the workload.
+func (w workload) daemons(daemonServices map[string]bool) []string {
+	daemons := make([]string, 0)
+	for _, service := range w.services() {
+		if daemonServices[service] {
+			daemons = append(daemons, service)
+		}
+	}
+	return daemons
+}
+
+// missingDaemons returns the daemon services without a RUNNING task on the
+// container instance.
+func (u *updater) missingDaemons(containerInstance string, daemons []string) ([]string, error) {
+	list, err := u.ecs.ListTasks(&ecs.ListTasksInput{
+		Cluster:           &u.cluster,
+		ContainerInstance: aws.String(containerInstance),
+		DesiredStatus:     aws.String(ecs.DesiredStatusRunning),
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to list tasks: %w", err)
+	}
+	running := make(map[string]bool)
+	if len(list.TaskArns) > 0 {
+		desc, err := u.ecs.DescribeTasks(&ecs.DescribeTasksInput{
+			Cluster: &u.cluster,
+			Tasks:   list.TaskArns,
+		})
+		if err != nil {
+			return nil, fmt.Errorf("failed to describe tasks: %w", err)
+		}
+		for _, task := range desc.Tasks {
+			if aws.StringValue(task.LastStatus) == "RUNNING" {
+				running[aws.StringValue(task.Group)] = true
+			}
+		}
+	}
+	missing := make([]string, 0)
+	for _, service := range daemons {
+		if !running[serviceGroupPrefix+service] {
+			missing = append(missing, service)
+		}
+	}
+	return missing, nil
+}
+
+// waitForDaemons waits until each of the daemon services that ran on the
+// container instance before its update has a RUNNING task on it again. The
+// ECS scheduler places daemon tasks on an instance when it becomes ACTIVE
+// rather than replacing them elsewhere during the drain, so an update is only
+// complete once they are back.
+func (u *updater) waitForDaemons(containerInstance string, daemons []string) error {
+	if len(daemons) == 0 {
+		return nil
+	}
+	log.Printf("Waiting for the tasks of daemon service(s) %q to run on container instance %q", daemons, containerInstance)
+	var waited time.Duration
+	for {
+		missing, err := u.missingDaemons(containerInstance, daemons)
+		if err == nil && len(missing) == 0 {
+			log.Printf("Daemon tasks are running on container instance %q", containerInstance)
+			return nil
+		}
+		if waited >= daemonWaitTimeout {
+			if err != nil {
+				return fmt.Errorf("failed to check daemon tasks: %w", err)
+			}
+			return fmt.Errorf("daemon service(s) %q have no running task after %s", missing, daemonWaitTimeout)
+		}
+		if err != nil {
+			log.Printf("Failed to check daemon tasks on container instance %q, retrying: %v", containerInstance, err)
+		}
+		sleep(daemonPollInterval)
+		waited += daemonPollInterval
+	}
+}
@@ -0,0 +1,98 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestDaemonServices(t *testing.T) {
+	workloads := map[string]workload{
+		"inst-1": {"service:log-router": 1, "service:web": 2},
+		"inst-2": {"service:log-router": 1, "family:batch": 1},
+	}
+	mockECS := MockECS{
+		DescribeServicesFn: func(input *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+			assert.Equal(t, aws.StringSlice([]string{"log-router", "web"}), input.Services)
+			return &ecs.DescribeServicesOutput{Services: []*ecs.Service{
+				{ServiceName: aws.String("log-router"), SchedulingStrategy: aws.String(ecs.SchedulingStrategyDaemon)},
+				{ServiceName: aws.String("web"), SchedulingStrategy: aws.String(ecs.SchedulingStrategyReplica)},
+			}}, nil
+		},
+	}
+	u := updater{ecs: mockECS}
+	daemons, err := u.daemonServices(workloads)
+	require.NoError(t, err)
+	assert.Equal(t, map[string]bool{"log-router": true}, daemons)
+	assert.Equal(t, []string{"log-router"}, workloads["inst-1"].daemons(daemons))
+	assert.Empty(t, workload(nil).daemons(daemons))
+
+	mockECS.DescribeServicesFn = func(_ *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+		return nil, errors.New("failed to describe")
+	}
+	u = updater{ecs: mockECS}
+	_, err = u.daemonServices(workloads)
+	assert.Error(t, err)
+}
+
+func TestWaitForDaemons(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	var slept time.Duration
+	sleep = func(d time.Duration) { slept += d }
+
+	// tasksAfter returns a mock whose daemon task starts running after the given number of checks.
+	tasksAfter := func(checks int) (MockECS, *int) {
+		calls := 0
+		return MockECS{
+			ListTasksFn: func(input *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+				assert.Equal(t, "cont-inst", aws.StringValue(input.ContainerInstance))
+				assert.Equal(t, ecs.DesiredStatusRunning, aws.StringValue(input.DesiredStatus))
+				calls++
+				if calls <= checks {
+					return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-web"})}, nil
+				}
+				return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-web", "task-daemon"})}, nil
+			},
+			DescribeTasksFn: func(input *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+				tasks := []*ecs.Task{}
+				for _, arn := range input.Tasks {
+					switch aws.StringValue(arn) {
+					case "task-web":
+						tasks = append(tasks, &ecs.Task{Group: aws.String("service:web"), LastStatus: aws.String("RUNNING")})
+					case "task-daemon":
+						tasks = append(tasks, &ecs.Task{Group: aws.String("service:log-router"), LastStatus: aws.String("RUNNING")})
+					}
+				}
+				return &ecs.DescribeTasksOutput{Tasks: tasks}, nil
+			},
+		}, &calls
+	}
+
+	t.Run("no daemons", func(t *testing.T) {
+		u := updater{ecs: MockECS{}}
+		assert.NoError(t, u.waitForDaemons("cont-inst", nil))
+	})
+	t.Run("daemon restarts", func(t *testing.T) {
+		slept = 0
+		mockECS, calls := tasksAfter(2)
+		u := updater{ecs: mockECS}
+		require.NoError(t, u.waitForDaemons("cont-inst", []string{"log-router"}))
+		assert.Equal(t, 3, *calls)
+		assert.Equal(t, 2*daemonPollInterval, slept)
+	})
+	t.Run("daemon does not restart", func(t *testing.T) {
+		slept = 0
+		mockECS, _ := tasksAfter(1000)
+		u := updater{ecs: mockECS}
+		err := u.waitForDaemons("cont-inst", []string{"log-router"})
+		require.Error(t, err)
+		assert.Contains(t, err.Error(), `daemon service(s) ["log-router"] have no running task`)
+		assert.Equal(t, daemonWaitTimeout, slept)
+	})
+}
@@ -0,0 +1,102 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+const (
+	// deferTagKey is the tag service owners set to "true" on an ECS service to
+	// have instances running its tasks updated after all other instances.
+	deferTagKey = "ecs-updater/defer"
+	// describeServicesPageSize is the most services DescribeServices accepts at once.
+	describeServicesPageSize = 10
+)
+
+// deferredServices returns the services among the given ones that are tagged
+// to defer updates.
+func (u *updater) deferredServices(services []string) (map[string]bool, error) {
+	deferred := make(map[string]bool)
+	_, err := eachPage(len(services), describeServicesPageSize, func(start, stop int) error {
+		resp, err := u.ecs.DescribeServices(&ecs.DescribeServicesInput{
+			Cluster:  &u.cluster,
+			Services: aws.StringSlice(services[start:stop]),
+			Include:  aws.StringSlice([]string{ecs.ServiceFieldTags}),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to describe services: %w", err)
+		}
+		for _, service := range resp.Services {
+			for _, tag := range service.Tags {
+				if aws.StringValue(tag.Key) == deferTagKey && strings.EqualFold(aws.StringValue(tag.Value), "true") {
+					deferred[aws.StringValue(service.ServiceName)] = true
+				}
+			}
+		}
+		return nil
+	})
+	if err != nil {
+		return nil, err
+	}
+	return deferred, nil
+}
+
+// deferTaggedInstances moves the candidates running tasks of services tagged
+// ecs-updater/defer=true to the end of the pass, keeping the order of the
+// candidates otherwise. Deferring is best effort: candidates without a known
+// workload are not deferred, and when the service tags can't be read the
+// order is left unchanged.
+func (u *updater) deferTaggedInstances(candidates []instance, workloads map[string]workload) []instance {
+	allServices := make(map[string]bool)
+	for _, w := range workloads {
+		for _, service := range w.services() {
+			allServices[service] = true
+		}
+	}
+	if len(allServices) == 0 {
+		return candidates
+	}
+	names := make([]string, 0, len(allServices))
+	for service := range allServices {
+		names = append(names, service)
+	}
+	sort.Strings(names)
+	deferred, err := u.deferredServices(names)
+	if err != nil {
+		log.Printf("Failed to read service tags, therefore not deferring any instances: %v", err)
+		u.warnings.add(warnDegraded, "instances were not deferred because service tags could not be read: %v", err)
+		return candidates
+	}
+	if len(deferred) == 0 {
+		return candidates
+	}
+
+	ordered := make([]instance, 0, len(candidates))
+	last := make([]instance, 0)
+	for _, inst := range candidates {
+		var deferring []string
+		for _, service := range workloads[inst.instanceID].services() {
+			if deferred[service] {
+				deferring = append(deferring, service)
+			}
+		}
+		if len(deferring) == 0 {
+			ordered = append(ordered, inst)
+			continue
+		}
+		if u.belowMinimum(inst) {
+			log.Printf("Not deferring instance %#q for service(s) %q since it runs Bottlerocket %s, older than the minimum version %s",
+				inst, deferring, inst.bottlerocketVersion, u.minimumVersion)
+			ordered = append(ordered, inst)
+			continue
+		}
+		log.Printf("Deferring instance %#q to the end of the pass because service(s) %q are tagged %s=true", inst, deferring, deferTagKey)
+		last = append(last, inst)
+	}
+	return append(ordered, last...)
+}
@@ -0,0 +1,89 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestDeferTaggedInstances(t *testing.T) {
+	candidates := []instance{
+		{instanceID: "inst-deferred", containerInstanceID: "cont-inst-deferred"},
+		{instanceID: "inst-web", containerInstanceID: "cont-inst-web"},
+		{instanceID: "inst-empty", containerInstanceID: "cont-inst-empty"},
+		{instanceID: "inst-mixed", containerInstanceID: "cont-inst-mixed"},
+		{instanceID: "inst-unknown", containerInstanceID: "cont-inst-unknown"},
+	}
+	workloads := map[string]workload{
+		"inst-deferred": {"service:database": 1},
+		"inst-web":      {"service:web": 1, "family:batch": 1},
+		"inst-empty":    {},
+		"inst-mixed":    {"service:web": 1, "service:database": 1},
+	}
+
+	t.Run("tagged services", func(t *testing.T) {
+		mockECS := MockECS{
+			DescribeServicesFn: func(input *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+				assert.Equal(t, []*string{aws.String("database"), aws.String("web")}, input.Services)
+				assert.Equal(t, []*string{aws.String(ecs.ServiceFieldTags)}, input.Include)
+				return &ecs.DescribeServicesOutput{Services: []*ecs.Service{
+					{
+						ServiceName: aws.String("database"),
+						Tags:        []*ecs.Tag{{Key: aws.String(deferTagKey), Value: aws.String("true")}},
+					},
+					{
+						ServiceName: aws.String("web"),
+						Tags:        []*ecs.Tag{{Key: aws.String(deferTagKey), Value: aws.String("false")}},
+					},
+				}}, nil
+			},
+		}
+		u := updater{ecs: mockECS}
+		ordered := u.deferTaggedInstances(candidates, workloads)
+		assert.Equal(t, []instance{candidates[1], candidates[2], candidates[4], candidates[0], candidates[3]}, ordered)
+
+		// Instances below the minimum version are not deferred.
+		belowMinimum := append([]instance{}, candidates...)
+		belowMinimum[0].bottlerocketVersion = "1.18.0"
+		u.minimumVersion = "1.19.2"
+		ordered = u.deferTaggedInstances(belowMinimum, workloads)
+		assert.Equal(t, []instance{belowMinimum[0], belowMinimum[1], belowMinimum[2], belowMinimum[4], belowMinimum[3]}, ordered)
+	})
+	t.Run("describe services err", func(t *testing.T) {
+		mockECS := MockECS{
+			DescribeServicesFn: func(_ *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+				return nil, errors.New("access denied")
+			},
+		}
+		u := updater{ecs: mockECS}
+		assert.Equal(t, candidates, u.deferTaggedInstances(candidates, workloads), "order should not change")
+	})
+	t.Run("no services", func(t *testing.T) {
+		u := updater{ecs: MockECS{}}
+		assert.Equal(t, candidates, u.deferTaggedInstances(candidates, map[string]workload{"inst-web": {"family:batch": 2}}))
+	})
+}
+
+func TestDeferredServicesPages(t *testing.T) {
+	services := make([]string, 0)
+	for i := 0; i < 25; i++ {
+		services = append(services, string(rune('a'+i)))
+	}
+	calls := 0
+	mockECS := MockECS{
+		DescribeServicesFn: func(input *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+			calls++
+			assert.LessOrEqual(t, len(input.Services), describeServicesPageSize)
+			return &ecs.DescribeServicesOutput{}, nil
+		},
+	}
+	u := updater{ecs: mockECS}
+	deferred, err := u.deferredServices(services)
+	require.NoError(t, err)
+	assert.Empty(t, deferred)
+	assert.Equal(t, 3, calls)
+}
@@ -0,0 +1,177 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/elbv2"
+)
+
+const (
+	// drainTagKey is the tag service owners set on an ECS service to choose
+	// when instances running its tasks count as drained.
+	drainTagKey = "ecs-updater/drain"
+	// drainTargetGroup makes an instance count as drained of a service's tasks
+	// once their targets have left the service's target groups, rather than
+	// once the tasks have stopped.
+	drainTargetGroup = "target-group"
+)
+
+// targetDrainServices returns the target groups of the services with tasks
+// in the workloads that are tagged ecs-updater/drain=target-group, by service.
+// Tagged services without target groups are drained by their tasks.
+func (u *updater) targetDrainServices(workloads map[string]workload) (map[string][]string, error) {
+	services := make([]string, 0)
+	seen := make(map[string]bool)
+	for _, w := range workloads {
+		for _, service := range w.services() {
+			if !seen[service] {
+				seen[service] = true
+				services = append(services, service)
+			}
+		}
+	}
+	sort.Strings(services)
+	targetGroups := make(map[string][]string)
+	_, err := eachPage(len(services), describeServicesPageSize, func(start, stop int) error {
+		resp, err := u.ecs.DescribeServices(&ecs.DescribeServicesInput{
+			Cluster:  &u.cluster,
+			Services: aws.StringSlice(services[start:stop]),
+			Include:  aws.StringSlice([]string{ecs.ServiceFieldTags}),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to describe services: %w", err)
+		}
+		for _, service := range resp.Services {
+			name := aws.StringValue(service.ServiceName)
+			if !taggedTargetDrain(service.Tags) {
+				continue
+			}
+			for _, lb := range service.LoadBalancers {
+				if lb.TargetGroupArn != nil {
+					targetGroups[name] = append(targetGroups[name], aws.StringValue(lb.TargetGroupArn))
+				}
+			}
+			if len(targetGroups[name]) == 0 {
+				log.Printf("Service %q is tagged %s=%s but has no target groups, therefore waiting for its tasks to stop when draining", name, drainTagKey, drainTargetGroup)
+			}
+		}
+		return nil
+	})
+	if err != nil {
+		return nil, err
+	}
+	return targetGroups, nil
+}
+
+// taggedTargetDrain returns whether the service tags choose draining by target group.
+func taggedTargetDrain(tags []*ecs.Tag) bool {
+	for _, tag := range tags {
+		if aws.StringValue(tag.Key) == drainTagKey && strings.EqualFold(aws.StringValue(tag.Value), drainTargetGroup) {
+			return true
+		}
+	}
+	return false
+}
+
+// taskPrivateIPs returns the private IP addresses of the task's network
+// interfaces, which are the IDs of its targets in awsvpc network mode.
+func taskPrivateIPs(task *ecs.Task) []string {
+	ips := make([]string, 0)
+	for _, attachment := range task.Attachments {
+		for _, detail := range attachment.Details {
+			if aws.StringValue(detail.Name) == "privateIPv4Address" {
+				ips = append(ips, aws.StringValue(detail.Value))
+			}
+		}
+	}
+	return ips
+}
+
+// waitUntilTargetsDrained waits until the targets of the tasks of services in
+// targetGroups have left the services' target groups, so that their
+// connections have been drained by the load balancers, and returns the tasks
+// of other services, whose drain is still to be waited for.
+func (u *updater) waitUntilTargetsDrained(inst instance, taskARNs []*string, targetGroups map[string][]string) ([]*string, error) {
+	desc, err := u.ecs.DescribeTasks(&ecs.DescribeTasksInput{
+		Cluster: &u.cluster,
+		Tasks:   taskARNs,
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe tasks: %w", err)
+	}
+	remaining := make([]*string, 0)
+	// Targets are registered by instance ID in bridge and host network mode,
+	// and by the task's IP address in awsvpc network mode.
+	targetIDs := map[string]bool{inst.instanceID: true}
+	groups := make([]string, 0)
+	seen := make(map[string]bool)
+	for _, task := range desc.Tasks {
+		var service string
+		if taskGroup := aws.StringValue(task.Group); strings.HasPrefix(taskGroup, serviceGroupPrefix) {
+			service = strings.TrimPrefix(taskGroup, serviceGroupPrefix)
+		}
+		if len(targetGroups[service]) == 0 {
+			remaining = append(remaining, task.TaskArn)
+			continue
+		}
+		for _, ip := range taskPrivateIPs(task) {
+			targetIDs[ip] = true
+		}
+		for _, group := range targetGroups[service] {
+			if !seen[group] {
+				seen[group] = true
+				groups = append(groups, group)
+			}
+		}
+	}
+	if len(groups) == 0 {
+		return remaining, nil
+	}
+	log.Printf("Waiting for the targets of instance %#q to drain from target group(s) %q", inst, groups)
+	for attempt := 1; ; attempt++ {
+		draining, err := u.drainingTargets(groups, targetIDs)
+		if err != nil {
+			return nil, err
+		}
+		if draining == 0 {
+			log.Printf("Targets of instance %#q drained from their target groups", inst)
+			return remaining, nil
+		}
+		if attempt >= waiterMaxAttempts {
+			return nil, fmt.Errorf("%d target(s) still registered after %s", draining, waiterDelay*waiterMaxAttempts)
+		}
+		if u.stopping() {
+			return nil, errShutdown
+		}
+		sleep(waiterDelay)
+	}
+}
+
+// drainingTargets counts the targets with the IDs that are still in use in
+// the target groups, i.e. not yet deregistered.
+func (u *updater) drainingTargets(groups []string, targetIDs map[string]bool) (int, error) {
+	draining := 0
+	for _, group := range groups {
+		resp, err := u.elbv2.DescribeTargetHealth(&elbv2.DescribeTargetHealthInput{
+			TargetGroupArn: aws.String(group),
+		})
+		if err != nil {
+			return 0, fmt.Errorf("failed to describe targets of target group %q: %w", group, err)
+		}
+		for _, description := range resp.TargetHealthDescriptions {
+			if description.Target == nil || !targetIDs[aws.StringValue(description.Target.Id)] {
+				continue
+			}
+			if description.TargetHealth != nil && aws.StringValue(description.TargetHealth.State) == elbv2.TargetHealthStateEnumUnused {
+				continue
+			}
+			draining++
+		}
+	}
+	return draining, nil
+}
@@ -0,0 +1,159 @@
+package main
+
+import (
+	"context"
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/elbv2"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestTargetDrainServices(t *testing.T) {
+	workloads := map[string]workload{
+		"inst-1": {"service:web": 2, "service:api": 1},
+		"inst-2": {"service:worker": 1, "family:batch": 1},
+	}
+	mockECS := MockECS{
+		DescribeServicesFn: func(input *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+			assert.Equal(t, []string{"api", "web", "worker"}, aws.StringValueSlice(input.Services))
+			assert.Equal(t, []string{ecs.ServiceFieldTags}, aws.StringValueSlice(input.Include))
+			tagged := []*ecs.Tag{{Key: aws.String(drainTagKey), Value: aws.String("Target-Group")}}
+			return &ecs.DescribeServicesOutput{Services: []*ecs.Service{
+				{ServiceName: aws.String("api"), LoadBalancers: []*ecs.LoadBalancer{{TargetGroupArn: aws.String("tg-api")}}},
+				{ServiceName: aws.String("web"), Tags: tagged, LoadBalancers: []*ecs.LoadBalancer{
+					{TargetGroupArn: aws.String("tg-web-http")},
+					{TargetGroupArn: aws.String("tg-web-grpc")},
+				}},
+				{ServiceName: aws.String("worker"), Tags: tagged},
+			}}, nil
+		},
+	}
+	u := updater{ecs: mockECS}
+	targetGroups, err := u.targetDrainServices(workloads)
+	require.NoError(t, err)
+	assert.Equal(t, map[string][]string{"web": {"tg-web-http", "tg-web-grpc"}}, targetGroups, "should only drain tagged services with target groups by target group")
+
+	mockECS.DescribeServicesFn = func(_ *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+		return nil, errors.New("failed to describe")
+	}
+	u = updater{ecs: mockECS}
+	_, err = u.targetDrainServices(workloads)
+	assert.Error(t, err)
+}
+
+func TestDrainInstanceByTargetGroup(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = func(time.Duration) {}
+
+	inst := instance{instanceID: "i-1", containerInstanceID: "cont-inst-id"}
+	targetGroups := map[string][]string{"web": {"tg-web"}}
+	newMockECS := func(waited *[]string) MockECS {
+		return MockECS{
+			UpdateContainerInstancesStateFn: func(_ *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+				return &ecs.UpdateContainerInstancesStateOutput{}, nil
+			},
+			ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+				return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-web-bridge", "task-web-awsvpc", "task-worker"})}, nil
+			},
+			DescribeTasksFn: func(_ *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+				return &ecs.DescribeTasksOutput{Tasks: []*ecs.Task{
+					{TaskArn: aws.String("task-web-bridge"), Group: aws.String("service:web")},
+					{TaskArn: aws.String("task-web-awsvpc"), Group: aws.String("service:web"), Attachments: []*ecs.Attachment{{
+						Type:    aws.String("ElasticNetworkInterface"),
+						Details: []*ecs.KeyValuePair{{Name: aws.String("privateIPv4Address"), Value: aws.String("10.0.0.5")}},
+					}}},
+					{TaskArn: aws.String("task-worker"), Group: aws.String("service:worker")},
+				}}, nil
+			},
+			WaitUntilTasksStoppedWithContextFn: func(_ aws.Context, input *ecs.DescribeTasksInput, _ ...request.WaiterOption) error {
+				*waited = append(*waited, aws.StringValueSlice(input.Tasks)...)
+				return nil
+			},
+		}
+	}
+	target := func(id, state string) *elbv2.TargetHealthDescription {
+		return &elbv2.TargetHealthDescription{
+			Target:       &elbv2.TargetDescription{Id: aws.String(id)},
+			TargetHealth: &elbv2.TargetHealth{State: aws.String(state)},
+		}
+	}
+
+	t.Run("drained", func(t *testing.T) {
+		var waited []string
+		checks := 0
+		mockELBV2 := MockELBV2{
+			DescribeTargetHealthFn: func(input *elbv2.DescribeTargetHealthInput) (*elbv2.DescribeTargetHealthOutput, error) {
+				assert.Equal(t, "tg-web", aws.StringValue(input.TargetGroupArn))
+				checks++
+				switch checks {
+				case 1:
+					return &elbv2.DescribeTargetHealthOutput{TargetHealthDescriptions: []*elbv2.TargetHealthDescription{
+						target("i-1", elbv2.TargetHealthStateEnumDraining),
+						target("10.0.0.5", elbv2.TargetHealthStateEnumDraining),
+						target("i-2", elbv2.TargetHealthStateEnumHealthy),
+					}}, nil
+				case 2:
+					return &elbv2.DescribeTargetHealthOutput{TargetHealthDescriptions: []*elbv2.TargetHealthDescription{
+						target("10.0.0.5", elbv2.TargetHealthStateEnumDraining),
+						target("i-2", elbv2.TargetHealthStateEnumHealthy),
+					}}, nil
+				default:
+					return &elbv2.DescribeTargetHealthOutput{TargetHealthDescriptions: []*elbv2.TargetHealthDescription{
+						target("i-2", elbv2.TargetHealthStateEnumHealthy),
+					}}, nil
+				}
+			},
+		}
+		u := updater{ecs: newMockECS(&waited), elbv2: mockELBV2, cluster: "test-cluster"}
+		require.NoError(t, u.drainInstance(inst, targetGroups))
+		assert.Equal(t, 3, checks, "should wait until the targets of the instance have left the target group")
+		assert.Equal(t, []string{"task-worker"}, waited, "should wait for the tasks of other services to stop")
+	})
+	t.Run("describe targets fails", func(t *testing.T) {
+		var waited []string
+		var states []string
+		mockECS := newMockECS(&waited)
+		mockECS.UpdateContainerInstancesStateFn = func(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+			states = append(states, aws.StringValue(input.Status))
+			return &ecs.UpdateContainerInstancesStateOutput{}, nil
+		}
+		mockELBV2 := MockELBV2{
+			DescribeTargetHealthFn: func(_ *elbv2.DescribeTargetHealthInput) (*elbv2.DescribeTargetHealthOutput, error) {
+				return nil, errors.New("access denied")
+			},
+		}
+		u := updater{ecs: mockECS, elbv2: mockELBV2, cluster: "test-cluster"}
+		assert.Error(t, u.drainInstance(inst, targetGroups))
+		assert.Equal(t, []string{"DRAINING", "ACTIVE"}, states, "should re-activate the instance")
+		assert.Empty(t, waited)
+	})
+	t.Run("shutdown", func(t *testing.T) {
+		var waited []string
+		var states []string
+		mockECS := newMockECS(&waited)
+		mockECS.UpdateContainerInstancesStateFn = func(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+			states = append(states, aws.StringValue(input.Status))
+			return &ecs.UpdateContainerInstancesStateOutput{}, nil
+		}
+		mockELBV2 := MockELBV2{
+			DescribeTargetHealthFn: func(_ *elbv2.DescribeTargetHealthInput) (*elbv2.DescribeTargetHealthOutput, error) {
+				return &elbv2.DescribeTargetHealthOutput{TargetHealthDescriptions: []*elbv2.TargetHealthDescription{
+					target("i-1", elbv2.TargetHealthStateEnumDraining),
+				}}, nil
+			},
+		}
+		shutdown, cancel := context.WithCancel(context.Background())
+		cancel()
+		u := updater{ecs: mockECS, elbv2: mockELBV2, cluster: "test-cluster", shutdown: shutdown}
+		assert.ErrorIs(t, u.drainInstance(inst, targetGroups), errShutdown)
+		assert.Equal(t, []string{"DRAINING", "ACTIVE"}, states, "should stop waiting and re-activate the instance")
+		assert.Empty(t, waited)
+	})
+}
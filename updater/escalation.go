@@ -0,0 +1,234 @@
+package main
+
+import (
+	"bytes"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"log"
+	"net/http"
+	"net/url"
+	"os"
+	"strconv"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+const (
+	// tagConsecutiveFailures counts the passes in a row in which the update of
+	// a container instance failed.
+	tagConsecutiveFailures = "bottlerocket-ecs-updater:consecutive-failures"
+	// webhookAuthorizationEnv holds the Authorization header sent with
+	// escalations, e.g. "Bearer <token>"; none is sent when it is empty.
+	webhookAuthorizationEnv = "FAILURE_WEBHOOK_AUTHORIZATION"
+)
+
+// webhookClient sends escalations to the failure webhook.
+var webhookClient = &http.Client{Timeout: 30 * time.Second}
+
+// consecutiveFailuresFromTags reads the number of consecutive failed passes
+// recorded in the tags of a container instance. A missing or invalid count is 0.
+func consecutiveFailuresFromTags(tags []*ecs.Tag) int {
+	for _, tag := range tags {
+		if aws.StringValue(tag.Key) != tagConsecutiveFailures {
+			continue
+		}
+		count, err := strconv.Atoi(aws.StringValue(tag.Value))
+		if err != nil || count < 0 {
+			return 0
+		}
+		return count
+	}
+	return 0
+}
+
+// failureEscalation is the body of the request sent to the failure webhook
+// when an instance has failed to update in escalateAfter passes in a row.
+// Title and Body are ready to be filed as an issue; the other fields carry
+// the same diagnostics for webhooks that build their own ticket. Services are
+// those with tasks on the instance at the start of the pass, and EC2 has the
+// instance's Name tag and Auto Scaling group, so on-call engineers can tell
+// which workloads are affected.
+type failureEscalation struct {
+	Title               string       `json:"title"`
+	Body                string       `json:"body"`
+	Cluster             string       `json:"cluster"`
+	InstanceID          string       `json:"instanceId"`
+	ContainerInstance   string       `json:"containerInstance"`
+	ConsecutiveFailures int          `json:"consecutiveFailures"`
+	PassID              string       `json:"passId"`
+	UpdateID            string       `json:"updateId,omitempty"`
+	ChangeRef           string       `json:"changeRef,omitempty"`
+	Reason              string       `json:"reason"`
+	Version             string       `json:"version,omitempty"`
+	Commands            []string     `json:"commands"`
+	EC2                 *ec2Metadata `json:"ec2,omitempty"`
+	Services            []string     `json:"services"`
+}
+
+// newFailureEscalation gathers the diagnostics of an instance that failed to
+// update in count passes in a row.
+func newFailureEscalation(cluster string, inst instance, count int, summary *passSummary, metadata *ec2Metadata) failureEscalation {
+	name := inst.instanceID
+	if metadata != nil && metadata.Name != "" {
+		name = fmt.Sprintf("%s (%s)", inst.instanceID, metadata.Name)
+	}
+	e := failureEscalation{
+		Title:               fmt.Sprintf("Bottlerocket instance %s in cluster %s failed to update in %d passes in a row", name, cluster, count),
+		Cluster:             cluster,
+		InstanceID:          inst.instanceID,
+		ContainerInstance:   inst.containerInstanceID,
+		ConsecutiveFailures: count,
+		PassID:              summary.passID,
+		UpdateID:            summary.updateIDs[inst.instanceID],
+		ChangeRef:           summary.changeRef,
+		Reason:              summary.results[inst.instanceID],
+		Version:             summary.versionsAfter[inst.instanceID],
+		Commands:            make([]string, 0),
+		EC2:                 metadata,
+		Services:            make([]string, 0),
+	}
+	e.Services = append(e.Services, summary.services[inst.instanceID]...)
+	for _, command := range summary.commands.forInstance(inst.instanceID) {
+		e.Commands = append(e.Commands, command.purpose+":"+command.id)
+	}
+	var body strings.Builder
+	fmt.Fprintf(&body, "The Bottlerocket ECS updater failed to update instance %s in cluster %s in %d passes in a row.\n\n", name, cluster, count)
+	fmt.Fprintf(&body, "* Last failure: %s\n", e.Reason)
+	fmt.Fprintf(&body, "* Pass: %s\n", e.PassID)
+	if e.UpdateID != "" {
+		fmt.Fprintf(&body, "* Update: %s\n", e.UpdateID)
+	}
+	if e.ChangeRef != "" {
+		fmt.Fprintf(&body, "* Change: %s\n", e.ChangeRef)
+	}
+	fmt.Fprintf(&body, "* Container instance: %s\n", e.ContainerInstance)
+	if e.Version != "" {
+		fmt.Fprintf(&body, "* Bottlerocket version: %s\n", e.Version)
+	}
+	if len(e.Commands) > 0 {
+		fmt.Fprintf(&body, "* SSM commands: %s\n", strings.Join(e.Commands, ", "))
+	}
+	if len(e.Services) > 0 {
+		fmt.Fprintf(&body, "* Services: %s\n", strings.Join(e.Services, ", "))
+	}
+	if metadata != nil {
+		fmt.Fprintf(&body, "* EC2: %s, %s, %s\n", metadata.ImageID, metadata.InstanceType, metadata.Architecture)
+		if metadata.AutoScalingGroup != "" {
+			fmt.Fprintf(&body, "* Auto Scaling group: %s\n", metadata.AutoScalingGroup)
+		}
+	}
+	body.WriteString("\nThe instance is retried in later passes; quarantine it with -quarantine to stop updating it while it is investigated.\n")
+	e.Body = body.String()
+	return e
+}
+
+// recordConsecutiveFailures counts the passes in a row in which the update of
+// each instance failed, in a tag on its container instance, and clears the
+// count of instances that were updated. When the count of an instance reaches
+// escalateAfter, the instance's diagnostics are sent to the failure webhook,
+// once per streak of failures.
+func (u *updater) recordConsecutiveFailures(instances []instance, summary *passSummary) {
+	type escalation struct {
+		inst  instance
+		count int
+	}
+	var escalations []escalation
+	for _, inst := range instances {
+		switch summary.outcomes[inst.instanceID] {
+		case outcomeFailed:
+			count := inst.consecutiveFailures + 1
+			_, err := u.ecs.TagResource(&ecs.TagResourceInput{
+				ResourceArn: aws.String(inst.containerInstanceID),
+				Tags:        []*ecs.Tag{{Key: aws.String(tagConsecutiveFailures), Value: aws.String(strconv.Itoa(count))}},
+			})
+			if err != nil {
+				log.Printf("Failed to record the consecutive failures of instance %#q: %v", inst, err)
+				u.warnings.add(warnDegraded, "consecutive failures of instance %q were not recorded: %v", inst.instanceID, err)
+			}
+			if count == u.escalateAfter {
+				escalations = append(escalations, escalation{inst: inst, count: count})
+			}
+		case outcomeUpdated:
+			if inst.consecutiveFailures == 0 {
+				continue
+			}
+			_, err := u.ecs.UntagResource(&ecs.UntagResourceInput{
+				ResourceArn: aws.String(inst.containerInstanceID),
+				TagKeys:     aws.StringSlice([]string{tagConsecutiveFailures}),
+			})
+			if err != nil {
+				log.Printf("Failed to clear the consecutive failures of instance %#q: %v", inst, err)
+				u.warnings.add(warnDegraded, "consecutive failures of instance %q were not cleared: %v", inst.instanceID, err)
+			}
+		}
+	}
+	if len(escalations) == 0 || u.failureWebhook == "" {
+		return
+	}
+	instanceIDs := make([]string, 0, len(escalations))
+	for _, e := range escalations {
+		instanceIDs = append(instanceIDs, e.inst.instanceID)
+	}
+	metadata, err := u.ec2Metadata(instanceIDs)
+	if err != nil {
+		log.Printf("Failed to describe EC2 instances, therefore leaving their metadata out of escalations: %v", err)
+	}
+	for _, e := range escalations {
+		var m *ec2Metadata
+		if instanceMetadata, ok := metadata[e.inst.instanceID]; ok {
+			m = &instanceMetadata
+		}
+		log.Printf("Instance %#q failed to update in %d passes in a row, escalating it to the failure webhook", e.inst, e.count)
+		if err := u.escalate(newFailureEscalation(u.clusterName(), e.inst, e.count, summary, m)); err != nil {
+			log.Printf("Failed to escalate instance %#q: %v", e.inst, err)
+			u.warnings.add(warnDegraded, "repeated failures of instance %q were not escalated: %v", e.inst.instanceID, err)
+		}
+	}
+}
+
+// escalate posts the escalation to the failure webhook. AWS account IDs are
+// masked unless logSensitive is set, as they are in the logs.
+func (u *updater) escalate(e failureEscalation) error {
+	body, err := json.Marshal(e)
+	if err != nil {
+		return fmt.Errorf("failed to encode escalation: %w", err)
+	}
+	if !u.logSensitive {
+		body = redact(body)
+	}
+	req, err := http.NewRequest(http.MethodPost, u.failureWebhook, bytes.NewReader(body))
+	if err != nil {
+		// The URL may hold a token, so leave it out of errors.
+		return errors.New("invalid failure webhook URL")
+	}
+	req.Header.Set("Content-Type", "application/json")
+	authorization := os.Getenv(webhookAuthorizationEnv)
+	if u.webhookAuth != nil {
+		authorization, err = u.webhookAuth.get(time.Now())
+		if err != nil {
+			return fmt.Errorf("failed to read the failure webhook authorization: %w", err)
+		}
+	}
+	if authorization != "" {
+		req.Header.Set("Authorization", authorization)
+	}
+	resp, err := webhookClient.Do(req)
+	if err != nil {
+		var urlErr *url.Error
+		if errors.As(err, &urlErr) {
+			err = urlErr.Err
+		}
+		return fmt.Errorf("failed to post escalation: %w", err)
+	}
+	defer resp.Body.Close()
+	io.Copy(io.Discard, io.LimitReader(resp.Body, 64*1024))
+	if resp.StatusCode < 200 || resp.StatusCode > 299 {
+		return fmt.Errorf("failure webhook responded with status %s", resp.Status)
+	}
+	return nil
+}
@@ -0,0 +1,171 @@
+package main
+
+import (
+	"encoding/json"
+	"errors"
+	"io"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ec2"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestConsecutiveFailuresFromTags(t *testing.T) {
+	tag := func(value string) []*ecs.Tag {
+		return []*ecs.Tag{{Key: aws.String(tagConsecutiveFailures), Value: aws.String(value)}}
+	}
+	assert.Equal(t, 0, consecutiveFailuresFromTags(nil))
+	assert.Equal(t, 2, consecutiveFailuresFromTags(tag("2")))
+	assert.Equal(t, 0, consecutiveFailuresFromTags(tag("two")), "should ignore invalid counts")
+	assert.Equal(t, 0, consecutiveFailuresFromTags(tag("-1")), "should ignore negative counts")
+}
+
+func TestRecordConsecutiveFailures(t *testing.T) {
+	instances := []instance{
+		{instanceID: "inst-first-failure", containerInstanceID: "arn:aws:ecs:us-west-2:123456789012:container-instance/test/first"},
+		{instanceID: "inst-third-failure", containerInstanceID: "arn:aws:ecs:us-west-2:123456789012:container-instance/test/third", consecutiveFailures: 2},
+		{instanceID: "inst-fourth-failure", containerInstanceID: "arn:aws:ecs:us-west-2:123456789012:container-instance/test/fourth", consecutiveFailures: 3},
+		{instanceID: "inst-recovered", containerInstanceID: "arn:aws:ecs:us-west-2:123456789012:container-instance/test/recovered", consecutiveFailures: 2},
+		{instanceID: "inst-updated", containerInstanceID: "arn:aws:ecs:us-west-2:123456789012:container-instance/test/updated"},
+		{instanceID: "inst-skipped", containerInstanceID: "arn:aws:ecs:us-west-2:123456789012:container-instance/test/skipped", consecutiveFailures: 2},
+	}
+	newSummary := func() *passSummary {
+		summary := newPassSummary()
+		summary.passID = "pass-1"
+		summary.changeRef = "CHG-1234"
+		summary.commands = &commandLog{}
+		summary.commands.record("command-1", "apply", []string{"inst-third-failure"})
+		summary.recordVersions([]instance{{instanceID: "inst-third-failure", bottlerocketVersion: "1.19.0"}})
+		summary.recordServices(map[string]workload{"inst-third-failure": {"service:web": 2, "service:api": 1, "family:batch": 1}})
+		summary.failedInstance("inst-first-failure", "Update failed")
+		summary.updateIDs["inst-third-failure"] = "update-1"
+		summary.failedInstance("inst-third-failure", "Failed to update: command timed out")
+		summary.failedInstance("inst-fourth-failure", "Update failed")
+		summary.updatedInstance("inst-recovered", "Instance updated successfully")
+		summary.updatedInstance("inst-updated", "Instance updated successfully")
+		summary.skippedInstance("inst-skipped", skipUpdateLimit, "Limit of 1 update(s) per pass reached")
+		return summary
+	}
+	mockEC2 := MockEC2{
+		DescribeInstancesPagesFn: func(input *ec2.DescribeInstancesInput, fn func(*ec2.DescribeInstancesOutput, bool) bool) error {
+			assert.Equal(t, []string{"inst-third-failure"}, aws.StringValueSlice(input.Filters[0].Values))
+			fn(&ec2.DescribeInstancesOutput{Reservations: []*ec2.Reservation{{
+				Instances: []*ec2.Instance{{
+					InstanceId:   aws.String("inst-third-failure"),
+					ImageId:      aws.String("ami-1"),
+					InstanceType: aws.String("m5.large"),
+					Architecture: aws.String("x86_64"),
+					Tags: []*ec2.Tag{
+						{Key: aws.String(tagName), Value: aws.String("web-1")},
+						{Key: aws.String(tagAutoScalingGroup), Value: aws.String("web-asg")},
+					},
+				}},
+			}}}, true)
+			return nil
+		},
+	}
+
+	t.Run("escalates", func(t *testing.T) {
+		t.Setenv(webhookAuthorizationEnv, "Bearer token")
+		var escalations []failureEscalation
+		server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+			assert.Equal(t, http.MethodPost, r.Method)
+			assert.Equal(t, "application/json", r.Header.Get("Content-Type"))
+			assert.Equal(t, "Bearer token", r.Header.Get("Authorization"))
+			body, err := io.ReadAll(r.Body)
+			require.NoError(t, err)
+			var e failureEscalation
+			require.NoError(t, json.Unmarshal(body, &e))
+			escalations = append(escalations, e)
+			w.WriteHeader(http.StatusCreated)
+		}))
+		defer server.Close()
+
+		tagged := make(map[string]string)
+		var untagged []string
+		mockECS := MockECS{
+			TagResourceFn: func(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+				require.Len(t, input.Tags, 1)
+				assert.Equal(t, tagConsecutiveFailures, aws.StringValue(input.Tags[0].Key))
+				tagged[aws.StringValue(input.ResourceArn)] = aws.StringValue(input.Tags[0].Value)
+				return &ecs.TagResourceOutput{}, nil
+			},
+			UntagResourceFn: func(input *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+				assert.Equal(t, []string{tagConsecutiveFailures}, aws.StringValueSlice(input.TagKeys))
+				untagged = append(untagged, aws.StringValue(input.ResourceArn))
+				return &ecs.UntagResourceOutput{}, nil
+			},
+		}
+		u := updater{cluster: "test", ecs: mockECS, ec2: mockEC2, warnings: newPassWarnings(), failureWebhook: server.URL, escalateAfter: 3}
+		u.recordConsecutiveFailures(instances, newSummary())
+
+		assert.Equal(t, map[string]string{
+			"arn:aws:ecs:us-west-2:123456789012:container-instance/test/first":  "1",
+			"arn:aws:ecs:us-west-2:123456789012:container-instance/test/third":  "3",
+			"arn:aws:ecs:us-west-2:123456789012:container-instance/test/fourth": "4",
+		}, tagged)
+		assert.Equal(t, []string{"arn:aws:ecs:us-west-2:123456789012:container-instance/test/recovered"}, untagged)
+		require.Len(t, escalations, 1, "should escalate once, when the threshold is reached")
+		e := escalations[0]
+		assert.Equal(t, "Bottlerocket instance inst-third-failure (web-1) in cluster test failed to update in 3 passes in a row", e.Title)
+		assert.Equal(t, "inst-third-failure", e.InstanceID)
+		assert.Equal(t, "arn:aws:ecs:us-west-2:************:container-instance/test/third", e.ContainerInstance, "should mask account IDs")
+		assert.Equal(t, 3, e.ConsecutiveFailures)
+		assert.Equal(t, "pass-1", e.PassID)
+		assert.Equal(t, "update-1", e.UpdateID)
+		assert.Equal(t, "CHG-1234", e.ChangeRef)
+		assert.Equal(t, "Failed to update: command timed out", e.Reason)
+		assert.Equal(t, "1.19.0", e.Version)
+		assert.Equal(t, []string{"apply:command-1"}, e.Commands)
+		assert.Equal(t, []string{"api", "web"}, e.Services)
+		assert.Equal(t, &ec2Metadata{ImageID: "ami-1", InstanceType: "m5.large", Architecture: "x86_64", Name: "web-1", AutoScalingGroup: "web-asg"}, e.EC2)
+		assert.Contains(t, e.Body, "* Last failure: Failed to update: command timed out\n")
+		assert.Contains(t, e.Body, "* Update: update-1\n")
+		assert.Contains(t, e.Body, "* Services: api, web\n")
+		assert.Contains(t, e.Body, "* EC2: ami-1, m5.large, x86_64\n")
+		assert.Contains(t, e.Body, "* Auto Scaling group: web-asg\n")
+		assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+	})
+	t.Run("webhook fails", func(t *testing.T) {
+		server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, _ *http.Request) {
+			w.WriteHeader(http.StatusUnauthorized)
+		}))
+		defer server.Close()
+		mockECS := MockECS{
+			TagResourceFn: func(_ *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+				return &ecs.TagResourceOutput{}, nil
+			},
+			UntagResourceFn: func(_ *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+				return &ecs.UntagResourceOutput{}, nil
+			},
+		}
+		u := updater{cluster: "test", ecs: mockECS, ec2: mockEC2, warnings: newPassWarnings(), failureWebhook: server.URL, escalateAfter: 3}
+		u.recordConsecutiveFailures(instances, newSummary())
+		assert.Equal(t, 1, u.warnings.counts[warnDegraded])
+	})
+}
+
+func TestEscalateAuthorizationSecret(t *testing.T) {
+	t.Setenv(webhookAuthorizationEnv, "Bearer from-env")
+	var authorizations []string
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		authorizations = append(authorizations, r.Header.Get("Authorization"))
+		w.WriteHeader(http.StatusCreated)
+	}))
+	defer server.Close()
+	provider := &fakeSecrets{values: []string{"Bearer from-secret"}}
+	u := updater{failureWebhook: server.URL, webhookAuth: &secret{provider: provider, name: "webhook", refresh: time.Hour}}
+	require.NoError(t, u.escalate(failureEscalation{Title: "title"}))
+	assert.Equal(t, []string{"Bearer from-secret"}, authorizations, "the secret should take precedence over the environment")
+
+	provider = &fakeSecrets{err: errors.New("access denied")}
+	u.webhookAuth = &secret{provider: provider, name: "webhook"}
+	assert.ErrorIs(t, u.escalate(failureEscalation{Title: "title"}), provider.err)
+	assert.Len(t, authorizations, 1, "should not post without the authorization")
+}
@@ -0,0 +1,83 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"strings"
+)
+
+// parseServices parses comma-separated ECS service names or ARNs, e.g.
+// arn:aws:ecs:us-west-2:111122223333:service/cluster/web, into a set of
+// service names, which is nil when there are none.
+func parseServices(spec string) map[string]bool {
+	var services map[string]bool
+	for _, service := range strings.Split(spec, ",") {
+		service = strings.TrimSpace(service)
+		if strings.HasPrefix(service, "arn:") {
+			service = service[strings.LastIndex(service, "/")+1:]
+		}
+		if service == "" {
+			continue
+		}
+		if services == nil {
+			services = make(map[string]bool)
+		}
+		services[service] = true
+	}
+	return services
+}
+
+// excludedServices returns the services of -exclude-services with tasks in the
+// workload, in order.
+func (u *updater) excludedServices(w workload) []string {
+	excluded := make([]string, 0)
+	for _, service := range w.services() {
+		if u.excludeServices[service] {
+			excluded = append(excluded, service)
+		}
+	}
+	return excluded
+}
+
+// skipExcluded skips the candidates running tasks of -exclude-services, which
+// require a manual update, and returns the others. Candidates whose workload
+// is unknown are skipped too, since they may run such tasks.
+func (u *updater) skipExcluded(candidates []instance, workloads map[string]workload, summary *passSummary) []instance {
+	if len(u.excludeServices) == 0 {
+		return candidates
+	}
+	kept := make([]instance, 0, len(candidates))
+	for _, inst := range candidates {
+		w, ok := workloads[inst.instanceID]
+		if !ok {
+			log.Printf("Tasks on instance %#q are unknown, therefore not updating it in case it runs excluded services", inst)
+			summary.skippedInstance(inst.instanceID, skipExcluded, "Requires manual update: tasks unknown, may run excluded services")
+			continue
+		}
+		if excluded := u.excludedServices(w); len(excluded) > 0 {
+			log.Printf("Instance %#q runs tasks of excluded service(s) %q, therefore not updating it", inst, excluded)
+			summary.skippedInstance(inst.instanceID, skipExcluded, fmt.Sprintf("Requires manual update: runs tasks of excluded service(s) %s", strings.Join(excluded, ", ")))
+			continue
+		}
+		kept = append(kept, inst)
+	}
+	return kept
+}
+
+// checkExcluded returns an error when the instance runs tasks of
+// -exclude-services, or its tasks can't be determined. Tasks move between
+// instances during a pass, e.g. off the instances drained before, so the
+// instance is checked again right before it is drained.
+func (u *updater) checkExcluded(inst instance) error {
+	if len(u.excludeServices) == 0 {
+		return nil
+	}
+	w, err := u.instanceWorkload(inst.containerInstanceID)
+	if err != nil {
+		return fmt.Errorf("tasks unknown, may run excluded services: %w", err)
+	}
+	if excluded := u.excludedServices(w); len(excluded) > 0 {
+		return fmt.Errorf("runs tasks of excluded service(s) %s", strings.Join(excluded, ", "))
+	}
+	return nil
+}
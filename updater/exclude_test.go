@@ -0,0 +1,77 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseServices(t *testing.T) {
+	assert.Nil(t, parseServices(""))
+	assert.Nil(t, parseServices(" , "))
+	assert.Equal(t, map[string]bool{"ledger": true, "payments": true, "legacy": true}, parseServices(
+		"ledger, arn:aws:ecs:us-west-2:111122223333:service/prod/payments,arn:aws:ecs:us-west-2:111122223333:service/legacy"))
+}
+
+func TestSkipExcluded(t *testing.T) {
+	candidates := []instance{
+		{instanceID: "inst-ledger", containerInstanceID: "cont-inst-ledger"},
+		{instanceID: "inst-web", containerInstanceID: "cont-inst-web"},
+		{instanceID: "inst-unknown", containerInstanceID: "cont-inst-unknown"},
+		{instanceID: "inst-family", containerInstanceID: "cont-inst-family"},
+	}
+	workloads := map[string]workload{
+		"inst-ledger": {"service:web": 1, "service:ledger": 2},
+		"inst-web":    {"service:web": 1},
+		"inst-family": {"family:ledger": 1},
+	}
+
+	u := updater{}
+	summary := newPassSummary()
+	assert.Equal(t, candidates, u.skipExcluded(candidates, workloads, summary), "nothing should be excluded without -exclude-services")
+	assert.Empty(t, summary.outcomes)
+
+	u.excludeServices = parseServices("ledger")
+	kept := u.skipExcluded(candidates, workloads, summary)
+	assert.Equal(t, []instance{candidates[1], candidates[3]}, kept)
+	assert.Equal(t, skipExcluded, summary.skipReasons["inst-ledger"])
+	assert.Equal(t, "Requires manual update: runs tasks of excluded service(s) ledger", summary.results["inst-ledger"])
+	assert.Equal(t, skipExcluded, summary.skipReasons["inst-unknown"], "instances with unknown tasks may run excluded services")
+}
+
+func TestCheckExcluded(t *testing.T) {
+	var listErr error
+	mockECS := MockECS{
+		ListTasksFn: func(input *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+			assert.Equal(t, "cont-inst-id", aws.StringValue(input.ContainerInstance))
+			return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-1", "task-2"})}, listErr
+		},
+		DescribeTasksFn: func(_ *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+			return &ecs.DescribeTasksOutput{Tasks: []*ecs.Task{
+				{TaskArn: aws.String("task-1"), Group: aws.String("service:web")},
+				{TaskArn: aws.String("task-2"), Group: aws.String("service:payments")},
+			}}, nil
+		},
+	}
+	inst := instance{instanceID: "inst-id", containerInstanceID: "cont-inst-id"}
+
+	u := updater{ecs: mockECS}
+	assert.NoError(t, u.checkExcluded(inst), "nothing is excluded without -exclude-services")
+
+	u.excludeServices = parseServices("ledger")
+	assert.NoError(t, u.checkExcluded(inst))
+
+	u.excludeServices = parseServices("ledger,payments")
+	err := u.checkExcluded(inst)
+	require.Error(t, err)
+	assert.Equal(t, "runs tasks of excluded service(s) payments", err.Error())
+
+	listErr = errors.New("failed to list")
+	err = u.checkExcluded(inst)
+	require.Error(t, err)
+	assert.ErrorIs(t, err, listErr)
+}
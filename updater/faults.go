@@ -0,0 +1,130 @@
+package main
+
+import (
+	"fmt"
+	"math/rand"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/aws/request"
+)
+
+// Kinds of faults that can be injected into AWS calls.
+const (
+	// faultFail makes a call fail before it reaches AWS.
+	faultFail = "fail"
+	// faultTimeout lets a call take effect in AWS but loses its response, as
+	// when the connection times out.
+	faultTimeout = "timeout"
+)
+
+// anyOperation matches every AWS operation in a fault spec.
+const anyOperation = "*"
+
+// faultRule injects faults of a kind into a share of the calls to an operation.
+type faultRule struct {
+	operation string
+	kind      string
+	rate      float64
+}
+
+// faultInjector makes AWS calls fail at random, to check that the updater's
+// retry, deferral and rollback paths hold up when AWS misbehaves. Faults are
+// only injected when -inject-faults is given; a nil faultInjector injects none.
+type faultInjector struct {
+	rules []faultRule
+	// seed seeds the random choice of the calls that fail, so a run can be repeated.
+	seed int64
+	mu   sync.Mutex
+	rand *rand.Rand
+}
+
+// parseFaults parses comma-separated fault rules of the form
+// operation[:kind]=rate, e.g. "SendCommand=0.05,UpdateContainerInstancesState:timeout=0.1".
+// The operation is an AWS API operation name or * for all operations, the
+// kind is fail (the default) or timeout, and the rate is the share of calls
+// that fault, from 0 to 1. A zero seed picks a seed from the current time.
+func parseFaults(spec string, seed int64) (*faultInjector, error) {
+	if seed == 0 {
+		seed = time.Now().UnixNano()
+	}
+	f := &faultInjector{seed: seed, rand: rand.New(rand.NewSource(seed))}
+	for _, part := range strings.Split(spec, ",") {
+		part = strings.TrimSpace(part)
+		i := strings.LastIndex(part, "=")
+		if i < 0 {
+			return nil, fmt.Errorf("fault %q must have the form operation[:kind]=rate", part)
+		}
+		rule := faultRule{operation: part[:i], kind: faultFail}
+		if j := strings.Index(rule.operation, ":"); j >= 0 {
+			rule.operation, rule.kind = rule.operation[:j], rule.operation[j+1:]
+		}
+		if rule.operation == "" {
+			return nil, fmt.Errorf("fault %q has no operation", part)
+		}
+		if rule.kind != faultFail && rule.kind != faultTimeout {
+			return nil, fmt.Errorf("fault %q has unknown kind %q, expected %s or %s", part, rule.kind, faultFail, faultTimeout)
+		}
+		rate, err := strconv.ParseFloat(part[i+1:], 64)
+		if err != nil || rate < 0 || rate > 1 {
+			return nil, fmt.Errorf("fault %q must have a rate from 0 to 1", part)
+		}
+		rule.rate = rate
+		f.rules = append(f.rules, rule)
+	}
+	return f, nil
+}
+
+// fault returns the error to inject into a call to the operation for the
+// first of the kinds that the call is chosen to fault with, or nil when the
+// call should go ahead. Injected errors look like the SDK's own network
+// errors, so they are retryable.
+func (f *faultInjector) fault(operation string, kinds ...string) error {
+	if f == nil {
+		return nil
+	}
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	for _, kind := range kinds {
+		for _, rule := range f.rules {
+			if rule.kind != kind || (rule.operation != operation && rule.operation != anyOperation) {
+				continue
+			}
+			if f.rand.Float64() >= rule.rate {
+				continue
+			}
+			if kind == faultTimeout {
+				return awserr.New(request.ErrCodeResponseTimeout, fmt.Sprintf("injected fault: response to %s lost", operation), nil)
+			}
+			return awserr.New(request.ErrCodeRequestError, fmt.Sprintf("injected fault: %s failed", operation), nil)
+		}
+	}
+	return nil
+}
+
+// install injects faults into the requests of every AWS client created with
+// the handlers. Failures are injected when a request is built, so the SDK
+// does not retry them. Timeouts are injected after a request is sent, so the
+// SDK retries them like real timeouts, except for calls that are not safe to
+// retry (see sendCommandRetryer).
+func (f *faultInjector) install(handlers *request.Handlers) {
+	handlers.Build.PushBack(func(r *request.Request) {
+		if err := f.fault(r.Operation.Name, faultFail); err != nil {
+			r.Error = err
+		}
+	})
+	handlers.Send.PushBack(func(r *request.Request) {
+		if r.Error != nil {
+			return
+		}
+		if err := f.fault(r.Operation.Name, faultTimeout); err != nil {
+			if r.HTTPResponse != nil && r.HTTPResponse.Body != nil {
+				r.HTTPResponse.Body.Close()
+			}
+			r.Error = err
+		}
+	})
+}
@@ -0,0 +1,52 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseFaults(t *testing.T) {
+	f, err := parseFaults("SendCommand=0.05, UpdateContainerInstancesState:timeout=1,*=0", 1)
+	require.NoError(t, err)
+	assert.Equal(t, []faultRule{
+		{operation: "SendCommand", kind: faultFail, rate: 0.05},
+		{operation: "UpdateContainerInstancesState", kind: faultTimeout, rate: 1},
+		{operation: anyOperation, kind: faultFail, rate: 0},
+	}, f.rules)
+
+	for _, spec := range []string{"SendCommand", "=0.5", "SendCommand:hang=0.5", "SendCommand=x", "SendCommand=2", "SendCommand=-1"} {
+		_, err := parseFaults(spec, 1)
+		assert.Error(t, err, "spec %q should be invalid", spec)
+	}
+}
+
+func TestInjectFault(t *testing.T) {
+	var none *faultInjector
+	assert.NoError(t, none.fault("SendCommand", faultFail, faultTimeout))
+
+	f, err := parseFaults("*:timeout=1,ListTasks=1", 1)
+	require.NoError(t, err)
+	err = f.fault("ListTasks", faultFail, faultTimeout)
+	assert.True(t, request.IsErrorRetryable(err))
+	assert.Contains(t, err.Error(), "ListTasks failed")
+	err = f.fault("SendCommand", faultFail)
+	assert.NoError(t, err)
+	err = f.fault("SendCommand", faultTimeout)
+	assert.True(t, request.IsErrorRetryable(err))
+	assert.Contains(t, err.Error(), "response to SendCommand lost")
+
+	// The same seed injects faults into the same calls.
+	choices := func() []bool {
+		f, err := parseFaults("SendCommand=0.5", 42)
+		require.NoError(t, err)
+		faulted := make([]bool, 20)
+		for i := range faulted {
+			faulted[i] = f.fault("SendCommand", faultFail) != nil
+		}
+		return faulted
+	}
+	assert.Equal(t, choices(), choices())
+}
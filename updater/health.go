@@ -0,0 +1,91 @@
+package main
+
+import (
+	"fmt"
+	"log"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+// clusterHealth counts a cluster's registered container instances and lists
+// the unhealthy ones.
+type clusterHealth struct {
+	total     int
+	unhealthy []instanceFailure
+}
+
+// unhealthyFraction returns the share of the container instances that are unhealthy.
+func (h clusterHealth) unhealthyFraction() float64 {
+	if h.total == 0 {
+		return 0
+	}
+	return float64(len(h.unhealthy)) / float64(h.total)
+}
+
+// clusterHealth lists every container instance registered to the cluster,
+// whatever its status, and reports as unhealthy those that are not ACTIVE,
+// whose ECS agent is disconnected, or that can't be described.
+func (u *updater) clusterHealth() (clusterHealth, error) {
+	var arns []*string
+	input := &ecs.ListContainerInstancesInput{Cluster: &u.cluster}
+	if err := u.ecs.ListContainerInstancesPages(input, func(output *ecs.ListContainerInstancesOutput, _ bool) bool {
+		arns = append(arns, output.ContainerInstanceArns...)
+		return true
+	}); err != nil {
+		return clusterHealth{}, fmt.Errorf("failed to list container instances: %w", err)
+	}
+	health := clusterHealth{total: len(arns)}
+	_, err := eachPage(len(arns), ecsPageSize, func(start, stop int) error {
+		resp, err := u.ecs.DescribeContainerInstances(&ecs.DescribeContainerInstancesInput{
+			Cluster:            &u.cluster,
+			ContainerInstances: arns[start:stop],
+		})
+		if err != nil {
+			return fmt.Errorf("failed to describe container instances: %w", err)
+		}
+		for _, failure := range resp.Failures {
+			health.unhealthy = append(health.unhealthy, instanceFailure{
+				arn:    aws.StringValue(failure.Arn),
+				reason: fmt.Sprintf("failed to describe container instance: %s", aws.StringValue(failure.Reason)),
+			})
+		}
+		for _, containerInstance := range resp.ContainerInstances {
+			arn := aws.StringValue(containerInstance.ContainerInstanceArn)
+			switch status := aws.StringValue(containerInstance.Status); {
+			case status != ecs.ContainerInstanceStatusActive:
+				health.unhealthy = append(health.unhealthy, instanceFailure{arn: arn, reason: fmt.Sprintf("status is %s", status)})
+			case !aws.BoolValue(containerInstance.AgentConnected):
+				health.unhealthy = append(health.unhealthy, instanceFailure{arn: arn, reason: "ECS agent is disconnected"})
+			}
+		}
+		return nil
+	})
+	if err != nil {
+		return clusterHealth{}, err
+	}
+	return health, nil
+}
+
+// checkClusterHealth returns an error when more than the allowed share of
+// the cluster's container instances is unhealthy, so that a pass does not
+// take instances out of service during an incident that already affects the
+// cluster. The check is skipped when maxUnhealthy is 1 or more.
+func (u *updater) checkClusterHealth() error {
+	if u.maxUnhealthy >= 1 {
+		return nil
+	}
+	health, err := u.clusterHealth()
+	if err != nil {
+		return fmt.Errorf("cannot determine the health of cluster %q, therefore not starting the pass: %w", u.cluster, err)
+	}
+	log.Printf("%d of %d container instance(s) in cluster %q are unhealthy", len(health.unhealthy), health.total, u.cluster)
+	if health.unhealthyFraction() <= u.maxUnhealthy {
+		return nil
+	}
+	for _, failure := range health.unhealthy {
+		log.Printf("Container instance %q is unhealthy: %s", failure.arn, failure.reason)
+	}
+	return fmt.Errorf("cluster %q is unhealthy, therefore not starting the pass: %d of %d container instance(s) are unhealthy, more than the %g allowed by -max-unhealthy-fraction",
+		u.cluster, len(health.unhealthy), health.total, u.maxUnhealthy)
+}
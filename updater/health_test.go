@@ -0,0 +1,99 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestCheckClusterHealth(t *testing.T) {
+	instances := &ecs.DescribeContainerInstancesOutput{
+		ContainerInstances: []*ecs.ContainerInstance{
+			{ContainerInstanceArn: aws.String("arn-active"), Status: aws.String(ecs.ContainerInstanceStatusActive), AgentConnected: aws.Bool(true)},
+			{ContainerInstanceArn: aws.String("arn-active-2"), Status: aws.String(ecs.ContainerInstanceStatusActive), AgentConnected: aws.Bool(true)},
+			{ContainerInstanceArn: aws.String("arn-draining"), Status: aws.String(ecs.ContainerInstanceStatusDraining), AgentConnected: aws.Bool(true)},
+		},
+		Failures: []*ecs.Failure{{Arn: aws.String("arn-missing"), Reason: aws.String("MISSING")}},
+	}
+	disconnected := &ecs.DescribeContainerInstancesOutput{
+		ContainerInstances: []*ecs.ContainerInstance{
+			{ContainerInstanceArn: aws.String("arn-active"), Status: aws.String(ecs.ContainerInstanceStatusActive), AgentConnected: aws.Bool(true)},
+			{ContainerInstanceArn: aws.String("arn-active-2"), Status: aws.String(ecs.ContainerInstanceStatusActive), AgentConnected: aws.Bool(false)},
+		},
+	}
+	cases := []struct {
+		name          string
+		maxUnhealthy  float64
+		describe      *ecs.DescribeContainerInstancesOutput
+		describeErr   error
+		expectedError string
+		expectCalls   bool
+	}{
+		{
+			name:         "disabled",
+			maxUnhealthy: 1,
+			describe:     instances,
+		},
+		{
+			name:         "within limit",
+			maxUnhealthy: 0.5,
+			describe:     instances,
+			expectCalls:  true,
+		},
+		{
+			name:          "over limit",
+			maxUnhealthy:  0.25,
+			describe:      instances,
+			expectedError: "2 of 4 container instance(s) are unhealthy",
+			expectCalls:   true,
+		},
+		{
+			name:          "agent disconnected",
+			maxUnhealthy:  0,
+			describe:      disconnected,
+			expectedError: "1 of 4 container instance(s) are unhealthy",
+			expectCalls:   true,
+		},
+		{
+			name:          "describe fails",
+			maxUnhealthy:  0.5,
+			describeErr:   errors.New("failed to describe"),
+			expectedError: "cannot determine the health of cluster",
+			expectCalls:   true,
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			called := false
+			mockECS := MockECS{
+				ListContainerInstancesPagesFn: func(input *ecs.ListContainerInstancesInput, fn func(*ecs.ListContainerInstancesOutput, bool) bool) error {
+					called = true
+					assert.Nil(t, input.Status, "should list container instances of every status")
+					fn(&ecs.ListContainerInstancesOutput{
+						ContainerInstanceArns: aws.StringSlice([]string{"arn-active", "arn-active-2", "arn-draining", "arn-missing"}),
+					}, true)
+					return nil
+				},
+				DescribeContainerInstancesFn: func(_ *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+					return tc.describe, tc.describeErr
+				},
+			}
+			u := updater{ecs: mockECS, cluster: "cluster", maxUnhealthy: tc.maxUnhealthy}
+			err := u.checkClusterHealth()
+			assert.Equal(t, tc.expectCalls, called)
+			if tc.expectedError == "" {
+				assert.NoError(t, err)
+				return
+			}
+			require.Error(t, err)
+			assert.Contains(t, err.Error(), tc.expectedError)
+			if tc.describeErr != nil {
+				assert.ErrorIs(t, err, tc.describeErr)
+			}
+		})
+	}
+}
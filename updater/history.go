@@ -0,0 +1,176 @@
+package main
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"io/fs"
+	"os"
+	"sort"
+	"text/tabwriter"
+	"time"
+)
+
+// passHistory is the file written with -history: a summary of every pass
+// within the retention, oldest first, shared by the updaters of any number of
+// clusters.
+type passHistory struct {
+	Passes []historyEntry `json:"passes"`
+}
+
+// historyEntry summarizes a pass for the history.
+type historyEntry struct {
+	Cluster         string           `json:"cluster"`
+	PassID          string           `json:"passId"`
+	Time            time.Time        `json:"time"`
+	DurationSeconds float64          `json:"durationSeconds"`
+	Updated         int              `json:"updated"`
+	Failed          int              `json:"failed"`
+	Skipped         int              `json:"skipped"`
+	Versions        versionHistogram `json:"versions"`
+}
+
+// newHistoryEntry summarizes the pass recorded in the summary, which started
+// at start and ended at now.
+func newHistoryEntry(cluster string, summary *passSummary, start time.Time, now time.Time) historyEntry {
+	return historyEntry{
+		Cluster:         cluster,
+		PassID:          summary.passID,
+		Time:            now.UTC(),
+		DurationSeconds: now.Sub(start).Seconds(),
+		Updated:         summary.updated,
+		Failed:          summary.failed,
+		Skipped:         summary.skipped,
+		Versions:        histogram(summary.versionsAfter),
+	}
+}
+
+// readHistory reads the history at path; a missing history is empty.
+func readHistory(path string) (passHistory, error) {
+	var history passHistory
+	data, err := os.ReadFile(path)
+	if errors.Is(err, fs.ErrNotExist) {
+		return history, nil
+	}
+	if err != nil {
+		return history, fmt.Errorf("failed to read history: %w", err)
+	}
+	if err := json.Unmarshal(data, &history); err != nil {
+		return history, fmt.Errorf("failed to parse history %q: %w", path, err)
+	}
+	return history, nil
+}
+
+// appendHistory adds the entry to the history at path, dropping the passes
+// that are retention or more older than now; a retention of 0 keeps every
+// pass.
+func appendHistory(path string, entry historyEntry, retention time.Duration, now time.Time) error {
+	history, err := readHistory(path)
+	if err != nil {
+		return err
+	}
+	kept := make([]historyEntry, 0, len(history.Passes)+1)
+	for _, pass := range history.Passes {
+		if retention <= 0 || now.Sub(pass.Time) < retention {
+			kept = append(kept, pass)
+		}
+	}
+	history.Passes = append(kept, entry)
+	return writeFileAtomic(path, history)
+}
+
+// historyWeek aggregates the passes over a cluster in a week.
+type historyWeek struct {
+	start    time.Time
+	passes   int
+	duration float64
+	updated  int
+	failed   int
+	// versions are those of the last pass of the week.
+	versions versionHistogram
+}
+
+// printHistory writes, for each cluster in the history at path, or only for
+// cluster when it is not empty, the passes per week with their average
+// duration, updates and failures, and the Bottlerocket versions the cluster
+// ran at the end of the week, e.g.:
+//
+//	Cluster "prod": 14 pass(es) from 2024-03-04 to 2024-03-17, 9m30s on average
+//	WEEK OF     PASSES  AVG DURATION  UPDATED  FAILED  VERSIONS
+//	2024-03-04  7       12m0s         10       1       1.19.0: 3, 1.20.0: 7
+//	2024-03-11  7       7m0s          3        0       1.20.0: 10
+func printHistory(w io.Writer, path string, cluster string) error {
+	history, err := readHistory(path)
+	if err != nil {
+		return err
+	}
+	byCluster := make(map[string][]historyEntry)
+	for _, pass := range history.Passes {
+		if cluster == "" || pass.Cluster == cluster {
+			byCluster[pass.Cluster] = append(byCluster[pass.Cluster], pass)
+		}
+	}
+	if len(byCluster) == 0 {
+		fmt.Fprintf(w, "No passes in history %q\n", path)
+		return nil
+	}
+	clusters := make([]string, 0, len(byCluster))
+	for name := range byCluster {
+		clusters = append(clusters, name)
+	}
+	sort.Strings(clusters)
+	for n, name := range clusters {
+		if n > 0 {
+			fmt.Fprintln(w)
+		}
+		passes := byCluster[name]
+		sort.Slice(passes, func(i, j int) bool { return passes[i].Time.Before(passes[j].Time) })
+		var total float64
+		for _, pass := range passes {
+			total += pass.DurationSeconds
+		}
+		fmt.Fprintf(w, "Cluster %q: %d pass(es) from %s to %s, %s on average\n", name, len(passes),
+			passes[0].Time.Format("2006-01-02"), passes[len(passes)-1].Time.Format("2006-01-02"), averageDuration(total, len(passes)))
+		tw := tabwriter.NewWriter(w, 0, 0, 2, ' ', 0)
+		fmt.Fprintln(tw, "WEEK OF\tPASSES\tAVG DURATION\tUPDATED\tFAILED\tVERSIONS")
+		for _, week := range historyWeeks(passes) {
+			fmt.Fprintf(tw, "%s\t%d\t%s\t%d\t%d\t%s\n", week.start.Format("2006-01-02"), week.passes,
+				averageDuration(week.duration, week.passes), week.updated, week.failed, week.versions)
+		}
+		if err := tw.Flush(); err != nil {
+			return fmt.Errorf("failed to print history: %w", err)
+		}
+	}
+	return nil
+}
+
+// historyWeeks aggregates passes, which are in order, per week starting on
+// Monday in UTC.
+func historyWeeks(passes []historyEntry) []historyWeek {
+	weeks := make([]historyWeek, 0)
+	for _, pass := range passes {
+		t := pass.Time.UTC()
+		day := time.Date(t.Year(), t.Month(), t.Day(), 0, 0, 0, 0, time.UTC)
+		start := day.AddDate(0, 0, -((int(day.Weekday()) + 6) % 7))
+		if len(weeks) == 0 || !weeks[len(weeks)-1].start.Equal(start) {
+			weeks = append(weeks, historyWeek{start: start})
+		}
+		week := &weeks[len(weeks)-1]
+		week.passes++
+		week.duration += pass.DurationSeconds
+		week.updated += pass.Updated
+		week.failed += pass.Failed
+		week.versions = pass.Versions
+	}
+	return weeks
+}
+
+// averageDuration returns the average of passes durations adding up to total
+// seconds, rounded to the second.
+func averageDuration(total float64, passes int) time.Duration {
+	if passes == 0 {
+		return 0
+	}
+	return time.Duration(total / float64(passes) * float64(time.Second)).Round(time.Second)
+}
@@ -0,0 +1,74 @@
+package main
+
+import (
+	"bytes"
+	"path/filepath"
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestAppendHistory(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "history.json")
+	now := time.Date(2024, 3, 15, 12, 0, 0, 0, time.UTC)
+	summary := newPassSummary()
+	summary.passID = "pass-1"
+	summary.recordVersions([]instance{{instanceID: "inst-id-1", bottlerocketVersion: "1.19.0"}, {instanceID: "inst-id-2", bottlerocketVersion: "1.19.0"}})
+	summary.updatedVersion("inst-id-1", "1.20.0")
+	summary.updatedInstance("inst-id-1", "Instance updated successfully")
+	entry := newHistoryEntry("prod", summary, now.Add(-10*time.Minute), now)
+	assert.Equal(t, historyEntry{
+		Cluster:         "prod",
+		PassID:          "pass-1",
+		Time:            now,
+		DurationSeconds: 600,
+		Updated:         1,
+		Versions:        versionHistogram{"1.19.0": 1, "1.20.0": 1},
+	}, entry)
+
+	old := entry
+	old.PassID = "pass-0"
+	old.Time = now.AddDate(0, 0, -100)
+	require.NoError(t, appendHistory(path, old, 0, old.Time))
+	require.NoError(t, appendHistory(path, entry, 90*24*time.Hour, now))
+	history, err := readHistory(path)
+	require.NoError(t, err)
+	require.Len(t, history.Passes, 1, "passes older than the retention should be dropped")
+	assert.Equal(t, "pass-1", history.Passes[0].PassID)
+
+	history, err = readHistory(filepath.Join(t.TempDir(), "missing.json"))
+	require.NoError(t, err)
+	assert.Empty(t, history.Passes)
+}
+
+func TestPrintHistory(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "history.json")
+	monday := time.Date(2024, 3, 4, 6, 0, 0, 0, time.UTC)
+	passes := []historyEntry{
+		{Cluster: "prod", Time: monday, DurationSeconds: 900, Updated: 7, Failed: 1, Versions: versionHistogram{"1.19.0": 3, "1.20.0": 7}},
+		{Cluster: "prod", Time: monday.AddDate(0, 0, 6), DurationSeconds: 540, Updated: 3, Versions: versionHistogram{"1.19.0": 1, "1.20.0": 9}},
+		{Cluster: "prod", Time: monday.AddDate(0, 0, 7), DurationSeconds: 420, Versions: versionHistogram{"1.20.0": 10}},
+		{Cluster: "staging", Time: monday, DurationSeconds: 60, Versions: versionHistogram{"1.20.0": 2}},
+	}
+	for _, pass := range passes {
+		require.NoError(t, appendHistory(path, pass, 0, pass.Time))
+	}
+
+	var out bytes.Buffer
+	require.NoError(t, printHistory(&out, path, "prod"))
+	assert.Equal(t, `Cluster "prod": 3 pass(es) from 2024-03-04 to 2024-03-11, 10m20s on average
+WEEK OF     PASSES  AVG DURATION  UPDATED  FAILED  VERSIONS
+2024-03-04  2       12m0s         10       1       1.19.0: 1, 1.20.0: 9
+2024-03-11  1       7m0s          0        0       1.20.0: 10
+`, out.String())
+
+	out.Reset()
+	require.NoError(t, printHistory(&out, path, ""))
+	assert.Contains(t, out.String(), `Cluster "staging": 1 pass(es)`)
+
+	out.Reset()
+	require.NoError(t, printHistory(&out, path, "dev"))
+	assert.Contains(t, out.String(), "No passes in history")
+}
@@ -0,0 +1,117 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"reflect"
+	"sort"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws/endpoints"
+)
+
+// iamPolicy is an IAM policy document.
+type iamPolicy struct {
+	Version   string         `json:"Version"`
+	Statement []iamStatement `json:"Statement"`
+}
+
+type iamStatement struct {
+	Effect   string   `json:"Effect"`
+	Action   []string `json:"Action"`
+	Resource []string `json:"Resource"`
+}
+
+// iamWaiterActions maps the SDK waiters the updater uses to the actions they call.
+var iamWaiterActions = map[string]string{
+	"WaitUntilTasksStopped":     "DescribeTasks",
+	"WaitUntilTasksRunning":     "DescribeTasks",
+	"WaitUntilInstanceStatusOk": "DescribeInstanceStatus",
+}
+
+// iamActions returns the IAM actions, in the service's IAM namespace, of the
+// calls of the API interfaces. Paginated and context variants of a call and
+// waiters map to the actions they call.
+func iamActions(namespace string, apis ...reflect.Type) []string {
+	actions := make([]string, 0)
+	for _, api := range apis {
+		for i := 0; i < api.NumMethod(); i++ {
+			name := strings.TrimSuffix(api.Method(i).Name, "WithContext")
+			name = strings.TrimSuffix(name, "Pages")
+			if action, ok := iamWaiterActions[name]; ok {
+				name = action
+			}
+			actions = append(actions, namespace+":"+name)
+		}
+	}
+	return actions
+}
+
+// apiType returns the type of the API interface I.
+func apiType(api interface{}) reflect.Type {
+	return reflect.TypeOf(api).Elem()
+}
+
+// updaterPolicy returns the IAM policy of the calls the updater makes in a
+// pass, generated from its read and write API interfaces, so that it can't
+// fall behind them. In read-only mode, the policy leaves out the write
+// interfaces and only allows SendCommand of the check document. Resources are
+// not scoped to the cluster; see the stack for a policy that is. Reading the
+// secret of -failure-webhook-authorization is left out, since it should only
+// be allowed for that secret.
+func updaterPolicy(region, checkDocument string, readOnly bool) iamPolicy {
+	actions := make([]string, 0)
+	actions = append(actions, iamActions("ecs", apiType((*ECSReadAPI)(nil)))...)
+	actions = append(actions, iamActions("ssm", apiType((*SSMReadAPI)(nil)))...)
+	actions = append(actions, iamActions("ec2", apiType((*EC2API)(nil)))...)
+	actions = append(actions, iamActions("autoscaling", apiType((*AutoScalingReadAPI)(nil)))...)
+	actions = append(actions, iamActions("elasticloadbalancing", apiType((*ELBV2API)(nil)))...)
+	actions = append(actions, iamActions("sts", apiType((*STSAPI)(nil)))...)
+	statements := make([]iamStatement, 0)
+	if readOnly {
+		partition := "aws"
+		if p, ok := endpoints.PartitionForRegion(endpoints.DefaultPartitions(), region); ok {
+			partition = p.ID()
+		}
+		document := checkDocument
+		if document == "" {
+			document = "*"
+		}
+		statements = append(statements, iamStatement{
+			Effect: "Allow",
+			Action: []string{"ssm:SendCommand"},
+			Resource: []string{
+				fmt.Sprintf("arn:%s:ssm:*:*:document/%s", partition, document),
+				fmt.Sprintf("arn:%s:ec2:*:*:instance/*", partition),
+			},
+		})
+	} else {
+		actions = append(actions, iamActions("ecs", apiType((*ECSWriteAPI)(nil)))...)
+		actions = append(actions, iamActions("ssm", apiType((*SSMWriteAPI)(nil)))...)
+		actions = append(actions, iamActions("autoscaling", apiType((*AutoScalingWriteAPI)(nil)))...)
+	}
+	statements = append([]iamStatement{{Effect: "Allow", Action: uniqueSorted(actions), Resource: []string{"*"}}}, statements...)
+	return iamPolicy{Version: "2012-10-17", Statement: statements}
+}
+
+// uniqueSorted sorts the strings and removes duplicates.
+func uniqueSorted(values []string) []string {
+	sort.Strings(values)
+	unique := make([]string, 0, len(values))
+	for i, value := range values {
+		if i == 0 || value != values[i-1] {
+			unique = append(unique, value)
+		}
+	}
+	return unique
+}
+
+// printUpdaterPolicy prints the policy of updaterPolicy as indented JSON.
+func printUpdaterPolicy(region, checkDocument string, readOnly bool) error {
+	out, err := json.MarshalIndent(updaterPolicy(region, checkDocument, readOnly), "", "  ")
+	if err != nil {
+		return fmt.Errorf("failed to encode IAM policy: %w", err)
+	}
+	fmt.Println(string(out))
+	return nil
+}
@@ -0,0 +1,33 @@
+package main
+
+import (
+	"strings"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestUpdaterPolicy(t *testing.T) {
+	full := updaterPolicy("us-west-2", "check-document", false)
+	require.Len(t, full.Statement, 1)
+	assert.Equal(t, []string{"*"}, full.Statement[0].Resource)
+	for _, action := range []string{"ecs:ListContainerInstances", "ecs:DescribeTasks", "ecs:UpdateContainerInstancesState", "ssm:SendCommand", "ec2:DescribeInstanceStatus", "autoscaling:SuspendProcesses"} {
+		assert.Contains(t, full.Statement[0].Action, action)
+	}
+	for _, action := range full.Statement[0].Action {
+		assert.False(t, strings.HasSuffix(action, "Pages") || strings.Contains(action, "WaitUntil") || strings.HasSuffix(action, "WithContext"), "%s is not an IAM action", action)
+	}
+
+	readOnly := updaterPolicy("cn-north-1", "check-document", true)
+	require.Len(t, readOnly.Statement, 2)
+	assert.Contains(t, readOnly.Statement[0].Action, "ecs:ListContainerInstances")
+	for _, action := range []string{"ecs:UpdateContainerInstancesState", "ecs:TagResource", "ssm:SendCommand", "ssm:PutParameter", "autoscaling:SuspendProcesses"} {
+		assert.NotContains(t, readOnly.Statement[0].Action, action)
+	}
+	assert.Equal(t, iamStatement{
+		Effect:   "Allow",
+		Action:   []string{"ssm:SendCommand"},
+		Resource: []string{"arn:aws-cn:ssm:*:*:document/check-document", "arn:aws-cn:ec2:*:*:instance/*"},
+	}, readOnly.Statement[1], "should only allow sending the check document")
+}
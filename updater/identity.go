@@ -0,0 +1,26 @@
+package main
+
+import (
+	"fmt"
+	"log"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/sts"
+)
+
+// callerIdentity returns the ARN of the IAM identity the updater runs as. When
+// expectedAccount is set, it fails if the identity belongs to another account,
+// so an updater configured for one account can't act on another by mistake.
+func callerIdentity(client STSAPI, expectedAccount string) (string, error) {
+	resp, err := client.GetCallerIdentity(&sts.GetCallerIdentityInput{})
+	if err != nil {
+		return "", fmt.Errorf("failed to get caller identity: %w", err)
+	}
+	identity := aws.StringValue(resp.Arn)
+	account := aws.StringValue(resp.Account)
+	log.Printf("Running as %q in account %q", identity, account)
+	if expectedAccount != "" && account != expectedAccount {
+		return "", fmt.Errorf("running in account %q, but expected account %q", account, expectedAccount)
+	}
+	return identity, nil
+}
@@ -0,0 +1,63 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/sts"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestCallerIdentity(t *testing.T) {
+	const identityARN = "arn:aws:sts::111111111111:assumed-role/updater-role/task-id"
+	mockSTS := MockSTS{
+		GetCallerIdentityFn: func(_ *sts.GetCallerIdentityInput) (*sts.GetCallerIdentityOutput, error) {
+			return &sts.GetCallerIdentityOutput{
+				Account: aws.String("111111111111"),
+				Arn:     aws.String(identityARN),
+			}, nil
+		},
+	}
+	cases := []struct {
+		name            string
+		expectedAccount string
+		expectedErr     string
+	}{
+		{
+			name: "no expected account",
+		},
+		{
+			name:            "expected account",
+			expectedAccount: "111111111111",
+		},
+		{
+			name:            "unexpected account",
+			expectedAccount: "222222222222",
+			expectedErr:     "running in account \"111111111111\", but expected account \"222222222222\"",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			identity, err := callerIdentity(mockSTS, tc.expectedAccount)
+			if tc.expectedErr != "" {
+				require.Error(t, err)
+				assert.EqualError(t, err, tc.expectedErr)
+				assert.Empty(t, identity)
+			} else {
+				require.NoError(t, err)
+				assert.Equal(t, identityARN, identity)
+			}
+		})
+	}
+	t.Run("sts err", func(t *testing.T) {
+		stsErr := errors.New("expired token")
+		_, err := callerIdentity(MockSTS{
+			GetCallerIdentityFn: func(_ *sts.GetCallerIdentityInput) (*sts.GetCallerIdentityOutput, error) {
+				return nil, stsErr
+			},
+		}, "")
+		assert.ErrorIs(t, err, stsErr)
+	})
+}
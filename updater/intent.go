@@ -0,0 +1,180 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+// tagIntent records on a container instance the destructive action the
+// updater is about to take on it, before taking it, as "<action> <update ID>
+// <RFC 3339 time>". The tag is removed once the instance is ACTIVE again, so
+// a tag found at the start of a pass is an action a crashed or interrupted
+// updater left half-done.
+const tagIntent = "bottlerocket-ecs-updater:intent"
+
+// Actions recorded in intents.
+const (
+	intentDrain  = "drain"
+	intentUpdate = "update"
+	intentReboot = "reboot"
+)
+
+// intent is an action the updater recorded on a container instance and did
+// not resolve.
+type intent struct {
+	containerInstanceID string
+	instanceID          string
+	// status is the status of the container instance when the intent was
+	// found, e.g. DRAINING.
+	status   string
+	action   string
+	updateID string
+	since    time.Time
+}
+
+// reportIntent is an unresolved intent found at the start of a pass, in the
+// -report.
+type reportIntent struct {
+	InstanceID string    `json:"instanceId"`
+	Action     string    `json:"action"`
+	UpdateID   string    `json:"updateId,omitempty"`
+	Since      time.Time `json:"since"`
+	Status     string    `json:"status"`
+	// Recovered is set when the pass returned the instance to ACTIVE, or
+	// found it ACTIVE, and resolved the intent.
+	Recovered bool `json:"recovered"`
+}
+
+// formatIntent returns the value of the intent tag of the action.
+func formatIntent(action string, updateID string, now time.Time) string {
+	return fmt.Sprintf("%s %s %s", action, updateID, now.UTC().Format(time.RFC3339))
+}
+
+// parseIntent parses the value of an intent tag into the intent's action,
+// update ID and time; an intent that can't be parsed has the action unknown,
+// so that it is still recovered.
+func parseIntent(value string) intent {
+	fields := strings.Fields(value)
+	if len(fields) != 3 {
+		return intent{action: "unknown"}
+	}
+	since, err := time.Parse(time.RFC3339, fields[2])
+	if err != nil {
+		return intent{action: "unknown"}
+	}
+	return intent{action: fields[0], updateID: fields[1], since: since}
+}
+
+// recordIntent tags the container instance of inst with the intent to take
+// the action in the current update. Actions must not be taken when their
+// intent could not be recorded, since a crash would leave them untracked.
+func (u *updater) recordIntent(inst instance, action string, now time.Time) error {
+	if !u.intentRecords {
+		return nil
+	}
+	_, err := u.ecs.TagResource(&ecs.TagResourceInput{
+		ResourceArn: aws.String(inst.containerInstanceID),
+		Tags:        []*ecs.Tag{{Key: aws.String(tagIntent), Value: aws.String(formatIntent(action, u.updateID, now))}},
+	})
+	if err != nil {
+		return fmt.Errorf("failed to record intent to %s: %w", action, err)
+	}
+	return nil
+}
+
+// resolveIntent removes the intent tag of the container instance once it is
+// ACTIVE again. An intent that could not be removed is recovered at the start
+// of the next pass.
+func (u *updater) resolveIntent(containerInstance string) {
+	_, err := u.ecs.UntagResource(&ecs.UntagResourceInput{
+		ResourceArn: aws.String(containerInstance),
+		TagKeys:     aws.StringSlice([]string{tagIntent}),
+	})
+	if err != nil {
+		log.Printf("Failed to resolve the intent recorded on container instance %q: %v", containerInstance, err)
+		u.warnings.add(warnDegraded, "intent on container instance %q was not resolved: %v", containerInstance, err)
+	}
+}
+
+// findIntents returns the unresolved intents of the ACTIVE and DRAINING
+// container instances of the cluster.
+func (u *updater) findIntents() ([]intent, error) {
+	var arns []*string
+	err := u.ecs.ListContainerInstancesPages(&ecs.ListContainerInstancesInput{
+		Cluster: &u.cluster,
+	}, func(output *ecs.ListContainerInstancesOutput, _ bool) bool {
+		arns = append(arns, output.ContainerInstanceArns...)
+		return true
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to list container instances: %w", err)
+	}
+	intents := make([]intent, 0)
+	_, err = eachPage(len(arns), ecsPageSize, func(start, stop int) error {
+		resp, err := u.ecs.DescribeContainerInstances(&ecs.DescribeContainerInstancesInput{
+			Cluster:            &u.cluster,
+			ContainerInstances: arns[start:stop],
+			Include:            aws.StringSlice([]string{ecs.ContainerInstanceFieldTags}),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to describe container instances: %w", err)
+		}
+		for _, containerInstance := range resp.ContainerInstances {
+			for _, tag := range containerInstance.Tags {
+				if aws.StringValue(tag.Key) != tagIntent {
+					continue
+				}
+				found := parseIntent(aws.StringValue(tag.Value))
+				found.containerInstanceID = aws.StringValue(containerInstance.ContainerInstanceArn)
+				found.instanceID = aws.StringValue(containerInstance.Ec2InstanceId)
+				found.status = aws.StringValue(containerInstance.Status)
+				intents = append(intents, found)
+			}
+		}
+		return nil
+	})
+	if err != nil {
+		return nil, err
+	}
+	return intents, nil
+}
+
+// recoverIntents warns about each unresolved intent, records it for the
+// report, and recovers it: DRAINING instances are returned to ACTIVE, as the
+// updater does when an update fails, and the intents of ACTIVE instances are
+// resolved. In check-only mode, intents are only warned about and reported.
+func (u *updater) recoverIntents(intents []intent, summary *passSummary, checkOnly bool) {
+	for _, in := range intents {
+		log.Printf("Instance %q has an unresolved intent to %s from update %s at %s; the updater stopped before completing it",
+			in.instanceID, in.action, in.updateID, in.since.Format(time.RFC3339))
+		u.warnings.add(warnIntent, "instance %q has an unresolved intent to %s", in.instanceID, in.action)
+		recovered := false
+		if !checkOnly {
+			if in.status == ecs.ContainerInstanceStatusDraining {
+				// activateInstance resolves the intent itself.
+				if err := u.activateInstance(in.containerInstanceID); err != nil {
+					log.Printf("Failed to re-activate instance %q with an unresolved intent: %v", in.instanceID, err)
+					u.warnings.add(warnDegraded, "unresolved intent on instance %q was not recovered: %v", in.instanceID, err)
+				} else {
+					recovered = true
+				}
+			} else {
+				u.resolveIntent(in.containerInstanceID)
+				recovered = true
+			}
+		}
+		summary.intents = append(summary.intents, reportIntent{
+			InstanceID: in.instanceID,
+			Action:     in.action,
+			UpdateID:   in.updateID,
+			Since:      in.since,
+			Status:     in.status,
+			Recovered:  recovered,
+		})
+	}
+}
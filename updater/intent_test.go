@@ -0,0 +1,126 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseIntent(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	assert.Equal(t, intent{action: intentDrain, updateID: "update-1", since: now}, parseIntent(formatIntent(intentDrain, "update-1", now)))
+	assert.Equal(t, "unknown", parseIntent("drain").action)
+	assert.Equal(t, "unknown", parseIntent("drain update-1 yesterday").action)
+}
+
+func TestRecordIntent(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	inst := instance{instanceID: "i-1", containerInstanceID: "cont-inst-1"}
+	var tagErr error
+	tagged := make([]string, 0)
+	mockECS := MockECS{
+		TagResourceFn: func(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+			assert.Equal(t, "cont-inst-1", aws.StringValue(input.ResourceArn))
+			require.Len(t, input.Tags, 1)
+			assert.Equal(t, tagIntent, aws.StringValue(input.Tags[0].Key))
+			tagged = append(tagged, aws.StringValue(input.Tags[0].Value))
+			return &ecs.TagResourceOutput{}, tagErr
+		},
+	}
+
+	u := updater{ecs: mockECS, updateID: "update-1"}
+	require.NoError(t, u.recordIntent(inst, intentDrain, now))
+	assert.Empty(t, tagged, "intents should only be recorded with -intent-records")
+
+	u.intentRecords = true
+	require.NoError(t, u.recordIntent(inst, intentDrain, now))
+	assert.Equal(t, []string{"drain update-1 2024-03-01T12:00:00Z"}, tagged)
+
+	tagErr = errors.New("access denied")
+	err := u.recordIntent(inst, intentUpdate, now)
+	require.Error(t, err)
+	assert.ErrorIs(t, err, tagErr)
+	assert.Contains(t, err.Error(), "failed to record intent to update")
+}
+
+func TestFindIntents(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	mockECS := MockECS{
+		ListContainerInstancesPagesFn: func(input *ecs.ListContainerInstancesInput, fn func(*ecs.ListContainerInstancesOutput, bool) bool) error {
+			assert.Nil(t, input.Status, "intents should be looked for on ACTIVE and DRAINING instances")
+			fn(&ecs.ListContainerInstancesOutput{ContainerInstanceArns: aws.StringSlice([]string{"cont-inst-1", "cont-inst-2", "cont-inst-3"})}, true)
+			return nil
+		},
+		DescribeContainerInstancesFn: func(input *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+			assert.Equal(t, []string{ecs.ContainerInstanceFieldTags}, aws.StringValueSlice(input.Include))
+			return &ecs.DescribeContainerInstancesOutput{ContainerInstances: []*ecs.ContainerInstance{
+				{ContainerInstanceArn: aws.String("cont-inst-1"), Ec2InstanceId: aws.String("i-1"), Status: aws.String("DRAINING"),
+					Tags: []*ecs.Tag{{Key: aws.String(tagIntent), Value: aws.String(formatIntent(intentUpdate, "update-1", now))}}},
+				{ContainerInstanceArn: aws.String("cont-inst-2"), Ec2InstanceId: aws.String("i-2"), Status: aws.String("ACTIVE"),
+					Tags: []*ecs.Tag{{Key: aws.String(tagDrainingSince), Value: aws.String(now.Format(time.RFC3339))}}},
+				{ContainerInstanceArn: aws.String("cont-inst-3"), Ec2InstanceId: aws.String("i-3"), Status: aws.String("ACTIVE")},
+			}}, nil
+		},
+	}
+	u := updater{ecs: mockECS, cluster: "test-cluster"}
+	intents, err := u.findIntents()
+	require.NoError(t, err)
+	assert.Equal(t, []intent{{
+		containerInstanceID: "cont-inst-1",
+		instanceID:          "i-1",
+		status:              "DRAINING",
+		action:              intentUpdate,
+		updateID:            "update-1",
+		since:               now,
+	}}, intents)
+}
+
+func TestRecoverIntents(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	intents := []intent{
+		{containerInstanceID: "cont-inst-1", instanceID: "i-1", status: "DRAINING", action: intentUpdate, updateID: "update-1", since: now},
+		{containerInstanceID: "cont-inst-2", instanceID: "i-2", status: "ACTIVE", action: intentDrain, updateID: "update-2", since: now},
+	}
+	newMockECS := func(calls *[]string) MockECS {
+		return MockECS{
+			UpdateContainerInstancesStateFn: func(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+				*calls = append(*calls, "state "+aws.StringValue(input.ContainerInstances[0])+" "+aws.StringValue(input.Status))
+				return &ecs.UpdateContainerInstancesStateOutput{}, nil
+			},
+			UntagResourceFn: func(input *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+				assert.Equal(t, []string{tagIntent}, aws.StringValueSlice(input.TagKeys))
+				*calls = append(*calls, "untag "+aws.StringValue(input.ResourceArn))
+				return &ecs.UntagResourceOutput{}, nil
+			},
+		}
+	}
+
+	t.Run("recover", func(t *testing.T) {
+		calls := make([]string, 0)
+		warnings := newPassWarnings()
+		summary := newPassSummary()
+		u := updater{ecs: newMockECS(&calls), cluster: "test-cluster", intentRecords: true, warnings: warnings}
+		u.recoverIntents(intents, summary, false)
+		assert.Equal(t, []string{"state cont-inst-1 ACTIVE", "untag cont-inst-1", "untag cont-inst-2"}, calls,
+			"DRAINING instances should be re-activated and every intent resolved")
+		assert.Equal(t, 2, warnings.counts[warnIntent])
+		assert.Equal(t, []reportIntent{
+			{InstanceID: "i-1", Action: intentUpdate, UpdateID: "update-1", Since: now, Status: "DRAINING", Recovered: true},
+			{InstanceID: "i-2", Action: intentDrain, UpdateID: "update-2", Since: now, Status: "ACTIVE", Recovered: true},
+		}, newFleetReport("test-cluster", summary, nil, now).Intents)
+	})
+	t.Run("check-only", func(t *testing.T) {
+		calls := make([]string, 0)
+		summary := newPassSummary()
+		u := updater{ecs: newMockECS(&calls), cluster: "test-cluster", intentRecords: true}
+		u.recoverIntents(intents, summary, true)
+		assert.Empty(t, calls, "check-only runs should only report intents")
+		require.Len(t, summary.intents, 2)
+		assert.False(t, summary.intents[0].Recovered)
+	})
+}
@@ -0,0 +1,57 @@
+package main
+
+import (
+	"context"
+	"errors"
+	"log"
+	"math/rand"
+	"time"
+)
+
+// passDelay returns how long to wait after a pass before starting the next
+// one: the interval, plus up to a tenth of it at random, so that updaters
+// started at the same time drift apart rather than calling AWS in lockstep.
+func passDelay(interval time.Duration, r *rand.Rand) time.Duration {
+	if interval <= 0 {
+		return 0
+	}
+	jitter := int64(interval / 10)
+	if jitter <= 0 {
+		return interval
+	}
+	return interval + time.Duration(r.Int63n(jitter))
+}
+
+// runEvery runs pass every interval, with jitter, until shutdown is canceled,
+// so that the updater can run as a long-lived ECS service rather than a
+// scheduled task. A failed pass is logged and retried at the next interval,
+// while a pass stopped early by the shutdown stops the updater (see
+// shutdown.go). With an update window, a pass also starts when the window
+// opens, when that is before the next interval.
+func runEvery(shutdown context.Context, interval time.Duration, window *updateWindow, pass func() error) error {
+	r := rand.New(rand.NewSource(time.Now().UnixNano()))
+	for {
+		if err := pass(); errors.Is(err, errShutdown) {
+			return err
+		} else if err != nil {
+			log.Printf("Pass failed, therefore retrying at the next interval: %v", err)
+		}
+		delay := passDelay(interval, r)
+		if window != nil {
+			now := time.Now()
+			if next, ok := window.nextOpen(now); ok && next.Sub(now) < delay {
+				delay = next.Sub(now)
+				log.Printf("Update window %s opens at %s", window, next.Format(time.RFC3339))
+			}
+		}
+		log.Printf("Waiting %s before the next pass", delay.Round(time.Second))
+		timer := time.NewTimer(delay)
+		select {
+		case <-shutdown.Done():
+			timer.Stop()
+			log.Printf("Shutting down between passes")
+			return nil
+		case <-timer.C:
+		}
+	}
+}
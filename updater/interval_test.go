@@ -0,0 +1,46 @@
+package main
+
+import (
+	"context"
+	"errors"
+	"math/rand"
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestPassDelay(t *testing.T) {
+	r := rand.New(rand.NewSource(1))
+	for i := 0; i < 100; i++ {
+		delay := passDelay(time.Hour, r)
+		assert.GreaterOrEqual(t, delay, time.Hour)
+		assert.Less(t, delay, time.Hour+6*time.Minute)
+	}
+	assert.Equal(t, time.Duration(0), passDelay(0, r))
+	assert.Equal(t, 5*time.Nanosecond, passDelay(5*time.Nanosecond, r))
+}
+
+func TestRunEvery(t *testing.T) {
+	shutdown, cancel := context.WithCancel(context.Background())
+	defer cancel()
+	passes := 0
+	err := runEvery(shutdown, time.Millisecond, nil, func() error {
+		passes++
+		if passes == 3 {
+			cancel()
+		}
+		return errors.New("instance failed to re-activate")
+	})
+	require.NoError(t, err, "shutting down between passes is not an error")
+	assert.Equal(t, 3, passes, "failed passes should not stop the updater")
+
+	passes = 0
+	err = runEvery(context.Background(), time.Millisecond, nil, func() error {
+		passes++
+		return errShutdown
+	})
+	assert.ErrorIs(t, err, errShutdown)
+	assert.Equal(t, 1, passes)
+}
@@ -0,0 +1,22 @@
+package main
+
+import (
+	"hash/fnv"
+	"math/rand"
+	"time"
+)
+
+// startJitter returns how long a pass over the cluster waits before it starts,
+// from 0 up to window. The delay is random across clusters but the same for every
+// pass over a cluster, since it is seeded by the cluster's account, region and
+// name: updaters of many clusters on the same schedule then spread their calls
+// to SSM and ECS over the window, and each cluster still runs at a predictable time.
+func startJitter(account string, region string, cluster string, window time.Duration) time.Duration {
+	if window <= 0 {
+		return 0
+	}
+	h := fnv.New64a()
+	h.Write([]byte(account + "/" + region + "/" + cluster))
+	r := rand.New(rand.NewSource(int64(h.Sum64())))
+	return time.Duration(r.Int63n(int64(window)))
+}
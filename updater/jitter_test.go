@@ -0,0 +1,27 @@
+package main
+
+import (
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+)
+
+func TestStartJitter(t *testing.T) {
+	assert.Zero(t, startJitter("000000000000", "us-west-2", "cluster", 0))
+
+	window := 15 * time.Minute
+	first := startJitter("000000000000", "us-west-2", "cluster", window)
+	assert.Equal(t, first, startJitter("000000000000", "us-west-2", "cluster", window), "should wait as long in every pass over a cluster")
+
+	distinct := make(map[time.Duration]bool)
+	for _, cluster := range []string{"cluster-a", "cluster-b", "cluster-c", "cluster-d"} {
+		for _, account := range []string{"000000000000", "111111111111"} {
+			d := startJitter(account, "us-west-2", cluster, window)
+			assert.GreaterOrEqual(t, d, time.Duration(0))
+			assert.Less(t, d, window)
+			distinct[d] = true
+		}
+	}
+	assert.Greater(t, len(distinct), 1, "should spread clusters over the window")
+}
@@ -0,0 +1,141 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+const (
+	// tagLastKnownGood records the cluster's last known good Bottlerocket
+	// version: the most recent version that all of its Bottlerocket instances
+	// ran at the end of a pass without failures.
+	tagLastKnownGood = "bottlerocket-ecs-updater:last-known-good"
+	// tagLastKnownGoodSince records when the cluster first proved its last known good version.
+	tagLastKnownGoodSince = "bottlerocket-ecs-updater:last-known-good-since"
+)
+
+// Update targets accepted by the -target flag.
+const (
+	// targetLatest updates instances to the latest release they can update to.
+	targetLatest = "latest"
+	// targetLastKnownGood only updates instances to the last known good
+	// version of another cluster.
+	targetLastKnownGood = "last-known-good"
+)
+
+// lastKnownGood is a cluster's last known good version and when it was first proven.
+type lastKnownGood struct {
+	version string
+	since   time.Time
+}
+
+// provenVersion returns the version the pass proved good, which is the
+// version all Bottlerocket instances run after a pass without failures. It
+// returns an empty string when the pass proved no version.
+func (s *passSummary) provenVersion() string {
+	if s.failed > 0 {
+		return ""
+	}
+	after := histogram(s.versionsAfter)
+	if len(after) != 1 {
+		return ""
+	}
+	version := after.versions()[0]
+	if version == unknownVersion {
+		return ""
+	}
+	return version
+}
+
+// clusterLastKnownGood reads the last known good version recorded in the
+// tags of the cluster, given by name or ARN. The version is empty when the
+// cluster has none.
+func (u *updater) clusterLastKnownGood(cluster string) (lastKnownGood, error) {
+	resp, err := u.ecs.DescribeClusters(&ecs.DescribeClustersInput{
+		Clusters: aws.StringSlice([]string{cluster}),
+		Include:  aws.StringSlice([]string{ecs.ClusterFieldTags}),
+	})
+	if err != nil {
+		return lastKnownGood{}, fmt.Errorf("failed to describe cluster %q: %w", cluster, err)
+	}
+	if len(resp.Clusters) == 0 {
+		return lastKnownGood{}, fmt.Errorf("cluster %q not found: %v", cluster, resp.Failures)
+	}
+	lkg := lastKnownGood{}
+	for _, tag := range resp.Clusters[0].Tags {
+		switch aws.StringValue(tag.Key) {
+		case tagLastKnownGood:
+			lkg.version = aws.StringValue(tag.Value)
+		case tagLastKnownGoodSince:
+			since, err := time.Parse(time.RFC3339, aws.StringValue(tag.Value))
+			if err != nil {
+				return lastKnownGood{}, fmt.Errorf("cluster %q has an invalid %s tag: %w", cluster, tagLastKnownGoodSince, err)
+			}
+			lkg.since = since
+		}
+	}
+	return lkg, nil
+}
+
+// lastKnownGoodTags returns the tags that record the version proven by the
+// pass as the last known good version of the cluster. It returns no tags
+// when the pass proved no version or the cluster already records it, so the
+// time the version was first proven is kept.
+func (u *updater) lastKnownGoodTags(clusterARN string, summary *passSummary, now time.Time) ([]*ecs.Tag, error) {
+	proven := summary.provenVersion()
+	if proven == "" {
+		return nil, nil
+	}
+	previous, err := u.clusterLastKnownGood(clusterARN)
+	if err != nil {
+		return nil, err
+	}
+	if previous.version == proven {
+		return nil, nil
+	}
+	log.Printf("Recording %s as the last known good version of cluster %q, replacing %q", proven, clusterARN, previous.version)
+	return []*ecs.Tag{
+		{Key: aws.String(tagLastKnownGood), Value: aws.String(proven)},
+		{Key: aws.String(tagLastKnownGoodSince), Value: aws.String(now.UTC().Format(time.RFC3339))},
+	}, nil
+}
+
+// filterLastKnownGood returns the candidates whose update is the last known
+// good version of the cluster the updater follows, recording the others as
+// skipped in the summary. All candidates are skipped when that version can't
+// be determined.
+func (u *updater) filterLastKnownGood(candidates []instance, summary *passSummary) []instance {
+	if u.provenIn == "" {
+		return candidates
+	}
+	lkg, err := u.clusterLastKnownGood(u.provenIn)
+	if err == nil && lkg.version == "" {
+		err = fmt.Errorf("cluster %q has no last known good version", u.provenIn)
+	}
+	if err != nil {
+		log.Printf("Failed to determine the last known good version, therefore not updating any instances: %v", err)
+		for _, inst := range candidates {
+			summary.skippedInstance(inst.instanceID, skipNotProven, fmt.Sprintf("Last known good version is unknown: %v", err))
+		}
+		u.warnings.add(warnDegraded, "last known good version of cluster %q is unknown: %v", u.provenIn, err)
+		return nil
+	}
+	log.Printf("Only updating instances to %s, the last known good version of cluster %q since %s",
+		lkg.version, u.provenIn, lkg.since.Format(time.RFC3339))
+	proven := make([]instance, 0, len(candidates))
+	for _, inst := range candidates {
+		if inst.targetRelease.Version != lkg.version {
+			log.Printf("Instance %#q would update to %q, not the last known good version %s, therefore not updating it",
+				inst, inst.targetRelease.Version, lkg.version)
+			summary.skippedInstance(inst.instanceID, skipNotProven, fmt.Sprintf("Release %q is not the last known good version %s of cluster %q",
+				inst.targetRelease.Version, lkg.version, u.provenIn))
+			continue
+		}
+		proven = append(proven, inst)
+	}
+	return proven
+}
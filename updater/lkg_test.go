@@ -0,0 +1,147 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+// lkgCluster returns a DescribeClusters output for a cluster with the given tags.
+func lkgCluster(tags map[string]string) *ecs.DescribeClustersOutput {
+	cluster := &ecs.Cluster{ClusterArn: aws.String("arn:aws:ecs:us-west-2:123456789012:cluster/staging")}
+	for key, value := range tags {
+		cluster.Tags = append(cluster.Tags, &ecs.Tag{Key: aws.String(key), Value: aws.String(value)})
+	}
+	return &ecs.DescribeClustersOutput{Clusters: []*ecs.Cluster{cluster}}
+}
+
+func TestProvenVersion(t *testing.T) {
+	cases := []struct {
+		name      string
+		instances []instance
+		failed    bool
+		expected  string
+	}{
+		{
+			name:      "single version",
+			instances: []instance{{instanceID: "inst-id-1", bottlerocketVersion: "1.12.0"}, {instanceID: "inst-id-2", bottlerocketVersion: "1.12.0"}},
+			expected:  "1.12.0",
+		},
+		{
+			name:      "mixed versions",
+			instances: []instance{{instanceID: "inst-id-1", bottlerocketVersion: "1.12.0"}, {instanceID: "inst-id-2", bottlerocketVersion: "1.11.0"}},
+		},
+		{
+			name:      "unknown version",
+			instances: []instance{{instanceID: "inst-id-1"}},
+		},
+		{
+			name:      "failures",
+			instances: []instance{{instanceID: "inst-id-1", bottlerocketVersion: "1.12.0"}},
+			failed:    true,
+		},
+		{
+			name: "no instances",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			summary := newPassSummary()
+			summary.recordVersions(tc.instances)
+			if tc.failed {
+				summary.failedInstance("inst-id-2", "Update failed")
+			}
+			assert.Equal(t, tc.expected, summary.provenVersion())
+		})
+	}
+}
+
+func TestLastKnownGoodTags(t *testing.T) {
+	const clusterARN = "arn:aws:ecs:us-west-2:123456789012:cluster/staging"
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	summary := newPassSummary()
+	summary.recordVersions([]instance{{instanceID: "inst-id-1", bottlerocketVersion: "1.12.0"}})
+
+	t.Run("new version", func(t *testing.T) {
+		u := updater{ecs: MockECS{
+			DescribeClustersFn: func(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+				assert.Equal(t, aws.StringSlice([]string{clusterARN}), input.Clusters)
+				assert.Equal(t, aws.StringSlice([]string{ecs.ClusterFieldTags}), input.Include)
+				return lkgCluster(map[string]string{tagLastKnownGood: "1.11.0", tagLastKnownGoodSince: "2024-01-01T00:00:00Z"}), nil
+			},
+		}}
+		tags, err := u.lastKnownGoodTags(clusterARN, summary, now)
+		require.NoError(t, err)
+		assert.Equal(t, []*ecs.Tag{
+			{Key: aws.String(tagLastKnownGood), Value: aws.String("1.12.0")},
+			{Key: aws.String(tagLastKnownGoodSince), Value: aws.String("2024-03-01T12:00:00Z")},
+		}, tags)
+	})
+	t.Run("same version", func(t *testing.T) {
+		u := updater{ecs: MockECS{
+			DescribeClustersFn: func(_ *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+				return lkgCluster(map[string]string{tagLastKnownGood: "1.12.0", tagLastKnownGoodSince: "2024-01-01T00:00:00Z"}), nil
+			},
+		}}
+		tags, err := u.lastKnownGoodTags(clusterARN, summary, now)
+		require.NoError(t, err)
+		assert.Empty(t, tags, "should keep the time the version was first proven")
+	})
+	t.Run("nothing proven", func(t *testing.T) {
+		u := updater{ecs: MockECS{}}
+		tags, err := u.lastKnownGoodTags(clusterARN, newPassSummary(), now)
+		require.NoError(t, err)
+		assert.Empty(t, tags)
+	})
+}
+
+func TestFilterLastKnownGood(t *testing.T) {
+	candidates := []instance{
+		{instanceID: "inst-proven", targetRelease: release{Version: "1.12.0"}},
+		{instanceID: "inst-newer", targetRelease: release{Version: "1.13.0"}},
+	}
+
+	t.Run("disabled", func(t *testing.T) {
+		u := updater{}
+		summary := newPassSummary()
+		assert.Equal(t, candidates, u.filterLastKnownGood(candidates, summary))
+		assert.Zero(t, summary.skipped)
+	})
+	t.Run("enabled", func(t *testing.T) {
+		u := updater{provenIn: "staging", ecs: MockECS{
+			DescribeClustersFn: func(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+				assert.Equal(t, aws.StringSlice([]string{"staging"}), input.Clusters)
+				return lkgCluster(map[string]string{tagLastKnownGood: "1.12.0", tagLastKnownGoodSince: "2024-01-01T00:00:00Z"}), nil
+			},
+		}}
+		summary := newPassSummary()
+		assert.Equal(t, candidates[:1], u.filterLastKnownGood(candidates, summary))
+		assert.Equal(t, 1, summary.skipped)
+		assert.Contains(t, summary.results["inst-newer"], "not the last known good version 1.12.0")
+	})
+	t.Run("no last known good version", func(t *testing.T) {
+		u := updater{provenIn: "staging", ecs: MockECS{
+			DescribeClustersFn: func(_ *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+				return lkgCluster(nil), nil
+			},
+		}}
+		summary := newPassSummary()
+		assert.Empty(t, u.filterLastKnownGood(candidates, summary))
+		assert.Equal(t, 2, summary.skipped)
+	})
+	t.Run("describe fails", func(t *testing.T) {
+		u := updater{provenIn: "staging", ecs: MockECS{
+			DescribeClustersFn: func(_ *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+				return nil, errors.New("access denied")
+			},
+		}}
+		summary := newPassSummary()
+		assert.Empty(t, u.filterLastKnownGood(candidates, summary))
+		assert.Contains(t, summary.results["inst-proven"], "access denied")
+	})
+}
@@ -0,0 +1,127 @@
+package main
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"log"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+// instanceLock is the value of the SSM parameter that locks an instance for
+// the updater or other automation that reboots hosts. A lock is held until it
+// is deleted by its owner or, when it has an expiry, until it expires.
+type instanceLock struct {
+	Owner   string    `json:"owner"`
+	Expires time.Time `json:"expires"`
+}
+
+// errInstanceLocked is returned when an instance is locked by someone else.
+var errInstanceLocked = errors.New("instance is locked")
+
+// lockParameter returns the name of the SSM parameter that locks the instance.
+func (u *updater) lockParameter(instanceID string) string {
+	return strings.TrimSuffix(u.lockPrefix, "/") + "/" + instanceID
+}
+
+// lockOwner identifies the pass in the locks it holds.
+func (u *updater) lockOwner() string {
+	return fmt.Sprintf("bottlerocket-ecs-updater pass=%s cluster=%s", u.passID, u.clusterName())
+}
+
+// lockInstance takes the advisory lock of the instance for lockTTL, so that
+// other automation sharing the locks does not reboot the instance while the
+// updater drains and updates it. It returns a function that releases the lock,
+// or an error wrapping errInstanceLocked when the lock is held by someone
+// else. An expired lock is taken over.
+func (u *updater) lockInstance(inst instance, now time.Time) (func(), error) {
+	name := u.lockParameter(inst.instanceID)
+	owner := u.lockOwner()
+	value, err := json.Marshal(instanceLock{Owner: owner, Expires: now.Add(u.lockTTL).UTC()})
+	if err != nil {
+		return nil, fmt.Errorf("failed to encode lock: %w", err)
+	}
+	put := func() error {
+		_, err := u.ssm.PutParameter(&ssm.PutParameterInput{
+			Name:        aws.String(name),
+			Value:       aws.String(string(value)),
+			Type:        aws.String(ssm.ParameterTypeString),
+			Description: aws.String("Lock of an instance held by the Bottlerocket ECS updater"),
+			Overwrite:   aws.Bool(false),
+		})
+		return err
+	}
+	err = put()
+	var aerr awserr.Error
+	if errors.As(err, &aerr) && aerr.Code() == ssm.ErrCodeParameterAlreadyExists {
+		if err := u.removeExpiredLock(inst, name, now); err != nil {
+			return nil, err
+		}
+		err = put()
+	}
+	if err != nil {
+		return nil, fmt.Errorf("failed to take lock %q: %w", name, err)
+	}
+	log.Printf("Took lock %q of instance %#q until %s", name, inst, now.Add(u.lockTTL).UTC().Format(time.RFC3339))
+	return func() { u.unlockInstance(inst, owner) }, nil
+}
+
+// removeExpiredLock removes the lock in the parameter if it has expired, and
+// returns an error wrapping errInstanceLocked if it has not.
+func (u *updater) removeExpiredLock(inst instance, name string, now time.Time) error {
+	held, err := u.heldLock(name)
+	if err != nil {
+		return err
+	}
+	if held.Expires.IsZero() {
+		return fmt.Errorf("%w by %q", errInstanceLocked, held.Owner)
+	}
+	if now.Before(held.Expires) {
+		return fmt.Errorf("%w by %q until %s", errInstanceLocked, held.Owner, held.Expires.Format(time.RFC3339))
+	}
+	log.Printf("Lock %q of instance %#q held by %q expired at %s, therefore taking it over", name, inst, held.Owner, held.Expires.Format(time.RFC3339))
+	if _, err := u.ssm.DeleteParameter(&ssm.DeleteParameterInput{Name: aws.String(name)}); err != nil {
+		return fmt.Errorf("failed to remove expired lock %q: %w", name, err)
+	}
+	return nil
+}
+
+// heldLock reads the lock in the parameter. A value that isn't a lock written
+// by the updater is held by its owner without expiry.
+func (u *updater) heldLock(name string) (instanceLock, error) {
+	resp, err := u.ssm.GetParameter(&ssm.GetParameterInput{Name: aws.String(name)})
+	if err != nil {
+		return instanceLock{}, fmt.Errorf("failed to read lock %q: %w", name, err)
+	}
+	value := aws.StringValue(resp.Parameter.Value)
+	var held instanceLock
+	if err := json.Unmarshal([]byte(value), &held); err != nil || held.Owner == "" {
+		return instanceLock{Owner: value}, nil
+	}
+	return held, nil
+}
+
+// unlockInstance releases the lock of the instance, unless it is no longer
+// held by owner, e.g. because it expired and was taken over.
+func (u *updater) unlockInstance(inst instance, owner string) {
+	name := u.lockParameter(inst.instanceID)
+	held, err := u.heldLock(name)
+	if err == nil && held.Owner != owner {
+		log.Printf("Lock %q of instance %#q is now held by %q, therefore not releasing it", name, inst, held.Owner)
+		return
+	}
+	if err == nil {
+		_, err = u.ssm.DeleteParameter(&ssm.DeleteParameterInput{Name: aws.String(name)})
+	}
+	if err != nil {
+		log.Printf("Failed to release lock %q of instance %#q; it expires on its own: %v", name, inst, err)
+		u.warnings.add(warnDegraded, "lock of instance %q was not released: %v", inst.instanceID, err)
+		return
+	}
+	log.Printf("Released lock %q of instance %#q", name, inst)
+}
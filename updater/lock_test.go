@@ -0,0 +1,91 @@
+package main
+
+import (
+	"encoding/json"
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+// lockMockSSM keeps parameters in memory like Parameter Store.
+func lockMockSSM(parameters map[string]string) MockSSM {
+	return MockSSM{
+		GetParameterFn: func(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error) {
+			value, ok := parameters[aws.StringValue(input.Name)]
+			if !ok {
+				return nil, awserr.New(ssm.ErrCodeParameterNotFound, "not found", nil)
+			}
+			return &ssm.GetParameterOutput{Parameter: &ssm.Parameter{Value: aws.String(value)}}, nil
+		},
+		PutParameterFn: func(input *ssm.PutParameterInput) (*ssm.PutParameterOutput, error) {
+			name := aws.StringValue(input.Name)
+			if _, ok := parameters[name]; ok && !aws.BoolValue(input.Overwrite) {
+				return nil, awserr.New(ssm.ErrCodeParameterAlreadyExists, "exists", nil)
+			}
+			parameters[name] = aws.StringValue(input.Value)
+			return &ssm.PutParameterOutput{}, nil
+		},
+		DeleteParameterFn: func(input *ssm.DeleteParameterInput) (*ssm.DeleteParameterOutput, error) {
+			delete(parameters, aws.StringValue(input.Name))
+			return &ssm.DeleteParameterOutput{}, nil
+		},
+	}
+}
+
+func encodeLock(t *testing.T, owner string, expires time.Time) string {
+	value, err := json.Marshal(instanceLock{Owner: owner, Expires: expires})
+	require.NoError(t, err)
+	return string(value)
+}
+
+func TestLockInstance(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	inst := instance{instanceID: "i-1"}
+
+	t.Run("free", func(t *testing.T) {
+		parameters := make(map[string]string)
+		u := updater{cluster: "test", passID: "pass-1", ssm: lockMockSSM(parameters), lockPrefix: "/host-locks/", lockTTL: time.Hour, warnings: newPassWarnings()}
+		release, err := u.lockInstance(inst, now)
+		require.NoError(t, err)
+		var held instanceLock
+		require.NoError(t, json.Unmarshal([]byte(parameters["/host-locks/i-1"]), &held))
+		assert.Equal(t, "bottlerocket-ecs-updater pass=pass-1 cluster=test", held.Owner)
+		assert.Equal(t, now.Add(time.Hour), held.Expires)
+
+		release()
+		assert.Empty(t, parameters)
+		assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+	})
+	t.Run("held", func(t *testing.T) {
+		parameters := map[string]string{"/host-locks/i-1": encodeLock(t, "patching", now.Add(time.Minute))}
+		u := updater{ssm: lockMockSSM(parameters), lockPrefix: "/host-locks", lockTTL: time.Hour}
+		_, err := u.lockInstance(inst, now)
+		assert.True(t, errors.Is(err, errInstanceLocked))
+		assert.Contains(t, err.Error(), `"patching"`)
+	})
+	t.Run("held without expiry", func(t *testing.T) {
+		parameters := map[string]string{"/host-locks/i-1": "debug session of alice"}
+		u := updater{ssm: lockMockSSM(parameters), lockPrefix: "/host-locks", lockTTL: time.Hour}
+		_, err := u.lockInstance(inst, now)
+		assert.True(t, errors.Is(err, errInstanceLocked), "should treat values it can't read as held")
+		assert.Equal(t, "debug session of alice", parameters["/host-locks/i-1"])
+	})
+	t.Run("expired", func(t *testing.T) {
+		parameters := map[string]string{"/host-locks/i-1": encodeLock(t, "patching", now.Add(-time.Minute))}
+		u := updater{cluster: "test", passID: "pass-1", ssm: lockMockSSM(parameters), lockPrefix: "/host-locks", lockTTL: time.Hour, warnings: newPassWarnings()}
+		release, err := u.lockInstance(inst, now)
+		require.NoError(t, err, "should take over expired locks")
+		assert.Contains(t, parameters["/host-locks/i-1"], "pass=pass-1")
+
+		// The lock expires and is taken over before it is released.
+		parameters["/host-locks/i-1"] = encodeLock(t, "patching", now.Add(2*time.Hour))
+		release()
+		assert.Contains(t, parameters["/host-locks/i-1"], `"patching"`, "should not release locks held by others")
+	})
+}
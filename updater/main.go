@@ -1,20 +1,31 @@
 package main
 
 import (
+	"context"
+	"crypto/rand"
 	"errors"
 	"flag"
 	"fmt"
 	"log"
 	"os"
+	"os/signal"
 	"strings"
+	"syscall"
 	"time"
 
 	"github.com/aws/aws-sdk-go/aws"
 	"github.com/aws/aws-sdk-go/aws/arn"
+	"github.com/aws/aws-sdk-go/aws/client"
+	"github.com/aws/aws-sdk-go/aws/request"
 	"github.com/aws/aws-sdk-go/aws/session"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+	"github.com/aws/aws-sdk-go/service/cloudwatchlogs"
 	"github.com/aws/aws-sdk-go/service/ec2"
 	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/elbv2"
+	"github.com/aws/aws-sdk-go/service/secretsmanager"
 	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/aws/aws-sdk-go/service/sts"
 )
 
 var (
@@ -23,29 +34,418 @@ var (
 	flagCheck   = flag.String("check-document", "", "The SSM document name for checking available updates.")
 	flagApply   = flag.String("apply-document", "", "The SSM document name for applying updates.")
 	flagReboot  = flag.String("reboot-document", "", "The SSM document name to initiate a reboot.")
+
+	flagSettingsDocument = flag.String("settings-document", "", "The SSM document name for applying Bottlerocket settings; required with -settings.")
+	flagSettings         = flag.String("settings", "", `Bottlerocket settings to apply to each instance before it reboots into an update, as a JSON object, e.g. {"ecs": {"enable-spot-instance-draining": true}}.`)
+
+	flagCheckOnly    = flag.Bool("check-only", false, "Only check for and report available updates without draining or updating any instances.")
+	flagReadOnly     = flag.Bool("read-only", false, "Refuse every AWS call that would change a resource, except sending -check-document, so that the updater can only observe and report; implies -check-only. The updater still writes -report and metrics.")
+	flagTagCluster   = flag.Bool("tag-cluster", false, "Record a summary of each run as tags on the cluster.")
+	flagLogSensitive = flag.Bool("log-sensitive", false, "Log sensitive values such as AWS account IDs without redaction.")
+	flagSSMComment   = flag.String("ssm-comment", "", "Comment to attach to SSM commands sent by the updater, after the updater version, cluster, change reference and shortened pass and update IDs.")
+	flagChangeRef    = flag.String("change-ref", "", "The change-management ticket the run belongs to, e.g. CHG-1234; recorded in SSM command comments, the after action summary, cluster tags, reports and failure events.")
+
+	flagSSMMaxConcurrency = flag.String("ssm-max-concurrency", "", "The most instances an SSM command runs on at once, as a number or a percentage of its instances, e.g. 10 or 20%; SSM's default of 50 applies when empty. Update checks are sent to up to 50 instances per command.")
+	flagSSMMaxErrors      = flag.String("ssm-max-errors", "", "The number or percentage of failed invocations of an SSM command, e.g. 5 or 10%, after which SSM stops running it on more instances; SSM's default of 0 applies when empty.")
+
+	flagVersionMetrics = flag.Bool("version-metrics", false, "Write the number of instances per Bottlerocket version before and after each pass, and the number of Bottlerocket and other container instances, as CloudWatch embedded metric format events.")
+	flagWarningMetrics = flag.Bool("warning-metrics", false, "Write the number of warnings of each pass, such as throttled or retried AWS calls, as CloudWatch embedded metric format events.")
+	flagTimingMetrics  = flag.Bool("timing-metrics", false, "Write how long each phase of a pass took, such as checking for updates or draining instances, as CloudWatch embedded metric format events.")
+	flagFailureMetrics = flag.Bool("failure-metrics", false, "Write an event counting a failed update for each instance that failed to update, with the pass and instance IDs, as CloudWatch embedded metric format events.")
+
+	flagReport     = flag.String("report", "", "Path to write a JSON report of each pass to, with the version and outcome of every instance.")
+	flagReportDiff = flag.String("report-diff", "", "Path to the report of a previous pass; the changes since it, such as new instances, version changes and new failures, are added to the report written with -report.")
+	flagAdvisories = flag.String("advisories", "", "Path to a YAML file of security advisories with the Bottlerocket versions they affect; the report written with -report lists the advisories affecting each instance. The file is read again at every pass.")
+
+	flagHistory          = flag.String("history", "", "Path to a JSON file that keeps a summary of each pass, with its duration, outcomes and the Bottlerocket versions after it, for -print-history. The updaters of several clusters can share the file.")
+	flagHistoryRetention = flag.Duration("history-retention", 90*24*time.Hour, "How long passes are kept in -history, e.g. 2160h for 90 days; 0 keeps every pass.")
+	flagPrintHistory     = flag.Bool("print-history", false, "Print the passes per week in -history, with their average duration, updates and failures and the Bottlerocket versions at the end of each week, for -cluster or every cluster, then exit.")
+
+	flagProgressFile = flag.String("progress-file", "", "Path to a JSON file that is kept up to date with the state of the pass, for sidecar containers to read.")
+
+	flagExpectedAccount = flag.String("expected-account", "", "The AWS account ID the updater is expected to run in; the updater exits without doing anything when its credentials belong to another account.")
+
+	flagTarget               = flag.String("target", targetLatest, "The releases to update instances to: latest, or last-known-good to only update to the last known good version of the cluster given by -last-known-good-cluster.")
+	flagLastKnownGoodCluster = flag.String("last-known-good-cluster", "", "The cluster whose last known good Bottlerocket version instances are updated to with -target last-known-good.")
+
+	flagPromotionPolicy = flag.String("promotion-policy-parameter", "", "The name of an SSM parameter holding a promotion policy shared between clusters, which can make this cluster wait until other clusters have run a version as their last known good version for a soak time.")
+
+	flagMinimumVersion = flag.String("minimum-version", "", "The oldest Bottlerocket version instances may run, e.g. 1.19.2. Instances running an older version count a below-minimum-version warning and are updated regardless of -maintenance-window and deferral tags.")
+
+	flagMinReleaseAge = flag.Duration("min-release-age", 0, "Do not update instances to a Bottlerocket release until it has been published for at least this long, e.g. 48h.")
+
+	flagMaxClockSkew = flag.Duration("max-clock-skew", 5*time.Minute, "The largest difference between the local clock and the clocks of AWS, measured from the Date of AWS responses, at which the updater still updates instances; release ages and soak times depend on the time. 0 disables the check.")
+
+	flagStartJitter = flag.Duration("start-jitter", 0, "The longest time to wait before starting a pass, e.g. 15m. The wait is the same for every pass over a cluster but differs between clusters, which spreads the AWS calls of updaters that run on the same schedule.")
+
+	flagCheckInterval = flag.Duration("check-interval", 0, "Keep running and start a pass over the cluster every interval, e.g. 1h, plus up to a tenth of it at random, so that the updater can run as a long-lived ECS service. 0 runs a single pass and exits.")
+
+	flagCheckCacheTTL = flag.Duration("check-cache-ttl", 0, "How long an update check that found an instance running the latest version of its variant is cached in a tag on its container instance, e.g. 6h; while the version is still the latest, later passes within this time don't send the instance an update check. 0 checks every instance in every pass.")
+
+	flagSettleTime = flag.Duration("settle-time", 0, "How long to wait after returning an updated instance to ACTIVE before starting to update the next instance, e.g. 10m, for services to place tasks on it and warm up; 0 starts the next instance right away.")
+
+	flagMaxUpdatesPerPass = flag.Int("max-updates-per-pass", 0, "The maximum number of instances to update in a single pass; 0 means no limit. Remaining instances are updated in later passes.")
+
+	flagMaxUnhealthyFraction = flag.Float64("max-unhealthy-fraction", 1, "The largest share of the cluster's container instances, from 0 to 1, that may be unhealthy (not ACTIVE or with a disconnected ECS agent) at the start of a pass; the updater exits with an error without updating any instances when more are. 1 disables the check.")
+	flagMaxUnavailable       = flag.String("max-unavailable", "", "The most container instances that may be unavailable (not ACTIVE or with a disconnected ECS agent) at once, as a number or a percentage of the cluster, e.g. 2 or 10%, counting the instance being updated; the updater waits for unavailable instances to recover before draining the next instance. Empty disables the budget.")
+
+	flagStageAttribute = flag.String("stage-attribute", "", "The container instance attribute that assigns instances to rollout stages, e.g. env; requires -stages.")
+	flagStages         = flag.String("stages", "", "The values of -stage-attribute in rollout order, each optionally followed by a soak time to wait after the stage, e.g. staging:1h,prod. Instances with other values are updated last.")
+
+	flagWaves           = flag.String("waves", "", "Wave sizes in rollout order, each a number of instances or a percentage of the instances to update, optionally followed by a bake time to wait after the wave, e.g. 1:30m,10%:1h,100%. Instances left after the last wave are updated last. Can't be used with -stages.")
+	flagMaxWaveFailures = flag.Int("max-wave-failures", 0, "The most failed updates a wave, or a stage of -stages, may have for the rollout to go on to the next one; with more, the remaining waves or stages are not started.")
+
+	flagSuspendScaling = flag.String("suspend-scaling", "", "Comma-separated capacity providers of the cluster whose Auto Scaling group scaling policies are suspended while instances are drained and updated, so that managed scaling does not launch instances for the tasks of draining instances; scaling is resumed at the end of the pass.")
+
+	flagPrewarmShare = flag.Float64("prewarm-share", 0, "The share of the CPU or memory registered by the cluster's Bottlerocket instances, from 0 to 1, at which an instance is large enough that the desired capacity of its Auto Scaling group is raised by one before the instance is drained, e.g. 0.2; each group is raised at most once per pass and lowered again at the end of the pass. 0 disables prewarming.")
+
+	flagSingletonFamilies = flag.String("singleton-families", "", "Comma-separated task definition families of standalone tasks that must keep running, e.g. scheduler,leader. Before an instance running such a task is drained, the updater starts a copy of the task on another instance, waits for it to run, and stops the original; instances with other standalone tasks are not updated.")
+
+	flagExcludeServices = flag.String("exclude-services", "", "Comma-separated names or ARNs of ECS services whose tasks must never be disturbed, e.g. ledger,arn:aws:ecs:us-west-2:111122223333:service/prod/payments. Instances running their tasks are never updated and are reported as requiring a manual update.")
+
+	flagDeferToManagedDraining = flag.Bool("defer-to-managed-draining", false, "Do not update instances in place when a capacity provider of the cluster has managed instance draining enabled.")
+
+	flagTaskCountWeight   = flag.Float64("task-count-weight", defaultReadinessWeights.taskCount, "Weight of each running task on an instance when ordering instances for update; less loaded instances are updated first.")
+	flagUtilizationWeight = flag.Float64("utilization-weight", defaultReadinessWeights.utilization, "Weight of an instance's CPU and memory utilization, from 0 to 1, when ordering instances for update.")
+
+	flagQuarantine       = flag.String("quarantine", "", "The EC2 instance ID of an instance to quarantine: tag its container instance so that passes do not update it until -quarantine-until, then exit. Requires -quarantine-reason and -quarantine-until.")
+	flagQuarantineReason = flag.String("quarantine-reason", "", "Why the instance given by -quarantine is quarantined, recorded with the quarantine.")
+	flagQuarantineUntil  = flag.String("quarantine-until", "", "When the quarantine of the instance given by -quarantine expires, as an RFC 3339 timestamp, e.g. 2024-03-01T00:00:00Z.")
+
+	flagSupportBundle         = flag.String("support-bundle", "", "Write a support bundle for the cluster to this path as a gzipped tarball, then exit: the updater version and flags, the IAM identity, the cluster's tags, the -report of the last pass and, with -support-bundle-log-group, the updater's logs of the last week. AWS account IDs are masked unless -log-sensitive is set.")
+	flagSupportBundleLogGroup = flag.String("support-bundle-log-group", "", "The log group of the updater, e.g. the LogGroupName of its stack, whose logs to include in -support-bundle.")
+
+	flagRebootFleet = flag.Bool("reboot-fleet", false, "Reboot every Bottlerocket instance in the cluster instead of updating instances, e.g. to pick up changes that take effect on boot. Instances are drained, rebooted with -reboot-document, checked and re-activated one at a time like updates, with the same limits, stages and -settings.")
+
+	flagFailureWebhook              = flag.String("failure-webhook", "", "URL to post the diagnostics of an instance to, as JSON with a title and body for an issue, when its update has failed in -failure-webhook-threshold passes in a row. The Authorization header is taken from -failure-webhook-authorization, else from the "+webhookAuthorizationEnv+" environment variable, if set.")
+	flagFailureWebhookThreshold     = flag.Int("failure-webhook-threshold", 3, "The number of passes in a row in which the update of an instance must fail for it to be posted to -failure-webhook.")
+	flagFailureWebhookAuthorization = flag.String("failure-webhook-authorization", "", "The secret holding the Authorization header sent to -failure-webhook: secretsmanager:<secret name or ARN>, ssm:<SecureString parameter name> or env:<variable>. It is read at startup, and again every -secret-refresh.")
+	flagSecretRefresh               = flag.Duration("secret-refresh", 15*time.Minute, "How long a secret is used before it is read again, so that rotated credentials are picked up during long passes; 0 reads secrets once.")
+
+	flagInstanceLockPrefix = flag.String("instance-lock-prefix", "", "SSM Parameter Store path of per-instance locks shared with other automation that reboots hosts, e.g. /host-locks. The updater takes the lock <path>/<instance ID> before draining an instance and releases it afterwards, and skips instances whose lock is held by others.")
+	flagInstanceLockTTL    = flag.Duration("instance-lock-ttl", 2*time.Hour, "How long a lock taken with -instance-lock-prefix is held if the updater stops before releasing it; expired locks are taken over.")
+	flagDeferToSSMActivity = flag.Bool("defer-to-ssm-activity", false, "Before draining an instance, look for SSM command invocations and Automation executions of others in progress on it, such as patching or debugging sessions, and leave the instance to a later pass if there are any.")
+
+	flagIntentRecords    = flag.Bool("intent-records", false, "Tag each instance with the action the updater is about to take on it, draining, updating or rebooting, before taking it, and remove the tag once the instance is ACTIVE again. At the start of each pass, instances still tagged, e.g. because the updater crashed, are reported and returned to ACTIVE.")
+	flagStuckDrainPolicy = flag.String("stuck-drain-policy", "", "Track how long the instances the updater drains are DRAINING, and at the start of each pass handle instances that have been DRAINING for more than twice the drain timeout, e.g. because the updater crashed: alert only warns, activate returns them to ACTIVE, and complete stops the service tasks still running on them. Empty disables tracking.")
+
+	flagSegmentTags = flag.String("segment-tags", "", "Comma-separated tag keys, e.g. team,environment, whose values segment the -report, the after action summary and, with -version-metrics, segment metrics. Each instance takes the value of its container instance's tag, else of the cluster's tag, else untagged.")
+
+	flagMaintenanceWindow = flag.String("maintenance-window", "", "The ID of an SSM maintenance window, e.g. mw-0123456789abcdef0, that must be open for the updater to update instances. The pass and its outcome are recorded in a tag on the window.")
+
+	flagUpdateWindow         = flag.String("update-window", "", "A cron expression, e.g. \"0 2 * * 6\" for Saturdays at 02:00, at which an update window opens for -update-window-duration; passes check for updates at any time, but only drain and update instances while the window is open.")
+	flagUpdateWindowDuration = flag.Duration("update-window-duration", 0, "How long the -update-window stays open, e.g. 4h, at most 168h; required with -update-window.")
+	flagUpdateWindowTimezone = flag.String("update-window-timezone", "UTC", "The time zone of -update-window, e.g. America/New_York.")
+
+	flagSupportedVariants = flag.String("supported-variants", defaultSupportedVariants, "Comma-separated Bottlerocket variants to update; instances of other variants are reported and left alone. An empty list updates every variant.")
+
+	flagSimulate = flag.String("simulate", "", "Path to a scenario file describing a synthetic cluster; runs the updater against the simulated cluster instead of AWS and prints the resulting timeline.")
+
+	flagInjectFaults = flag.String("inject-faults", "", "For testing only: make a share of AWS calls fail at random, as comma-separated operation[:kind]=rate rules where kind is fail or timeout, e.g. SendCommand=0.05,UpdateContainerInstancesState:timeout=0.1. The operation * matches all calls.")
+	flagFaultSeed    = flag.Int64("fault-seed", 0, "Seed for choosing the calls that -inject-faults makes fail, to repeat a run; 0 picks a seed from the current time.")
+
+	flagIAMPolicy = flag.Bool("iam-policy", false, "Print the IAM policy of the AWS calls the updater makes in a pass and exit; with -read-only, the policy allows no calls that change resources and only allows sending -check-document.")
+
+	flagVersion = flag.Bool("version", false, "Print the updater version and exit.")
+	flagVerbose = flag.Bool("verbose", false, "Print detailed build metadata when used with -version.")
 )
 
 const taskDefARNEnv = "TASK_DEFINITION_ARN"
 
+// checkOnlyEnv set to true makes a run check-only like -check-only, so that
+// the stack's check schedule runs the task definition's command, with all its
+// filtering flags, unchanged.
+const checkOnlyEnv = "CHECK_ONLY"
+
+// sleep pauses the updater; simulation mode replaces it to advance a simulated clock.
+var sleep = time.Sleep
+
 type updater struct {
-	cluster        string
-	checkDocument  string
-	applyDocument  string
-	rebootDocument string
-	ecs            ECSAPI
-	ssm            SSMAPI
-	ec2            EC2API
+	cluster          string
+	checkDocument    string
+	applyDocument    string
+	rebootDocument   string
+	settingsDocument string
+	settings         string
+	ssmComment       string
+	ecs              ECSAPI
+	ssm              SSMAPI
+	ec2              EC2API
+	autoscaling      AutoScalingAPI
+	elbv2            ELBV2API
+	// commands runs documents on instances; SSM Run Command when nil.
+	commands commandChannel
+	// ssmMaxConcurrency and ssmMaxErrors are the rate controls of the SSM
+	// commands the updater sends; SSM's defaults apply when they are empty.
+	ssmMaxConcurrency string
+	ssmMaxErrors      string
+	// passID identifies a single pass of the updater over the cluster.
+	passID string
+	// updateID identifies the update of the instance in progress; it is empty
+	// between updates (see beginUpdate).
+	updateID string
+	// shutdown is canceled when the updater is asked to shut down (see
+	// shutdown.go); a nil shutdown is never canceled.
+	shutdown context.Context
+	// progress keeps the state of the pass up to date in a file; nil without
+	// -progress-file.
+	progress *progressFile
+	// readOnly makes the pass report without updating instances; its clients
+	// refuse writes (see makeReadOnly).
+	readOnly bool
+	// rebootOnly makes the pass reboot every instance instead of updating the
+	// instances with available updates.
+	rebootOnly bool
+	// startJitter is the longest time a pass waits before it starts.
+	startJitter time.Duration
+	// settleTime is how long the pass waits after returning an instance to
+	// ACTIVE before it starts on the next instance.
+	settleTime time.Duration
+	// changeRef is the change-management ticket the pass belongs to, if any.
+	changeRef string
+	// maxUpdates limits the number of instances updated in one pass; 0 means no limit.
+	maxUpdates int
+	// maxUnhealthy is the largest share of unhealthy container instances the
+	// cluster may have for a pass to start; 1 or more disables the check.
+	maxUnhealthy float64
+	// maxUnavailable is the disruption budget, a number or a percentage of
+	// the cluster's container instances (see budget.go); there is no budget
+	// when it is empty.
+	maxUnavailable string
+	// minReleaseAge is how long a release must have been published before instances update to it.
+	minReleaseAge time.Duration
+	// minimumVersion is the oldest Bottlerocket version instances may run
+	// (see minimum.go); there is no minimum when it is empty.
+	minimumVersion string
+	// maxClockSkew is the largest skew of the local clock measured by clock
+	// at which instances are updated; 0 disables the check.
+	maxClockSkew time.Duration
+	clock        *clockSkew
+	// stageAttribute names the container instance attribute that assigns
+	// instances to the rollout stages; instances are not staged when empty.
+	stageAttribute string
+	stages         []rolloutStage
+	// waves are the waves of -waves, sized by their value (see waves.go);
+	// instances are not split into waves when nil.
+	waves []rolloutStage
+	// maxWaveFailures is the most failed updates a wave or stage may have for
+	// the next one to start.
+	maxWaveFailures int
+	// variants are the Bottlerocket variants the updater updates; every variant when nil.
+	variants map[string]bool
+	// provenIn names the cluster whose last known good version is the only
+	// version instances are updated to; any version when empty.
+	provenIn string
+	// promotionParameter names the SSM parameter holding the promotion policy; none when empty.
+	promotionParameter string
+	// identity is the ARN of the IAM identity the updater runs as.
+	identity string
+	// region and account are where the updater runs; container instances
+	// elsewhere are never sent commands. Empty values are not checked.
+	region  string
+	account string
+	// warnings collects the soft problems of the pass; run creates it when nil.
+	warnings *passWarnings
+	// sent records the SSM commands sent during the pass; run creates it when nil.
+	sent *commandLog
+	// timings records how long each phase of the pass took; run creates it when nil.
+	timings *passTimings
+	// scalingProviders are the capacity providers whose scaling is suspended
+	// while instances are updated.
+	scalingProviders []string
+	// failureWebhook is the URL instances that failed to update in
+	// escalateAfter passes in a row are posted to; failures are not counted
+	// when it is empty.
+	failureWebhook string
+	escalateAfter  int
+	// webhookAuth is the Authorization header sent to failureWebhook; it is
+	// taken from webhookAuthorizationEnv when nil.
+	webhookAuth *secret
+	// logSensitive keeps AWS account IDs in what is posted to failureWebhook.
+	logSensitive bool
+	// lockPrefix is the SSM parameter path of the locks taken on instances
+	// before they are drained; instances are not locked when it is empty.
+	lockPrefix string
+	lockTTL    time.Duration
+	// deferToSSMActivity leaves instances with SSM activity of others in
+	// progress to a later pass (see ssmactivity.go).
+	deferToSSMActivity bool
+	// maintenanceWindow is the ID of the SSM maintenance window instances are
+	// only updated in; they are updated at any time when it is empty.
+	maintenanceWindow string
+	// updateWindow is the schedule instances are only updated in (see
+	// schedule.go); they are updated at any time when it is nil.
+	updateWindow *updateWindow
+	// stuckDrainPolicy handles instances whose drain is stuck (see stuck.go);
+	// drains are not tracked when it is empty.
+	stuckDrainPolicy string
+	// intentRecords records each destructive action on an instance before it
+	// is taken, and recovers the actions left unresolved (see intent.go).
+	intentRecords bool
+	// segmentTags are the tag keys that segment the report, summary and
+	// metrics of the pass; the pass is not segmented when it is empty.
+	segmentTags []string
+	// singletonFamilies are the task definition families whose standalone
+	// tasks are replaced on another instance before their instance is drained.
+	singletonFamilies map[string]bool
+	// excludeServices are the names of the services whose instances are never
+	// updated (see exclude.go).
+	excludeServices map[string]bool
+	// prewarmShare is the share of the cluster's capacity at which an instance
+	// is large enough to prewarm capacity for; 0 disables prewarming.
+	prewarmShare float64
+	// checkCacheTTL is how long an update check that found an instance
+	// running the latest version is cached; 0 disables the cache.
+	checkCacheTTL time.Duration
+	// checkOnly only checks for and reports available updates, like readOnly,
+	// but still lets the pass write its report, history and metrics.
+	checkOnly bool
+	// deferToManagedDraining leaves the cluster alone when a capacity provider
+	// has managed instance draining enabled.
+	deferToManagedDraining bool
+	// readiness weighs the signals that order instances for update.
+	readiness readinessWeights
+	// versionMetrics, warningMetrics, timingMetrics and failureMetrics write
+	// the respective CloudWatch embedded metric format events of the pass.
+	versionMetrics bool
+	warningMetrics bool
+	timingMetrics  bool
+	failureMetrics bool
+	// reportPath is where the JSON report of the pass is written; no report
+	// is written when it is empty.
+	reportPath string
+	// reportDiffPath is the report of a previous pass that the report is
+	// compared with; the report has no changes when it is empty.
+	reportDiffPath string
+	// advisoriesPath is the YAML file of security advisories listed in the
+	// report; the report has no advisories when it is empty.
+	advisoriesPath string
+	// historyPath is the JSON file the pass is appended to; the pass is not
+	// recorded when it is empty.
+	historyPath string
+	// historyRetention is how long passes are kept in the history; 0 keeps
+	// every pass.
+	historyRetention time.Duration
+	// tagCluster records a summary of the pass as tags on the cluster.
+	tagCluster bool
 }
 
 func main() {
 	if err := _main(); err != nil {
 		log.Println(err.Error())
+		if errors.Is(err, errShutdown) {
+			os.Exit(shutdownExitCode)
+		}
 		os.Exit(1)
 	}
 }
 
 func _main() error {
 	flag.Parse()
+	if *flagVersion {
+		if *flagVerbose {
+			fmt.Print(verboseVersionString())
+		} else {
+			fmt.Println(versionString())
+		}
+		return nil
+	}
+	if *flagIAMPolicy {
+		return printUpdaterPolicy(*flagRegion, *flagCheck, *flagReadOnly)
+	}
+	if *flagPrintHistory {
+		if *flagHistory == "" {
+			flag.Usage()
+			return errors.New("history is required with print-history")
+		}
+		return printHistory(os.Stdout, *flagHistory, (&updater{cluster: *flagCluster}).clusterName())
+	}
+	if !*flagLogSensitive {
+		log.SetOutput(redactingWriter{w: os.Stderr})
+	}
+	var faults *faultInjector
+	if *flagInjectFaults != "" {
+		var err error
+		faults, err = parseFaults(*flagInjectFaults, *flagFaultSeed)
+		if err != nil {
+			flag.Usage()
+			return err
+		}
+		log.Printf("Injecting faults into AWS calls: %s (fault seed %d)", *flagInjectFaults, faults.seed)
+	}
+	if *flagSimulate != "" {
+		sc, err := loadScenario(*flagSimulate)
+		if err != nil {
+			return err
+		}
+		sim := newSimulation(sc)
+		sim.faults = faults
+		sleep = sim.sleep
+		defer sim.printTimeline(os.Stdout)
+		log.Printf("Starting %s in simulation mode", versionString())
+		u := sim.updater()
+		u.rebootOnly = *flagRebootFleet
+		u.setPassOptions()
+		if *flagReadOnly {
+			u.makeReadOnly()
+		}
+		return run(u)
+	}
+	if *flagSupportBundle != "" {
+		switch {
+		case *flagCluster == "":
+			flag.Usage()
+			return errors.New("cluster is required")
+		case *flagRegion == "":
+			flag.Usage()
+			return errors.New("region is required")
+		}
+		sess := session.Must(session.NewSession(&aws.Config{
+			Region: aws.String(*flagRegion),
+		}))
+		bundle := supportBundle{
+			ecs:        ecs.New(sess, aws.NewConfig()),
+			sts:        sts.New(sess, aws.NewConfig()),
+			logs:       cloudwatchlogs.New(sess, aws.NewConfig()),
+			cluster:    *flagCluster,
+			logGroup:   *flagSupportBundleLogGroup,
+			reportPath: *flagReport,
+			sensitive:  *flagLogSensitive,
+		}
+		now := time.Now()
+		if err := writeSupportBundle(*flagSupportBundle, bundle.collect(now), now); err != nil {
+			return err
+		}
+		log.Printf("Wrote support bundle to %q; review it before attaching it to an issue", *flagSupportBundle)
+		return nil
+	}
+	if *flagQuarantine != "" {
+		until, err := time.Parse(time.RFC3339, *flagQuarantineUntil)
+		switch {
+		case *flagCluster == "":
+			flag.Usage()
+			return errors.New("cluster is required")
+		case *flagRegion == "":
+			flag.Usage()
+			return errors.New("region is required")
+		case *flagQuarantineReason == "":
+			flag.Usage()
+			return errors.New("quarantine-reason is required with quarantine")
+		case *flagReadOnly:
+			flag.Usage()
+			return errors.New("quarantine is not allowed with read-only")
+		case err != nil:
+			flag.Usage()
+			return fmt.Errorf("quarantine-until must be an RFC 3339 timestamp: %w", err)
+		case !until.After(time.Now()):
+			return errors.New("quarantine-until must be in the future")
+		}
+		sess := session.Must(session.NewSession(&aws.Config{
+			Region: aws.String(*flagRegion),
+		}))
+		u := &updater{cluster: *flagCluster, ecs: ecs.New(sess, aws.NewConfig())}
+		return u.quarantineInstance(*flagQuarantine, *flagQuarantineReason, until)
+	}
 	switch {
 	case *flagCluster == "":
 		flag.Usage()
@@ -62,28 +462,311 @@ func _main() error {
 	case *flagReboot == "":
 		flag.Usage()
 		return errors.New("reboot-document is required")
+	case *flagSettings != "" && *flagSettingsDocument == "":
+		flag.Usage()
+		return errors.New("settings-document is required with settings")
+	case *flagMaxUpdatesPerPass < 0:
+		flag.Usage()
+		return errors.New("max-updates-per-pass must not be negative")
+	case *flagMaxUnhealthyFraction < 0 || *flagMaxUnhealthyFraction > 1:
+		flag.Usage()
+		return errors.New("max-unhealthy-fraction must be from 0 to 1")
+	case *flagMaxUnavailable != "" && !instanceCountPattern.MatchString(*flagMaxUnavailable):
+		flag.Usage()
+		return errors.New("max-unavailable must be a positive number or a percentage from 1% to 100%")
+	case *flagMinimumVersion != "" && !minimumVersionPattern.MatchString(*flagMinimumVersion):
+		flag.Usage()
+		return errors.New("minimum-version must be a version such as 1.19.2")
+	case *flagMinReleaseAge < 0:
+		flag.Usage()
+		return errors.New("min-release-age must not be negative")
+	case *flagMaxClockSkew < 0:
+		flag.Usage()
+		return errors.New("max-clock-skew must not be negative")
+	case *flagStartJitter < 0:
+		flag.Usage()
+		return errors.New("start-jitter must not be negative")
+	case *flagCheckInterval < 0:
+		flag.Usage()
+		return errors.New("check-interval must not be negative")
+	case *flagSettleTime < 0:
+		flag.Usage()
+		return errors.New("settle-time must not be negative")
+	case *flagCheckCacheTTL < 0:
+		flag.Usage()
+		return errors.New("check-cache-ttl must not be negative")
+	case *flagPrewarmShare < 0 || *flagPrewarmShare > 1:
+		flag.Usage()
+		return errors.New("prewarm-share must be from 0 to 1")
+	case *flagTarget != targetLatest && *flagTarget != targetLastKnownGood:
+		flag.Usage()
+		return fmt.Errorf("target must be %s or %s", targetLatest, targetLastKnownGood)
+	case (*flagTarget == targetLastKnownGood) != (*flagLastKnownGoodCluster != ""):
+		flag.Usage()
+		return errors.New("last-known-good-cluster is required with, and only valid with, target last-known-good")
+	case (*flagStageAttribute == "") != (*flagStages == ""):
+		flag.Usage()
+		return errors.New("stage-attribute and stages must be used together")
+	case *flagWaves != "" && *flagStages != "":
+		flag.Usage()
+		return errors.New("waves and stages can't be used together")
+	case *flagMaxWaveFailures < 0:
+		flag.Usage()
+		return errors.New("max-wave-failures must not be negative")
+	case *flagChangeRef != "" && !changeRefPattern.MatchString(*flagChangeRef):
+		flag.Usage()
+		return errors.New("change-ref must be 1 to 64 letters, numbers or _.:/=+-@ characters")
+	case *flagSSMMaxConcurrency != "" && !ssmMaxConcurrencyPattern.MatchString(*flagSSMMaxConcurrency):
+		flag.Usage()
+		return errors.New("ssm-max-concurrency must be a positive number or a percentage from 1% to 100%")
+	case *flagSSMMaxErrors != "" && !ssmMaxErrorsPattern.MatchString(*flagSSMMaxErrors):
+		flag.Usage()
+		return errors.New("ssm-max-errors must be a number or a percentage from 0% to 100%")
+	case *flagReportDiff != "" && *flagReport == "":
+		flag.Usage()
+		return errors.New("report is required with report-diff")
+	case *flagAdvisories != "" && *flagReport == "":
+		flag.Usage()
+		return errors.New("report is required with advisories")
+	case *flagHistoryRetention < 0:
+		flag.Usage()
+		return errors.New("history-retention must not be negative")
+	case *flagFailureWebhookThreshold < 1:
+		flag.Usage()
+		return errors.New("failure-webhook-threshold must be at least 1")
+	case *flagFailureWebhookAuthorization != "" && *flagFailureWebhook == "":
+		flag.Usage()
+		return errors.New("failure-webhook is required with failure-webhook-authorization")
+	case *flagSecretRefresh < 0:
+		flag.Usage()
+		return errors.New("secret-refresh must not be negative")
+	case *flagInstanceLockPrefix != "" && !strings.HasPrefix(*flagInstanceLockPrefix, "/"):
+		flag.Usage()
+		return errors.New("instance-lock-prefix must start with /")
+	case *flagInstanceLockTTL <= 0:
+		flag.Usage()
+		return errors.New("instance-lock-ttl must be positive")
+	case *flagStuckDrainPolicy != "" && *flagStuckDrainPolicy != stuckDrainAlert && *flagStuckDrainPolicy != stuckDrainActivate && *flagStuckDrainPolicy != stuckDrainComplete:
+		flag.Usage()
+		return fmt.Errorf("stuck-drain-policy must be %s, %s or %s", stuckDrainAlert, stuckDrainActivate, stuckDrainComplete)
+	case *flagMaintenanceWindow != "" && !maintenanceWindowPattern.MatchString(*flagMaintenanceWindow):
+		flag.Usage()
+		return errors.New("maintenance-window must be a maintenance window ID, e.g. mw-0123456789abcdef0")
+	case (*flagUpdateWindow == "") != (*flagUpdateWindowDuration == 0):
+		flag.Usage()
+		return errors.New("update-window and update-window-duration must be used together")
+	}
+	segmentTags, err := parseSegmentTags(*flagSegmentTags)
+	if err != nil {
+		flag.Usage()
+		return err
+	}
+	if *flagAdvisories != "" {
+		if _, err := readAdvisories(*flagAdvisories); err != nil {
+			return err
+		}
+	}
+	var stages []rolloutStage
+	if *flagStages != "" {
+		stages, err = parseStages(*flagStages)
+		if err != nil {
+			return err
+		}
+	}
+	var window *updateWindow
+	if *flagUpdateWindow != "" {
+		window, err = newUpdateWindow(*flagUpdateWindow, *flagUpdateWindowDuration, *flagUpdateWindowTimezone)
+		if err != nil {
+			flag.Usage()
+			return err
+		}
+	}
+	var waves []rolloutStage
+	if *flagWaves != "" {
+		waves, err = parseWaves(*flagWaves)
+		if err != nil {
+			return err
+		}
+	}
+	if *flagSettings != "" {
+		if err := validateSettings(*flagSettings); err != nil {
+			return err
+		}
 	}
 
+	log.Printf("Starting %s", versionString())
+
 	sess := session.Must(session.NewSession(&aws.Config{
 		Region: aws.String(*flagRegion),
 	}))
+	if faults != nil {
+		faults.install(&sess.Handlers)
+	}
+	warnings := newPassWarnings()
+	warnings.install(&sess.Handlers)
+	clock := &clockSkew{}
+	clock.install(&sess.Handlers)
+
+	identity, err := callerIdentity(sts.New(sess, aws.NewConfig()), *flagExpectedAccount)
+	if err != nil {
+		return err
+	}
+
+	identityARN, err := arn.Parse(identity)
+	if err != nil {
+		return fmt.Errorf("failed to parse caller identity %q: %w", identity, err)
+	}
 
+	ssmConfig := request.WithRetryer(aws.NewConfig(), sendCommandRetryer{
+		client.DefaultRetryer{NumMaxRetries: client.DefaultRetryerMaxNumRetries},
+	})
 	u := &updater{
-		cluster:        *flagCluster,
-		checkDocument:  *flagCheck,
-		applyDocument:  *flagApply,
-		rebootDocument: *flagReboot,
-		ecs:            ecs.New(sess, aws.NewConfig()),
-		ssm:            ssm.New(sess, aws.NewConfig()),
-		ec2:            ec2.New(sess, aws.NewConfig()),
+		cluster:            *flagCluster,
+		checkDocument:      *flagCheck,
+		applyDocument:      *flagApply,
+		rebootDocument:     *flagReboot,
+		settingsDocument:   *flagSettingsDocument,
+		settings:           *flagSettings,
+		ssmComment:         *flagSSMComment,
+		ssmMaxConcurrency:  *flagSSMMaxConcurrency,
+		ssmMaxErrors:       *flagSSMMaxErrors,
+		changeRef:          *flagChangeRef,
+		ecs:                ecs.New(sess, aws.NewConfig()),
+		ssm:                ssm.New(sess, ssmConfig),
+		ec2:                ec2.New(sess, aws.NewConfig()),
+		autoscaling:        autoscaling.New(sess, aws.NewConfig()),
+		elbv2:              elbv2.New(sess, aws.NewConfig()),
+		maxUpdates:         *flagMaxUpdatesPerPass,
+		maxUnhealthy:       *flagMaxUnhealthyFraction,
+		maxUnavailable:     *flagMaxUnavailable,
+		minReleaseAge:      *flagMinReleaseAge,
+		minimumVersion:     *flagMinimumVersion,
+		startJitter:        *flagStartJitter,
+		settleTime:         *flagSettleTime,
+		maxClockSkew:       *flagMaxClockSkew,
+		clock:              clock,
+		stageAttribute:     *flagStageAttribute,
+		stages:             stages,
+		waves:              waves,
+		maxWaveFailures:    *flagMaxWaveFailures,
+		variants:           parseVariants(*flagSupportedVariants),
+		provenIn:           *flagLastKnownGoodCluster,
+		promotionParameter: *flagPromotionPolicy,
+		identity:           identity,
+		region:             *flagRegion,
+		account:            identityARN.AccountID,
+		warnings:           warnings,
+		rebootOnly:         *flagRebootFleet,
+		scalingProviders:   parseScalingProviders(*flagSuspendScaling),
+		failureWebhook:     *flagFailureWebhook,
+		escalateAfter:      *flagFailureWebhookThreshold,
+		logSensitive:       *flagLogSensitive,
+		lockPrefix:         *flagInstanceLockPrefix,
+		lockTTL:            *flagInstanceLockTTL,
+		deferToSSMActivity: *flagDeferToSSMActivity,
+		maintenanceWindow:  *flagMaintenanceWindow,
+		updateWindow:       window,
+		stuckDrainPolicy:   *flagStuckDrainPolicy,
+		intentRecords:      *flagIntentRecords,
+		segmentTags:        segmentTags,
+		singletonFamilies:  parseFamilies(*flagSingletonFamilies),
+		excludeServices:    parseServices(*flagExcludeServices),
+		prewarmShare:       *flagPrewarmShare,
+		checkCacheTTL:      *flagCheckCacheTTL,
+		progress:           newProgressFile(*flagProgressFile),
+	}
+	u.setPassOptions()
+	if *flagFailureWebhookAuthorization != "" {
+		u.webhookAuth, err = newSecret(*flagFailureWebhookAuthorization, *flagSecretRefresh, secretsmanager.New(sess, aws.NewConfig()), u.ssm)
+		if err != nil {
+			return err
+		}
+		// Read the secret at startup, so that a missing secret or permission
+		// fails the run rather than the escalation of a failure.
+		if _, err := u.webhookAuth.get(time.Now()); err != nil {
+			return fmt.Errorf("failed to read the failure webhook authorization: %w", err)
+		}
+	}
+	if *flagReadOnly {
+		u.makeReadOnly()
+	}
+	shutdown, stopSignals := signal.NotifyContext(context.Background(), syscall.SIGTERM, os.Interrupt)
+	defer stopSignals()
+	u.shutdown = shutdown
+	pass := func() error {
+		err := run(u)
+		u.endProgress(err)
+		return err
+	}
+	if *flagCheckInterval == 0 {
+		return pass()
+	}
+	return runEvery(shutdown, *flagCheckInterval, u.updateWindow, func() error {
+		// Passes start afresh; the session's handlers keep recording into
+		// the same warnings.
+		u.warnings.reset()
+		u.sent = nil
+		u.timings = nil
+		return pass()
+	})
+}
+
+// run performs a single pass of the updater over the cluster.
+// setPassOptions sets the options of the pass that apply to simulations as
+// well as to real clusters from their flags.
+func (u *updater) setPassOptions() {
+	u.checkOnly = *flagCheckOnly
+	u.deferToManagedDraining = *flagDeferToManagedDraining
+	u.readiness = readinessWeights{taskCount: *flagTaskCountWeight, utilization: *flagUtilizationWeight}
+	u.versionMetrics = *flagVersionMetrics
+	u.warningMetrics = *flagWarningMetrics
+	u.timingMetrics = *flagTimingMetrics
+	u.failureMetrics = *flagFailureMetrics
+	u.reportPath = *flagReport
+	u.reportDiffPath = *flagReportDiff
+	u.advisoriesPath = *flagAdvisories
+	u.historyPath = *flagHistory
+	u.historyRetention = *flagHistoryRetention
+	u.tagCluster = *flagTagCluster
+}
+
+func run(u *updater) error {
+	u.passID = newCorrelationID()
+	restoreLog := u.correlateLog()
+	defer restoreLog()
+	if u.changeRef != "" {
+		log.Printf("Starting pass %s over cluster %q for change %s", u.passID, u.cluster, u.changeRef)
+	} else {
+		log.Printf("Starting pass %s over cluster %q", u.passID, u.cluster)
+	}
+	checkOnly := u.checkOnly || u.readOnly || os.Getenv(checkOnlyEnv) == "true"
+	if u.readOnly {
+		log.Printf("Running in read-only mode, therefore only checking for and reporting available updates")
+	}
+	if wait := startJitter(u.account, u.region, u.clusterName(), u.startJitter); wait > 0 {
+		log.Printf("Waiting %s before starting the pass to spread the load of updaters on the same schedule", wait.Round(time.Second))
+		if err := sleepContext(u.shutdownContext(), wait); err != nil {
+			log.Printf("Shutting down, therefore not starting the pass")
+			return err
+		}
 	}
 
+	if u.warnings == nil {
+		u.warnings = newPassWarnings()
+	}
 	family, err := taskDefFamily()
-	if err != nil {
+	if checkOnly {
+		// Check-only runs change nothing, so they run alongside other updaters.
+		log.Printf("Running in check-only mode, therefore not checking for already running updaters")
+	} else if err != nil {
 		log.Printf("Failed to parse updater task definition arn: %v", err)
 		log.Printf("Ignoring check for already running updater")
 	} else {
-		ok, err := u.alreadyRunning(family)
+		self, err := ownTaskARN()
+		if err != nil {
+			log.Printf("Failed to determine this updater's task, therefore deferring to any other running updater: %v", err)
+		}
+		ok, err := u.alreadyRunning(family, self)
 		if err != nil {
 			return fmt.Errorf("Cannot determine running updater tasks, therefore stopping this run to avoid risk of multiple runs: %w", err)
 		}
@@ -93,6 +776,138 @@ func _main() error {
 		}
 	}
 
+	managedDraining, err := u.managedDrainingProviders()
+	if err != nil {
+		log.Printf("Failed to check capacity providers of cluster %q: %v", u.cluster, err)
+	} else if len(managedDraining) > 0 {
+		log.Printf("Capacity provider(s) %q have managed instance draining enabled; ECS drains instances when their"+
+			" Auto Scaling group terminates them, while the updater drains instances itself before updating in place", managedDraining)
+		if u.deferToManagedDraining {
+			log.Printf("Deferring to managed instance draining, therefore not updating instances in place")
+			return nil
+		}
+	}
+
+	if u.sent == nil {
+		u.sent = &commandLog{}
+	}
+	if u.timings == nil {
+		u.timings = newPassTimings()
+	}
+	summary := newPassSummary()
+	summary.passID = u.passID
+	summary.changeRef = u.changeRef
+	summary.identity = u.identity
+	summary.warnings = u.warnings
+	summary.commands = u.sent
+	summary.timings = u.timings
+	u.startProgress(summary)
+	if u.warningMetrics {
+		defer func() {
+			if err := writeWarningMetrics(metricsOutput, u.clusterName(), summary.warnings, time.Now()); err != nil {
+				log.Printf("Failed to write warning metrics: %v", err)
+			}
+		}()
+	}
+	if u.timingMetrics {
+		defer func() {
+			if err := writeTimingMetrics(metricsOutput, u.clusterName(), summary.timings, time.Now()); err != nil {
+				log.Printf("Failed to write timing metrics: %v", err)
+			}
+		}()
+	}
+	if u.failureMetrics {
+		defer func() {
+			if err := writeFailureMetrics(metricsOutput, u.clusterName(), summary, time.Now()); err != nil {
+				log.Printf("Failed to write failure metrics: %v", err)
+			}
+		}()
+	}
+	if u.reportPath != "" {
+		defer func() {
+			metadata, err := u.ec2Metadata(summary.reportedEC2Instances())
+			if err != nil {
+				log.Printf("Failed to describe EC2 instances, therefore leaving their metadata out of the report: %v", err)
+			}
+			var advisories []advisory
+			if u.advisoriesPath != "" {
+				if advisories, err = readAdvisories(u.advisoriesPath); err != nil {
+					log.Printf("Failed to read advisories, therefore leaving them out of the report: %v", err)
+					u.warnings.add(warnDegraded, "advisories were left out of the report: %v", err)
+				}
+			}
+			if err := writeReport(u.reportPath, u.reportDiffPath, u.clusterName(), summary, metadata, advisories, u.logSensitive, time.Now()); err != nil {
+				log.Printf("Failed to write report: %v", err)
+			}
+		}()
+	}
+	if u.historyPath != "" {
+		passStart := time.Now()
+		defer func() {
+			now := time.Now()
+			if err := appendHistory(u.historyPath, newHistoryEntry(u.clusterName(), summary, passStart, now), u.historyRetention, now); err != nil {
+				log.Printf("Failed to record the pass in the history: %v", err)
+			}
+		}()
+	}
+	if u.tagCluster && !checkOnly {
+		defer func() {
+			if err := u.tagClusterSummary(summary); err != nil {
+				log.Printf("Failed to record run summary on cluster %q: %v", u.cluster, err)
+			}
+		}()
+	}
+
+	if len(u.segmentTags) > 0 {
+		clusterTags, err := u.clusterTags()
+		if err != nil {
+			log.Printf("Failed to read the cluster's tags, therefore segmenting by instance tags alone: %v", err)
+			u.warnings.add(warnDegraded, "cluster tags for segments are unknown: %v", err)
+		}
+		summary.segmentBy(u.segmentTags, clusterTags)
+		if u.versionMetrics {
+			defer func() {
+				if err := writeSegmentMetrics(metricsOutput, u.clusterName(), summary, time.Now()); err != nil {
+					log.Printf("Failed to write segment metrics: %v", err)
+				}
+			}()
+		}
+	}
+	if u.intentRecords {
+		intents, err := u.findIntents()
+		if err != nil {
+			log.Printf("Failed to look for unresolved intents: %v", err)
+			u.warnings.add(warnDegraded, "unresolved intents are unknown: %v", err)
+		}
+		u.recoverIntents(intents, summary, checkOnly)
+	}
+	if u.stuckDrainPolicy != "" {
+		stuck, err := u.findStuckDrains(time.Now())
+		if err != nil {
+			log.Printf("Failed to look for stuck drains: %v", err)
+			u.warnings.add(warnDegraded, "stuck drains are unknown: %v", err)
+		}
+		u.recoverStuckDrains(stuck, time.Now(), checkOnly)
+	}
+	if err := u.checkClusterHealth(); err != nil && !checkOnly {
+		return err
+	} else if err != nil {
+		// Check-only runs take no instances out of service, so they still
+		// report the updates available to an unhealthy cluster.
+		log.Printf("Checking for updates anyway: %v", err)
+		u.warnings.add(warnDegraded, "cluster health check failed: %v", err)
+	}
+	if err := u.checkClockSkew(); err != nil {
+		return err
+	}
+	if len(u.scalingProviders) > 0 && !checkOnly {
+		u.resumeLeftoverScaling()
+	}
+	if u.prewarmShare > 0 && !checkOnly {
+		u.relinquishLeftoverCapacity()
+	}
+
+	discoverStart := time.Now()
 	listedInstances, err := u.listContainerInstances()
 	if err != nil {
 		return fmt.Errorf("Failed to get container instances in cluster %q: %w", u.cluster, err)
@@ -102,84 +917,451 @@ func _main() error {
 		return nil
 	}
 
-	bottlerocketInstances, err := u.filterBottlerocketInstances(listedInstances)
+	listedInstances, foreign := u.filterForeignInstances(listedInstances)
+	if len(foreign) > 0 {
+		log.Printf("Warning: %d container instance(s) do not belong to region %q and account %q; they are excluded from this pass",
+			len(foreign), u.region, u.account)
+		for _, failure := range foreign {
+			log.Printf("Container instance %q: %s", failure.arn, failure.reason)
+			summary.skippedInstance(failure.arn, skipForeign, fmt.Sprintf("Foreign instance: %s", failure.reason))
+			u.warnings.add(warnSkipped, "container instance %q is foreign: %s", failure.arn, failure.reason)
+		}
+	}
+	if len(listedInstances) == 0 {
+		log.Print("No container instances in the updater's region and account")
+		return nil
+	}
+
+	described, err := u.filterBottlerocketInstances(listedInstances)
 	if err != nil {
 		return fmt.Errorf("Failed to filter Bottlerocket instances: %w", err)
 	}
+	u.timings.record(phaseDiscover, discoverStart)
+	if len(described.failures) > 0 {
+		log.Printf("The state of %d container instance(s) is unknown; they are excluded from this pass", len(described.failures))
+		for _, failure := range described.failures {
+			log.Printf("Container instance %q: %s", failure.arn, failure.reason)
+			summary.skippedInstance(failure.arn, skipStateUnknown, fmt.Sprintf("State unknown: %s", failure.reason))
+			u.warnings.add(warnSkipped, "state of container instance %q is unknown: %s", failure.arn, failure.reason)
+		}
+	}
+	if len(described.stale) > 0 {
+		log.Printf("Warning: %d container instance(s) are stale registrations of instances registered more than once;"+
+			" they are excluded from this pass and should be deregistered", len(described.stale))
+		for _, inst := range described.stale {
+			log.Printf("Container instance %q is a stale registration of instance %q", inst.containerInstanceID, inst.instanceID)
+			summary.skippedInstance(inst.containerInstanceID, skipStale, fmt.Sprintf("Stale registration of instance %s; deregister it", inst.instanceID))
+			u.warnings.add(warnSkipped, "container instance %q is a stale registration of instance %q", inst.containerInstanceID, inst.instanceID)
+		}
+	}
+	if len(described.unsupported) > 0 {
+		log.Printf("Warning: %d Bottlerocket instance(s) run a variant that is not in -supported-variants %q; they are excluded from this pass",
+			len(described.unsupported), formatVariants(u.variants))
+		for _, inst := range described.unsupported {
+			summary.skippedInstance(inst.instanceID, skipUnsupported, fmt.Sprintf("Unsupported variant %q", inst.variant))
+			u.warnings.add(warnUnsupported, "instance %q runs unsupported variant %q", inst.instanceID, inst.variant)
+		}
+	}
+	bottlerocketInstances := described.bottlerocket
+	summary.recordSegments(bottlerocketInstances)
+	summary.recordSegments(described.unsupported)
+	if u.versionMetrics {
+		defer func() {
+			if err := writeInstanceMetrics(metricsOutput, u.clusterName(), len(bottlerocketInstances), described.others, time.Now()); err != nil {
+				log.Printf("Failed to write instance metrics: %v", err)
+			}
+		}()
+	}
 
 	if len(bottlerocketInstances) == 0 {
-		log.Printf("No Bottlerocket instances detected")
+		if described.others > 0 {
+			log.Printf("No Bottlerocket instances found; %d other container instance(s) present. Bottlerocket instances are"+
+				" detected by their bottlerocket.variant attribute, so check that it is reported by the ECS agent", described.others)
+		} else {
+			log.Printf("No Bottlerocket instances detected")
+		}
 		return nil
 	}
+	u.clearExpiredQuarantines(bottlerocketInstances, time.Now(), checkOnly)
+	u.progressState(progressChecking)
+	checkStart := time.Now()
+	var latest map[string]string
+	if u.checkCacheTTL > 0 {
+		latest = u.latestVersions(bottlerocketInstances)
+		u.applyCheckCache(bottlerocketInstances, latest, time.Now())
+	}
 	candidates, err := u.filterAvailableUpdates(bottlerocketInstances)
 	if err != nil {
 		return fmt.Errorf("Failed to check updates: %w", err)
 	}
+	if u.checkCacheTTL > 0 && !checkOnly {
+		u.recordCheckCache(bottlerocketInstances, candidates, latest, time.Now())
+	}
+	if u.rebootOnly {
+		log.Printf("Running in reboot-fleet mode, therefore rebooting instances without updating them")
+		candidates = rebootCandidates(bottlerocketInstances)
+	}
+	u.timings.record(phaseCheck, checkStart)
+	planStart := time.Now()
+	summary.recordVersions(bottlerocketInstances)
+	log.Printf("Bottlerocket versions in the cluster: %s", histogram(summary.versionsBefore))
+	u.warnBelowMinimum(bottlerocketInstances)
+	for _, inst := range bottlerocketInstances {
+		if inst.unparseableOutput != "" {
+			summary.unparseableInstance(inst.instanceID, "Update check output could not be parsed", inst.unparseableOutput)
+		}
+		if inst.invalidTarget {
+			summary.skippedInstance(inst.instanceID, skipNotSSMTarget, "Not a valid SSM target, e.g. because it was terminated")
+		}
+	}
+	if u.versionMetrics {
+		defer func() {
+			if err := writeVersionMetrics(metricsOutput, u.clusterName(), summary, time.Now()); err != nil {
+				log.Printf("Failed to write version metrics: %v", err)
+			}
+		}()
+	}
+	candidates = filterQuarantined(candidates, summary, time.Now())
+	if !u.rebootOnly {
+		candidates = u.filterReleaseAge(candidates, summary, time.Now())
+		candidates = u.filterLastKnownGood(candidates, summary)
+		candidates = u.filterPromotion(candidates, summary, time.Now())
+	}
+	candidates = u.filterMaintenanceEvents(candidates, summary, time.Now())
 	if len(candidates) == 0 {
 		log.Printf("No instances to update")
 		return nil
 	}
+	sortByReadiness(candidates, u.readiness)
+	scheduledRebootsFirst(candidates)
 	log.Printf("Instances ready for update: %#q", candidates)
-
-	summary := make(map[string]string)
-	for _, i := range candidates {
-		eligible, err := u.eligible(i.containerInstanceID)
+	workloads := u.candidateWorkloads(candidates)
+	summary.recordServices(workloads)
+	candidates = u.skipExcluded(candidates, workloads, summary)
+	candidates = u.deferTaggedInstances(candidates, workloads)
+	stages := u.stageCandidates(candidates)
+	candidates = stagedOrder(stages)
+	logUpdatePlan(candidates, workloads)
+	u.timings.record(phasePlan, planStart)
+	if checkOnly {
+		log.Printf("Running in check-only mode, therefore not updating %d instance(s)", len(candidates))
+		return nil
+	}
+	if u.maintenanceWindow != "" {
+		execution, err := u.openWindowExecution(time.Now())
 		if err != nil {
-			log.Printf("Failed to determine eligibility for update of instance %#q: %v", i, err)
-			summary[i.instanceID] = fmt.Sprintf("Failed to determine eligibility for update: %v", err)
-			continue
+			log.Printf("Failed to check maintenance window %q, therefore not updating %d instance(s): %v", u.maintenanceWindow, len(candidates), err)
+			u.warnings.add(warnDegraded, "state of maintenance window %q is unknown: %v", u.maintenanceWindow, err)
+		} else if execution == "" {
+			log.Printf("Maintenance window %q is not open, therefore not updating %d instance(s)", u.maintenanceWindow, len(candidates))
 		}
-		if !eligible {
-			log.Printf("Instance %#q is not eligible for updates because it contains non-service task", i)
-			summary[i.instanceID] = "Instance is not eligible for updates because it contains non-service task(s)"
-			continue
+		if execution == "" {
+			mandatory := u.keepBelowMinimum(candidates, summary, skipWindowClosed, fmt.Sprintf("Maintenance window %s is not open", u.maintenanceWindow))
+			if len(mandatory) == 0 {
+				return nil
+			}
+			log.Printf("Updating %d instance(s) running Bottlerocket older than the minimum version %s outside maintenance window %q",
+				len(mandatory), u.minimumVersion, u.maintenanceWindow)
+			candidates = mandatory
+			stages = u.stageCandidates(candidates)
+		} else {
+			log.Printf("Updating instances in execution %s of maintenance window %q", execution, u.maintenanceWindow)
+			defer u.recordWindowPass(execution, summary)
+		}
+	}
+	if u.updateWindow != nil {
+		now := time.Now()
+		if end, open := u.updateWindow.openUntil(now); open {
+			log.Printf("Update window %s is open until %s", u.updateWindow, end.Format(time.RFC3339))
+		} else {
+			next, _ := u.updateWindow.nextOpen(now)
+			log.Printf("Update window %s is not open until %s, therefore not updating %d instance(s)", u.updateWindow, next.Format(time.RFC3339), len(candidates))
+			mandatory := u.keepBelowMinimum(candidates, summary, skipOutsideWindow, fmt.Sprintf("Update window opens at %s", next.Format(time.RFC3339)))
+			if len(mandatory) == 0 {
+				return nil
+			}
+			log.Printf("Updating %d instance(s) running Bottlerocket older than the minimum version %s outside the update window", len(mandatory), u.minimumVersion)
+			candidates = mandatory
+			stages = u.stageCandidates(candidates)
 		}
-		log.Printf("Instance %q is eligible for update", i)
+	}
+	daemonServices, err := u.daemonServices(workloads)
+	if err != nil {
+		log.Printf("Failed to determine daemon services, therefore not waiting for daemon tasks after updates: %v", err)
+		u.warnings.add(warnDegraded, "daemon services are unknown: %v", err)
+	}
+	targetDrain, err := u.targetDrainServices(workloads)
+	if err != nil {
+		log.Printf("Failed to read service tags, therefore waiting for all tasks to stop when draining: %v", err)
+		u.warnings.add(warnDegraded, "services drained by target group are unknown: %v", err)
+	}
+	if u.failureWebhook != "" {
+		defer u.recordConsecutiveFailures(bottlerocketInstances, summary)
+	}
+	if len(u.scalingProviders) > 0 {
+		suspended := u.suspendScaling()
+		defer u.resumeScaling(suspended)
+	}
+	prewarmed := make(map[string]bool)
+	if u.prewarmShare > 0 {
+		defer u.relinquishCapacity(prewarmed)
+	}
 
-		err = u.drainInstance(i.containerInstanceID)
-		if err != nil {
-			log.Printf("Failed to drain instance %#q: %v", i, err)
-			summary[i.instanceID] = fmt.Sprintf("Failed to drain: %v", err)
-			continue
-		}
-		log.Printf("Instance %#q successfully drained!", i)
-
-		updateErr := u.updateInstance(i)
-		activateErr := u.activateInstance(i.containerInstanceID)
-		if updateErr != nil && activateErr != nil {
-			log.Printf("Failed to update instance %#q: %v", i, updateErr)
-			return fmt.Errorf("instance %#q failed to re-activate after failing to update: %w", i, activateErr)
-		} else if updateErr != nil {
-			log.Printf("Failed to update instance %#q: %v", i, updateErr)
-			summary[i.instanceID] = fmt.Sprintf("Failed to update: %v", updateErr)
-			continue
-		} else if activateErr != nil {
-			return fmt.Errorf("instance %#q failed to re-activate after update: %w", i, activateErr)
-		}
-
-		// Reboots are not immediate, and initiating an SSM command races with reboot. Add some
-		// sleep time to allow the reboot to progress before we verify update.
-		time.Sleep(20 * time.Second)
-		ok, err := u.verifyUpdate(i)
-		if err != nil {
-			log.Printf("Failed to verify update for instance %#q: %v", i, err)
+	u.progressCandidates(candidates)
+	updatesStarted := 0
+	// settling is set while an instance the pass returned to ACTIVE has not
+	// yet settled for settleTime.
+	settling := false
+	// overBudget is set once the pass gave up waiting for -max-unavailable to
+	// allow draining an instance; the instances left are skipped.
+	overBudget := false
+	for n, stage := range stages {
+		if len(stages) > 1 {
+			log.Printf("Starting stage %d of %d: %s, %d instance(s)", n+1, len(stages), stage.name, len(stage.instances))
 		}
-		if !ok {
-			log.Printf("Update failed for instance %#q", i)
-			summary[i.instanceID] = "Update failed"
-		} else {
-			log.Printf("Instance %#q updated successfully!", i)
-			summary[i.instanceID] = "Instance updated successfully"
+		updatedBefore, failedBefore := summary.updated, summary.failed
+		for _, i := range stage.instances {
+			if u.stopping() {
+				log.Printf("Shutting down, therefore not updating instance %#q", i)
+				summary.skippedInstance(i.instanceID, skipShutdown, "Updater shut down before updating the instance")
+				continue
+			}
+			if u.updateWindow != nil && !u.belowMinimum(i) {
+				if _, open := u.updateWindow.openUntil(time.Now()); !open {
+					log.Printf("Update window %s closed, therefore not updating instance %#q", u.updateWindow, i)
+					summary.skippedInstance(i.instanceID, skipOutsideWindow, "Update window closed during the pass")
+					continue
+				}
+			}
+			if u.maxUpdates > 0 && updatesStarted >= u.maxUpdates {
+				log.Printf("Reached the limit of %d update(s) per pass, therefore not updating instance %#q", u.maxUpdates, i)
+				summary.skippedInstance(i.instanceID, skipUpdateLimit, fmt.Sprintf("Limit of %d update(s) per pass reached", u.maxUpdates))
+				continue
+			}
+			if settling && u.settleTime > 0 {
+				log.Printf("Letting the last re-activated instance settle for %s before starting instance %#q", u.settleTime, i)
+				if err := sleepContext(u.shutdownContext(), u.settleTime); err != nil {
+					log.Printf("Shutting down, therefore stopped letting the last re-activated instance settle")
+					summary.skippedInstance(i.instanceID, skipShutdown, "Updater shut down before updating the instance")
+					continue
+				}
+			}
+			settling = false
+			if overBudget {
+				summary.skippedInstance(i.instanceID, skipBudget, fmt.Sprintf("More instances unavailable than -max-unavailable %s allows", u.maxUnavailable))
+				continue
+			}
+			if err := u.waitForBudget(i); err != nil && u.stopping() {
+				log.Printf("Shutting down, therefore stopped waiting to update instance %#q: %v", i, err)
+				summary.skippedInstance(i.instanceID, skipShutdown, "Updater shut down before updating the instance")
+				continue
+			} else if err != nil {
+				log.Printf("Not updating instance %#q or the instances left: %v", i, err)
+				summary.skippedInstance(i.instanceID, skipBudget, fmt.Sprintf("More instances unavailable than -max-unavailable %s allows: %v", u.maxUnavailable, err))
+				overBudget = true
+				continue
+			}
+			eligible, err := u.eligible(i.containerInstanceID)
+			if err != nil {
+				log.Printf("Failed to determine eligibility for update of instance %#q: %v", i, err)
+				summary.failedInstance(i.instanceID, fmt.Sprintf("Failed to determine eligibility for update: %v", err))
+				continue
+			}
+			if !eligible {
+				log.Printf("Instance %#q is not eligible for updates because it contains non-service task", i)
+				summary.skippedInstance(i.instanceID, skipStandaloneTasks, "Instance is not eligible for updates because it contains non-service task(s)")
+				continue
+			}
+			log.Printf("Instance %q is eligible for update", i)
+			if err := u.checkExcluded(i); err != nil {
+				log.Printf("Not updating instance %#q: %v", i, err)
+				summary.skippedInstance(i.instanceID, skipExcluded, fmt.Sprintf("Requires manual update: %v", err))
+				continue
+			}
+			if err := u.checkSSMActivity(i, time.Now()); err != nil {
+				log.Printf("Deferring instance %#q to a later pass: %v", i, err)
+				summary.skippedInstance(i.instanceID, skipSSMActivity, fmt.Sprintf("Deferred: %v", err))
+				continue
+			}
+			release := func() {}
+			if u.lockPrefix != "" {
+				release, err = u.lockInstance(i, time.Now())
+				if errors.Is(err, errInstanceLocked) {
+					log.Printf("Instance %#q is locked by other automation, therefore not updating it: %v", i, err)
+					summary.skippedInstance(i.instanceID, skipLocked, fmt.Sprintf("Locked by other automation: %v", err))
+					continue
+				} else if err != nil {
+					log.Printf("Failed to lock instance %#q, therefore not updating it: %v", i, err)
+					summary.skippedInstance(i.instanceID, skipLockFailed, fmt.Sprintf("Failed to lock: %v", err))
+					u.warnings.add(warnDegraded, "instance %q was not locked: %v", i.instanceID, err)
+					continue
+				}
+			}
+			updatesStarted++
+			release = u.beginUpdate(i, summary, release)
+
+			if u.prewarmShare > 0 {
+				u.prewarm(i, bottlerocketInstances, prewarmed)
+			}
+			if len(u.singletonFamilies) > 0 {
+				if err := u.replaceSingletons(i, bottlerocketInstances); err != nil {
+					log.Printf("Failed to replace singleton tasks of instance %#q, therefore not draining it: %v", i, err)
+					summary.failedInstance(i.instanceID, fmt.Sprintf("Failed to replace singleton tasks: %v", err))
+					release()
+					continue
+				}
+			}
+
+			if err := u.recordIntent(i, intentDrain, time.Now()); err != nil {
+				log.Printf("Not draining instance %#q: %v", i, err)
+				summary.failedInstance(i.instanceID, fmt.Sprintf("Failed to drain: %v", err))
+				release()
+				continue
+			}
+			u.progressStep(i, stepDraining)
+			drainStart := time.Now()
+			err = u.drainInstance(i, targetDrain)
+			u.timings.record(phaseDrain, drainStart)
+			if err != nil && u.stopping() {
+				// drainInstance re-activated the instance when it stopped waiting.
+				log.Printf("Shutting down, therefore stopped draining instance %#q: %v", i, err)
+				summary.skippedInstance(i.instanceID, skipShutdown, "Updater shut down while draining the instance")
+				release()
+				continue
+			} else if err != nil {
+				log.Printf("Failed to drain instance %#q: %v", i, err)
+				summary.failedInstance(i.instanceID, fmt.Sprintf("Failed to drain: %v", err))
+				release()
+				continue
+			}
+			log.Printf("Instance %#q successfully drained!", i)
+			if u.stopping() {
+				log.Printf("Shutting down, therefore re-activating instance %#q without updating it", i)
+				if err := u.activateInstance(i.containerInstanceID); err != nil {
+					return fmt.Errorf("instance %#q failed to re-activate when shutting down: %w", i, err)
+				}
+				summary.skippedInstance(i.instanceID, skipShutdown, "Updater shut down before updating the instance")
+				release()
+				continue
+			}
+
+			u.progressStep(i, stepUpdating)
+			updateStart := time.Now()
+			action, apply := intentUpdate, u.updateInstance
+			if u.rebootOnly {
+				action, apply = intentReboot, u.rebootInstance
+			}
+			updateErr := u.recordIntent(i, action, time.Now())
+			if updateErr == nil {
+				updateErr = apply(i)
+			}
+			activateErr := u.activateInstance(i.containerInstanceID)
+			settling = activateErr == nil
+			u.timings.record(phaseUpdate, updateStart)
+			if updateErr != nil && activateErr != nil {
+				log.Printf("Failed to update instance %#q: %v", i, updateErr)
+				return fmt.Errorf("instance %#q failed to re-activate after failing to update: %w", i, activateErr)
+			} else if updateErr != nil {
+				log.Printf("Failed to update instance %#q: %v", i, updateErr)
+				summary.failedInstance(i.instanceID, fmt.Sprintf("Failed to update: %v", updateErr))
+				release()
+				continue
+			} else if activateErr != nil {
+				return fmt.Errorf("instance %#q failed to re-activate after update: %w", i, activateErr)
+			}
+
+			u.progressStep(i, stepVerifying)
+			verifyStart := time.Now()
+			// Reboots are not immediate, and initiating an SSM command races with reboot. Add some
+			// sleep time to allow the reboot to progress before we verify update.
+			if err := sleepContext(u.shutdownContext(), 20*time.Second); err != nil {
+				log.Printf("Shutting down, therefore not verifying the update of instance %#q", i)
+				summary.failedInstance(i.instanceID, "Updater shut down before verifying the update")
+				release()
+				continue
+			}
+			var updatedVersion string
+			var ok bool
+			if u.rebootOnly {
+				updatedVersion, err = u.verifyReboot(i)
+				ok = err == nil
+			} else {
+				updatedVersion, ok, err = u.verifyUpdate(i)
+			}
+			if err != nil {
+				log.Printf("Failed to verify update for instance %#q: %v", i, err)
+			}
+			summary.updatedVersion(i.instanceID, updatedVersion)
+			if !ok && u.rebootOnly {
+				log.Printf("Reboot failed for instance %#q", i)
+				summary.failedInstance(i.instanceID, "Reboot failed")
+			} else if !ok {
+				log.Printf("Update failed for instance %#q", i)
+				summary.failedInstance(i.instanceID, "Update failed")
+			} else if err := u.waitForDaemons(i.containerInstanceID, workloads[i.instanceID].daemons(daemonServices)); err != nil {
+				log.Printf("Instance %#q updated, but its daemon tasks are not running: %v", i, err)
+				summary.failedInstance(i.instanceID, fmt.Sprintf("Daemon tasks not running after update: %v", err))
+			} else if u.rebootOnly {
+				log.Printf("Instance %#q rebooted successfully!", i)
+				summary.updatedInstance(i.instanceID, "Instance rebooted successfully")
+			} else {
+				log.Printf("Instance %#q updated successfully!", i)
+				summary.updatedInstance(i.instanceID, "Instance updated successfully")
+			}
+			u.timings.record(phaseVerify, verifyStart)
+			release()
+		}
+		if n == len(stages)-1 {
+			break
+		}
+		if failures := summary.failed - failedBefore; failures > u.maxWaveFailures {
+			log.Printf("Stage %s had %d failure(s), more than the %d allowed, therefore not starting the remaining stages", stage.name, failures, u.maxWaveFailures)
+			for _, remaining := range stages[n+1:] {
+				for _, i := range remaining.instances {
+					summary.skippedInstance(i.instanceID, skipStageFailed, fmt.Sprintf("Stage %s was not started because stage %s had %d failure(s)", remaining.name, stage.name, failures))
+				}
+			}
+			break
+		}
+		if stage.soak > 0 && summary.updated > updatedBefore && !u.stopping() {
+			log.Printf("Letting stage %s soak for %s before starting the next stage", stage.name, stage.soak)
+			if err := sleepContext(u.shutdownContext(), stage.soak); err != nil {
+				// The instances of the remaining stages are skipped as shutting down.
+				log.Printf("Shutting down, therefore stopped letting stage %s soak", stage.name)
+			}
 		}
 	}
-	log.Printf("After action summary:")
-	for k, v := range summary {
-		log.Printf("%s: %s", k, v)
+	summary.log()
+	if u.stopping() {
+		return errShutdown
 	}
 	log.Printf("Update operations complete!")
 	return nil
 }
 
+// newCorrelationID returns a random version 4 UUID identifying a pass or the
+// update of an instance.
+func newCorrelationID() string {
+	b := make([]byte, 16)
+	if _, err := rand.Read(b); err != nil {
+		// The ID only helps correlate artifacts, so fall back to the time.
+		return fmt.Sprintf("id-%d", time.Now().UnixNano())
+	}
+	b[6] = (b[6] & 0x0f) | 0x40
+	b[8] = (b[8] & 0x3f) | 0x80
+	return fmt.Sprintf("%x-%x-%x-%x-%x", b[0:4], b[4:6], b[6:8], b[8:10], b[10:])
+}
+
+// shortCorrelationID returns the first group of the UUID id, e.g. "6f1c9a52"
+// for "6f1c9a52-8d0e-4b8e-9a51-0c2f8e6f4b1d", which is enough to find its logs
+// and artifacts, and other IDs unchanged.
+func shortCorrelationID(id string) string {
+	if strings.IndexByte(id, '-') == 8 {
+		return id[:8]
+	}
+	return id
+}
+
 func taskDefFamily() (string, error) {
 	taskDefInput := os.Getenv(taskDefARNEnv)
 	taskDefARN, err := arn.Parse(taskDefInput)
@@ -2,6 +2,7 @@ package main
 
 import (
 	"os"
+	"regexp"
 	"testing"
 
 	"github.com/stretchr/testify/assert"
@@ -55,3 +56,9 @@ func TestTaskDefFamily(t *testing.T) {
 		})
 	}
 }
+
+func TestNewCorrelationID(t *testing.T) {
+	id := newCorrelationID()
+	assert.Regexp(t, regexp.MustCompile(`^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$`), id)
+	assert.NotEqual(t, id, newCorrelationID(), "IDs should be unique")
+}
@@ -0,0 +1,120 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ec2"
+)
+
+// maintenanceHorizon is how far ahead a scheduled reboot or maintenance of an
+// instance's host keeps the updater from rebooting the instance for an update.
+const maintenanceHorizon = 7 * 24 * time.Hour
+
+// scheduledEvent is an EC2 scheduled event of an instance, such as a
+// retirement or a reboot for host maintenance.
+type scheduledEvent struct {
+	code      string
+	notBefore time.Time
+}
+
+// String describes the event, e.g. "system-reboot on 2024-03-01T00:00:00Z".
+func (e scheduledEvent) String() string {
+	return fmt.Sprintf("%s on %s", e.code, e.notBefore.UTC().Format(time.RFC3339))
+}
+
+// scheduledEvents returns the earliest pending EC2 scheduled event of each of
+// the instances that have one.
+func (u *updater) scheduledEvents(instanceIDs []string) (map[string]scheduledEvent, error) {
+	events := make(map[string]scheduledEvent)
+	_, err := eachPage(len(instanceIDs), ecsPageSize, func(start, stop int) error {
+		input := &ec2.DescribeInstanceStatusInput{
+			InstanceIds:         aws.StringSlice(instanceIDs[start:stop]),
+			IncludeAllInstances: aws.Bool(true),
+		}
+		return u.ec2.DescribeInstanceStatusPages(input, func(output *ec2.DescribeInstanceStatusOutput, _ bool) bool {
+			for _, status := range output.InstanceStatuses {
+				for _, event := range status.Events {
+					// EC2 keeps completed and canceled events for a while, marking
+					// them in their description.
+					description := aws.StringValue(event.Description)
+					if strings.HasPrefix(description, "[Completed]") || strings.HasPrefix(description, "[Canceled]") {
+						continue
+					}
+					e := scheduledEvent{code: aws.StringValue(event.Code), notBefore: aws.TimeValue(event.NotBefore)}
+					instanceID := aws.StringValue(status.InstanceId)
+					if earliest, ok := events[instanceID]; !ok || e.notBefore.Before(earliest.notBefore) {
+						events[instanceID] = e
+					}
+				}
+			}
+			return true
+		})
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe instance status: %w", err)
+	}
+	return events, nil
+}
+
+// filterMaintenanceEvents returns the candidates that are not better left
+// alone because of an EC2 scheduled event, recording the others as skipped in
+// the summary:
+//   - instances scheduled for retirement or stop are skipped, since they are
+//     replaced or stopped anyway;
+//   - instances whose host is rebooted or maintained within maintenanceHorizon
+//     are skipped until a later pass, so that they do not reboot twice in a week.
+//
+// Instances with a scheduled instance-reboot event are kept and marked, since
+// rebooting them into the update completes the event. When the events can't
+// be determined, all candidates are returned.
+func (u *updater) filterMaintenanceEvents(candidates []instance, summary *passSummary, now time.Time) []instance {
+	instanceIDs := make([]string, 0, len(candidates))
+	for _, inst := range candidates {
+		instanceIDs = append(instanceIDs, inst.instanceID)
+	}
+	events, err := u.scheduledEvents(instanceIDs)
+	if err != nil {
+		log.Printf("Failed to check EC2 scheduled events, therefore not taking them into account: %v", err)
+		u.warnings.add(warnDegraded, "EC2 scheduled events are unknown: %v", err)
+		return candidates
+	}
+	ready := make([]instance, 0, len(candidates))
+	for _, inst := range candidates {
+		event, ok := events[inst.instanceID]
+		if !ok {
+			ready = append(ready, inst)
+			continue
+		}
+		switch event.code {
+		case ec2.EventCodeInstanceRetirement, ec2.EventCodeInstanceStop:
+			log.Printf("Instance %#q has scheduled event %s, therefore not updating it", inst, event)
+			summary.skippedInstance(inst.instanceID, skipScheduledEvent, fmt.Sprintf("Scheduled %s", event))
+			continue
+		case ec2.EventCodeSystemReboot, ec2.EventCodeSystemMaintenance:
+			if event.notBefore.Sub(now) < maintenanceHorizon {
+				log.Printf("Instance %#q has scheduled event %s, therefore not updating it before then to avoid rebooting it twice", inst, event)
+				summary.skippedInstance(inst.instanceID, skipScheduledEvent, fmt.Sprintf("Scheduled %s; updating after it", event))
+				continue
+			}
+		case ec2.EventCodeInstanceReboot:
+			log.Printf("Instance %#q has scheduled event %s; rebooting it into the update completes the event", inst, event)
+			inst.scheduledReboot = true
+		}
+		ready = append(ready, inst)
+	}
+	return ready
+}
+
+// scheduledRebootsFirst moves the instances with a scheduled instance-reboot
+// event to the front, keeping the order of the instances otherwise, so that
+// their update reboots them before EC2 does.
+func scheduledRebootsFirst(instances []instance) {
+	sort.SliceStable(instances, func(i, j int) bool {
+		return instances[i].scheduledReboot && !instances[j].scheduledReboot
+	})
+}
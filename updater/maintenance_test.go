@@ -0,0 +1,101 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ec2"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestFilterMaintenanceEvents(t *testing.T) {
+	now := time.Date(2024, 3, 1, 0, 0, 0, 0, time.UTC)
+	event := func(code string, in time.Duration, description string) *ec2.InstanceStatusEvent {
+		return &ec2.InstanceStatusEvent{
+			Code:        aws.String(code),
+			NotBefore:   aws.Time(now.Add(in)),
+			Description: aws.String(description),
+		}
+	}
+	statuses := []*ec2.InstanceStatus{
+		{InstanceId: aws.String("inst-retiring"), Events: []*ec2.InstanceStatusEvent{event(ec2.EventCodeInstanceRetirement, 14*24*time.Hour, "The instance is running on degraded hardware")}},
+		{InstanceId: aws.String("inst-host-reboot"), Events: []*ec2.InstanceStatusEvent{event(ec2.EventCodeSystemReboot, 2*24*time.Hour, "Scheduled reboot")}},
+		{InstanceId: aws.String("inst-later-maintenance"), Events: []*ec2.InstanceStatusEvent{event(ec2.EventCodeSystemMaintenance, 10*24*time.Hour, "Scheduled maintenance")}},
+		{InstanceId: aws.String("inst-completed"), Events: []*ec2.InstanceStatusEvent{event(ec2.EventCodeSystemReboot, -time.Hour, "[Completed] Scheduled reboot")}},
+		{InstanceId: aws.String("inst-reboot"), Events: []*ec2.InstanceStatusEvent{event(ec2.EventCodeInstanceReboot, 24*time.Hour, "Scheduled reboot")}},
+		{InstanceId: aws.String("inst-none")},
+	}
+	candidates := []instance{
+		{instanceID: "inst-retiring"},
+		{instanceID: "inst-host-reboot"},
+		{instanceID: "inst-later-maintenance"},
+		{instanceID: "inst-completed"},
+		{instanceID: "inst-reboot"},
+		{instanceID: "inst-none"},
+	}
+
+	t.Run("events", func(t *testing.T) {
+		mockEC2 := MockEC2{
+			DescribeInstanceStatusPagesFn: func(input *ec2.DescribeInstanceStatusInput, fn func(*ec2.DescribeInstanceStatusOutput, bool) bool) error {
+				assert.Len(t, input.InstanceIds, len(candidates))
+				fn(&ec2.DescribeInstanceStatusOutput{InstanceStatuses: statuses}, true)
+				return nil
+			},
+		}
+		u := updater{ec2: mockEC2}
+		summary := newPassSummary()
+		ready := u.filterMaintenanceEvents(candidates, summary, now)
+		assert.Equal(t, []instance{
+			{instanceID: "inst-later-maintenance"},
+			{instanceID: "inst-completed"},
+			{instanceID: "inst-reboot", scheduledReboot: true},
+			{instanceID: "inst-none"},
+		}, ready)
+		assert.Equal(t, map[string]string{
+			"inst-retiring":    "Scheduled instance-retirement on 2024-03-15T00:00:00Z",
+			"inst-host-reboot": "Scheduled system-reboot on 2024-03-03T00:00:00Z; updating after it",
+		}, summary.results)
+
+		scheduledRebootsFirst(ready)
+		assert.Equal(t, "inst-reboot", ready[0].instanceID)
+		assert.Equal(t, "inst-later-maintenance", ready[1].instanceID, "should keep the order of other instances")
+	})
+	t.Run("describe fails", func(t *testing.T) {
+		mockEC2 := MockEC2{
+			DescribeInstanceStatusPagesFn: func(_ *ec2.DescribeInstanceStatusInput, _ func(*ec2.DescribeInstanceStatusOutput, bool) bool) error {
+				return errors.New("failed to describe")
+			},
+		}
+		u := updater{ec2: mockEC2, warnings: newPassWarnings()}
+		summary := newPassSummary()
+		ready := u.filterMaintenanceEvents(candidates, summary, now)
+		assert.Equal(t, candidates, ready)
+		assert.Empty(t, summary.results)
+		assert.Equal(t, 1, u.warnings.counts[warnDegraded])
+	})
+}
+
+func TestScheduledEventsEarliest(t *testing.T) {
+	now := time.Date(2024, 3, 1, 0, 0, 0, 0, time.UTC)
+	mockEC2 := MockEC2{
+		DescribeInstanceStatusPagesFn: func(_ *ec2.DescribeInstanceStatusInput, fn func(*ec2.DescribeInstanceStatusOutput, bool) bool) error {
+			fn(&ec2.DescribeInstanceStatusOutput{InstanceStatuses: []*ec2.InstanceStatus{{
+				InstanceId: aws.String("inst-id-1"),
+				Events: []*ec2.InstanceStatusEvent{
+					{Code: aws.String(ec2.EventCodeSystemMaintenance), NotBefore: aws.Time(now.Add(48 * time.Hour))},
+					{Code: aws.String(ec2.EventCodeInstanceRetirement), NotBefore: aws.Time(now.Add(24 * time.Hour))},
+				},
+			}}}, true)
+			return nil
+		},
+	}
+	u := updater{ec2: mockEC2}
+	events, err := u.scheduledEvents([]string{"inst-id-1"})
+	require.NoError(t, err)
+	assert.Equal(t, map[string]scheduledEvent{
+		"inst-id-1": {code: ec2.EventCodeInstanceRetirement, notBefore: now.Add(24 * time.Hour)},
+	}, events)
+}
@@ -0,0 +1,309 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"io"
+	"os"
+	"time"
+)
+
+// metricsNamespace is the CloudWatch namespace of the metrics the updater emits.
+const metricsNamespace = "Bottlerocket/ECSUpdater"
+
+// metricsOutput receives metric events. The updater's log driver forwards them
+// to CloudWatch Logs, which extracts the metrics from the events.
+var metricsOutput io.Writer = os.Stdout
+
+// emfMetadata is the metadata of a CloudWatch embedded metric format event.
+type emfMetadata struct {
+	Timestamp         int64
+	CloudWatchMetrics []emfDirective
+}
+
+type emfDirective struct {
+	Namespace  string
+	Dimensions [][]string
+	Metrics    []emfMetric
+}
+
+type emfMetric struct {
+	Name string
+	Unit string
+}
+
+// writeVersionMetrics writes one embedded metric format event per Bottlerocket
+// version seen during the pass, with the number of instances running it before
+// and after the pass. A version whose count drops without instances being
+// updated away from it indicates instances were lost or rolled back.
+func writeVersionMetrics(w io.Writer, cluster string, summary *passSummary, now time.Time) error {
+	before := histogram(summary.versionsBefore)
+	after := histogram(summary.versionsAfter)
+	versions := make(versionHistogram)
+	for version := range before {
+		versions[version]++
+	}
+	for version := range after {
+		versions[version]++
+	}
+	metadata := emfMetadata{
+		Timestamp: now.UnixNano() / int64(time.Millisecond),
+		CloudWatchMetrics: []emfDirective{{
+			Namespace:  metricsNamespace,
+			Dimensions: [][]string{{"Cluster", "Version"}},
+			Metrics: []emfMetric{
+				{Name: "InstancesBeforePass", Unit: "Count"},
+				{Name: "InstancesAfterPass", Unit: "Count"},
+			},
+		}},
+	}
+	for _, version := range versions.versions() {
+		event, err := json.Marshal(map[string]interface{}{
+			"_aws":                metadata,
+			"Cluster":             cluster,
+			"Version":             version,
+			"InstancesBeforePass": before[version],
+			"InstancesAfterPass":  after[version],
+		})
+		if err != nil {
+			return fmt.Errorf("failed to encode metrics for version %q: %w", version, err)
+		}
+		if _, err := fmt.Fprintln(w, string(event)); err != nil {
+			return fmt.Errorf("failed to write metrics for version %q: %w", version, err)
+		}
+	}
+	return nil
+}
+
+// writeInstanceMetrics writes an embedded metric format event with the number
+// of container instances found running Bottlerocket and the number of other
+// container instances. A cluster with other instances but no Bottlerocket
+// ones usually means the bottlerocket.variant attribute is not detected.
+func writeInstanceMetrics(w io.Writer, cluster string, bottlerocket int, others int, now time.Time) error {
+	event, err := json.Marshal(map[string]interface{}{
+		"_aws": emfMetadata{
+			Timestamp: now.UnixNano() / int64(time.Millisecond),
+			CloudWatchMetrics: []emfDirective{{
+				Namespace:  metricsNamespace,
+				Dimensions: [][]string{{"Cluster"}},
+				Metrics: []emfMetric{
+					{Name: "BottlerocketInstances", Unit: "Count"},
+					{Name: "OtherInstances", Unit: "Count"},
+				},
+			}},
+		},
+		"Cluster":               cluster,
+		"BottlerocketInstances": bottlerocket,
+		"OtherInstances":        others,
+	})
+	if err != nil {
+		return fmt.Errorf("failed to encode instance metrics: %w", err)
+	}
+	if _, err := fmt.Fprintln(w, string(event)); err != nil {
+		return fmt.Errorf("failed to write instance metrics: %w", err)
+	}
+	return nil
+}
+
+// writeWarningMetrics writes one embedded metric format event per warning
+// category with the number of warnings of the category during the pass, and
+// one per error class with the number of AWS calls that failed with it.
+// Every category and class is written, so alarms on warnings see zero rather
+// than missing data in a clean pass.
+func writeWarningMetrics(w io.Writer, cluster string, warnings *passWarnings, now time.Time) error {
+	metadata := emfMetadata{
+		Timestamp: now.UnixNano() / int64(time.Millisecond),
+		CloudWatchMetrics: []emfDirective{{
+			Namespace:  metricsNamespace,
+			Dimensions: [][]string{{"Cluster"}, {"Cluster", "Category"}},
+			Metrics: []emfMetric{
+				{Name: "Warnings", Unit: "Count"},
+			},
+		}},
+	}
+	for _, category := range warningCategories {
+		event, err := json.Marshal(map[string]interface{}{
+			"_aws":     metadata,
+			"Cluster":  cluster,
+			"Category": category,
+			"Warnings": warnings.count(category),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to encode metrics for warning category %q: %w", category, err)
+		}
+		if _, err := fmt.Fprintln(w, string(event)); err != nil {
+			return fmt.Errorf("failed to write metrics for warning category %q: %w", category, err)
+		}
+	}
+	errorMetadata := emfMetadata{
+		Timestamp: metadata.Timestamp,
+		CloudWatchMetrics: []emfDirective{{
+			Namespace:  metricsNamespace,
+			Dimensions: [][]string{{"Cluster"}, {"Cluster", "ErrorClass"}},
+			Metrics: []emfMetric{
+				{Name: "AWSErrors", Unit: "Count"},
+			},
+		}},
+	}
+	for _, class := range errorClasses {
+		event, err := json.Marshal(map[string]interface{}{
+			"_aws":       errorMetadata,
+			"Cluster":    cluster,
+			"ErrorClass": class,
+			"AWSErrors":  warnings.awsErrorCount(class),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to encode metrics for error class %q: %w", class, err)
+		}
+		if _, err := fmt.Fprintln(w, string(event)); err != nil {
+			return fmt.Errorf("failed to write metrics for error class %q: %w", class, err)
+		}
+	}
+	return nil
+}
+
+// writeTimingMetrics writes one embedded metric format event per phase that
+// ran during the pass with how long the phase took, so a phase that slows
+// down, e.g. after a change that polls instances one at a time, can be
+// graphed and alarmed on.
+func writeTimingMetrics(w io.Writer, cluster string, timings *passTimings, now time.Time) error {
+	metadata := emfMetadata{
+		Timestamp: now.UnixNano() / int64(time.Millisecond),
+		CloudWatchMetrics: []emfDirective{{
+			Namespace:  metricsNamespace,
+			Dimensions: [][]string{{"Cluster", "Phase"}},
+			Metrics: []emfMetric{
+				{Name: "PhaseDuration", Unit: "Seconds"},
+			},
+		}},
+	}
+	durations := timings.recorded()
+	for _, phase := range phases {
+		d, ok := durations[phase]
+		if !ok {
+			continue
+		}
+		event, err := json.Marshal(map[string]interface{}{
+			"_aws":          metadata,
+			"Cluster":       cluster,
+			"Phase":         phase,
+			"PhaseDuration": d.Seconds(),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to encode metrics for phase %q: %w", phase, err)
+		}
+		if _, err := fmt.Fprintln(w, string(event)); err != nil {
+			return fmt.Errorf("failed to write metrics for phase %q: %w", phase, err)
+		}
+	}
+	return nil
+}
+
+// writeFailureMetrics writes one embedded metric format event per instance
+// whose update failed during the pass, counting one FailedUpdates each, or a
+// single event counting none after a pass without failures. Each event carries
+// the pass ID, change reference, instance ID, reason and the SSM commands sent
+// to the instance, and the ID of the instance's update when it started, as
+// properties. Embedded metric format
+// events are log events, so a spike in the metric can be traced to the
+// failures behind it, and from there to the pass's logs, by querying the log
+// group for the events.
+func writeFailureMetrics(w io.Writer, cluster string, summary *passSummary, now time.Time) error {
+	metadata := emfMetadata{
+		Timestamp: now.UnixNano() / int64(time.Millisecond),
+		CloudWatchMetrics: []emfDirective{{
+			Namespace:  metricsNamespace,
+			Dimensions: [][]string{{"Cluster"}},
+			Metrics: []emfMetric{
+				{Name: "FailedUpdates", Unit: "Count"},
+			},
+		}},
+	}
+	events := make([]map[string]interface{}, 0, len(summary.failures))
+	for _, instanceID := range summary.failures {
+		commands := make([]string, 0)
+		for _, command := range summary.commands.forInstance(instanceID) {
+			commands = append(commands, command.purpose+":"+command.id)
+		}
+		event := map[string]interface{}{
+			"_aws":          metadata,
+			"Cluster":       cluster,
+			"FailedUpdates": 1,
+			"PassID":        summary.passID,
+			"InstanceID":    instanceID,
+			"Reason":        summary.results[instanceID],
+			"Commands":      commands,
+		}
+		// The IDs are properties rather than dimensions, which keeps the
+		// number of metrics bounded.
+		if updateID := summary.updateIDs[instanceID]; updateID != "" {
+			event["UpdateID"] = updateID
+		}
+		events = append(events, event)
+	}
+	if len(events) == 0 {
+		events = append(events, map[string]interface{}{
+			"_aws":          metadata,
+			"Cluster":       cluster,
+			"FailedUpdates": 0,
+			"PassID":        summary.passID,
+		})
+	}
+	if summary.changeRef != "" {
+		for _, event := range events {
+			event["ChangeRef"] = summary.changeRef
+		}
+	}
+	for _, event := range events {
+		line, err := json.Marshal(event)
+		if err != nil {
+			return fmt.Errorf("failed to encode failure metrics: %w", err)
+		}
+		if _, err := fmt.Fprintln(w, string(line)); err != nil {
+			return fmt.Errorf("failed to write failure metrics: %w", err)
+		}
+	}
+	return nil
+}
+
+// writeSegmentMetrics writes one embedded metric format event per segment of
+// -segment-tags with the number of instances of the segment and how many of
+// them were updated, failed or skipped during the pass. The segment tags are
+// dimensions next to Cluster, so a central updater's metrics can be graphed
+// and alarmed on per team or environment.
+func writeSegmentMetrics(w io.Writer, cluster string, summary *passSummary, now time.Time) error {
+	metadata := emfMetadata{
+		Timestamp: now.UnixNano() / int64(time.Millisecond),
+		CloudWatchMetrics: []emfDirective{{
+			Namespace:  metricsNamespace,
+			Dimensions: [][]string{append([]string{"Cluster"}, summary.segmentTags...)},
+			Metrics: []emfMetric{
+				{Name: "SegmentInstances", Unit: "Count"},
+				{Name: "SegmentUpdated", Unit: "Count"},
+				{Name: "SegmentFailed", Unit: "Count"},
+				{Name: "SegmentSkipped", Unit: "Count"},
+			},
+		}},
+	}
+	for _, count := range summary.segmentCounts() {
+		event := map[string]interface{}{
+			"_aws":             metadata,
+			"Cluster":          cluster,
+			"SegmentInstances": count.Instances,
+			"SegmentUpdated":   count.Updated,
+			"SegmentFailed":    count.Failed,
+			"SegmentSkipped":   count.Skipped,
+		}
+		for key, value := range count.Segment {
+			event[key] = value
+		}
+		line, err := json.Marshal(event)
+		if err != nil {
+			return fmt.Errorf("failed to encode metrics for segment %q: %w", count.name(summary.segmentTags), err)
+		}
+		if _, err := fmt.Fprintln(w, string(line)); err != nil {
+			return fmt.Errorf("failed to write metrics for segment %q: %w", count.name(summary.segmentTags), err)
+		}
+	}
+	return nil
+}
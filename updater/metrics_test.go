@@ -0,0 +1,211 @@
+package main
+
+import (
+	"bytes"
+	"encoding/json"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestWriteVersionMetrics(t *testing.T) {
+	summary := newPassSummary()
+	summary.recordVersions([]instance{
+		{instanceID: "inst-id-1", bottlerocketVersion: "1.11.0"},
+		{instanceID: "inst-id-2", bottlerocketVersion: "1.12.0"},
+	})
+	summary.updatedVersion("inst-id-1", "1.12.0")
+
+	var out bytes.Buffer
+	now := time.Unix(1700000000, 0)
+	require.NoError(t, writeVersionMetrics(&out, "test-cluster", summary, now))
+
+	lines := strings.Split(strings.TrimSpace(out.String()), "\n")
+	require.Len(t, lines, 2)
+	expected := []struct {
+		version string
+		before  float64
+		after   float64
+	}{
+		{version: "1.11.0", before: 1, after: 0},
+		{version: "1.12.0", before: 1, after: 2},
+	}
+	for i, line := range lines {
+		var event map[string]interface{}
+		require.NoError(t, json.Unmarshal([]byte(line), &event))
+		assert.Equal(t, "test-cluster", event["Cluster"])
+		assert.Equal(t, expected[i].version, event["Version"])
+		assert.Equal(t, expected[i].before, event["InstancesBeforePass"])
+		assert.Equal(t, expected[i].after, event["InstancesAfterPass"])
+
+		metadata := event["_aws"].(map[string]interface{})
+		assert.Equal(t, float64(1700000000000), metadata["Timestamp"])
+		directives := metadata["CloudWatchMetrics"].([]interface{})
+		require.Len(t, directives, 1)
+		assert.Equal(t, metricsNamespace, directives[0].(map[string]interface{})["Namespace"])
+	}
+}
+
+func TestWriteInstanceMetrics(t *testing.T) {
+	var out bytes.Buffer
+	require.NoError(t, writeInstanceMetrics(&out, "test-cluster", 0, 3, time.Unix(1700000000, 0)))
+
+	var event map[string]interface{}
+	require.NoError(t, json.Unmarshal(out.Bytes(), &event))
+	assert.Equal(t, "test-cluster", event["Cluster"])
+	assert.Equal(t, float64(0), event["BottlerocketInstances"])
+	assert.Equal(t, float64(3), event["OtherInstances"])
+	metadata := event["_aws"].(map[string]interface{})
+	assert.Equal(t, float64(1700000000000), metadata["Timestamp"])
+}
+
+func TestWriteWarningMetrics(t *testing.T) {
+	warnings := newPassWarnings()
+	warnings.add(warnThrottled, "ListTasks was throttled")
+	warnings.add(warnThrottled, "ListTasks was throttled")
+	warnings.addAWSError(errClassAccessDenied, "ssm:GetParameter")
+
+	var out bytes.Buffer
+	require.NoError(t, writeWarningMetrics(&out, "test-cluster", warnings, time.Unix(1700000000, 0)))
+
+	lines := strings.Split(strings.TrimSpace(out.String()), "\n")
+	require.Len(t, lines, len(warningCategories)+len(errorClasses))
+	counts := make(map[string]float64)
+	errorCounts := make(map[string]float64)
+	for _, line := range lines {
+		var event map[string]interface{}
+		require.NoError(t, json.Unmarshal([]byte(line), &event))
+		assert.Equal(t, "test-cluster", event["Cluster"])
+		if class, ok := event["ErrorClass"].(string); ok {
+			errorCounts[class] = event["AWSErrors"].(float64)
+		} else {
+			counts[event["Category"].(string)] = event["Warnings"].(float64)
+		}
+	}
+	expected := make(map[string]float64)
+	for _, category := range warningCategories {
+		expected[category] = 0
+	}
+	expected[warnThrottled] = 2
+	assert.Equal(t, expected, counts)
+	assert.Equal(t, map[string]float64{
+		errClassThrottling:   0,
+		errClassAccessDenied: 1,
+		errClassNotFound:     0,
+		errClassTimeout:      0,
+		errClassOther:        0,
+	}, errorCounts)
+}
+
+func TestWriteTimingMetrics(t *testing.T) {
+	timings := newPassTimings()
+	timings.add(phaseCheck, 90*time.Second)
+	timings.add(phaseDiscover, 2*time.Second)
+
+	var out bytes.Buffer
+	require.NoError(t, writeTimingMetrics(&out, "test-cluster", timings, time.Unix(1700000000, 0)))
+
+	lines := strings.Split(strings.TrimSpace(out.String()), "\n")
+	require.Len(t, lines, 2, "should only write phases that ran")
+	expected := []struct {
+		phase   string
+		seconds float64
+	}{
+		{phase: phaseDiscover, seconds: 2},
+		{phase: phaseCheck, seconds: 90},
+	}
+	for i, line := range lines {
+		var event map[string]interface{}
+		require.NoError(t, json.Unmarshal([]byte(line), &event))
+		assert.Equal(t, "test-cluster", event["Cluster"])
+		assert.Equal(t, expected[i].phase, event["Phase"])
+		assert.Equal(t, expected[i].seconds, event["PhaseDuration"])
+	}
+}
+
+func TestWriteFailureMetrics(t *testing.T) {
+	now := time.Unix(1700000000, 0)
+	decode := func(out string) []map[string]interface{} {
+		events := make([]map[string]interface{}, 0)
+		for _, line := range strings.Split(strings.TrimSpace(out), "\n") {
+			var event map[string]interface{}
+			require.NoError(t, json.Unmarshal([]byte(line), &event))
+			events = append(events, event)
+		}
+		return events
+	}
+
+	t.Run("failures", func(t *testing.T) {
+		summary := newPassSummary()
+		summary.passID = "pass-id"
+		summary.changeRef = "CHG-1234"
+		summary.updatedInstance("inst-id-1", "Instance updated successfully")
+		summary.failedInstance("inst-id-2", "Failed to drain: timed out")
+		summary.updateIDs["inst-id-3"] = "update-id"
+		summary.failedInstance("inst-id-3", "Update failed")
+		summary.commands = &commandLog{}
+		summary.commands.record("check-id", "check", []string{"inst-id-1", "inst-id-2", "inst-id-3"})
+		summary.commands.record("apply-id", "apply", []string{"inst-id-3"})
+
+		var out bytes.Buffer
+		require.NoError(t, writeFailureMetrics(&out, "test-cluster", summary, now))
+		events := decode(out.String())
+		require.Len(t, events, 2)
+		assert.Equal(t, "inst-id-2", events[0]["InstanceID"])
+		assert.Equal(t, "Failed to drain: timed out", events[0]["Reason"])
+		assert.Equal(t, []interface{}{"check:check-id"}, events[0]["Commands"])
+		assert.NotContains(t, events[0], "UpdateID", "the update of inst-id-2 never started")
+		assert.Equal(t, "inst-id-3", events[1]["InstanceID"])
+		assert.Equal(t, "update-id", events[1]["UpdateID"])
+		assert.Equal(t, []interface{}{"check:check-id", "apply:apply-id"}, events[1]["Commands"])
+		for _, event := range events {
+			assert.Equal(t, float64(1), event["FailedUpdates"])
+			assert.Equal(t, "pass-id", event["PassID"])
+			assert.Equal(t, "CHG-1234", event["ChangeRef"])
+			assert.Equal(t, "test-cluster", event["Cluster"])
+		}
+	})
+	t.Run("no failures", func(t *testing.T) {
+		var out bytes.Buffer
+		require.NoError(t, writeFailureMetrics(&out, "test-cluster", newPassSummary(), now))
+		events := decode(out.String())
+		require.Len(t, events, 1)
+		assert.Equal(t, float64(0), events[0]["FailedUpdates"])
+		assert.NotContains(t, events[0], "InstanceID")
+		assert.NotContains(t, events[0], "ChangeRef")
+	})
+}
+
+func TestWriteSegmentMetrics(t *testing.T) {
+	summary := newPassSummary()
+	summary.segmentBy([]string{"team"}, nil)
+	instances := []instance{
+		{instanceID: "inst-id-1", bottlerocketVersion: "1.19.0", tags: map[string]string{"team": "payments"}},
+		{instanceID: "inst-id-2", bottlerocketVersion: "1.19.0", tags: map[string]string{"team": "search"}},
+	}
+	summary.recordVersions(instances)
+	summary.recordSegments(instances)
+	summary.failedInstance("inst-id-2", "Update failed")
+
+	var out bytes.Buffer
+	require.NoError(t, writeSegmentMetrics(&out, "test-cluster", summary, time.Unix(1700000000, 0)))
+
+	lines := strings.Split(strings.TrimSpace(out.String()), "\n")
+	require.Len(t, lines, 2)
+	var event map[string]interface{}
+	require.NoError(t, json.Unmarshal([]byte(lines[1]), &event))
+	assert.Equal(t, "test-cluster", event["Cluster"])
+	assert.Equal(t, "search", event["team"])
+	assert.Equal(t, float64(1), event["SegmentInstances"])
+	assert.Equal(t, float64(1), event["SegmentFailed"])
+	assert.Equal(t, float64(0), event["SegmentUpdated"])
+	directive := event["_aws"].(map[string]interface{})["CloudWatchMetrics"].([]interface{})[0].(map[string]interface{})
+	assert.Equal(t, []interface{}{[]interface{}{"Cluster", "team"}}, directive["Dimensions"])
+
+	out.Reset()
+	require.NoError(t, writeSegmentMetrics(&out, "test-cluster", newPassSummary(), time.Unix(1700000000, 0)))
+	assert.Empty(t, out.String())
+}
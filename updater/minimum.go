@@ -0,0 +1,51 @@
+package main
+
+import (
+	"log"
+	"regexp"
+)
+
+// minimumVersionPattern matches the versions accepted by -minimum-version,
+// e.g. 1.19.2 or v1.19.2.
+var minimumVersionPattern = regexp.MustCompile(`^v?[0-9]+\.[0-9]+\.[0-9]+$`)
+
+// belowMinimum reports whether the instance runs a Bottlerocket version older
+// than -minimum-version. Such instances are updated regardless of maintenance
+// windows and deferral tags, so that versions with known vulnerabilities can
+// be purged within a deadline. Instances whose version is unknown are not
+// below the minimum.
+func (u *updater) belowMinimum(inst instance) bool {
+	if u.minimumVersion == "" || inst.bottlerocketVersion == "" || inst.bottlerocketVersion == unknownVersion {
+		return false
+	}
+	return compareVersions(inst.bottlerocketVersion, u.minimumVersion) < 0
+}
+
+// keepBelowMinimum skips the candidates with the reason and message while a
+// window is closed, except those below the minimum version, which it returns
+// to be updated regardless.
+func (u *updater) keepBelowMinimum(candidates []instance, summary *passSummary, reason string, message string) []instance {
+	mandatory := make([]instance, 0)
+	for _, i := range candidates {
+		if u.belowMinimum(i) {
+			mandatory = append(mandatory, i)
+			continue
+		}
+		summary.skippedInstance(i.instanceID, reason, message)
+	}
+	return mandatory
+}
+
+// warnBelowMinimum counts a warning for each Bottlerocket instance running a
+// version older than -minimum-version, whether or not an update is available
+// to it, so that alarms on the warning metrics notify the fleet's owners.
+func (u *updater) warnBelowMinimum(instances []instance) {
+	for _, inst := range instances {
+		if !u.belowMinimum(inst) {
+			continue
+		}
+		log.Printf("Instance %#q runs Bottlerocket %s, older than the minimum version %s", inst, inst.bottlerocketVersion, u.minimumVersion)
+		u.warnings.add(warnBelowMinimum, "instance %q runs Bottlerocket %s, older than the minimum version %s",
+			inst.instanceID, inst.bottlerocketVersion, u.minimumVersion)
+	}
+}
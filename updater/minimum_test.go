@@ -0,0 +1,55 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+)
+
+func TestBelowMinimum(t *testing.T) {
+	u := updater{minimumVersion: "1.19.2"}
+	cases := map[string]bool{
+		"1.18.0":       true,
+		"1.19.1":       true,
+		"1.19.2":       false,
+		"1.20.0":       false,
+		"v1.19.0":      true,
+		"":             false,
+		unknownVersion: false,
+	}
+	for version, expected := range cases {
+		assert.Equal(t, expected, u.belowMinimum(instance{bottlerocketVersion: version}), version)
+	}
+	assert.False(t, (&updater{}).belowMinimum(instance{bottlerocketVersion: "1.0.0"}), "there is no minimum without -minimum-version")
+
+	for _, version := range []string{"1.19.2", "v1.19.2"} {
+		assert.True(t, minimumVersionPattern.MatchString(version), version)
+	}
+	for _, version := range []string{"1.19", "latest", "1.19.2-rc1"} {
+		assert.False(t, minimumVersionPattern.MatchString(version), version)
+	}
+}
+
+func TestWarnBelowMinimum(t *testing.T) {
+	u := updater{minimumVersion: "1.19.2", warnings: newPassWarnings()}
+	u.warnBelowMinimum([]instance{
+		{instanceID: "inst-id-1", bottlerocketVersion: "1.18.0"},
+		{instanceID: "inst-id-2", bottlerocketVersion: "1.19.2"},
+		{instanceID: "inst-id-3", bottlerocketVersion: unknownVersion},
+	})
+	assert.Equal(t, 1, u.warnings.count(warnBelowMinimum))
+}
+
+func TestKeepBelowMinimum(t *testing.T) {
+	u := updater{minimumVersion: "1.19.2"}
+	candidates := []instance{
+		{instanceID: "inst-old", bottlerocketVersion: "1.18.0"},
+		{instanceID: "inst-current", bottlerocketVersion: "1.20.0"},
+	}
+	summary := newPassSummary()
+	mandatory := u.keepBelowMinimum(candidates, summary, skipOutsideWindow, "Update window opens at 2024-03-02T02:00:00Z")
+	assert.Equal(t, candidates[:1], mandatory)
+	assert.Equal(t, outcomeSkipped, summary.outcomes["inst-current"])
+	assert.Equal(t, skipOutsideWindow, summary.skipReasons["inst-current"])
+	assert.Empty(t, summary.outcomes["inst-old"])
+}
@@ -3,9 +3,14 @@ package main
 import (
 	"github.com/aws/aws-sdk-go/aws"
 	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+	"github.com/aws/aws-sdk-go/service/cloudwatchlogs"
 	"github.com/aws/aws-sdk-go/service/ec2"
 	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/elbv2"
+	"github.com/aws/aws-sdk-go/service/secretsmanager"
 	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/aws/aws-sdk-go/service/sts"
 )
 
 type MockECS struct {
@@ -14,27 +19,79 @@ type MockECS struct {
 	UpdateContainerInstancesStateFn    func(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error)
 	ListTasksFn                        func(input *ecs.ListTasksInput) (*ecs.ListTasksOutput, error)
 	DescribeTasksFn                    func(input *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error)
+	DescribeServicesFn                 func(input *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error)
 	WaitUntilTasksStoppedWithContextFn func(ctx aws.Context, input *ecs.DescribeTasksInput, opts ...request.WaiterOption) error
+	WaitUntilTasksRunningWithContextFn func(ctx aws.Context, input *ecs.DescribeTasksInput, opts ...request.WaiterOption) error
+	DescribeClustersFn                 func(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error)
+	DescribeCapacityProvidersFn        func(input *ecs.DescribeCapacityProvidersInput) (*ecs.DescribeCapacityProvidersOutput, error)
+	TagResourceFn                      func(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error)
+	UntagResourceFn                    func(input *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error)
+	StopTaskFn                         func(input *ecs.StopTaskInput) (*ecs.StopTaskOutput, error)
+	StartTaskFn                        func(input *ecs.StartTaskInput) (*ecs.StartTaskOutput, error)
 }
 
 var _ ECSAPI = (*MockECS)(nil)
 
 type MockSSM struct {
-	// WaitUntilCommandExecutedWithContextFn is executed concurrently through
-	// ECS code paths and tests should treat any data in a parallel safe manner
-	WaitUntilCommandExecutedWithContextFn func(ctx aws.Context, input *ssm.GetCommandInvocationInput, opts ...request.WaiterOption) error
 	SendCommandFn                         func(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error)
 	GetCommandInvocationFn                func(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error)
+	ListCommandInvocationsPagesFn         func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error
+	GetParameterFn                        func(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error)
+	ListCommandsPagesFn                   func(input *ssm.ListCommandsInput, fn func(*ssm.ListCommandsOutput, bool) bool) error
+	PutParameterFn                        func(input *ssm.PutParameterInput) (*ssm.PutParameterOutput, error)
+	DeleteParameterFn                     func(input *ssm.DeleteParameterInput) (*ssm.DeleteParameterOutput, error)
+	GetMaintenanceWindowFn                func(input *ssm.GetMaintenanceWindowInput) (*ssm.GetMaintenanceWindowOutput, error)
+	DescribeMaintenanceWindowExecutionsFn func(input *ssm.DescribeMaintenanceWindowExecutionsInput) (*ssm.DescribeMaintenanceWindowExecutionsOutput, error)
+	DescribeAutomationExecutionsFn        func(input *ssm.DescribeAutomationExecutionsInput) (*ssm.DescribeAutomationExecutionsOutput, error)
+	AddTagsToResourceFn                   func(input *ssm.AddTagsToResourceInput) (*ssm.AddTagsToResourceOutput, error)
 }
 
 var _ SSMAPI = (*MockSSM)(nil)
 
 type MockEC2 struct {
-	WaitUntilInstanceStatusOkFn func(input *ec2.DescribeInstanceStatusInput) error
+	WaitUntilInstanceStatusOkWithContextFn func(ctx aws.Context, input *ec2.DescribeInstanceStatusInput, opts ...request.WaiterOption) error
+	DescribeInstancesPagesFn               func(input *ec2.DescribeInstancesInput, fn func(*ec2.DescribeInstancesOutput, bool) bool) error
+	DescribeInstanceStatusPagesFn          func(input *ec2.DescribeInstanceStatusInput, fn func(*ec2.DescribeInstanceStatusOutput, bool) bool) error
 }
 
 var _ EC2API = (*MockEC2)(nil)
 
+type MockAutoScaling struct {
+	DescribeAutoScalingGroupsFn    func(input *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error)
+	DescribeAutoScalingInstancesFn func(input *autoscaling.DescribeAutoScalingInstancesInput) (*autoscaling.DescribeAutoScalingInstancesOutput, error)
+	SuspendProcessesFn             func(input *autoscaling.ScalingProcessQuery) (*autoscaling.SuspendProcessesOutput, error)
+	ResumeProcessesFn              func(input *autoscaling.ScalingProcessQuery) (*autoscaling.ResumeProcessesOutput, error)
+	CreateOrUpdateTagsFn           func(input *autoscaling.CreateOrUpdateTagsInput) (*autoscaling.CreateOrUpdateTagsOutput, error)
+	DeleteTagsFn                   func(input *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error)
+	SetDesiredCapacityFn           func(input *autoscaling.SetDesiredCapacityInput) (*autoscaling.SetDesiredCapacityOutput, error)
+}
+
+var _ AutoScalingAPI = (*MockAutoScaling)(nil)
+
+type MockELBV2 struct {
+	DescribeTargetHealthFn func(input *elbv2.DescribeTargetHealthInput) (*elbv2.DescribeTargetHealthOutput, error)
+}
+
+var _ ELBV2API = (*MockELBV2)(nil)
+
+type MockCloudWatchLogs struct {
+	FilterLogEventsPagesFn func(input *cloudwatchlogs.FilterLogEventsInput, fn func(*cloudwatchlogs.FilterLogEventsOutput, bool) bool) error
+}
+
+var _ CloudWatchLogsAPI = (*MockCloudWatchLogs)(nil)
+
+type MockSTS struct {
+	GetCallerIdentityFn func(input *sts.GetCallerIdentityInput) (*sts.GetCallerIdentityOutput, error)
+}
+
+var _ STSAPI = (*MockSTS)(nil)
+
+type MockSecretsManager struct {
+	GetSecretValueFn func(input *secretsmanager.GetSecretValueInput) (*secretsmanager.GetSecretValueOutput, error)
+}
+
+var _ SecretsManagerAPI = (*MockSecretsManager)(nil)
+
 func (m MockECS) ListContainerInstancesPages(input *ecs.ListContainerInstancesInput, fn func(*ecs.ListContainerInstancesOutput, bool) bool) error {
 	return m.ListContainerInstancesPagesFn(input, fn)
 }
@@ -55,22 +112,152 @@ func (m MockECS) DescribeTasks(input *ecs.DescribeTasksInput) (*ecs.DescribeTask
 	return m.DescribeTasksFn(input)
 }
 
+func (m MockECS) DescribeServices(input *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+	return m.DescribeServicesFn(input)
+}
+
 func (m MockECS) WaitUntilTasksStoppedWithContext(ctx aws.Context, input *ecs.DescribeTasksInput, opts ...request.WaiterOption) error {
 	return m.WaitUntilTasksStoppedWithContextFn(ctx, input, opts...)
 }
 
-func (m MockSSM) SendCommand(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
-	return m.SendCommandFn(input)
+func (m MockECS) WaitUntilTasksRunningWithContext(ctx aws.Context, input *ecs.DescribeTasksInput, opts ...request.WaiterOption) error {
+	return m.WaitUntilTasksRunningWithContextFn(ctx, input, opts...)
+}
+
+func (m MockECS) DescribeClusters(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+	return m.DescribeClustersFn(input)
+}
+
+func (m MockECS) DescribeCapacityProviders(input *ecs.DescribeCapacityProvidersInput) (*ecs.DescribeCapacityProvidersOutput, error) {
+	return m.DescribeCapacityProvidersFn(input)
+}
+
+func (m MockECS) TagResource(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+	return m.TagResourceFn(input)
+}
+
+func (m MockECS) UntagResource(input *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+	return m.UntagResourceFn(input)
+}
+
+func (m MockECS) StopTask(input *ecs.StopTaskInput) (*ecs.StopTaskOutput, error) {
+	return m.StopTaskFn(input)
+}
+
+func (m MockECS) StartTask(input *ecs.StartTaskInput) (*ecs.StartTaskOutput, error) {
+	return m.StartTaskFn(input)
 }
 
-func (m MockSSM) WaitUntilCommandExecutedWithContext(ctx aws.Context, input *ssm.GetCommandInvocationInput, opts ...request.WaiterOption) error {
-	return m.WaitUntilCommandExecutedWithContextFn(ctx, input, opts...)
+func (m MockSSM) SendCommand(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+	return m.SendCommandFn(input)
 }
 
 func (m MockSSM) GetCommandInvocation(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
 	return m.GetCommandInvocationFn(input)
 }
 
-func (c MockEC2) WaitUntilInstanceStatusOk(input *ec2.DescribeInstanceStatusInput) error {
-	return c.WaitUntilInstanceStatusOkFn(input)
+func (m MockSSM) ListCommandInvocationsPages(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+	return m.ListCommandInvocationsPagesFn(input, fn)
+}
+
+func (m MockSSM) GetParameter(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error) {
+	return m.GetParameterFn(input)
+}
+
+func (m MockSSM) ListCommandsPages(input *ssm.ListCommandsInput, fn func(*ssm.ListCommandsOutput, bool) bool) error {
+	return m.ListCommandsPagesFn(input, fn)
+}
+
+func (m MockSSM) PutParameter(input *ssm.PutParameterInput) (*ssm.PutParameterOutput, error) {
+	return m.PutParameterFn(input)
+}
+
+func (m MockSSM) DeleteParameter(input *ssm.DeleteParameterInput) (*ssm.DeleteParameterOutput, error) {
+	return m.DeleteParameterFn(input)
+}
+
+func (m MockSSM) GetMaintenanceWindow(input *ssm.GetMaintenanceWindowInput) (*ssm.GetMaintenanceWindowOutput, error) {
+	return m.GetMaintenanceWindowFn(input)
+}
+
+func (m MockSSM) DescribeMaintenanceWindowExecutions(input *ssm.DescribeMaintenanceWindowExecutionsInput) (*ssm.DescribeMaintenanceWindowExecutionsOutput, error) {
+	return m.DescribeMaintenanceWindowExecutionsFn(input)
+}
+
+func (m MockSSM) DescribeAutomationExecutions(input *ssm.DescribeAutomationExecutionsInput) (*ssm.DescribeAutomationExecutionsOutput, error) {
+	return m.DescribeAutomationExecutionsFn(input)
+}
+
+func (m MockSSM) AddTagsToResource(input *ssm.AddTagsToResourceInput) (*ssm.AddTagsToResourceOutput, error) {
+	return m.AddTagsToResourceFn(input)
+}
+
+// invocationsOutput returns a page of command invocations in the given status,
+// one for each instance.
+func invocationsOutput(status string, instanceIDs ...string) *ssm.ListCommandInvocationsOutput {
+	out := &ssm.ListCommandInvocationsOutput{}
+	for _, instanceID := range instanceIDs {
+		out.CommandInvocations = append(out.CommandInvocations, &ssm.CommandInvocation{
+			CommandId:  aws.String("command-id"),
+			InstanceId: aws.String(instanceID),
+			Status:     aws.String(status),
+		})
+	}
+	return out
+}
+
+func (c MockEC2) WaitUntilInstanceStatusOkWithContext(ctx aws.Context, input *ec2.DescribeInstanceStatusInput, opts ...request.WaiterOption) error {
+	return c.WaitUntilInstanceStatusOkWithContextFn(ctx, input, opts...)
+}
+
+func (c MockEC2) DescribeInstancesPages(input *ec2.DescribeInstancesInput, fn func(*ec2.DescribeInstancesOutput, bool) bool) error {
+	return c.DescribeInstancesPagesFn(input, fn)
+}
+
+func (c MockEC2) DescribeInstanceStatusPages(input *ec2.DescribeInstanceStatusInput, fn func(*ec2.DescribeInstanceStatusOutput, bool) bool) error {
+	return c.DescribeInstanceStatusPagesFn(input, fn)
+}
+
+func (m MockAutoScaling) DescribeAutoScalingGroups(input *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error) {
+	return m.DescribeAutoScalingGroupsFn(input)
+}
+
+func (m MockAutoScaling) DescribeAutoScalingInstances(input *autoscaling.DescribeAutoScalingInstancesInput) (*autoscaling.DescribeAutoScalingInstancesOutput, error) {
+	return m.DescribeAutoScalingInstancesFn(input)
+}
+
+func (m MockAutoScaling) SuspendProcesses(input *autoscaling.ScalingProcessQuery) (*autoscaling.SuspendProcessesOutput, error) {
+	return m.SuspendProcessesFn(input)
+}
+
+func (m MockAutoScaling) ResumeProcesses(input *autoscaling.ScalingProcessQuery) (*autoscaling.ResumeProcessesOutput, error) {
+	return m.ResumeProcessesFn(input)
+}
+
+func (m MockAutoScaling) CreateOrUpdateTags(input *autoscaling.CreateOrUpdateTagsInput) (*autoscaling.CreateOrUpdateTagsOutput, error) {
+	return m.CreateOrUpdateTagsFn(input)
+}
+
+func (m MockAutoScaling) DeleteTags(input *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error) {
+	return m.DeleteTagsFn(input)
+}
+
+func (m MockAutoScaling) SetDesiredCapacity(input *autoscaling.SetDesiredCapacityInput) (*autoscaling.SetDesiredCapacityOutput, error) {
+	return m.SetDesiredCapacityFn(input)
+}
+
+func (m MockELBV2) DescribeTargetHealth(input *elbv2.DescribeTargetHealthInput) (*elbv2.DescribeTargetHealthOutput, error) {
+	return m.DescribeTargetHealthFn(input)
+}
+
+func (m MockCloudWatchLogs) FilterLogEventsPages(input *cloudwatchlogs.FilterLogEventsInput, fn func(*cloudwatchlogs.FilterLogEventsOutput, bool) bool) error {
+	return m.FilterLogEventsPagesFn(input, fn)
+}
+
+func (m MockSTS) GetCallerIdentity(input *sts.GetCallerIdentityInput) (*sts.GetCallerIdentityOutput, error) {
+	return m.GetCallerIdentityFn(input)
+}
+
+func (m MockSecretsManager) GetSecretValue(input *secretsmanager.GetSecretValueInput) (*secretsmanager.GetSecretValueOutput, error) {
+	return m.GetSecretValueFn(input)
 }
@@ -0,0 +1,103 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+// serviceGroupPrefix prefixes the group of tasks started by a service.
+const serviceGroupPrefix = "service:"
+
+// workload counts the tasks running on a container instance by task group,
+// e.g. "service:web" for tasks of the web service or "family:batch" for
+// standalone tasks of the batch task definition family.
+type workload map[string]int
+
+// services returns the names of the services with tasks in the workload.
+func (w workload) services() []string {
+	services := make([]string, 0)
+	for group := range w {
+		if strings.HasPrefix(group, serviceGroupPrefix) {
+			services = append(services, strings.TrimPrefix(group, serviceGroupPrefix))
+		}
+	}
+	sort.Strings(services)
+	return services
+}
+
+// String describes the workload, e.g. "service web: 2 task(s), family batch: 1 task(s)".
+func (w workload) String() string {
+	if len(w) == 0 {
+		return "no tasks"
+	}
+	groups := make([]string, 0, len(w))
+	for group := range w {
+		groups = append(groups, group)
+	}
+	sort.Strings(groups)
+	parts := make([]string, 0, len(groups))
+	for _, group := range groups {
+		parts = append(parts, fmt.Sprintf("%s: %d task(s)", strings.Replace(group, ":", " ", 1), w[group]))
+	}
+	return strings.Join(parts, ", ")
+}
+
+// instanceWorkload returns the tasks running on the container instance by task group.
+func (u *updater) instanceWorkload(containerInstance string) (workload, error) {
+	list, err := u.ecs.ListTasks(&ecs.ListTasksInput{
+		Cluster:           &u.cluster,
+		ContainerInstance: aws.String(containerInstance),
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to list tasks: %w", err)
+	}
+	w := make(workload)
+	if len(list.TaskArns) == 0 {
+		return w, nil
+	}
+	desc, err := u.ecs.DescribeTasks(&ecs.DescribeTasksInput{
+		Cluster: &u.cluster,
+		Tasks:   list.TaskArns,
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe tasks: %w", err)
+	}
+	for _, task := range desc.Tasks {
+		w[aws.StringValue(task.Group)]++
+	}
+	return w, nil
+}
+
+// candidateWorkloads returns the workload of each candidate by instance ID.
+// Candidates whose tasks can't be determined are left out.
+func (u *updater) candidateWorkloads(candidates []instance) map[string]workload {
+	workloads := make(map[string]workload)
+	for _, inst := range candidates {
+		w, err := u.instanceWorkload(inst.containerInstanceID)
+		if err != nil {
+			log.Printf("Failed to determine the tasks on instance %#q: %v", inst, err)
+			u.warnings.add(warnDegraded, "tasks on instance %q are unknown: %v", inst.instanceID, err)
+			continue
+		}
+		workloads[inst.instanceID] = w
+	}
+	return workloads
+}
+
+// logUpdatePlan logs the instances to update in order, with the tasks each
+// update would disturb, so the impact of a pass can be reviewed.
+func logUpdatePlan(candidates []instance, workloads map[string]workload) {
+	log.Printf("Update plan for %d instance(s):", len(candidates))
+	for n, inst := range candidates {
+		impact := "unknown tasks"
+		if w, ok := workloads[inst.instanceID]; ok {
+			impact = w.String()
+		}
+		log.Printf("%d. %s: %s", n+1, inst.instanceID, impact)
+	}
+}
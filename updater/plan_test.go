@@ -0,0 +1,76 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestInstanceWorkload(t *testing.T) {
+	t.Run("tasks", func(t *testing.T) {
+		mockECS := MockECS{
+			ListTasksFn: func(input *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+				assert.Equal(t, "cont-inst-id", aws.StringValue(input.ContainerInstance))
+				return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-1", "task-2", "task-3"})}, nil
+			},
+			DescribeTasksFn: func(input *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+				assert.Len(t, input.Tasks, 3)
+				return &ecs.DescribeTasksOutput{Tasks: []*ecs.Task{
+					{TaskArn: aws.String("task-1"), Group: aws.String("service:web")},
+					{TaskArn: aws.String("task-2"), Group: aws.String("service:web")},
+					{TaskArn: aws.String("task-3"), Group: aws.String("family:batch")},
+				}}, nil
+			},
+		}
+		u := updater{ecs: mockECS}
+		w, err := u.instanceWorkload("cont-inst-id")
+		require.NoError(t, err)
+		assert.Equal(t, workload{"service:web": 2, "family:batch": 1}, w)
+		assert.Equal(t, []string{"web"}, w.services())
+		assert.Equal(t, "family batch: 1 task(s), service web: 2 task(s)", w.String())
+	})
+	t.Run("no tasks", func(t *testing.T) {
+		mockECS := MockECS{
+			ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+				return &ecs.ListTasksOutput{}, nil
+			},
+		}
+		u := updater{ecs: mockECS}
+		w, err := u.instanceWorkload("cont-inst-id")
+		require.NoError(t, err)
+		assert.Empty(t, w)
+		assert.Equal(t, "no tasks", w.String())
+	})
+	t.Run("list err", func(t *testing.T) {
+		listErr := errors.New("throttled")
+		mockECS := MockECS{
+			ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+				return nil, listErr
+			},
+		}
+		u := updater{ecs: mockECS}
+		_, err := u.instanceWorkload("cont-inst-id")
+		assert.ErrorIs(t, err, listErr)
+	})
+}
+
+func TestCandidateWorkloads(t *testing.T) {
+	mockECS := MockECS{
+		ListTasksFn: func(input *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+			if aws.StringValue(input.ContainerInstance) == "cont-inst-2" {
+				return nil, errors.New("throttled")
+			}
+			return &ecs.ListTasksOutput{}, nil
+		},
+	}
+	u := updater{ecs: mockECS}
+	workloads := u.candidateWorkloads([]instance{
+		{instanceID: "inst-id-1", containerInstanceID: "cont-inst-1"},
+		{instanceID: "inst-id-2", containerInstanceID: "cont-inst-2"},
+	})
+	assert.Equal(t, map[string]workload{"inst-id-1": {}}, workloads, "should leave out instances whose tasks are unknown")
+}
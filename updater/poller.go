@@ -0,0 +1,131 @@
+package main
+
+import (
+	"fmt"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+// invocation identifies the execution of an SSM command on one instance.
+type invocation struct {
+	commandID  string
+	instanceID string
+}
+
+// pollListInterval is the least time between two calls the poller makes to
+// list command invocations, which keeps the polls of many pending commands
+// from being throttled.
+const pollListInterval = 200 * time.Millisecond
+
+// commandPoller waits for SSM command invocations to finish. Instead of polling
+// every invocation separately, it lists the invocations of each pending command
+// once per interval, so the number of SSM API calls grows with the number of
+// commands rather than the number of instances they were sent to. Commands
+// awaited together, such as the pages of an update check, share the polls and
+// their rate limit.
+type commandPoller struct {
+	ssm      SSMReadAPI
+	interval time.Duration
+	maxPolls int
+	// listInterval is the least time between two calls to list command
+	// invocations; zero does not limit them.
+	listInterval time.Duration
+	// shutdown is canceled when the updater is asked to shut down, which stops
+	// the polling.
+	shutdown aws.Context
+}
+
+func (u *updater) commandPoller() commandPoller {
+	return commandPoller{
+		ssm:          u.ssm,
+		interval:     waiterDelay,
+		maxPolls:     waiterMaxAttempts,
+		listInterval: pollListInterval,
+		shutdown:     u.shutdownContext(),
+	}
+}
+
+// wait polls until every invocation of the pending commands has finished.
+// pending maps each command ID to the instances the command was sent to. The
+// result holds an error for every invocation that did not succeed, or that was
+// still running when the updater was asked to shut down.
+func (p commandPoller) wait(pending map[string][]string) map[invocation]error {
+	failed := make(map[invocation]error)
+	remaining := make(map[string]map[string]bool)
+	for commandID, instanceIDs := range pending {
+		if len(instanceIDs) == 0 {
+			continue
+		}
+		remaining[commandID] = make(map[string]bool)
+		for _, instanceID := range instanceIDs {
+			remaining[commandID][instanceID] = true
+		}
+	}
+
+	for poll := 1; len(remaining) > 0; poll++ {
+		listed := false
+		for commandID, instanceIDs := range remaining {
+			// The interval between polls already spaces the first list call
+			// of a poll from the last one of the previous poll.
+			if listed && p.listInterval > 0 {
+				sleep(p.listInterval)
+			}
+			listed = true
+			err := p.ssm.ListCommandInvocationsPages(&ssm.ListCommandInvocationsInput{
+				CommandId: aws.String(commandID),
+			}, func(page *ssm.ListCommandInvocationsOutput, _ bool) bool {
+				for _, inv := range page.CommandInvocations {
+					instanceID := aws.StringValue(inv.InstanceId)
+					if !instanceIDs[instanceID] {
+						continue
+					}
+					status := aws.StringValue(inv.Status)
+					switch status {
+					case ssm.CommandInvocationStatusSuccess:
+					case ssm.CommandInvocationStatusCancelled,
+						ssm.CommandInvocationStatusTimedOut,
+						ssm.CommandInvocationStatusFailed:
+						failed[invocation{commandID, instanceID}] = fmt.Errorf("command finished with status %q (%s)",
+							status, aws.StringValue(inv.StatusDetails))
+					default:
+						continue
+					}
+					delete(instanceIDs, instanceID)
+				}
+				return true
+			})
+			if err != nil {
+				for instanceID := range instanceIDs {
+					failed[invocation{commandID, instanceID}] = fmt.Errorf("failed to list command invocations: %w", err)
+				}
+				delete(remaining, commandID)
+				continue
+			}
+			if len(instanceIDs) == 0 {
+				delete(remaining, commandID)
+			}
+		}
+		if len(remaining) == 0 {
+			break
+		}
+		if poll >= p.maxPolls {
+			for commandID, instanceIDs := range remaining {
+				for instanceID := range instanceIDs {
+					failed[invocation{commandID, instanceID}] = fmt.Errorf("command did not finish after %d polls", poll)
+				}
+			}
+			break
+		}
+		if err := sleepContext(p.shutdown, p.interval); err != nil {
+			for commandID, instanceIDs := range remaining {
+				for instanceID := range instanceIDs {
+					failed[invocation{commandID, instanceID}] = fmt.Errorf("stopped waiting for the command: %w", err)
+				}
+			}
+			break
+		}
+	}
+	return failed
+}
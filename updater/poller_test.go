@@ -0,0 +1,115 @@
+package main
+
+import (
+	"context"
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestCommandPollerWait(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	var slept []time.Duration
+	sleep = func(d time.Duration) { slept = append(slept, d) }
+
+	t.Run("multiple commands", func(t *testing.T) {
+		slept = nil
+		listCalls := map[string]int{}
+		mockSSM := MockSSM{
+			ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+				commandID := aws.StringValue(input.CommandId)
+				listCalls[commandID]++
+				var out *ssm.ListCommandInvocationsOutput
+				switch {
+				case commandID == "command-1":
+					out = invocationsOutput(ssm.CommandInvocationStatusSuccess, "inst-1", "inst-2", "inst-unrelated")
+				case listCalls[commandID] == 1:
+					out = invocationsOutput(ssm.CommandInvocationStatusInProgress, "inst-3")
+				default:
+					out = invocationsOutput(ssm.CommandInvocationStatusFailed, "inst-3")
+				}
+				fn(out, true)
+				return nil
+			},
+		}
+		p := commandPoller{ssm: mockSSM, interval: time.Second, maxPolls: 5}
+		failed := p.wait(map[string][]string{
+			"command-1": {"inst-1", "inst-2"},
+			"command-2": {"inst-3"},
+		})
+		require.Len(t, failed, 1)
+		assert.Contains(t, failed[invocation{"command-2", "inst-3"}].Error(), "Failed")
+		assert.Equal(t, map[string]int{"command-1": 1, "command-2": 2}, listCalls, "should stop polling finished commands")
+		assert.Equal(t, []time.Duration{time.Second}, slept)
+	})
+	t.Run("not finished", func(t *testing.T) {
+		slept = nil
+		mockSSM := MockSSM{
+			ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+				// Invocations don't show up right after a command is sent
+				fn(&ssm.ListCommandInvocationsOutput{}, true)
+				return nil
+			},
+		}
+		p := commandPoller{ssm: mockSSM, interval: time.Second, maxPolls: 3}
+		failed := p.wait(map[string][]string{"command-id": {"inst-1"}})
+		require.Len(t, failed, 1)
+		assert.EqualError(t, failed[invocation{"command-id", "inst-1"}], "command did not finish after 3 polls")
+		assert.Len(t, slept, 2)
+	})
+	t.Run("rate limited", func(t *testing.T) {
+		slept = nil
+		mockSSM := MockSSM{
+			ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+				fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, "inst-1", "inst-2", "inst-3"), true)
+				return nil
+			},
+		}
+		p := commandPoller{ssm: mockSSM, interval: time.Second, maxPolls: 3, listInterval: 100 * time.Millisecond}
+		failed := p.wait(map[string][]string{"command-1": {"inst-1"}, "command-2": {"inst-2"}, "command-3": {"inst-3"}})
+		assert.Empty(t, failed)
+		assert.Equal(t, []time.Duration{100 * time.Millisecond, 100 * time.Millisecond}, slept, "list calls should be spaced by the list interval")
+	})
+	t.Run("list error", func(t *testing.T) {
+		slept = nil
+		listErr := errors.New("throttled")
+		mockSSM := MockSSM{
+			ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, _ func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+				return listErr
+			},
+		}
+		p := commandPoller{ssm: mockSSM, interval: time.Second, maxPolls: 3}
+		failed := p.wait(map[string][]string{"command-id": {"inst-1", "inst-2"}})
+		require.Len(t, failed, 2)
+		assert.ErrorIs(t, failed[invocation{"command-id", "inst-1"}], listErr)
+		assert.ErrorIs(t, failed[invocation{"command-id", "inst-2"}], listErr)
+		assert.Empty(t, slept)
+	})
+	t.Run("shutdown", func(t *testing.T) {
+		slept = nil
+		shutdown, cancel := context.WithCancel(context.Background())
+		mockSSM := MockSSM{
+			ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+				// ECS stops the updater while the command runs.
+				cancel()
+				fn(invocationsOutput(ssm.CommandInvocationStatusInProgress, "inst-1"), true)
+				return nil
+			},
+		}
+		p := commandPoller{ssm: mockSSM, interval: time.Second, maxPolls: 3, shutdown: shutdown}
+		failed := p.wait(map[string][]string{"command-id": {"inst-1"}})
+		require.Len(t, failed, 1)
+		assert.ErrorIs(t, failed[invocation{"command-id", "inst-1"}], errShutdown)
+		assert.Empty(t, slept, "polling should stop without waiting for the next poll")
+	})
+	t.Run("nothing to wait for", func(t *testing.T) {
+		p := commandPoller{ssm: MockSSM{}, interval: time.Second, maxPolls: 3}
+		assert.Empty(t, p.wait(map[string][]string{"command-id": {}}))
+	})
+}
@@ -0,0 +1,265 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+)
+
+// tagPrewarmed marks an Auto Scaling group whose desired capacity the updater
+// raised by one, with the cluster and the ID of the pass that raised it as
+// "cluster=<name> pass=<ID>", so that a later pass over the same cluster
+// lowers it again if the pass that raised it could not.
+const tagPrewarmed = "bottlerocket-ecs-updater:prewarmed"
+
+// formatPrewarmed returns the value of the prewarmed tag of a group raised by
+// the pass over the cluster.
+func formatPrewarmed(cluster string, passID string) string {
+	return fmt.Sprintf("cluster=%s pass=%s", cluster, passID)
+}
+
+// parsePrewarmed returns the cluster and pass ID in the value of a prewarmed
+// tag; the cluster is empty when the value does not name one.
+func parsePrewarmed(value string) (cluster string, passID string) {
+	for _, field := range strings.Fields(value) {
+		if strings.HasPrefix(field, "cluster=") {
+			cluster = strings.TrimPrefix(field, "cluster=")
+		} else if strings.HasPrefix(field, "pass=") {
+			passID = strings.TrimPrefix(field, "pass=")
+		}
+	}
+	return cluster, passID
+}
+
+// capacityShare returns the largest share of the CPU and memory registered by
+// the instances that inst registered.
+func capacityShare(inst instance, instances []instance) float64 {
+	var cpu, memory int64
+	for _, other := range instances {
+		cpu += other.registeredCPU
+		memory += other.registeredMemory
+	}
+	share := 0.0
+	if cpu > 0 {
+		share = float64(inst.registeredCPU) / float64(cpu)
+	}
+	if memory > 0 && float64(inst.registeredMemory)/float64(memory) > share {
+		share = float64(inst.registeredMemory) / float64(memory)
+	}
+	return share
+}
+
+// instanceScalingGroup returns the name of the Auto Scaling group of the
+// instance, or an empty name when it is not in one.
+func (u *updater) instanceScalingGroup(instanceID string) (string, error) {
+	resp, err := u.autoscaling.DescribeAutoScalingInstances(&autoscaling.DescribeAutoScalingInstancesInput{
+		InstanceIds: aws.StringSlice([]string{instanceID}),
+	})
+	if err != nil {
+		return "", fmt.Errorf("failed to describe Auto Scaling instance %q: %w", instanceID, err)
+	}
+	if len(resp.AutoScalingInstances) == 0 {
+		return "", nil
+	}
+	return aws.StringValue(resp.AutoScalingInstances[0].AutoScalingGroupName), nil
+}
+
+// prewarm raises the desired capacity of the Auto Scaling group of an instance
+// that registered at least prewarmShare of the CPU or memory of the instances,
+// and waits for the instance it launches to join the cluster, so that the
+// tasks of the instance have somewhere to go when it drains. Each group is
+// raised at most once per pass, which keeps the headroom constant while its
+// instances are updated one after the other; prewarmed holds the groups
+// raised so far, which relinquishCapacity lowers at the end of the pass.
+// Prewarming is best effort: the instance is drained regardless.
+func (u *updater) prewarm(inst instance, instances []instance, prewarmed map[string]bool) {
+	share := capacityShare(inst, instances)
+	if share < u.prewarmShare {
+		return
+	}
+	group, err := u.instanceScalingGroup(inst.instanceID)
+	if err != nil {
+		log.Printf("Failed to find the Auto Scaling group of instance %#q, therefore not prewarming capacity: %v", inst, err)
+		u.warnings.add(warnDegraded, "capacity was not prewarmed for instance %q: %v", inst.instanceID, err)
+		return
+	}
+	if group == "" {
+		log.Printf("Instance %#q holds %.0f%% of the cluster's capacity but is not in an Auto Scaling group, therefore not prewarming capacity", inst, share*100)
+		return
+	}
+	if prewarmed[group] {
+		log.Printf("Auto Scaling group %q already has prewarmed capacity for instance %#q", group, inst)
+		return
+	}
+	resp, err := u.autoscaling.DescribeAutoScalingGroups(&autoscaling.DescribeAutoScalingGroupsInput{
+		AutoScalingGroupNames: aws.StringSlice([]string{group}),
+	})
+	if err == nil && len(resp.AutoScalingGroups) == 0 {
+		err = fmt.Errorf("no Auto Scaling group %q", group)
+	}
+	if err != nil {
+		log.Printf("Failed to describe Auto Scaling group %q, therefore not prewarming capacity: %v", group, err)
+		u.warnings.add(warnDegraded, "capacity was not prewarmed for instance %q: %v", inst.instanceID, err)
+		return
+	}
+	desired := aws.Int64Value(resp.AutoScalingGroups[0].DesiredCapacity)
+	if desired >= aws.Int64Value(resp.AutoScalingGroups[0].MaxSize) {
+		log.Printf("Auto Scaling group %q is at its maximum size, therefore not prewarming capacity for instance %#q", group, inst)
+		u.warnings.add(warnDegraded, "capacity was not prewarmed for instance %q: Auto Scaling group %q is at its maximum size", inst.instanceID, group)
+		return
+	}
+	known, err := u.listContainerInstances()
+	if err != nil {
+		log.Printf("Failed to list container instances, therefore not prewarming capacity: %v", err)
+		u.warnings.add(warnDegraded, "capacity was not prewarmed for instance %q: %v", inst.instanceID, err)
+		return
+	}
+	// Tag the group before raising its capacity, so that a pass that stops
+	// before lowering it leaves a mark for the next pass.
+	_, err = u.autoscaling.CreateOrUpdateTags(&autoscaling.CreateOrUpdateTagsInput{
+		Tags: []*autoscaling.Tag{{
+			ResourceId:        aws.String(group),
+			ResourceType:      aws.String("auto-scaling-group"),
+			Key:               aws.String(tagPrewarmed),
+			Value:             aws.String(formatPrewarmed(u.clusterName(), u.passID)),
+			PropagateAtLaunch: aws.Bool(false),
+		}},
+	})
+	if err != nil {
+		log.Printf("Failed to tag Auto Scaling group %q, therefore not prewarming capacity: %v", group, err)
+		u.warnings.add(warnDegraded, "capacity was not prewarmed for instance %q: %v", inst.instanceID, err)
+		return
+	}
+	_, err = u.autoscaling.SetDesiredCapacity(&autoscaling.SetDesiredCapacityInput{
+		AutoScalingGroupName: aws.String(group),
+		DesiredCapacity:      aws.Int64(desired + 1),
+	})
+	if err != nil {
+		log.Printf("Failed to raise the desired capacity of Auto Scaling group %q, therefore not prewarming capacity: %v", group, err)
+		u.warnings.add(warnDegraded, "capacity was not prewarmed for instance %q: %v", inst.instanceID, err)
+		// Untag the group, or the next pass would lower capacity that was
+		// never raised.
+		if err := u.untagPrewarmed(group); err != nil {
+			log.Printf("Failed to untag Auto Scaling group %q: %v", group, err)
+		}
+		return
+	}
+	prewarmed[group] = true
+	log.Printf("Raised the desired capacity of Auto Scaling group %q to %d before draining instance %#q, which holds %.0f%% of the cluster's capacity", group, desired+1, inst, share*100)
+	if err := u.waitForNewInstance(known); err != nil {
+		log.Printf("Prewarmed capacity for instance %#q did not join the cluster: %v", inst, err)
+		u.warnings.add(warnDegraded, "prewarmed capacity for instance %q did not join the cluster: %v", inst.instanceID, err)
+	}
+}
+
+// waitForNewInstance waits until an ACTIVE container instance that is not one
+// of the known instances joins the cluster.
+func (u *updater) waitForNewInstance(known []*string) error {
+	seen := make(map[string]bool, len(known))
+	for _, arn := range known {
+		seen[aws.StringValue(arn)] = true
+	}
+	for attempt := 0; attempt < waiterMaxAttempts; attempt++ {
+		if err := sleepContext(u.shutdownContext(), waiterDelay); err != nil {
+			return err
+		}
+		current, err := u.listContainerInstances()
+		if err != nil {
+			return err
+		}
+		for _, arn := range current {
+			if !seen[aws.StringValue(arn)] {
+				log.Printf("Container instance %q joined the cluster", aws.StringValue(arn))
+				return nil
+			}
+		}
+	}
+	return fmt.Errorf("no new container instance after %s", waiterDelay*waiterMaxAttempts)
+}
+
+// relinquishCapacity lowers the desired capacity of the prewarmed Auto Scaling
+// groups by one again and removes their tags. The group chooses the instance
+// it terminates, which scale-in protection, e.g. from ECS managed termination
+// protection, keeps from being one that runs tasks.
+func (u *updater) relinquishCapacity(prewarmed map[string]bool) {
+	groups := make([]string, 0, len(prewarmed))
+	for group := range prewarmed {
+		groups = append(groups, group)
+	}
+	sort.Strings(groups)
+	for _, group := range groups {
+		resp, err := u.autoscaling.DescribeAutoScalingGroups(&autoscaling.DescribeAutoScalingGroupsInput{
+			AutoScalingGroupNames: aws.StringSlice([]string{group}),
+		})
+		if err == nil && len(resp.AutoScalingGroups) == 0 {
+			err = fmt.Errorf("no Auto Scaling group %q", group)
+		}
+		if err == nil {
+			desired := aws.Int64Value(resp.AutoScalingGroups[0].DesiredCapacity)
+			if desired > aws.Int64Value(resp.AutoScalingGroups[0].MinSize) {
+				_, err = u.autoscaling.SetDesiredCapacity(&autoscaling.SetDesiredCapacityInput{
+					AutoScalingGroupName: aws.String(group),
+					DesiredCapacity:      aws.Int64(desired - 1),
+				})
+			}
+		}
+		if err == nil {
+			err = u.untagPrewarmed(group)
+		}
+		if err != nil {
+			log.Printf("Failed to relinquish the prewarmed capacity of Auto Scaling group %q; the next pass retries: %v", group, err)
+			u.warnings.add(warnDegraded, "prewarmed capacity of Auto Scaling group %q was not relinquished: %v", group, err)
+			continue
+		}
+		log.Printf("Relinquished the prewarmed capacity of Auto Scaling group %q", group)
+	}
+}
+
+// untagPrewarmed removes the tag of a prewarmed Auto Scaling group.
+func (u *updater) untagPrewarmed(group string) error {
+	_, err := u.autoscaling.DeleteTags(&autoscaling.DeleteTagsInput{
+		Tags: []*autoscaling.Tag{{
+			ResourceId:   aws.String(group),
+			ResourceType: aws.String("auto-scaling-group"),
+			Key:          aws.String(tagPrewarmed),
+		}},
+	})
+	return err
+}
+
+// relinquishLeftoverCapacity relinquishes the capacity that earlier passes over
+// the cluster prewarmed but could not relinquish, e.g. because they were
+// stopped. Groups prewarmed for other clusters are left to their updaters,
+// whose passes may still be running.
+func (u *updater) relinquishLeftoverCapacity() {
+	resp, err := u.autoscaling.DescribeAutoScalingGroups(&autoscaling.DescribeAutoScalingGroupsInput{
+		Filters: []*autoscaling.Filter{{
+			Name:   aws.String("tag-key"),
+			Values: aws.StringSlice([]string{tagPrewarmed}),
+		}},
+	})
+	if err != nil {
+		log.Printf("Failed to check for capacity left prewarmed by earlier passes: %v", err)
+		u.warnings.add(warnDegraded, "capacity left prewarmed by earlier passes is unknown: %v", err)
+		return
+	}
+	leftover := make(map[string]bool)
+	for _, group := range resp.AutoScalingGroups {
+		for _, tag := range group.Tags {
+			if aws.StringValue(tag.Key) != tagPrewarmed {
+				continue
+			}
+			cluster, passID := parsePrewarmed(aws.StringValue(tag.Value))
+			if cluster != u.clusterName() {
+				continue
+			}
+			log.Printf("Capacity of Auto Scaling group %q was left prewarmed by pass %s", aws.StringValue(group.AutoScalingGroupName), passID)
+			leftover[aws.StringValue(group.AutoScalingGroupName)] = true
+		}
+	}
+	u.relinquishCapacity(leftover)
+}
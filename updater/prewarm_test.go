@@ -0,0 +1,230 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestCapacityShare(t *testing.T) {
+	instances := []instance{
+		{instanceID: "inst-id-1", registeredCPU: 4096, registeredMemory: 8192},
+		{instanceID: "inst-id-2", registeredCPU: 2048, registeredMemory: 24576},
+		{instanceID: "inst-id-3", registeredCPU: 2048, registeredMemory: 32768},
+	}
+	assert.Equal(t, 0.5, capacityShare(instances[0], instances), "should be the share of CPU")
+	assert.Equal(t, 0.5, capacityShare(instances[2], instances), "should be the share of memory")
+	assert.Equal(t, 0.0, capacityShare(instance{}, nil))
+}
+
+func TestPrewarm(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = func(time.Duration) {}
+
+	instances := []instance{
+		{instanceID: "inst-id-1", registeredCPU: 8192, registeredMemory: 32768},
+		{instanceID: "inst-id-2", registeredCPU: 8192, registeredMemory: 32768},
+		{instanceID: "inst-id-3", registeredCPU: 1024, registeredMemory: 4096},
+	}
+	var desired []int64
+	var tagged, untagged []string
+	capacity := int64(2)
+	lists := 0
+	u := updater{
+		cluster:      "test-cluster",
+		passID:       "pass-1",
+		prewarmShare: 0.3,
+		warnings:     newPassWarnings(),
+		ecs: MockECS{
+			ListContainerInstancesPagesFn: func(_ *ecs.ListContainerInstancesInput, fn func(*ecs.ListContainerInstancesOutput, bool) bool) error {
+				lists++
+				arns := []string{"arn-1", "arn-2", "arn-3"}
+				if lists > 2 {
+					arns = append(arns, "arn-new")
+				}
+				fn(&ecs.ListContainerInstancesOutput{ContainerInstanceArns: aws.StringSlice(arns)}, true)
+				return nil
+			},
+		},
+		autoscaling: MockAutoScaling{
+			DescribeAutoScalingInstancesFn: func(input *autoscaling.DescribeAutoScalingInstancesInput) (*autoscaling.DescribeAutoScalingInstancesOutput, error) {
+				return &autoscaling.DescribeAutoScalingInstancesOutput{AutoScalingInstances: []*autoscaling.InstanceDetails{{
+					InstanceId:           input.InstanceIds[0],
+					AutoScalingGroupName: aws.String("large-asg"),
+				}}}, nil
+			},
+			DescribeAutoScalingGroupsFn: func(input *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error) {
+				assert.Equal(t, []string{"large-asg"}, aws.StringValueSlice(input.AutoScalingGroupNames))
+				return &autoscaling.DescribeAutoScalingGroupsOutput{AutoScalingGroups: []*autoscaling.Group{{
+					AutoScalingGroupName: aws.String("large-asg"),
+					DesiredCapacity:      aws.Int64(capacity),
+					MinSize:              aws.Int64(1),
+					MaxSize:              aws.Int64(4),
+				}}}, nil
+			},
+			CreateOrUpdateTagsFn: func(input *autoscaling.CreateOrUpdateTagsInput) (*autoscaling.CreateOrUpdateTagsOutput, error) {
+				require.Len(t, input.Tags, 1)
+				assert.Equal(t, tagPrewarmed, aws.StringValue(input.Tags[0].Key))
+				assert.Equal(t, "cluster=test-cluster pass=pass-1", aws.StringValue(input.Tags[0].Value))
+				tagged = append(tagged, aws.StringValue(input.Tags[0].ResourceId))
+				return &autoscaling.CreateOrUpdateTagsOutput{}, nil
+			},
+			SetDesiredCapacityFn: func(input *autoscaling.SetDesiredCapacityInput) (*autoscaling.SetDesiredCapacityOutput, error) {
+				assert.Equal(t, "large-asg", aws.StringValue(input.AutoScalingGroupName))
+				capacity = aws.Int64Value(input.DesiredCapacity)
+				desired = append(desired, capacity)
+				return &autoscaling.SetDesiredCapacityOutput{}, nil
+			},
+			DeleteTagsFn: func(input *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error) {
+				require.Len(t, input.Tags, 1)
+				assert.Equal(t, tagPrewarmed, aws.StringValue(input.Tags[0].Key))
+				untagged = append(untagged, aws.StringValue(input.Tags[0].ResourceId))
+				return &autoscaling.DeleteTagsOutput{}, nil
+			},
+		},
+	}
+	prewarmed := make(map[string]bool)
+	u.prewarm(instances[2], instances, prewarmed)
+	assert.Empty(t, desired, "should not prewarm for small instances")
+
+	u.prewarm(instances[0], instances, prewarmed)
+	assert.Equal(t, []int64{3}, desired)
+	assert.Equal(t, []string{"large-asg"}, tagged)
+	assert.Equal(t, 3, lists, "should wait for the new instance to join the cluster")
+	u.prewarm(instances[1], instances, prewarmed)
+	assert.Equal(t, []int64{3}, desired, "should raise each group at most once per pass")
+
+	u.relinquishCapacity(prewarmed)
+	assert.Equal(t, []int64{3, 2}, desired)
+	assert.Equal(t, []string{"large-asg"}, untagged)
+	assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+}
+
+func TestPrewarmDegraded(t *testing.T) {
+	instances := []instance{{instanceID: "inst-id-1", registeredCPU: 1024, registeredMemory: 4096}}
+	describeInstances := func(_ *autoscaling.DescribeAutoScalingInstancesInput) (*autoscaling.DescribeAutoScalingInstancesOutput, error) {
+		return &autoscaling.DescribeAutoScalingInstancesOutput{AutoScalingInstances: []*autoscaling.InstanceDetails{{
+			AutoScalingGroupName: aws.String("full-asg"),
+		}}}, nil
+	}
+
+	t.Run("at maximum size", func(t *testing.T) {
+		u := updater{prewarmShare: 1, warnings: newPassWarnings(), autoscaling: MockAutoScaling{
+			DescribeAutoScalingInstancesFn: describeInstances,
+			DescribeAutoScalingGroupsFn: func(_ *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error) {
+				return &autoscaling.DescribeAutoScalingGroupsOutput{AutoScalingGroups: []*autoscaling.Group{{
+					DesiredCapacity: aws.Int64(4),
+					MaxSize:         aws.Int64(4),
+				}}}, nil
+			},
+		}}
+		prewarmed := make(map[string]bool)
+		u.prewarm(instances[0], instances, prewarmed)
+		assert.Empty(t, prewarmed)
+		assert.Equal(t, 1, u.warnings.counts[warnDegraded])
+	})
+
+	t.Run("raise err", func(t *testing.T) {
+		var untagged []string
+		u := updater{
+			cluster:      "test-cluster",
+			prewarmShare: 1,
+			warnings:     newPassWarnings(),
+			ecs: MockECS{
+				ListContainerInstancesPagesFn: func(_ *ecs.ListContainerInstancesInput, _ func(*ecs.ListContainerInstancesOutput, bool) bool) error {
+					return nil
+				},
+			},
+			autoscaling: MockAutoScaling{
+				DescribeAutoScalingInstancesFn: describeInstances,
+				DescribeAutoScalingGroupsFn: func(_ *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error) {
+					return &autoscaling.DescribeAutoScalingGroupsOutput{AutoScalingGroups: []*autoscaling.Group{{
+						DesiredCapacity: aws.Int64(2),
+						MaxSize:         aws.Int64(4),
+					}}}, nil
+				},
+				CreateOrUpdateTagsFn: func(_ *autoscaling.CreateOrUpdateTagsInput) (*autoscaling.CreateOrUpdateTagsOutput, error) {
+					return &autoscaling.CreateOrUpdateTagsOutput{}, nil
+				},
+				SetDesiredCapacityFn: func(_ *autoscaling.SetDesiredCapacityInput) (*autoscaling.SetDesiredCapacityOutput, error) {
+					return nil, errors.New("failed to set desired capacity")
+				},
+				DeleteTagsFn: func(input *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error) {
+					untagged = append(untagged, aws.StringValue(input.Tags[0].ResourceId))
+					return &autoscaling.DeleteTagsOutput{}, nil
+				},
+			},
+		}
+		prewarmed := make(map[string]bool)
+		u.prewarm(instances[0], instances, prewarmed)
+		assert.Empty(t, prewarmed)
+		assert.Equal(t, []string{"full-asg"}, untagged, "should untag the group so the next pass does not lower its capacity")
+		assert.Equal(t, 1, u.warnings.counts[warnDegraded])
+	})
+}
+
+func TestRelinquishLeftoverCapacity(t *testing.T) {
+	groups := map[string]*autoscaling.Group{
+		"large-asg": {
+			AutoScalingGroupName: aws.String("large-asg"),
+			DesiredCapacity:      aws.Int64(3),
+			MinSize:              aws.Int64(1),
+			Tags:                 []*autoscaling.TagDescription{{Key: aws.String(tagPrewarmed), Value: aws.String("cluster=test-cluster pass=pass-0")}},
+		},
+		"other-asg": {
+			AutoScalingGroupName: aws.String("other-asg"),
+			DesiredCapacity:      aws.Int64(5),
+			MinSize:              aws.Int64(1),
+			Tags:                 []*autoscaling.TagDescription{{Key: aws.String(tagPrewarmed), Value: aws.String("cluster=other-cluster pass=pass-9")}},
+		},
+		"untagged-cluster-asg": {
+			AutoScalingGroupName: aws.String("untagged-cluster-asg"),
+			DesiredCapacity:      aws.Int64(4),
+			MinSize:              aws.Int64(1),
+			Tags:                 []*autoscaling.TagDescription{{Key: aws.String(tagPrewarmed), Value: aws.String("pass-8")}},
+		},
+	}
+	desired := make(map[string]int64)
+	var untagged []string
+	u := updater{cluster: "arn:aws:ecs:us-west-2:123456789012:cluster/test-cluster", warnings: newPassWarnings(), autoscaling: MockAutoScaling{
+		DescribeAutoScalingGroupsFn: func(input *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error) {
+			if len(input.Filters) > 0 {
+				assert.Equal(t, []string{tagPrewarmed}, aws.StringValueSlice(input.Filters[0].Values))
+				return &autoscaling.DescribeAutoScalingGroupsOutput{AutoScalingGroups: []*autoscaling.Group{
+					groups["large-asg"], groups["other-asg"], groups["untagged-cluster-asg"],
+				}}, nil
+			}
+			return &autoscaling.DescribeAutoScalingGroupsOutput{AutoScalingGroups: []*autoscaling.Group{
+				groups[aws.StringValue(input.AutoScalingGroupNames[0])],
+			}}, nil
+		},
+		SetDesiredCapacityFn: func(input *autoscaling.SetDesiredCapacityInput) (*autoscaling.SetDesiredCapacityOutput, error) {
+			desired[aws.StringValue(input.AutoScalingGroupName)] = aws.Int64Value(input.DesiredCapacity)
+			return &autoscaling.SetDesiredCapacityOutput{}, nil
+		},
+		DeleteTagsFn: func(input *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error) {
+			untagged = append(untagged, aws.StringValue(input.Tags[0].ResourceId))
+			return &autoscaling.DeleteTagsOutput{}, nil
+		},
+	}}
+	u.relinquishLeftoverCapacity()
+	assert.Equal(t, map[string]int64{"large-asg": 2}, desired, "groups prewarmed for other clusters should be left alone")
+	assert.Equal(t, []string{"large-asg"}, untagged)
+	assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+}
+
+func TestParsePrewarmed(t *testing.T) {
+	cluster, passID := parsePrewarmed(formatPrewarmed("test-cluster", "pass-1"))
+	assert.Equal(t, "test-cluster", cluster)
+	assert.Equal(t, "pass-1", passID)
+	cluster, passID = parsePrewarmed("pass-0")
+	assert.Empty(t, cluster, "tags of earlier versions do not name the cluster")
+	assert.Empty(t, passID)
+}
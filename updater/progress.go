@@ -0,0 +1,191 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"log"
+	"os"
+	"path/filepath"
+	"time"
+)
+
+// States of a pass in the progress file.
+const (
+	progressDiscovering = "discovering"
+	progressChecking    = "checking"
+	progressUpdating    = "updating"
+	progressDone        = "done"
+	progressFailed      = "failed"
+)
+
+// Steps of the update of an instance in the progress file.
+const (
+	stepPreparing = "preparing"
+	stepDraining  = "draining"
+	stepUpdating  = "updating"
+	stepVerifying = "verifying"
+)
+
+// passProgress is the state of a pass, as written to the progress file.
+type passProgress struct {
+	Cluster   string    `json:"cluster"`
+	PassID    string    `json:"passId"`
+	ChangeRef string    `json:"changeRef,omitempty"`
+	State     string    `json:"state"`
+	StartedAt time.Time `json:"startedAt"`
+	UpdatedAt time.Time `json:"updatedAt"`
+	// Candidates counts the instances the pass plans to update, once known.
+	Candidates int `json:"candidates"`
+	Updated    int `json:"updated"`
+	Failed     int `json:"failed"`
+	Skipped    int `json:"skipped"`
+	// Current is the update of an instance in progress.
+	Current *progressUpdate `json:"current,omitempty"`
+	// Error is why a failed pass stopped.
+	Error string `json:"error,omitempty"`
+}
+
+// progressUpdate is the update of an instance in progress.
+type progressUpdate struct {
+	InstanceID string    `json:"instanceId"`
+	UpdateID   string    `json:"updateId"`
+	Step       string    `json:"step"`
+	StartedAt  time.Time `json:"startedAt"`
+}
+
+// progressFile keeps the state of a pass up to date in a JSON file, for
+// sidecar containers such as dashboards and exporters to read. A nil
+// progressFile writes nothing.
+type progressFile struct {
+	path     string
+	summary  *passSummary
+	progress passProgress
+	// warned is set once a write failed, so that a pass warns of failed
+	// writes once.
+	warned bool
+}
+
+// newProgressFile returns the progress file at path, or nil when path is empty.
+func newProgressFile(path string) *progressFile {
+	if path == "" {
+		return nil
+	}
+	return &progressFile{path: path}
+}
+
+// startProgress starts recording the progress of the pass, whose counts are
+// those of summary.
+func (u *updater) startProgress(summary *passSummary) {
+	if u.progress == nil {
+		return
+	}
+	u.progress.summary = summary
+	u.progress.progress = passProgress{
+		Cluster:   u.clusterName(),
+		PassID:    u.passID,
+		ChangeRef: u.changeRef,
+		State:     progressDiscovering,
+		StartedAt: time.Now().UTC(),
+	}
+	u.writeProgress()
+}
+
+// progressState records the state of the pass.
+func (u *updater) progressState(state string) {
+	if u.progress == nil {
+		return
+	}
+	u.progress.progress.State = state
+	u.writeProgress()
+}
+
+// progressCandidates records that the pass starts updating the candidates.
+func (u *updater) progressCandidates(candidates []instance) {
+	if u.progress == nil {
+		return
+	}
+	u.progress.progress.State = progressUpdating
+	u.progress.progress.Candidates = len(candidates)
+	u.writeProgress()
+}
+
+// progressStep records the step of the update of the instance in progress; an
+// empty step records that no update is in progress.
+func (u *updater) progressStep(inst instance, step string) {
+	if u.progress == nil {
+		return
+	}
+	current := u.progress.progress.Current
+	switch {
+	case step == "":
+		current = nil
+	case current == nil || current.InstanceID != inst.instanceID:
+		current = &progressUpdate{InstanceID: inst.instanceID, UpdateID: u.updateID, StartedAt: time.Now().UTC()}
+	}
+	if current != nil {
+		current.Step = step
+	}
+	u.progress.progress.Current = current
+	u.writeProgress()
+}
+
+// endProgress records the end of the pass, which err stopped unless nil.
+func (u *updater) endProgress(err error) {
+	if u.progress == nil {
+		return
+	}
+	u.progress.progress.State = progressDone
+	u.progress.progress.Current = nil
+	if err != nil {
+		u.progress.progress.State = progressFailed
+		u.progress.progress.Error = err.Error()
+	}
+	u.writeProgress()
+}
+
+// writeProgress writes the progress of the pass. Progress is best effort: a
+// failed write is logged, and the pass goes on.
+func (u *updater) writeProgress() {
+	p := &u.progress.progress
+	if s := u.progress.summary; s != nil {
+		p.Updated, p.Failed, p.Skipped = s.updated, s.failed, s.skipped
+	}
+	p.UpdatedAt = time.Now().UTC()
+	if err := writeFileAtomic(u.progress.path, *p); err != nil {
+		log.Printf("Failed to write progress file: %v", err)
+		if !u.progress.warned {
+			u.progress.warned = true
+			u.warnings.add(warnDegraded, "progress file was not written: %v", err)
+		}
+	}
+}
+
+// writeFileAtomic writes v as JSON to a temporary file next to path and
+// renames it to path, so that readers never see a partially written file.
+func writeFileAtomic(path string, v interface{}) error {
+	data, err := json.MarshalIndent(v, "", "  ")
+	if err != nil {
+		return fmt.Errorf("failed to encode %q: %w", path, err)
+	}
+	f, err := os.CreateTemp(filepath.Dir(path), "."+filepath.Base(path)+".*")
+	if err != nil {
+		return fmt.Errorf("failed to write %q: %w", path, err)
+	}
+	defer os.Remove(f.Name())
+	if _, err := f.Write(append(data, '\n')); err != nil {
+		f.Close()
+		return fmt.Errorf("failed to write %q: %w", path, err)
+	}
+	if err := f.Close(); err != nil {
+		return fmt.Errorf("failed to write %q: %w", path, err)
+	}
+	// Temporary files are created readable only by their owner; sidecars may
+	// run as other users.
+	if err := os.Chmod(f.Name(), 0o644); err != nil {
+		return fmt.Errorf("failed to write %q: %w", path, err)
+	}
+	if err := os.Rename(f.Name(), path); err != nil {
+		return fmt.Errorf("failed to write %q: %w", path, err)
+	}
+	return nil
+}
@@ -0,0 +1,70 @@
+package main
+
+import (
+	"encoding/json"
+	"errors"
+	"os"
+	"path/filepath"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestProgressFile(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "progress.json")
+	read := func() passProgress {
+		data, err := os.ReadFile(path)
+		require.NoError(t, err)
+		var p passProgress
+		require.NoError(t, json.Unmarshal(data, &p))
+		return p
+	}
+	u := updater{cluster: "test-cluster", passID: "pass-1", progress: newProgressFile(path), warnings: newPassWarnings()}
+	summary := newPassSummary()
+	u.startProgress(summary)
+	p := read()
+	assert.Equal(t, "test-cluster", p.Cluster)
+	assert.Equal(t, "pass-1", p.PassID)
+	assert.Equal(t, progressDiscovering, p.State)
+
+	inst := instance{instanceID: "inst-id-1"}
+	u.progressCandidates([]instance{inst, {instanceID: "inst-id-2"}})
+	release := u.beginUpdate(inst, summary, func() {})
+	u.progressStep(inst, stepDraining)
+	p = read()
+	assert.Equal(t, progressUpdating, p.State)
+	assert.Equal(t, 2, p.Candidates)
+	require.NotNil(t, p.Current)
+	assert.Equal(t, "inst-id-1", p.Current.InstanceID)
+	assert.Equal(t, summary.updateIDs["inst-id-1"], p.Current.UpdateID)
+	assert.Equal(t, stepDraining, p.Current.Step)
+
+	summary.updatedInstance("inst-id-1", "Instance updated successfully")
+	release()
+	p = read()
+	assert.Nil(t, p.Current, "no update should be in progress after the instance is released")
+	assert.Equal(t, 1, p.Updated)
+
+	u.endProgress(errors.New("instance failed to re-activate"))
+	p = read()
+	assert.Equal(t, progressFailed, p.State)
+	assert.Equal(t, "instance failed to re-activate", p.Error)
+	entries, err := os.ReadDir(filepath.Dir(path))
+	require.NoError(t, err)
+	assert.Len(t, entries, 1, "temporary files should not be left behind")
+	assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+}
+
+func TestProgressFileDegraded(t *testing.T) {
+	u := updater{passID: "pass-1", progress: newProgressFile(filepath.Join(t.TempDir(), "missing", "progress.json")), warnings: newPassWarnings()}
+	u.startProgress(newPassSummary())
+	u.progressState(progressChecking)
+	u.endProgress(nil)
+	assert.Equal(t, 1, u.warnings.counts[warnDegraded], "should warn of failed writes once")
+
+	// Without -progress-file nothing is written.
+	u = updater{}
+	u.startProgress(newPassSummary())
+	u.endProgress(nil)
+}
@@ -0,0 +1,189 @@
+package main
+
+import (
+	"bytes"
+	"errors"
+	"fmt"
+	"io"
+	"log"
+	"sort"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"gopkg.in/yaml.v3"
+)
+
+// promotionPolicy describes the order in which clusters take up Bottlerocket
+// versions. The same policy is shared by the updaters of all the clusters it
+// names, e.g.:
+//
+//	clusters:
+//	  prod:
+//	    after:
+//	      - cluster: staging
+//	        soak: 24h
+type promotionPolicy struct {
+	// Clusters maps cluster names to the clusters they wait for.
+	Clusters map[string]promotionRule `yaml:"clusters"`
+}
+
+// promotionRule lists the clusters a cluster waits for.
+type promotionRule struct {
+	After []promotionDependency `yaml:"after"`
+}
+
+// promotionDependency makes a cluster wait until another cluster has run a
+// version as its last known good version for at least the soak time.
+type promotionDependency struct {
+	Cluster string        `yaml:"cluster"`
+	Soak    time.Duration `yaml:"soak"`
+}
+
+// parsePromotionPolicy parses and validates a promotion policy.
+func parsePromotionPolicy(data []byte) (promotionPolicy, error) {
+	policy := promotionPolicy{}
+	decoder := yaml.NewDecoder(bytes.NewReader(data))
+	decoder.KnownFields(true)
+	if err := decoder.Decode(&policy); err != nil && !errors.Is(err, io.EOF) {
+		return policy, fmt.Errorf("failed to parse promotion policy: %w", err)
+	}
+	for cluster, rule := range policy.Clusters {
+		for _, dep := range rule.After {
+			switch {
+			case dep.Cluster == "":
+				return policy, fmt.Errorf("cluster %q waits for a cluster without a name", cluster)
+			case dep.Cluster == cluster:
+				return policy, fmt.Errorf("cluster %q waits for itself", cluster)
+			case dep.Soak < 0:
+				return policy, fmt.Errorf("cluster %q waits for cluster %q with a negative soak time", cluster, dep.Cluster)
+			}
+		}
+	}
+	if cycle := policy.cycle(); cycle != nil {
+		return policy, fmt.Errorf("promotion policy has a cycle: %s", strings.Join(cycle, " -> "))
+	}
+	return policy, nil
+}
+
+// cycle returns the clusters of a cycle of waits in the policy, or nil when
+// there is none.
+func (p promotionPolicy) cycle() []string {
+	const (
+		visiting = 1
+		done     = 2
+	)
+	state := make(map[string]int)
+	var path []string
+	var visit func(cluster string) []string
+	visit = func(cluster string) []string {
+		switch state[cluster] {
+		case visiting:
+			for i, c := range path {
+				if c == cluster {
+					return append(append([]string{}, path[i:]...), cluster)
+				}
+			}
+		case done:
+			return nil
+		}
+		state[cluster] = visiting
+		path = append(path, cluster)
+		for _, dep := range p.Clusters[cluster].After {
+			if cycle := visit(dep.Cluster); cycle != nil {
+				return cycle
+			}
+		}
+		path = path[:len(path)-1]
+		state[cluster] = done
+		return nil
+	}
+	clusters := make([]string, 0, len(p.Clusters))
+	for cluster := range p.Clusters {
+		clusters = append(clusters, cluster)
+	}
+	sort.Strings(clusters)
+	for _, cluster := range clusters {
+		if cycle := visit(cluster); cycle != nil {
+			return cycle
+		}
+	}
+	return nil
+}
+
+// promotionDependencies reads the promotion policy from the SSM parameter and
+// returns the clusters the updater's cluster waits for.
+func (u *updater) promotionDependencies(parameter string) ([]promotionDependency, error) {
+	resp, err := u.ssm.GetParameter(&ssm.GetParameterInput{Name: aws.String(parameter)})
+	if err != nil {
+		return nil, fmt.Errorf("failed to get promotion policy parameter %q: %w", parameter, err)
+	}
+	if resp.Parameter == nil {
+		return nil, fmt.Errorf("promotion policy parameter %q has no value", parameter)
+	}
+	policy, err := parsePromotionPolicy([]byte(aws.StringValue(resp.Parameter.Value)))
+	if err != nil {
+		return nil, err
+	}
+	return policy.Clusters[u.clusterName()].After, nil
+}
+
+// filterPromotion returns the candidates whose update every cluster the
+// updater's cluster waits for has run as its last known good version for at
+// least the soak time, recording the others as skipped in the summary. All
+// candidates are skipped when the policy or the last known good version of a
+// cluster it waits for can't be determined.
+func (u *updater) filterPromotion(candidates []instance, summary *passSummary, now time.Time) []instance {
+	if u.promotionParameter == "" {
+		return candidates
+	}
+	skipAll := func(reason string) []instance {
+		log.Printf("%s, therefore not updating any instances", reason)
+		for _, inst := range candidates {
+			summary.skippedInstance(inst.instanceID, skipPromotion, reason)
+		}
+		u.warnings.add(warnDegraded, "%s", reason)
+		return nil
+	}
+	deps, err := u.promotionDependencies(u.promotionParameter)
+	if err != nil {
+		return skipAll(fmt.Sprintf("Promotion policy is unknown: %v", err))
+	}
+	if len(deps) == 0 {
+		log.Printf("The promotion policy does not make cluster %q wait for other clusters", u.clusterName())
+		return candidates
+	}
+	lkgs := make([]lastKnownGood, 0, len(deps))
+	for _, dep := range deps {
+		lkg, err := u.clusterLastKnownGood(dep.Cluster)
+		if err != nil {
+			return skipAll(fmt.Sprintf("Last known good version of cluster %q is unknown: %v", dep.Cluster, err))
+		}
+		log.Printf("Cluster %q waits for cluster %q, whose last known good version is %q since %s",
+			u.clusterName(), dep.Cluster, lkg.version, lkg.since.Format(time.RFC3339))
+		lkgs = append(lkgs, lkg)
+	}
+	promoted := make([]instance, 0, len(candidates))
+	for _, inst := range candidates {
+		var reason string
+		for i, dep := range deps {
+			version := inst.targetRelease.Version
+			if lkgs[i].version != version {
+				reason = fmt.Sprintf("Cluster %q has not proven release %q", dep.Cluster, version)
+				break
+			}
+			if soaked := now.Sub(lkgs[i].since); soaked < dep.Soak {
+				reason = fmt.Sprintf("Cluster %q has only run release %q for %s of %s", dep.Cluster, version, soaked.Round(time.Minute), dep.Soak)
+				break
+			}
+		}
+		if reason != "" {
+			log.Printf("%s, therefore not updating instance %#q", reason, inst)
+			summary.skippedInstance(inst.instanceID, skipPromotion, reason)
+			continue
+		}
+		promoted = append(promoted, inst)
+	}
+	return promoted
+}
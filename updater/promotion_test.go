@@ -0,0 +1,116 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParsePromotionPolicy(t *testing.T) {
+	policy, err := parsePromotionPolicy([]byte(`
+clusters:
+  prod:
+    after:
+      - cluster: staging
+        soak: 24h
+      - cluster: qa
+  staging:
+    after:
+      - cluster: dev
+`))
+	require.NoError(t, err)
+	assert.Equal(t, []promotionDependency{{Cluster: "staging", Soak: 24 * time.Hour}, {Cluster: "qa"}}, policy.Clusters["prod"].After)
+
+	cases := []struct {
+		name        string
+		policy      string
+		expectedErr string
+	}{
+		{
+			name:        "unknown key",
+			policy:      "clusters: {prod: {before: []}}",
+			expectedErr: "field before not found",
+		},
+		{
+			name:        "waits for itself",
+			policy:      "clusters: {prod: {after: [{cluster: prod}]}}",
+			expectedErr: `cluster "prod" waits for itself`,
+		},
+		{
+			name:        "negative soak",
+			policy:      "clusters: {prod: {after: [{cluster: staging, soak: -1h}]}}",
+			expectedErr: "negative soak time",
+		},
+		{
+			name:        "cycle",
+			policy:      "clusters: {prod: {after: [{cluster: staging}]}, staging: {after: [{cluster: dev}]}, dev: {after: [{cluster: prod}]}}",
+			expectedErr: "cycle: dev -> prod -> staging -> dev",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			_, err := parsePromotionPolicy([]byte(tc.policy))
+			require.Error(t, err)
+			assert.Contains(t, err.Error(), tc.expectedErr)
+		})
+	}
+}
+
+func TestFilterPromotion(t *testing.T) {
+	now := time.Date(2024, 3, 2, 12, 0, 0, 0, time.UTC)
+	candidates := []instance{
+		{instanceID: "inst-soaked", targetRelease: release{Version: "1.12.0"}},
+		{instanceID: "inst-unproven", targetRelease: release{Version: "1.13.0"}},
+	}
+	mockSSM := func(policy string) MockSSM {
+		return MockSSM{
+			GetParameterFn: func(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error) {
+				assert.Equal(t, "/promotion-policy", aws.StringValue(input.Name))
+				return &ssm.GetParameterOutput{Parameter: &ssm.Parameter{Value: aws.String(policy)}}, nil
+			},
+		}
+	}
+	mockECS := func(since string) MockECS {
+		return MockECS{
+			DescribeClustersFn: func(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+				assert.Equal(t, aws.StringSlice([]string{"staging"}), input.Clusters)
+				return lkgCluster(map[string]string{tagLastKnownGood: "1.12.0", tagLastKnownGoodSince: since}), nil
+			},
+		}
+	}
+	const policy = "clusters: {prod: {after: [{cluster: staging, soak: 24h}]}}"
+
+	t.Run("soaked", func(t *testing.T) {
+		u := updater{cluster: "prod", promotionParameter: "/promotion-policy", ssm: mockSSM(policy), ecs: mockECS("2024-03-01T00:00:00Z")}
+		summary := newPassSummary()
+		assert.Equal(t, candidates[:1], u.filterPromotion(candidates, summary, now))
+		assert.Equal(t, `Cluster "staging" has not proven release "1.13.0"`, summary.results["inst-unproven"])
+	})
+	t.Run("soaking", func(t *testing.T) {
+		u := updater{cluster: "prod", promotionParameter: "/promotion-policy", ssm: mockSSM(policy), ecs: mockECS("2024-03-02T00:00:00Z")}
+		summary := newPassSummary()
+		assert.Empty(t, u.filterPromotion(candidates, summary, now))
+		assert.Equal(t, `Cluster "staging" has only run release "1.12.0" for 12h0m0s of 24h0m0s`, summary.results["inst-soaked"])
+	})
+	t.Run("cluster not in policy", func(t *testing.T) {
+		u := updater{cluster: "dev", promotionParameter: "/promotion-policy", ssm: mockSSM(policy), ecs: MockECS{}}
+		assert.Equal(t, candidates, u.filterPromotion(candidates, newPassSummary(), now))
+	})
+	t.Run("policy unreadable", func(t *testing.T) {
+		u := updater{cluster: "prod", promotionParameter: "/promotion-policy", ssm: MockSSM{
+			GetParameterFn: func(_ *ssm.GetParameterInput) (*ssm.GetParameterOutput, error) {
+				return nil, errors.New("access denied")
+			},
+		}}
+		summary := newPassSummary()
+		assert.Empty(t, u.filterPromotion(candidates, summary, now))
+		assert.Equal(t, 2, summary.skipped)
+		assert.Contains(t, summary.results["inst-soaked"], "access denied")
+	})
+}
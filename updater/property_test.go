@@ -0,0 +1,167 @@
+package main
+
+import (
+	"fmt"
+	"math/rand"
+	"reflect"
+	"testing"
+	"testing/quick"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+// propertyChecks is how many random clusters each property is checked against.
+const propertyChecks = 100
+
+// propertyCase is a random simulated cluster and the policy of a pass over it.
+type propertyCase struct {
+	Scenario     scenario
+	MaxUpdates   int
+	MaxUnhealthy float64
+	RebootOnly   bool
+}
+
+// Generate implements quick.Generator. The clusters mix Bottlerocket and
+// other instances, instances with and without updates, service and
+// standalone tasks, offline SSM agents, disconnected ECS agents, and failures
+// of every step of an update.
+func (propertyCase) Generate(r *rand.Rand, _ int) reflect.Value {
+	c := propertyCase{
+		Scenario:     scenario{Version: scenarioVersion, Cluster: defaultSimCluster},
+		MaxUpdates:   r.Intn(4),
+		MaxUnhealthy: []float64{0.2, 0.5, 1}[r.Intn(3)],
+		RebootOnly:   r.Intn(5) == 0,
+	}
+	services := []string{"web", "api", "worker"}
+	failures := []string{failDrain, failCheck, failApply, failReboot}
+	instances := 1 + r.Intn(8)
+	for i := 0; i < instances; i++ {
+		inst := scenarioInstance{
+			ID:        fmt.Sprintf("i-%017d", i),
+			Version:   "1.19.0",
+			DrainTime: time.Duration(1+r.Intn(10)) * time.Minute,
+		}
+		if r.Intn(6) > 0 {
+			inst.Variant = "aws-ecs-2"
+		}
+		if r.Intn(3) > 0 {
+			inst.UpdateVersion = "1.20.0"
+		}
+		for n := r.Intn(4); n > 0; n-- {
+			inst.Services = append(inst.Services, services[r.Intn(len(services))])
+		}
+		if r.Intn(6) == 0 {
+			inst.StandaloneTasks = 1
+		}
+		if r.Intn(4) == 0 {
+			inst.Fail = []string{failures[r.Intn(len(failures))]}
+		}
+		inst.SSM.Offline = r.Intn(10) == 0
+		inst.AgentDisconnected = r.Intn(10) == 0
+		c.Scenario.Instances = append(c.Scenario.Instances, inst)
+	}
+	return reflect.ValueOf(c)
+}
+
+// propertyConfig generates the same cases on every run, so that a failing
+// case can be reproduced.
+func propertyConfig() *quick.Config {
+	return &quick.Config{MaxCount: propertyChecks, Rand: rand.New(rand.NewSource(1))}
+}
+
+// simulatePass runs a pass over the simulated cluster of the case with the
+// case's policy, and fails the test if the pass does not terminate.
+func simulatePass(t *testing.T, c propertyCase) (*simulation, error) {
+	sim := newSimulation(c.Scenario)
+	sleep = sim.sleep
+	u := sim.updater()
+	u.maxUpdates = c.MaxUpdates
+	u.maxUnhealthy = c.MaxUnhealthy
+	u.rebootOnly = c.RebootOnly
+	done := make(chan error, 1)
+	go func() { done <- run(u) }()
+	select {
+	case err := <-done:
+		return sim, err
+	case <-time.After(time.Minute):
+		t.Fatalf("pass did not terminate: %+v", c)
+		return nil, nil
+	}
+}
+
+// drainedInstances returns the instances the pass set to DRAINING, and the
+// largest number of instances that were DRAINING at the same time.
+func drainedInstances(sim *simulation) (map[string]bool, int) {
+	drained := make(map[string]bool)
+	draining := make(map[string]bool)
+	concurrent := 0
+	for _, event := range sim.events {
+		switch event.message {
+		case "container instance state changed to DRAINING":
+			drained[event.instanceID] = true
+			draining[event.instanceID] = true
+			if len(draining) > concurrent {
+				concurrent = len(draining)
+			}
+		case "container instance state changed to ACTIVE":
+			delete(draining, event.instanceID)
+		}
+	}
+	return drained, concurrent
+}
+
+func TestPropertyPassInvariants(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+
+	property := func(c propertyCase) bool {
+		sim, err := simulatePass(t, c)
+		drained, concurrent := drainedInstances(sim)
+		unhealthy := 0
+		for _, inst := range c.Scenario.Instances {
+			if inst.AgentDisconnected {
+				unhealthy++
+			}
+		}
+		var violations []string
+		if concurrent > 1 {
+			violations = append(violations, fmt.Sprintf("%d instances were DRAINING at the same time", concurrent))
+		}
+		if c.MaxUpdates > 0 && len(drained) > c.MaxUpdates {
+			violations = append(violations, fmt.Sprintf("%d instances were drained, more than the limit of %d", len(drained), c.MaxUpdates))
+		}
+		if float64(unhealthy)/float64(len(c.Scenario.Instances)) > c.MaxUnhealthy && (len(drained) > 0 || err == nil) {
+			violations = append(violations, "the pass went ahead although the cluster was unhealthy")
+		}
+		for _, inst := range c.Scenario.Instances {
+			eligible := inst.Variant != "" && inst.StandaloneTasks == 0 && !inst.SSM.Offline && !sim.byID[inst.ID].fails(failCheck) &&
+				(inst.UpdateVersion != "" || c.RebootOnly)
+			if drained[inst.ID] && !eligible {
+				violations = append(violations, fmt.Sprintf("instance %s was drained although it is not eligible", inst.ID))
+			}
+			if status := sim.byID[inst.ID].status; status != "ACTIVE" {
+				violations = append(violations, fmt.Sprintf("instance %s was left %s", inst.ID, status))
+			}
+		}
+		for _, violation := range violations {
+			t.Logf("%s: %+v", violation, c)
+		}
+		return len(violations) == 0
+	}
+	require.NoError(t, quick.Check(property, propertyConfig()))
+}
+
+func TestPropertyPassDeterministic(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+
+	property := func(c propertyCase) bool {
+		first, firstErr := simulatePass(t, c)
+		second, secondErr := simulatePass(t, c)
+		return assert.Equal(t, first.events, second.events, "passes over the same cluster should have the same timeline: %+v", c) &&
+			assert.Equal(t, fmt.Sprint(firstErr), fmt.Sprint(secondErr))
+	}
+	require.NoError(t, quick.Check(property, propertyConfig()))
+}
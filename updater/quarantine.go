@@ -0,0 +1,141 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"log"
+	"regexp"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+const (
+	// tagQuarantineUntil records when the quarantine of a container instance
+	// expires, in RFC 3339 format.
+	tagQuarantineUntil = "bottlerocket-ecs-updater:quarantine-until"
+	// tagQuarantineReason records why a container instance is quarantined.
+	tagQuarantineReason = "bottlerocket-ecs-updater:quarantine-reason"
+)
+
+// quarantineReasonPattern matches the characters ECS allows in tag values.
+var quarantineReasonPattern = regexp.MustCompile(`^[\p{L}\p{Z}\p{N}_.:/=+\-@]{1,256}$`)
+
+// quarantine excludes an instance from updates until it expires.
+type quarantine struct {
+	reason string
+	// until is when the quarantine expires; it is zero when the expiry
+	// recorded on the instance is invalid, which keeps the instance
+	// quarantined until the tag is fixed or removed.
+	until time.Time
+}
+
+// expired returns whether the quarantine has expired at now.
+func (q *quarantine) expired(now time.Time) bool {
+	return !q.until.IsZero() && !now.Before(q.until)
+}
+
+// quarantineFromTags reads the quarantine recorded in the tags of a container
+// instance. It returns nil when the instance is not quarantined.
+func quarantineFromTags(tags []*ecs.Tag) (*quarantine, error) {
+	var until, reason string
+	for _, tag := range tags {
+		switch aws.StringValue(tag.Key) {
+		case tagQuarantineUntil:
+			until = aws.StringValue(tag.Value)
+		case tagQuarantineReason:
+			reason = aws.StringValue(tag.Value)
+		}
+	}
+	if until == "" {
+		return nil, nil
+	}
+	q := &quarantine{reason: reason}
+	expiry, err := time.Parse(time.RFC3339, until)
+	if err != nil {
+		return q, fmt.Errorf("invalid quarantine expiry %q: %w", until, err)
+	}
+	q.until = expiry
+	return q, nil
+}
+
+// filterQuarantined returns the candidates that are not quarantined, recording
+// the others as skipped in the summary. Expired quarantines no longer apply.
+func filterQuarantined(candidates []instance, summary *passSummary, now time.Time) []instance {
+	ready := make([]instance, 0, len(candidates))
+	for _, inst := range candidates {
+		q := inst.quarantine
+		if q == nil || q.expired(now) {
+			ready = append(ready, inst)
+			continue
+		}
+		if q.until.IsZero() {
+			log.Printf("Instance %#q is quarantined without a valid expiry, therefore not updating it: %s", inst, q.reason)
+			summary.skippedInstance(inst.instanceID, skipQuarantined, fmt.Sprintf("Quarantined without a valid expiry: %s", q.reason))
+			continue
+		}
+		log.Printf("Instance %#q is quarantined until %s, therefore not updating it: %s", inst, q.until.Format(time.RFC3339), q.reason)
+		summary.skippedInstance(inst.instanceID, skipQuarantined, fmt.Sprintf("Quarantined until %s: %s", q.until.Format(time.RFC3339), q.reason))
+	}
+	return ready
+}
+
+// clearExpiredQuarantines removes the tags of the quarantines of the instances
+// that have expired, and warns about each one so that an instance returning to
+// updates does not go unnoticed. In check-only mode the tags are left in place.
+func (u *updater) clearExpiredQuarantines(instances []instance, now time.Time, checkOnly bool) {
+	for _, inst := range instances {
+		q := inst.quarantine
+		if q == nil || !q.expired(now) {
+			continue
+		}
+		log.Printf("Quarantine of instance %#q expired at %s; it is updated again (reason was: %s)", inst, q.until.Format(time.RFC3339), q.reason)
+		u.warnings.add(warnQuarantineExpired, "quarantine of instance %q expired at %s: %s", inst.instanceID, q.until.Format(time.RFC3339), q.reason)
+		if checkOnly {
+			continue
+		}
+		_, err := u.ecs.UntagResource(&ecs.UntagResourceInput{
+			ResourceArn: aws.String(inst.containerInstanceID),
+			TagKeys:     aws.StringSlice([]string{tagQuarantineUntil, tagQuarantineReason}),
+		})
+		if err != nil {
+			log.Printf("Failed to clear the expired quarantine of instance %#q: %v", inst, err)
+			u.warnings.add(warnDegraded, "expired quarantine of instance %q was not cleared: %v", inst.instanceID, err)
+		}
+	}
+}
+
+// quarantineInstance quarantines the container instance of the EC2 instance
+// until the given time by tagging it, so that passes skip it until then.
+func (u *updater) quarantineInstance(instanceID string, reason string, until time.Time) error {
+	if !quarantineReasonPattern.MatchString(reason) {
+		return errors.New("quarantine reason must be 1 to 256 letters, numbers, spaces or _.:/=+-@ characters")
+	}
+	var containerInstances []*string
+	err := u.ecs.ListContainerInstancesPages(&ecs.ListContainerInstancesInput{
+		Cluster: &u.cluster,
+		Filter:  aws.String(fmt.Sprintf("ec2InstanceId == %s", instanceID)),
+	}, func(output *ecs.ListContainerInstancesOutput, _ bool) bool {
+		containerInstances = append(containerInstances, output.ContainerInstanceArns...)
+		return true
+	})
+	if err != nil {
+		return fmt.Errorf("failed to find the container instance of instance %q: %w", instanceID, err)
+	}
+	if len(containerInstances) != 1 {
+		return fmt.Errorf("found %d container instance(s) of instance %q in cluster %q, expected 1", len(containerInstances), instanceID, u.cluster)
+	}
+	_, err = u.ecs.TagResource(&ecs.TagResourceInput{
+		ResourceArn: containerInstances[0],
+		Tags: []*ecs.Tag{
+			{Key: aws.String(tagQuarantineUntil), Value: aws.String(until.UTC().Format(time.RFC3339))},
+			{Key: aws.String(tagQuarantineReason), Value: aws.String(reason)},
+		},
+	})
+	if err != nil {
+		return fmt.Errorf("failed to tag container instance %q: %w", aws.StringValue(containerInstances[0]), err)
+	}
+	log.Printf("Quarantined instance %q until %s: %s", instanceID, until.UTC().Format(time.RFC3339), reason)
+	return nil
+}
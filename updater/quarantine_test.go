@@ -0,0 +1,168 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestQuarantineFromTags(t *testing.T) {
+	until := time.Date(2024, 3, 1, 0, 0, 0, 0, time.UTC)
+	cases := []struct {
+		name          string
+		tags          []*ecs.Tag
+		expected      *quarantine
+		expectedError string
+	}{
+		{
+			name: "not quarantined",
+			tags: []*ecs.Tag{{Key: aws.String("team"), Value: aws.String("web")}},
+		},
+		{
+			name: "quarantined",
+			tags: []*ecs.Tag{
+				{Key: aws.String(tagQuarantineUntil), Value: aws.String("2024-03-01T00:00:00Z")},
+				{Key: aws.String(tagQuarantineReason), Value: aws.String("kernel panic")},
+			},
+			expected: &quarantine{reason: "kernel panic", until: until},
+		},
+		{
+			name: "invalid expiry",
+			tags: []*ecs.Tag{
+				{Key: aws.String(tagQuarantineUntil), Value: aws.String("next week")},
+				{Key: aws.String(tagQuarantineReason), Value: aws.String("kernel panic")},
+			},
+			expected:      &quarantine{reason: "kernel panic"},
+			expectedError: "invalid quarantine expiry",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			q, err := quarantineFromTags(tc.tags)
+			if tc.expectedError == "" {
+				require.NoError(t, err)
+			} else {
+				require.Error(t, err)
+				assert.Contains(t, err.Error(), tc.expectedError)
+			}
+			assert.Equal(t, tc.expected, q)
+		})
+	}
+}
+
+func TestFilterQuarantined(t *testing.T) {
+	now := time.Date(2024, 3, 1, 0, 0, 0, 0, time.UTC)
+	candidates := []instance{
+		{instanceID: "inst-id-1"},
+		{instanceID: "inst-id-2", quarantine: &quarantine{reason: "kernel panic", until: now.Add(time.Hour)}},
+		{instanceID: "inst-id-3", quarantine: &quarantine{reason: "expired", until: now}},
+		{instanceID: "inst-id-4", quarantine: &quarantine{reason: "invalid"}},
+	}
+	summary := newPassSummary()
+	ready := filterQuarantined(candidates, summary, now)
+	assert.Equal(t, []instance{candidates[0], candidates[2]}, ready)
+	assert.Equal(t, map[string]string{
+		"inst-id-2": "Quarantined until 2024-03-01T01:00:00Z: kernel panic",
+		"inst-id-4": "Quarantined without a valid expiry: invalid",
+	}, summary.results)
+}
+
+func TestClearExpiredQuarantines(t *testing.T) {
+	now := time.Date(2024, 3, 1, 0, 0, 0, 0, time.UTC)
+	instances := []instance{
+		{instanceID: "inst-id-1", containerInstanceID: "cont-inst-1", quarantine: &quarantine{reason: "kernel panic", until: now.Add(time.Hour)}},
+		{instanceID: "inst-id-2", containerInstanceID: "cont-inst-2", quarantine: &quarantine{reason: "expired", until: now.Add(-time.Hour)}},
+		{instanceID: "inst-id-3", containerInstanceID: "cont-inst-3"},
+	}
+	t.Run("clears expired", func(t *testing.T) {
+		var untagged []string
+		mockECS := MockECS{
+			UntagResourceFn: func(input *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+				untagged = append(untagged, aws.StringValue(input.ResourceArn))
+				assert.Equal(t, []string{tagQuarantineUntil, tagQuarantineReason}, aws.StringValueSlice(input.TagKeys))
+				return &ecs.UntagResourceOutput{}, nil
+			},
+		}
+		u := updater{ecs: mockECS, warnings: newPassWarnings()}
+		u.clearExpiredQuarantines(instances, now, false)
+		assert.Equal(t, []string{"cont-inst-2"}, untagged)
+		assert.Equal(t, 1, u.warnings.counts[warnQuarantineExpired])
+	})
+	t.Run("check-only", func(t *testing.T) {
+		u := updater{ecs: MockECS{}, warnings: newPassWarnings()}
+		u.clearExpiredQuarantines(instances, now, true)
+		assert.Equal(t, 1, u.warnings.counts[warnQuarantineExpired], "should warn without clearing the quarantine")
+	})
+	t.Run("untag fails", func(t *testing.T) {
+		mockECS := MockECS{
+			UntagResourceFn: func(_ *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+				return nil, errors.New("access denied")
+			},
+		}
+		u := updater{ecs: mockECS, warnings: newPassWarnings()}
+		u.clearExpiredQuarantines(instances, now, false)
+		assert.Equal(t, 1, u.warnings.counts[warnQuarantineExpired])
+		assert.Equal(t, 1, u.warnings.counts[warnDegraded])
+	})
+}
+
+func TestQuarantineInstance(t *testing.T) {
+	until := time.Date(2024, 3, 1, 0, 0, 0, 0, time.UTC)
+	cases := []struct {
+		name          string
+		reason        string
+		arns          []string
+		expectedError string
+	}{
+		{
+			name:   "success",
+			reason: "kernel panic",
+			arns:   []string{"cont-inst-1"},
+		},
+		{
+			name:          "invalid reason",
+			reason:        "kernel panic; see ticket #123",
+			expectedError: "quarantine reason must be",
+		},
+		{
+			name:          "not in cluster",
+			reason:        "kernel panic",
+			expectedError: "found 0 container instance(s)",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			var tagged *ecs.TagResourceInput
+			mockECS := MockECS{
+				ListContainerInstancesPagesFn: func(input *ecs.ListContainerInstancesInput, fn func(*ecs.ListContainerInstancesOutput, bool) bool) error {
+					assert.Equal(t, "ec2InstanceId == i-1", aws.StringValue(input.Filter))
+					fn(&ecs.ListContainerInstancesOutput{ContainerInstanceArns: aws.StringSlice(tc.arns)}, true)
+					return nil
+				},
+				TagResourceFn: func(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+					tagged = input
+					return &ecs.TagResourceOutput{}, nil
+				},
+			}
+			u := updater{ecs: mockECS, cluster: "cluster"}
+			err := u.quarantineInstance("i-1", tc.reason, until)
+			if tc.expectedError != "" {
+				require.Error(t, err)
+				assert.Contains(t, err.Error(), tc.expectedError)
+				assert.Nil(t, tagged)
+				return
+			}
+			require.NoError(t, err)
+			require.NotNil(t, tagged)
+			assert.Equal(t, "cont-inst-1", aws.StringValue(tagged.ResourceArn))
+			q, err := quarantineFromTags(tagged.Tags)
+			require.NoError(t, err)
+			assert.Equal(t, &quarantine{reason: "kernel panic", until: until}, q)
+		})
+	}
+}
@@ -0,0 +1,61 @@
+package main
+
+import (
+	"sort"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+// readinessWeights controls how much each signal contributes to an instance's
+// readiness score; instances with lower scores are updated first.
+type readinessWeights struct {
+	// taskCount is the weight of each running or pending task on the instance.
+	taskCount float64
+	// utilization is the weight of the instance's CPU and memory utilization,
+	// which ranges from 0 for an idle instance to 1 for a full one.
+	utilization float64
+}
+
+// defaultReadinessWeights are the weights instances are ordered by unless
+// -task-count-weight or -utilization-weight are given.
+var defaultReadinessWeights = readinessWeights{taskCount: 1, utilization: 10}
+
+// readinessScore returns a score for updating the instance; the less loaded an
+// instance is, the lower its score and the less disruptive its update.
+func readinessScore(inst instance, weights readinessWeights) float64 {
+	return weights.taskCount*float64(inst.taskCount) + weights.utilization*inst.utilization
+}
+
+// sortByReadiness orders instances so that the least loaded instances are updated first.
+func sortByReadiness(instances []instance, weights readinessWeights) {
+	sort.SliceStable(instances, func(i, j int) bool {
+		return readinessScore(instances[i], weights) < readinessScore(instances[j], weights)
+	})
+}
+
+// utilization returns the highest utilization of CPU and memory on the container instance.
+func utilization(containerInstance *ecs.ContainerInstance) float64 {
+	highest := 0.0
+	for _, name := range []string{"CPU", "MEMORY"} {
+		registered := resourceValue(containerInstance.RegisteredResources, name)
+		if registered <= 0 {
+			continue
+		}
+		used := float64(registered-resourceValue(containerInstance.RemainingResources, name)) / float64(registered)
+		if used > highest {
+			highest = used
+		}
+	}
+	return highest
+}
+
+// resourceValue returns the integer value of the named resource.
+func resourceValue(resources []*ecs.Resource, name string) int64 {
+	for _, resource := range resources {
+		if aws.StringValue(resource.Name) == name {
+			return aws.Int64Value(resource.IntegerValue)
+		}
+	}
+	return 0
+}
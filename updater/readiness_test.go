@@ -0,0 +1,60 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+)
+
+func TestSortByReadiness(t *testing.T) {
+	instances := []instance{
+		{instanceID: "busy", taskCount: 8, utilization: 0.9},
+		{instanceID: "idle", taskCount: 0, utilization: 0},
+		{instanceID: "few-large-tasks", taskCount: 2, utilization: 0.95},
+		{instanceID: "many-small-tasks", taskCount: 6, utilization: 0.2},
+	}
+
+	t.Run("default weights", func(t *testing.T) {
+		sorted := append([]instance{}, instances...)
+		sortByReadiness(sorted, readinessWeights{taskCount: 1, utilization: 10})
+		assert.Equal(t, []string{"idle", "many-small-tasks", "few-large-tasks", "busy"}, instanceIDs(sorted))
+	})
+	t.Run("task count only", func(t *testing.T) {
+		sorted := append([]instance{}, instances...)
+		sortByReadiness(sorted, readinessWeights{taskCount: 1})
+		assert.Equal(t, []string{"idle", "few-large-tasks", "many-small-tasks", "busy"}, instanceIDs(sorted))
+	})
+	t.Run("zero weights keep order", func(t *testing.T) {
+		sorted := append([]instance{}, instances...)
+		sortByReadiness(sorted, readinessWeights{})
+		assert.Equal(t, instanceIDs(instances), instanceIDs(sorted))
+	})
+}
+
+func TestUtilization(t *testing.T) {
+	resources := func(cpu, memory int64) []*ecs.Resource {
+		return []*ecs.Resource{
+			{Name: aws.String("CPU"), IntegerValue: aws.Int64(cpu)},
+			{Name: aws.String("MEMORY"), IntegerValue: aws.Int64(memory)},
+		}
+	}
+	assert.Equal(t, 0.0, utilization(&ecs.ContainerInstance{}))
+	assert.Equal(t, 0.75, utilization(&ecs.ContainerInstance{
+		RegisteredResources: resources(4096, 8000),
+		RemainingResources:  resources(1024, 6000),
+	}))
+	assert.Equal(t, 0.5, utilization(&ecs.ContainerInstance{
+		RegisteredResources: resources(4096, 8000),
+		RemainingResources:  resources(4096, 4000),
+	}))
+}
+
+func instanceIDs(instances []instance) []string {
+	ids := make([]string, 0, len(instances))
+	for _, inst := range instances {
+		ids = append(ids, inst.instanceID)
+	}
+	return ids
+}
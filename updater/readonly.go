@@ -0,0 +1,127 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+// errReadOnly is returned in place of every call a read-only updater makes
+// that would change the cluster, its instances or any other resource.
+var errReadOnly = errors.New("not allowed in read-only mode")
+
+// refuseWrite returns the error for a call to the operation in read-only mode.
+func refuseWrite(operation string) error {
+	return fmt.Errorf("%s: %w", operation, errReadOnly)
+}
+
+// makeReadOnly replaces the updater's clients with read-only ones, so that the
+// pass can only observe and report. Each read-only client holds the read half
+// of the client it replaces and nothing else, so none of its writes can reach
+// AWS whatever the code that calls it does. The pass also runs as if
+// -check-only were given, so it doesn't attempt the writes in the first place.
+func (u *updater) makeReadOnly() {
+	u.readOnly = true
+	if u.ecs != nil {
+		u.ecs = readOnlyECS{u.ecs}
+	}
+	if u.ssm != nil {
+		u.ssm = readOnlySSM{SSMReadAPI: u.ssm, checks: u.ssm, checkDocument: u.checkDocument}
+	}
+	if u.autoscaling != nil {
+		u.autoscaling = readOnlyAutoScaling{u.autoscaling}
+	}
+}
+
+// readOnlyECS refuses the writes of an ECS client.
+type readOnlyECS struct {
+	ECSReadAPI
+}
+
+var _ ECSAPI = readOnlyECS{}
+
+func (readOnlyECS) UpdateContainerInstancesState(_ *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+	return nil, refuseWrite("UpdateContainerInstancesState")
+}
+
+func (readOnlyECS) TagResource(_ *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+	return nil, refuseWrite("TagResource")
+}
+
+func (readOnlyECS) UntagResource(_ *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+	return nil, refuseWrite("UntagResource")
+}
+
+func (readOnlyECS) StopTask(_ *ecs.StopTaskInput) (*ecs.StopTaskOutput, error) {
+	return nil, refuseWrite("StopTask")
+}
+
+func (readOnlyECS) StartTask(_ *ecs.StartTaskInput) (*ecs.StartTaskOutput, error) {
+	return nil, refuseWrite("StartTask")
+}
+
+// updateChecker sends the commands of the update check document.
+type updateChecker interface {
+	SendCommand(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error)
+}
+
+// readOnlySSM refuses the writes of an SSM client, except for sending the
+// update check document, which only reads the state of the instances and
+// without which there would be nothing to report.
+type readOnlySSM struct {
+	SSMReadAPI
+	checks        updateChecker
+	checkDocument string
+}
+
+var _ SSMAPI = readOnlySSM{}
+
+func (m readOnlySSM) SendCommand(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+	if m.checkDocument == "" || aws.StringValue(input.DocumentName) != m.checkDocument {
+		return nil, refuseWrite(fmt.Sprintf("SendCommand of document %q", aws.StringValue(input.DocumentName)))
+	}
+	return m.checks.SendCommand(input)
+}
+
+func (readOnlySSM) PutParameter(_ *ssm.PutParameterInput) (*ssm.PutParameterOutput, error) {
+	return nil, refuseWrite("PutParameter")
+}
+
+func (readOnlySSM) DeleteParameter(_ *ssm.DeleteParameterInput) (*ssm.DeleteParameterOutput, error) {
+	return nil, refuseWrite("DeleteParameter")
+}
+
+func (readOnlySSM) AddTagsToResource(_ *ssm.AddTagsToResourceInput) (*ssm.AddTagsToResourceOutput, error) {
+	return nil, refuseWrite("AddTagsToResource")
+}
+
+// readOnlyAutoScaling refuses the writes of an Auto Scaling client.
+type readOnlyAutoScaling struct {
+	AutoScalingReadAPI
+}
+
+var _ AutoScalingAPI = readOnlyAutoScaling{}
+
+func (readOnlyAutoScaling) SuspendProcesses(_ *autoscaling.ScalingProcessQuery) (*autoscaling.SuspendProcessesOutput, error) {
+	return nil, refuseWrite("SuspendProcesses")
+}
+
+func (readOnlyAutoScaling) ResumeProcesses(_ *autoscaling.ScalingProcessQuery) (*autoscaling.ResumeProcessesOutput, error) {
+	return nil, refuseWrite("ResumeProcesses")
+}
+
+func (readOnlyAutoScaling) CreateOrUpdateTags(_ *autoscaling.CreateOrUpdateTagsInput) (*autoscaling.CreateOrUpdateTagsOutput, error) {
+	return nil, refuseWrite("CreateOrUpdateTags")
+}
+
+func (readOnlyAutoScaling) DeleteTags(_ *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error) {
+	return nil, refuseWrite("DeleteTags")
+}
+
+func (readOnlyAutoScaling) SetDesiredCapacity(_ *autoscaling.SetDesiredCapacityInput) (*autoscaling.SetDesiredCapacityOutput, error) {
+	return nil, refuseWrite("SetDesiredCapacity")
+}
@@ -0,0 +1,70 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestMakeReadOnly(t *testing.T) {
+	wrote := make([]string, 0)
+	mockECS := MockECS{
+		ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+			return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-1"})}, nil
+		},
+		UpdateContainerInstancesStateFn: func(_ *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+			wrote = append(wrote, "UpdateContainerInstancesState")
+			return &ecs.UpdateContainerInstancesStateOutput{}, nil
+		},
+		TagResourceFn: func(_ *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+			wrote = append(wrote, "TagResource")
+			return &ecs.TagResourceOutput{}, nil
+		},
+	}
+	mockSSM := MockSSM{
+		SendCommandFn: func(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+			wrote = append(wrote, "SendCommand "+aws.StringValue(input.DocumentName))
+			return &ssm.SendCommandOutput{Command: &ssm.Command{CommandId: aws.String("command-id")}}, nil
+		},
+		PutParameterFn: func(_ *ssm.PutParameterInput) (*ssm.PutParameterOutput, error) {
+			wrote = append(wrote, "PutParameter")
+			return &ssm.PutParameterOutput{}, nil
+		},
+	}
+	mockAutoScaling := MockAutoScaling{
+		SuspendProcessesFn: func(_ *autoscaling.ScalingProcessQuery) (*autoscaling.SuspendProcessesOutput, error) {
+			wrote = append(wrote, "SuspendProcesses")
+			return &autoscaling.SuspendProcessesOutput{}, nil
+		},
+	}
+	u := updater{ecs: mockECS, ssm: mockSSM, autoscaling: mockAutoScaling, checkDocument: "check-document"}
+	u.makeReadOnly()
+	assert.True(t, u.readOnly)
+
+	list, err := u.ecs.ListTasks(&ecs.ListTasksInput{})
+	require.NoError(t, err, "should pass reads through")
+	assert.Equal(t, []string{"task-1"}, aws.StringValueSlice(list.TaskArns))
+
+	_, err = u.ssm.SendCommand(&ssm.SendCommandInput{DocumentName: aws.String("check-document")})
+	require.NoError(t, err, "should send the update check document")
+
+	_, err = u.ecs.UpdateContainerInstancesState(&ecs.UpdateContainerInstancesStateInput{})
+	assert.True(t, errors.Is(err, errReadOnly))
+	_, err = u.ecs.TagResource(&ecs.TagResourceInput{})
+	assert.True(t, errors.Is(err, errReadOnly))
+	_, err = u.ssm.SendCommand(&ssm.SendCommandInput{DocumentName: aws.String("apply-document")})
+	assert.True(t, errors.Is(err, errReadOnly))
+	assert.Contains(t, err.Error(), `"apply-document"`)
+	_, err = u.ssm.PutParameter(&ssm.PutParameterInput{})
+	assert.True(t, errors.Is(err, errReadOnly))
+	_, err = u.autoscaling.SuspendProcesses(&autoscaling.ScalingProcessQuery{})
+	assert.True(t, errors.Is(err, errReadOnly))
+
+	assert.Equal(t, []string{"SendCommand check-document"}, wrote, "should only send the update check document")
+}
@@ -0,0 +1,34 @@
+package main
+
+import (
+	"io"
+	"regexp"
+)
+
+// accountIDPattern matches 12-digit AWS account IDs where they follow the
+// region of an ARN or the word account, as in error messages returned by AWS
+// APIs and in the updater's own logs. The first group is what precedes the ID.
+// Other 12-digit numbers, such as sizes or timestamps, are left alone.
+var accountIDPattern = regexp.MustCompile(`(arn:[^:\s]*:[^:\s]*:[^:\s]*:|(?i:account)(?:[ _-]?(?i:id))?[\s=:"']*)\d{12}\b`)
+
+const redactedAccountID = "************"
+
+// redactingWriter masks sensitive values before passing log output on to the
+// underlying writer.
+type redactingWriter struct {
+	w io.Writer
+}
+
+func (r redactingWriter) Write(p []byte) (int, error) {
+	if _, err := r.w.Write(redact(p)); err != nil {
+		return 0, err
+	}
+	// report the length of the original input so callers do not treat the
+	// redacted write as a short write.
+	return len(p), nil
+}
+
+// redact masks AWS account IDs in the given log output.
+func redact(p []byte) []byte {
+	return accountIDPattern.ReplaceAll(p, []byte("${1}"+redactedAccountID))
+}
@@ -0,0 +1,62 @@
+package main
+
+import (
+	"bytes"
+	"log"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestRedact(t *testing.T) {
+	cases := []struct {
+		name     string
+		input    string
+		expected string
+	}{
+		{
+			name:     "cluster arn",
+			input:    "Listing active container instances in cluster \"arn:aws:ecs:us-west-2:123456789012:cluster/test\"",
+			expected: "Listing active container instances in cluster \"arn:aws:ecs:us-west-2:************:cluster/test\"",
+		},
+		{
+			name:     "error message",
+			input:    "AccessDeniedException: User: arn:aws:sts::123456789012:assumed-role/updater is not authorized",
+			expected: "AccessDeniedException: User: arn:aws:sts::************:assumed-role/updater is not authorized",
+		},
+		{
+			name:     "account",
+			input:    "container instance is in account \"210987654321\", but the updater runs in account=123456789012 (accountId: 123456789012)",
+			expected: "container instance is in account \"************\", but the updater runs in account=************ (accountId: ************)",
+		},
+		{
+			name:     "other 12-digit number",
+			input:    "Volume of 107374182400 bytes created at 171000000000",
+			expected: "Volume of 107374182400 bytes created at 171000000000",
+		},
+		{
+			name:     "instance id",
+			input:    "Bottlerocket instance \"i-0123456789abcdef0\" detected.",
+			expected: "Bottlerocket instance \"i-0123456789abcdef0\" detected.",
+		},
+		{
+			name:     "longer number",
+			input:    "value 1234567890123 is not an account id",
+			expected: "value 1234567890123 is not an account id",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			assert.Equal(t, tc.expected, string(redact([]byte(tc.input))))
+		})
+	}
+}
+
+func TestRedactingWriter(t *testing.T) {
+	var buf bytes.Buffer
+	logger := log.New(redactingWriter{w: &buf}, "", 0)
+	logger.Printf("Failed to drain instance %q", "arn:aws:ecs:us-west-2:123456789012:container-instance/test/abc")
+	require.NotEmpty(t, buf.String())
+	assert.Equal(t, "Failed to drain instance \"arn:aws:ecs:us-west-2:************:container-instance/test/abc\"\n", buf.String())
+}
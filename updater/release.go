@@ -0,0 +1,86 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+// release identifies the Bottlerocket release an instance would update to, as
+// reported in the chosen_update of the update check output.
+type release struct {
+	Arch    string `json:"arch"`
+	Version string `json:"version"`
+	Variant string `json:"variant"`
+}
+
+// parameter returns the name of the public SSM parameter holding the release's
+// AMI ID. The parameter is created when the release is published. apiclient
+// reports ARM instances as aarch64, while the parameters name them arm64.
+func (r release) parameter() string {
+	arch := r.Arch
+	if arch == "aarch64" {
+		arch = "arm64"
+	}
+	return fmt.Sprintf("/aws/service/bottlerocket/%s/%s/%s/image_id", r.Variant, arch, r.Version)
+}
+
+// releasePublished returns when the release was published, based on the last
+// modification time of its public SSM parameter.
+func (u *updater) releasePublished(r release) (time.Time, error) {
+	name := r.parameter()
+	resp, err := u.ssm.GetParameter(&ssm.GetParameterInput{Name: aws.String(name)})
+	if err != nil {
+		return time.Time{}, fmt.Errorf("failed to get parameter %q: %w", name, err)
+	}
+	if resp.Parameter == nil || resp.Parameter.LastModifiedDate == nil {
+		return time.Time{}, fmt.Errorf("parameter %q has no modification time", name)
+	}
+	return aws.TimeValue(resp.Parameter.LastModifiedDate), nil
+}
+
+// filterReleaseAge returns the candidates whose update was published at least
+// minReleaseAge before now, recording the others as skipped in the summary.
+// Candidates whose release age can't be determined are skipped too.
+func (u *updater) filterReleaseAge(candidates []instance, summary *passSummary, now time.Time) []instance {
+	if u.minReleaseAge <= 0 {
+		return candidates
+	}
+	type lookup struct {
+		published time.Time
+		err       error
+	}
+	lookups := make(map[release]lookup)
+	ready := make([]instance, 0, len(candidates))
+	for _, inst := range candidates {
+		if inst.targetRelease.Version == "" {
+			log.Printf("Update check for instance %#q did not report the release it would update to, therefore not updating it", inst)
+			summary.skippedInstance(inst.instanceID, skipReleaseUnknown, "Release of available update is unknown")
+			u.warnings.add(warnSkipped, "release that instance %q would update to is unknown", inst.instanceID)
+			continue
+		}
+		l, ok := lookups[inst.targetRelease]
+		if !ok {
+			l.published, l.err = u.releasePublished(inst.targetRelease)
+			lookups[inst.targetRelease] = l
+		}
+		if l.err != nil {
+			log.Printf("Failed to determine when Bottlerocket %s was published, therefore not updating instance %#q: %v",
+				inst.targetRelease.Version, inst, l.err)
+			summary.skippedInstance(inst.instanceID, skipReleaseUnknown, fmt.Sprintf("Failed to determine release age: %v", l.err))
+			u.warnings.add(warnSkipped, "age of the release instance %q would update to is unknown: %v", inst.instanceID, l.err)
+			continue
+		}
+		if age := now.Sub(l.published); age < u.minReleaseAge {
+			log.Printf("Bottlerocket %s was published %s ago, less than the minimum release age of %s, therefore not updating instance %#q",
+				inst.targetRelease.Version, age.Round(time.Minute), u.minReleaseAge, inst)
+			summary.skippedInstance(inst.instanceID, skipReleaseAge, fmt.Sprintf("Release %s is younger than %s", inst.targetRelease.Version, u.minReleaseAge))
+			continue
+		}
+		ready = append(ready, inst)
+	}
+	return ready
+}
@@ -0,0 +1,75 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseCommandOutputChosenUpdate(t *testing.T) {
+	output, err := parseCommandOutput([]byte(`{"update_state": "Available", "chosen_update": {"arch": "x86_64", "version": "1.12.0", "variant": "aws-ecs-1"}, "active_partition": {"image": {"version": "1.11.0"}}}`))
+	require.NoError(t, err)
+	require.NotNil(t, output.ChosenUpdate)
+	assert.Equal(t, release{Arch: "x86_64", Version: "1.12.0", Variant: "aws-ecs-1"}, *output.ChosenUpdate)
+	assert.Equal(t, "/aws/service/bottlerocket/aws-ecs-1/x86_64/1.12.0/image_id", output.ChosenUpdate.parameter())
+
+	output, err = parseCommandOutput([]byte(`{"update_state": "Available", "chosen_update": {"arch": "aarch64", "version": "1.12.0", "variant": "aws-ecs-1"}, "active_partition": {"image": {"version": "1.11.0"}}}`))
+	require.NoError(t, err)
+	require.NotNil(t, output.ChosenUpdate)
+	assert.Equal(t, "/aws/service/bottlerocket/aws-ecs-1/arm64/1.12.0/image_id", output.ChosenUpdate.parameter(), "ARM parameters are named arm64")
+}
+
+func TestFilterReleaseAge(t *testing.T) {
+	now := time.Date(2023, 6, 1, 0, 0, 0, 0, time.UTC)
+	oldRelease := release{Arch: "x86_64", Version: "1.11.0", Variant: "aws-ecs-1"}
+	newRelease := release{Arch: "x86_64", Version: "1.12.0", Variant: "aws-ecs-1"}
+	missingRelease := release{Arch: "x86_64", Version: "1.13.0", Variant: "aws-ecs-1"}
+	published := map[string]time.Time{
+		oldRelease.parameter(): now.Add(-72 * time.Hour),
+		newRelease.parameter(): now.Add(-time.Hour),
+	}
+	candidates := []instance{
+		{instanceID: "inst-old-1", targetRelease: oldRelease},
+		{instanceID: "inst-new", targetRelease: newRelease},
+		{instanceID: "inst-old-2", targetRelease: oldRelease},
+		{instanceID: "inst-missing", targetRelease: missingRelease},
+		{instanceID: "inst-unknown"},
+	}
+	getParameterCalls := 0
+	mockSSM := MockSSM{
+		GetParameterFn: func(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error) {
+			getParameterCalls++
+			date, ok := published[aws.StringValue(input.Name)]
+			if !ok {
+				return nil, errors.New("parameter not found")
+			}
+			return &ssm.GetParameterOutput{Parameter: &ssm.Parameter{
+				Name:             input.Name,
+				LastModifiedDate: aws.Time(date),
+			}}, nil
+		},
+	}
+
+	t.Run("disabled", func(t *testing.T) {
+		u := updater{ssm: MockSSM{}}
+		summary := newPassSummary()
+		assert.Equal(t, candidates, u.filterReleaseAge(candidates, summary, now))
+		assert.Zero(t, summary.skipped)
+	})
+	t.Run("enabled", func(t *testing.T) {
+		u := updater{ssm: mockSSM, minReleaseAge: 48 * time.Hour}
+		summary := newPassSummary()
+		ready := u.filterReleaseAge(candidates, summary, now)
+		assert.Equal(t, []instance{candidates[0], candidates[2]}, ready)
+		assert.Equal(t, 3, getParameterCalls, "should look up each release once")
+		assert.Equal(t, 3, summary.skipped)
+		assert.Equal(t, "Release 1.12.0 is younger than 48h0m0s", summary.results["inst-new"])
+		assert.Contains(t, summary.results["inst-missing"], "Failed to determine release age")
+		assert.Equal(t, "Release of available update is unknown", summary.results["inst-unknown"])
+	})
+}
@@ -0,0 +1,341 @@
+package main
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io/fs"
+	"log"
+	"os"
+	"sort"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ec2"
+)
+
+const (
+	// tagLaunchTemplateID and tagLaunchTemplateVersion are set by EC2 on
+	// instances launched from a launch template.
+	tagLaunchTemplateID      = "aws:ec2launchtemplate:id"
+	tagLaunchTemplateVersion = "aws:ec2launchtemplate:version"
+	// tagAutoScalingGroup is set by EC2 Auto Scaling on the instances it launches.
+	tagAutoScalingGroup = "aws:autoscaling:groupName"
+	// tagName is the name of an instance shown in the EC2 console.
+	tagName = "Name"
+)
+
+// fleetReport is the machine-readable record of a pass written with -report.
+type fleetReport struct {
+	Cluster   string           `json:"cluster"`
+	PassID    string           `json:"passId"`
+	ChangeRef string           `json:"changeRef,omitempty"`
+	Time      time.Time        `json:"time"`
+	Instances []reportInstance `json:"instances"`
+	// UpdaterVersion and UpdaterCommit identify the build of the updater that
+	// ran the pass.
+	UpdaterVersion string `json:"updaterVersion"`
+	UpdaterCommit  string `json:"updaterCommit"`
+	// SegmentTags are the tag keys of -segment-tags, and Segments count the
+	// instances of each segment and their outcomes.
+	SegmentTags []string       `json:"segmentTags,omitempty"`
+	Segments    []segmentCount `json:"segments,omitempty"`
+	// Skips count the skipped instances per reason, with what to do about them.
+	Skips []skipCount `json:"skips,omitempty"`
+	// Advisories count the instances running versions affected by each of the
+	// -advisories.
+	Advisories []advisoryCount `json:"advisories,omitempty"`
+	// Intents are the actions an earlier pass recorded and left half-done,
+	// found at the start of the pass with -intent-records.
+	Intents []reportIntent `json:"intents,omitempty"`
+	// Changes are the differences from the previous report given with -report-diff.
+	Changes *reportChanges `json:"changes,omitempty"`
+}
+
+// reportInstance is the state of an instance at the end of a pass.
+type reportInstance struct {
+	InstanceID    string `json:"instanceId"`
+	VersionBefore string `json:"versionBefore,omitempty"`
+	Version       string `json:"version,omitempty"`
+	Outcome       string `json:"outcome,omitempty"`
+	Result        string `json:"result,omitempty"`
+	// SkipReason is why the instance was skipped, when it was.
+	SkipReason string `json:"skipReason,omitempty"`
+	// Advisories are the IDs of the -advisories affecting the instance's version.
+	Advisories []string `json:"advisories,omitempty"`
+	// UpdateID identifies the update of the instance, when one started.
+	UpdateID string `json:"updateId,omitempty"`
+	// RawOutput is the update check output of an instance whose outcome is
+	// unparseable, with account IDs masked.
+	RawOutput string `json:"rawOutput,omitempty"`
+	// EC2 describes the EC2 instance, when it could be described.
+	EC2 *ec2Metadata `json:"ec2,omitempty"`
+	// Segment is the instance's value of each of the -segment-tags.
+	Segment map[string]string `json:"segment,omitempty"`
+}
+
+// ec2Metadata is what EC2 reports about an instance, to correlate update
+// failures with instance types, AMIs or launch templates, and to tell on-call
+// engineers which instance and Auto Scaling group are affected.
+type ec2Metadata struct {
+	ImageID               string `json:"imageId"`
+	InstanceType          string `json:"instanceType"`
+	Architecture          string `json:"architecture"`
+	LaunchTemplateID      string `json:"launchTemplateId,omitempty"`
+	LaunchTemplateVersion string `json:"launchTemplateVersion,omitempty"`
+	Name                  string `json:"name,omitempty"`
+	AutoScalingGroup      string `json:"autoScalingGroup,omitempty"`
+}
+
+// reportChanges lists what changed between two reports, so jobs that run the
+// updater on a schedule can post only the changes rather than the whole fleet.
+type reportChanges struct {
+	PreviousPassID string          `json:"previousPassId"`
+	NewInstances   []string        `json:"newInstances"`
+	GoneInstances  []string        `json:"goneInstances"`
+	VersionChanges []versionChange `json:"versionChanges"`
+	NewFailures    []string        `json:"newFailures"`
+}
+
+// versionChange is an instance that runs another version than in the previous report.
+type versionChange struct {
+	InstanceID string `json:"instanceId"`
+	From       string `json:"from"`
+	To         string `json:"to"`
+}
+
+// newFleetReport builds the report of the pass recorded in the summary, with
+// the EC2 metadata of the instances described in metadata.
+func newFleetReport(cluster string, summary *passSummary, metadata map[string]ec2Metadata, now time.Time) fleetReport {
+	instanceIDs := make([]string, 0, len(summary.results))
+	seen := make(map[string]bool)
+	for _, ids := range []map[string]string{summary.versionsBefore, summary.results} {
+		for id := range ids {
+			if !seen[id] {
+				seen[id] = true
+				instanceIDs = append(instanceIDs, id)
+			}
+		}
+	}
+	sort.Strings(instanceIDs)
+	report := fleetReport{
+		Cluster:   cluster,
+		PassID:    summary.passID,
+		ChangeRef: summary.changeRef,
+		Time:      now.UTC(),
+		Instances: make([]reportInstance, 0, len(instanceIDs)),
+		// UpdaterVersion and UpdaterCommit are set at build time.
+		UpdaterVersion: version,
+		UpdaterCommit:  commit(),
+		// SegmentTags and Segments are nil without -segment-tags.
+		SegmentTags: summary.segmentTags,
+		Segments:    summary.segmentCounts(),
+		// Intents are nil without unresolved intents.
+		Intents: summary.intents,
+	}
+	if skips := summary.skipCounts(); len(skips) > 0 {
+		report.Skips = skips
+	}
+	for _, id := range instanceIDs {
+		inst := reportInstance{
+			InstanceID:    id,
+			VersionBefore: summary.versionsBefore[id],
+			Version:       summary.versionsAfter[id],
+			Outcome:       summary.outcomes[id],
+			Result:        summary.results[id],
+			SkipReason:    summary.skipReasons[id],
+			UpdateID:      summary.updateIDs[id],
+			RawOutput:     summary.rawOutputs[id],
+			Segment:       summary.segment(id),
+		}
+		if m, ok := metadata[id]; ok {
+			inst.EC2 = &m
+		}
+		report.Instances = append(report.Instances, inst)
+	}
+	return report
+}
+
+// reportedEC2Instances returns the EC2 instance IDs among the instances of
+// the summary; instances that could not be described are only known by their
+// container instance ARN.
+func (s *passSummary) reportedEC2Instances() []string {
+	seen := make(map[string]bool)
+	instanceIDs := make([]string, 0, len(s.versionsBefore))
+	for _, ids := range []map[string]string{s.versionsBefore, s.results} {
+		for id := range ids {
+			if strings.HasPrefix(id, "i-") && !seen[id] {
+				seen[id] = true
+				instanceIDs = append(instanceIDs, id)
+			}
+		}
+	}
+	sort.Strings(instanceIDs)
+	return instanceIDs
+}
+
+// ec2Metadata describes the EC2 instances. Instances are looked up with a
+// filter rather than by ID, so instances terminated since the pass began are
+// left out instead of failing the whole call.
+func (u *updater) ec2Metadata(instanceIDs []string) (map[string]ec2Metadata, error) {
+	metadata := make(map[string]ec2Metadata)
+	_, err := eachPage(len(instanceIDs), ecsPageSize, func(start, stop int) error {
+		input := &ec2.DescribeInstancesInput{
+			Filters: []*ec2.Filter{{
+				Name:   aws.String("instance-id"),
+				Values: aws.StringSlice(instanceIDs[start:stop]),
+			}},
+		}
+		return u.ec2.DescribeInstancesPages(input, func(output *ec2.DescribeInstancesOutput, _ bool) bool {
+			for _, reservation := range output.Reservations {
+				for _, inst := range reservation.Instances {
+					m := ec2Metadata{
+						ImageID:      aws.StringValue(inst.ImageId),
+						InstanceType: aws.StringValue(inst.InstanceType),
+						Architecture: aws.StringValue(inst.Architecture),
+					}
+					for _, tag := range inst.Tags {
+						switch aws.StringValue(tag.Key) {
+						case tagLaunchTemplateID:
+							m.LaunchTemplateID = aws.StringValue(tag.Value)
+						case tagLaunchTemplateVersion:
+							m.LaunchTemplateVersion = aws.StringValue(tag.Value)
+						case tagAutoScalingGroup:
+							m.AutoScalingGroup = aws.StringValue(tag.Value)
+						case tagName:
+							m.Name = aws.StringValue(tag.Value)
+						}
+					}
+					metadata[aws.StringValue(inst.InstanceId)] = m
+				}
+			}
+			return true
+		})
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe EC2 instances: %w", err)
+	}
+	return metadata, nil
+}
+
+// diffReports lists what changed in the current report since the previous one:
+// instances that appeared or disappeared, instances that run another version,
+// and instances that failed to update without having failed in the previous report.
+func diffReports(previous fleetReport, current fleetReport) reportChanges {
+	changes := reportChanges{
+		PreviousPassID: previous.PassID,
+		NewInstances:   make([]string, 0),
+		GoneInstances:  make([]string, 0),
+		VersionChanges: make([]versionChange, 0),
+		NewFailures:    make([]string, 0),
+	}
+	before := make(map[string]reportInstance)
+	for _, inst := range previous.Instances {
+		before[inst.InstanceID] = inst
+	}
+	present := make(map[string]bool)
+	for _, inst := range current.Instances {
+		present[inst.InstanceID] = true
+		old, ok := before[inst.InstanceID]
+		if !ok {
+			changes.NewInstances = append(changes.NewInstances, inst.InstanceID)
+		} else if old.Version != "" && inst.Version != "" && old.Version != inst.Version {
+			changes.VersionChanges = append(changes.VersionChanges, versionChange{
+				InstanceID: inst.InstanceID,
+				From:       old.Version,
+				To:         inst.Version,
+			})
+		}
+		if inst.Outcome == outcomeFailed && old.Outcome != outcomeFailed {
+			changes.NewFailures = append(changes.NewFailures, inst.InstanceID)
+		}
+	}
+	for _, inst := range previous.Instances {
+		if !present[inst.InstanceID] {
+			changes.GoneInstances = append(changes.GoneInstances, inst.InstanceID)
+		}
+	}
+	return changes
+}
+
+// readFleetReport reads a report written by writeFleetReport.
+func readFleetReport(path string) (fleetReport, error) {
+	var report fleetReport
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return report, fmt.Errorf("failed to read report: %w", err)
+	}
+	if err := json.Unmarshal(data, &report); err != nil {
+		return report, fmt.Errorf("failed to parse report %q: %w", path, err)
+	}
+	return report, nil
+}
+
+// writeFleetReport writes the report to path as indented JSON.
+func writeFleetReport(path string, report fleetReport) error {
+	data, err := json.MarshalIndent(report, "", "  ")
+	if err != nil {
+		return fmt.Errorf("failed to encode report: %w", err)
+	}
+	if err := os.WriteFile(path, append(data, '\n'), 0o644); err != nil {
+		return fmt.Errorf("failed to write report: %w", err)
+	}
+	return nil
+}
+
+// redactFleetReport masks the AWS account IDs in the report, such as those in
+// the ARNs that foreign and stale container instances are keyed by, as they
+// are masked in the logs.
+func redactFleetReport(report fleetReport) (fleetReport, error) {
+	data, err := json.Marshal(report)
+	if err != nil {
+		return report, fmt.Errorf("failed to encode report: %w", err)
+	}
+	var redacted fleetReport
+	if err := json.Unmarshal(redact(data), &redacted); err != nil {
+		return report, fmt.Errorf("failed to redact report: %w", err)
+	}
+	return redacted, nil
+}
+
+// writeReport writes the report of the pass to path, with the advisories
+// affecting the instances, and the changes since the report at previousPath
+// unless previousPath is empty. A missing previous report, as on the first
+// scheduled run, leaves the changes out. AWS account IDs are masked unless
+// sensitive is set; the report is masked before it is compared, so that
+// instances keyed by ARN match those of a masked previous report.
+func writeReport(path string, previousPath string, cluster string, summary *passSummary, metadata map[string]ec2Metadata, advisories []advisory, sensitive bool, now time.Time) error {
+	report := newFleetReport(cluster, summary, metadata, now)
+	if !sensitive {
+		var err error
+		if report, err = redactFleetReport(report); err != nil {
+			return err
+		}
+	}
+	if len(advisories) > 0 {
+		report.addAdvisories(advisories)
+		affected := 0
+		for _, inst := range report.Instances {
+			if len(inst.Advisories) > 0 {
+				affected++
+			}
+		}
+		log.Printf("%d instance(s) run versions affected by %d of %d advisory(ies)", affected, len(report.Advisories), len(advisories))
+	}
+	if previousPath != "" {
+		previous, err := readFleetReport(previousPath)
+		if errors.Is(err, fs.ErrNotExist) {
+			log.Printf("No previous report at %q, therefore not listing changes", previousPath)
+			return writeFleetReport(path, report)
+		}
+		if err != nil {
+			return fmt.Errorf("cannot compare with the previous report: %w", err)
+		}
+		changes := diffReports(previous, report)
+		report.Changes = &changes
+		log.Printf("Since pass %s: %d new instance(s), %d gone, %d version change(s), %d new failure(s)", previous.PassID,
+			len(changes.NewInstances), len(changes.GoneInstances), len(changes.VersionChanges), len(changes.NewFailures))
+	}
+	return writeFleetReport(path, report)
+}
@@ -0,0 +1,219 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"os"
+	"path/filepath"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ec2"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestNewFleetReport(t *testing.T) {
+	summary := newPassSummary()
+	summary.passID = "pass-1"
+	summary.changeRef = "CHG-1234"
+	summary.recordVersions([]instance{
+		{instanceID: "inst-id-2", bottlerocketVersion: "1.19.0"},
+		{instanceID: "inst-id-1", bottlerocketVersion: "1.19.0"},
+		{instanceID: "inst-id-3"},
+	})
+	summary.updateIDs["inst-id-1"] = "update-1"
+	summary.updatedVersion("inst-id-1", "1.20.0")
+	summary.updatedInstance("inst-id-1", "Instance updated successfully")
+	summary.skippedInstance("arn-foreign", skipForeign, "Foreign instance: MISSING")
+	summary.unparseableInstance("inst-id-3", "Update check output could not be parsed", "Error: unknown subcommand")
+	assert.Equal(t, []string{"inst-id-1", "inst-id-2", "inst-id-3"}, summary.reportedEC2Instances())
+	metadata := map[string]ec2Metadata{
+		"inst-id-1": {ImageID: "ami-1", InstanceType: "m5.large", Architecture: "x86_64", LaunchTemplateID: "lt-1", LaunchTemplateVersion: "3"},
+	}
+
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	report := newFleetReport("cluster", summary, metadata, now)
+	assert.Equal(t, fleetReport{
+		Cluster:   "cluster",
+		PassID:    "pass-1",
+		ChangeRef: "CHG-1234",
+		Time:      now,
+		Instances: []reportInstance{
+			{InstanceID: "arn-foreign", Outcome: outcomeSkipped, Result: "Foreign instance: MISSING", SkipReason: skipForeign},
+			{
+				InstanceID:    "inst-id-1",
+				VersionBefore: "1.19.0",
+				Version:       "1.20.0",
+				Outcome:       outcomeUpdated,
+				Result:        "Instance updated successfully",
+				UpdateID:      "update-1",
+				EC2:           &ec2Metadata{ImageID: "ami-1", InstanceType: "m5.large", Architecture: "x86_64", LaunchTemplateID: "lt-1", LaunchTemplateVersion: "3"},
+			},
+			{InstanceID: "inst-id-2", VersionBefore: "1.19.0", Version: "1.19.0"},
+			{
+				InstanceID:    "inst-id-3",
+				VersionBefore: unknownVersion,
+				Version:       unknownVersion,
+				Outcome:       outcomeUnparseable,
+				Result:        "Update check output could not be parsed",
+				RawOutput:     "Error: unknown subcommand",
+			},
+		},
+		Skips: []skipCount{{
+			Reason:      skipForeign,
+			Description: "registered from another account or region",
+			Instances:   1,
+			Remedy:      "update them with an updater in their own account and region",
+			Link:        readmeURL + "#why-were-instances-skipped",
+		}},
+		UpdaterVersion: version,
+		UpdaterCommit:  commit(),
+	}, report)
+
+	summary.segmentBy([]string{"team"}, map[string]string{"team": "platform"})
+	summary.recordSegments([]instance{{instanceID: "inst-id-1", tags: map[string]string{"team": "payments"}}})
+	report = newFleetReport("cluster", summary, metadata, now)
+	assert.Equal(t, []string{"team"}, report.SegmentTags)
+	assert.Equal(t, map[string]string{"team": "payments"}, report.Instances[1].Segment)
+	assert.Equal(t, map[string]string{"team": "platform"}, report.Instances[2].Segment)
+	assert.Equal(t, []segmentCount{
+		{Segment: map[string]string{"team": "payments"}, Instances: 1, Updated: 1},
+		{Segment: map[string]string{"team": "platform"}, Instances: 3, Skipped: 1},
+	}, report.Segments)
+}
+
+func TestEC2Metadata(t *testing.T) {
+	instanceIDs := make([]string, 150)
+	for i := range instanceIDs {
+		instanceIDs[i] = fmt.Sprintf("i-%d", i)
+	}
+	pages := 0
+	mockEC2 := MockEC2{
+		DescribeInstancesPagesFn: func(input *ec2.DescribeInstancesInput, fn func(*ec2.DescribeInstancesOutput, bool) bool) error {
+			pages++
+			require.Len(t, input.Filters, 1)
+			assert.Equal(t, "instance-id", aws.StringValue(input.Filters[0].Name))
+			assert.Empty(t, input.InstanceIds, "should filter by instance ID so that missing instances do not fail the call")
+			if pages == 2 {
+				assert.Len(t, input.Filters[0].Values, 50)
+				return errors.New("failed to describe")
+			}
+			assert.Len(t, input.Filters[0].Values, 100)
+			fn(&ec2.DescribeInstancesOutput{Reservations: []*ec2.Reservation{{
+				Instances: []*ec2.Instance{{
+					InstanceId:   aws.String("i-0"),
+					ImageId:      aws.String("ami-1"),
+					InstanceType: aws.String("m5.large"),
+					Architecture: aws.String("x86_64"),
+					Tags: []*ec2.Tag{
+						{Key: aws.String(tagLaunchTemplateID), Value: aws.String("lt-1")},
+						{Key: aws.String(tagLaunchTemplateVersion), Value: aws.String("3")},
+						{Key: aws.String(tagName), Value: aws.String("web-1")},
+					},
+				}},
+			}}}, true)
+			return nil
+		},
+	}
+	u := updater{ec2: mockEC2}
+	_, err := u.ec2Metadata(instanceIDs)
+	require.Error(t, err)
+	assert.Contains(t, err.Error(), "failed to describe")
+
+	pages = 0
+	metadata, err := u.ec2Metadata(instanceIDs[:100])
+	require.NoError(t, err)
+	assert.Equal(t, map[string]ec2Metadata{
+		"i-0": {ImageID: "ami-1", InstanceType: "m5.large", Architecture: "x86_64", LaunchTemplateID: "lt-1", LaunchTemplateVersion: "3", Name: "web-1"},
+	}, metadata)
+}
+
+func TestDiffReports(t *testing.T) {
+	previous := fleetReport{
+		PassID: "pass-1",
+		Instances: []reportInstance{
+			{InstanceID: "inst-gone", Version: "1.19.0"},
+			{InstanceID: "inst-updated", Version: "1.19.0"},
+			{InstanceID: "inst-still-failing", Version: "1.19.0", Outcome: outcomeFailed},
+			{InstanceID: "inst-unchanged", Version: "1.20.0"},
+		},
+	}
+	current := fleetReport{
+		PassID: "pass-2",
+		Instances: []reportInstance{
+			{InstanceID: "inst-new", Version: "1.20.0"},
+			{InstanceID: "inst-updated", Version: "1.20.0", Outcome: outcomeUpdated},
+			{InstanceID: "inst-still-failing", Version: "1.19.0", Outcome: outcomeFailed},
+			{InstanceID: "inst-unchanged", Version: "1.20.0", Outcome: outcomeFailed},
+		},
+	}
+	assert.Equal(t, reportChanges{
+		PreviousPassID: "pass-1",
+		NewInstances:   []string{"inst-new"},
+		GoneInstances:  []string{"inst-gone"},
+		VersionChanges: []versionChange{{InstanceID: "inst-updated", From: "1.19.0", To: "1.20.0"}},
+		NewFailures:    []string{"inst-unchanged"},
+	}, diffReports(previous, current))
+}
+
+func TestWriteReport(t *testing.T) {
+	dir := t.TempDir()
+	previousPath := filepath.Join(dir, "previous.json")
+	path := filepath.Join(dir, "report.json")
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+
+	summary := newPassSummary()
+	summary.passID = "pass-1"
+	summary.recordVersions([]instance{{instanceID: "inst-id-1", bottlerocketVersion: "1.19.0"}})
+
+	// Without a previous report, the report has no changes.
+	require.NoError(t, writeReport(previousPath, filepath.Join(dir, "missing.json"), "cluster", summary, nil, nil, false, now))
+	previous, err := readFleetReport(previousPath)
+	require.NoError(t, err)
+	assert.Equal(t, newFleetReport("cluster", summary, nil, now), previous)
+	assert.Nil(t, previous.Changes)
+
+	summary = newPassSummary()
+	summary.passID = "pass-2"
+	summary.recordVersions([]instance{{instanceID: "inst-id-1", bottlerocketVersion: "1.19.0"}})
+	summary.failedInstance("inst-id-1", "Update failed")
+	require.NoError(t, writeReport(path, previousPath, "cluster", summary, nil, nil, false, now))
+	report, err := readFleetReport(path)
+	require.NoError(t, err)
+	require.NotNil(t, report.Changes)
+	assert.Equal(t, "pass-1", report.Changes.PreviousPassID)
+	assert.Equal(t, []string{"inst-id-1"}, report.Changes.NewFailures)
+	assert.Empty(t, report.Changes.NewInstances)
+}
+
+func TestWriteReportRedacts(t *testing.T) {
+	dir := t.TempDir()
+	path := filepath.Join(dir, "report.json")
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	const foreignARN = "arn:aws:ecs:us-west-2:111122223333:container-instance/test/abc"
+
+	summary := newPassSummary()
+	summary.passID = "pass-1"
+	summary.skippedInstance(foreignARN, skipForeign, `Foreign instance: container instance is in account "111122223333", but the updater runs in account "444455556666"`)
+	require.NoError(t, writeReport(path, "", "cluster", summary, nil, nil, false, now))
+	data, err := os.ReadFile(path)
+	require.NoError(t, err)
+	assert.NotContains(t, string(data), "111122223333")
+	assert.NotContains(t, string(data), "444455556666")
+	assert.Contains(t, string(data), "arn:aws:ecs:us-west-2:************:container-instance/test/abc")
+
+	// A later pass finds the same foreign instance, which is not new.
+	require.NoError(t, writeReport(path, path, "cluster", summary, nil, nil, false, now))
+	report, err := readFleetReport(path)
+	require.NoError(t, err)
+	require.NotNil(t, report.Changes)
+	assert.Empty(t, report.Changes.NewInstances)
+	assert.Empty(t, report.Changes.GoneInstances)
+
+	require.NoError(t, writeReport(path, "", "cluster", summary, nil, nil, true, now))
+	data, err = os.ReadFile(path)
+	require.NoError(t, err)
+	assert.Contains(t, string(data), foreignARN, "-log-sensitive should keep account IDs")
+}
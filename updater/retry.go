@@ -0,0 +1,24 @@
+package main
+
+import (
+	"github.com/aws/aws-sdk-go/aws/client"
+	"github.com/aws/aws-sdk-go/aws/request"
+)
+
+// sendCommandRetryer is the SDK's default retryer, except that it never
+// retries SendCommand. SendCommand has no client token, so a retried call may
+// start the document a second time when the first call's response was lost.
+// ssmChannel.send retries it instead, after checking whether the command was
+// created. The other mutating calls the updater makes, UpdateContainerInstancesState
+// and TagResource, set state rather than start actions, so the SDK may retry them.
+type sendCommandRetryer struct {
+	client.DefaultRetryer
+}
+
+// ShouldRetry implements request.Retryer.
+func (r sendCommandRetryer) ShouldRetry(req *request.Request) bool {
+	if req.Operation != nil && req.Operation.Name == "SendCommand" {
+		return false
+	}
+	return r.DefaultRetryer.ShouldRetry(req)
+}
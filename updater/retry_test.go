@@ -0,0 +1,21 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/stretchr/testify/assert"
+)
+
+func TestSendCommandRetryer(t *testing.T) {
+	retryer := sendCommandRetryer{}
+	throttled := func(operation string) *request.Request {
+		return &request.Request{
+			Operation: &request.Operation{Name: operation},
+			Error:     awserr.New("ThrottlingException", "rate exceeded", nil),
+		}
+	}
+	assert.False(t, retryer.ShouldRetry(throttled("SendCommand")), "SendCommand must not be retried by the SDK")
+	assert.True(t, retryer.ShouldRetry(throttled("UpdateContainerInstancesState")))
+}
@@ -0,0 +1,205 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+const (
+	// tagScalingSuspended marks an Auto Scaling group whose scaling the updater
+	// suspended, with the ID of the pass that suspended it, so that a later
+	// pass resumes it if the pass that suspended it could not.
+	tagScalingSuspended = "bottlerocket-ecs-updater:scaling-suspended"
+	// scalingProcess is the Auto Scaling process that runs the scaling policies
+	// of ECS managed scaling.
+	scalingProcess = "AlarmNotification"
+)
+
+// parseScalingProviders parses the comma-separated capacity provider names of
+// -suspend-scaling.
+func parseScalingProviders(value string) []string {
+	providers := make([]string, 0)
+	for _, name := range strings.Split(value, ",") {
+		if name = strings.TrimSpace(name); name != "" {
+			providers = append(providers, name)
+		}
+	}
+	return providers
+}
+
+// autoScalingGroupName returns the name of the Auto Scaling group with the ARN,
+// e.g. arn:aws:autoscaling:us-west-2:111122223333:autoScalingGroup:uuid:autoScalingGroupName/my-asg.
+func autoScalingGroupName(groupARN string) (string, error) {
+	const namePrefix = "autoScalingGroupName/"
+	i := strings.Index(groupARN, namePrefix)
+	if i < 0 {
+		return "", fmt.Errorf("not an Auto Scaling group ARN: %q", groupARN)
+	}
+	return groupARN[i+len(namePrefix):], nil
+}
+
+// scalingGroups returns the Auto Scaling groups of the capacity providers in
+// scalingProviders.
+func (u *updater) scalingGroups() ([]string, error) {
+	resp, err := u.ecs.DescribeCapacityProviders(&ecs.DescribeCapacityProvidersInput{
+		CapacityProviders: aws.StringSlice(u.scalingProviders),
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe capacity providers: %w", err)
+	}
+	if len(resp.Failures) > 0 {
+		return nil, fmt.Errorf("failed to describe capacity provider %q: %s", aws.StringValue(resp.Failures[0].Arn), aws.StringValue(resp.Failures[0].Reason))
+	}
+	groups := make([]string, 0)
+	for _, provider := range resp.CapacityProviders {
+		if provider.AutoScalingGroupProvider == nil {
+			log.Printf("Capacity provider %q has no Auto Scaling group, therefore not suspending its scaling", aws.StringValue(provider.Name))
+			continue
+		}
+		group, err := autoScalingGroupName(aws.StringValue(provider.AutoScalingGroupProvider.AutoScalingGroupArn))
+		if err != nil {
+			return nil, err
+		}
+		groups = append(groups, group)
+	}
+	return groups, nil
+}
+
+// describeScalingGroups describes the Auto Scaling groups of the capacity
+// providers in scalingProviders.
+func (u *updater) describeScalingGroups() ([]*autoscaling.Group, error) {
+	names, err := u.scalingGroups()
+	if err != nil {
+		return nil, err
+	}
+	if len(names) == 0 {
+		return nil, nil
+	}
+	resp, err := u.autoscaling.DescribeAutoScalingGroups(&autoscaling.DescribeAutoScalingGroupsInput{
+		AutoScalingGroupNames: aws.StringSlice(names),
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe Auto Scaling groups %q: %w", names, err)
+	}
+	return resp.AutoScalingGroups, nil
+}
+
+// resumeLeftoverScaling resumes the scaling of the Auto Scaling groups that an
+// earlier pass suspended but could not resume, e.g. because it was stopped.
+func (u *updater) resumeLeftoverScaling() {
+	groups, err := u.describeScalingGroups()
+	if err != nil {
+		log.Printf("Failed to check for Auto Scaling groups left suspended by earlier passes: %v", err)
+		u.warnings.add(warnDegraded, "scaling left suspended by earlier passes is unknown: %v", err)
+		return
+	}
+	leftover := make([]string, 0)
+	for _, group := range groups {
+		for _, tag := range group.Tags {
+			if aws.StringValue(tag.Key) == tagScalingSuspended {
+				log.Printf("Scaling of Auto Scaling group %q was left suspended by pass %s", aws.StringValue(group.AutoScalingGroupName), aws.StringValue(tag.Value))
+				leftover = append(leftover, aws.StringValue(group.AutoScalingGroupName))
+			}
+		}
+	}
+	u.resumeScaling(leftover)
+}
+
+// suspendScaling suspends the scaling policies of the Auto Scaling groups of
+// the capacity providers in scalingProviders, so that the capacity reserved
+// for tasks of draining instances does not make managed scaling launch
+// instances the cluster does not need. Groups whose scaling was suspended by
+// someone else are left alone. It returns the groups it suspended, which
+// resumeScaling resumes at the end of the pass.
+func (u *updater) suspendScaling() []string {
+	groups, err := u.describeScalingGroups()
+	if err != nil {
+		log.Printf("Failed to find the Auto Scaling groups of capacity providers %q, therefore not suspending their scaling: %v", u.scalingProviders, err)
+		u.warnings.add(warnDegraded, "scaling of capacity providers %q was not suspended: %v", u.scalingProviders, err)
+		return nil
+	}
+	suspended := make([]string, 0)
+	for _, group := range groups {
+		name := aws.StringValue(group.AutoScalingGroupName)
+		if suspendedByOthers(group) {
+			log.Printf("Scaling of Auto Scaling group %q is already suspended, therefore leaving it as is", name)
+			continue
+		}
+		// Tag the group before suspending its scaling, so that a pass that
+		// stops before resuming it leaves a mark for the next pass.
+		_, err := u.autoscaling.CreateOrUpdateTags(&autoscaling.CreateOrUpdateTagsInput{
+			Tags: []*autoscaling.Tag{{
+				ResourceId:        aws.String(name),
+				ResourceType:      aws.String("auto-scaling-group"),
+				Key:               aws.String(tagScalingSuspended),
+				Value:             aws.String(u.passID),
+				PropagateAtLaunch: aws.Bool(false),
+			}},
+		})
+		if err != nil {
+			log.Printf("Failed to tag Auto Scaling group %q, therefore not suspending its scaling: %v", name, err)
+			u.warnings.add(warnDegraded, "scaling of Auto Scaling group %q was not suspended: %v", name, err)
+			continue
+		}
+		// Resume even a group that failed to suspend, to remove its tag.
+		suspended = append(suspended, name)
+		_, err = u.autoscaling.SuspendProcesses(&autoscaling.ScalingProcessQuery{
+			AutoScalingGroupName: aws.String(name),
+			ScalingProcesses:     aws.StringSlice([]string{scalingProcess}),
+		})
+		if err != nil {
+			log.Printf("Failed to suspend scaling of Auto Scaling group %q: %v", name, err)
+			u.warnings.add(warnDegraded, "scaling of Auto Scaling group %q was not suspended: %v", name, err)
+			continue
+		}
+		log.Printf("Suspended scaling of Auto Scaling group %q for the pass", name)
+	}
+	return suspended
+}
+
+// suspendedByOthers returns whether the scaling of the group was suspended by
+// someone other than the updater.
+func suspendedByOthers(group *autoscaling.Group) bool {
+	for _, tag := range group.Tags {
+		if aws.StringValue(tag.Key) == tagScalingSuspended {
+			return false
+		}
+	}
+	for _, process := range group.SuspendedProcesses {
+		if aws.StringValue(process.ProcessName) == scalingProcess {
+			return true
+		}
+	}
+	return false
+}
+
+// resumeScaling resumes the scaling of the Auto Scaling groups suspended by
+// suspendScaling and removes their tags.
+func (u *updater) resumeScaling(groups []string) {
+	for _, name := range groups {
+		_, err := u.autoscaling.ResumeProcesses(&autoscaling.ScalingProcessQuery{
+			AutoScalingGroupName: aws.String(name),
+			ScalingProcesses:     aws.StringSlice([]string{scalingProcess}),
+		})
+		if err == nil {
+			_, err = u.autoscaling.DeleteTags(&autoscaling.DeleteTagsInput{
+				Tags: []*autoscaling.Tag{{
+					ResourceId:   aws.String(name),
+					ResourceType: aws.String("auto-scaling-group"),
+					Key:          aws.String(tagScalingSuspended),
+				}},
+			})
+		}
+		if err != nil {
+			log.Printf("Failed to resume scaling of Auto Scaling group %q; the next pass retries: %v", name, err)
+			u.warnings.add(warnDegraded, "scaling of Auto Scaling group %q was not resumed: %v", name, err)
+			continue
+		}
+		log.Printf("Resumed scaling of Auto Scaling group %q", name)
+	}
+}
@@ -0,0 +1,138 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/autoscaling"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestAutoScalingGroupName(t *testing.T) {
+	name, err := autoScalingGroupName("arn:aws:autoscaling:us-west-2:123456789012:autoScalingGroup:8e3a1b1c-0a63-4b2e-9d8f-1f4c2b0d5a6e:autoScalingGroupName/my-asg")
+	require.NoError(t, err)
+	assert.Equal(t, "my-asg", name)
+	_, err = autoScalingGroupName("my-asg")
+	assert.Error(t, err)
+}
+
+func TestParseScalingProviders(t *testing.T) {
+	assert.Equal(t, []string{"spot", "on-demand"}, parseScalingProviders(" spot, on-demand,"))
+	assert.Empty(t, parseScalingProviders(""))
+}
+
+func scalingMockECS(t *testing.T) MockECS {
+	return MockECS{
+		DescribeCapacityProvidersFn: func(input *ecs.DescribeCapacityProvidersInput) (*ecs.DescribeCapacityProvidersOutput, error) {
+			assert.Equal(t, []string{"spot", "on-demand"}, aws.StringValueSlice(input.CapacityProviders))
+			return &ecs.DescribeCapacityProvidersOutput{
+				CapacityProviders: []*ecs.CapacityProvider{
+					{Name: aws.String("spot"), AutoScalingGroupProvider: &ecs.AutoScalingGroupProvider{
+						AutoScalingGroupArn: aws.String("arn:aws:autoscaling:us-west-2:123456789012:autoScalingGroup:uuid-1:autoScalingGroupName/spot-asg"),
+					}},
+					{Name: aws.String("on-demand"), AutoScalingGroupProvider: &ecs.AutoScalingGroupProvider{
+						AutoScalingGroupArn: aws.String("arn:aws:autoscaling:us-west-2:123456789012:autoScalingGroup:uuid-2:autoScalingGroupName/on-demand-asg"),
+					}},
+				},
+			}, nil
+		},
+	}
+}
+
+func TestSuspendAndResumeScaling(t *testing.T) {
+	var tagged, suspended, resumed, untagged []string
+	mockAutoScaling := MockAutoScaling{
+		DescribeAutoScalingGroupsFn: func(input *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error) {
+			assert.Equal(t, []string{"spot-asg", "on-demand-asg"}, aws.StringValueSlice(input.AutoScalingGroupNames))
+			return &autoscaling.DescribeAutoScalingGroupsOutput{
+				AutoScalingGroups: []*autoscaling.Group{
+					{AutoScalingGroupName: aws.String("spot-asg")},
+					{
+						AutoScalingGroupName: aws.String("on-demand-asg"),
+						SuspendedProcesses:   []*autoscaling.SuspendedProcess{{ProcessName: aws.String(scalingProcess)}},
+					},
+				},
+			}, nil
+		},
+		CreateOrUpdateTagsFn: func(input *autoscaling.CreateOrUpdateTagsInput) (*autoscaling.CreateOrUpdateTagsOutput, error) {
+			require.Len(t, input.Tags, 1)
+			assert.Equal(t, tagScalingSuspended, aws.StringValue(input.Tags[0].Key))
+			assert.Equal(t, "pass-1", aws.StringValue(input.Tags[0].Value))
+			assert.False(t, aws.BoolValue(input.Tags[0].PropagateAtLaunch))
+			tagged = append(tagged, aws.StringValue(input.Tags[0].ResourceId))
+			return &autoscaling.CreateOrUpdateTagsOutput{}, nil
+		},
+		SuspendProcessesFn: func(input *autoscaling.ScalingProcessQuery) (*autoscaling.SuspendProcessesOutput, error) {
+			assert.Equal(t, []string{scalingProcess}, aws.StringValueSlice(input.ScalingProcesses))
+			suspended = append(suspended, aws.StringValue(input.AutoScalingGroupName))
+			return &autoscaling.SuspendProcessesOutput{}, nil
+		},
+		ResumeProcessesFn: func(input *autoscaling.ScalingProcessQuery) (*autoscaling.ResumeProcessesOutput, error) {
+			assert.Equal(t, []string{scalingProcess}, aws.StringValueSlice(input.ScalingProcesses))
+			resumed = append(resumed, aws.StringValue(input.AutoScalingGroupName))
+			return &autoscaling.ResumeProcessesOutput{}, nil
+		},
+		DeleteTagsFn: func(input *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error) {
+			require.Len(t, input.Tags, 1)
+			assert.Equal(t, tagScalingSuspended, aws.StringValue(input.Tags[0].Key))
+			untagged = append(untagged, aws.StringValue(input.Tags[0].ResourceId))
+			return &autoscaling.DeleteTagsOutput{}, nil
+		},
+	}
+	u := updater{
+		ecs:              scalingMockECS(t),
+		autoscaling:      mockAutoScaling,
+		passID:           "pass-1",
+		scalingProviders: []string{"spot", "on-demand"},
+		warnings:         newPassWarnings(),
+	}
+	groups := u.suspendScaling()
+	assert.Equal(t, []string{"spot-asg"}, groups, "should leave groups suspended by others alone")
+	assert.Equal(t, []string{"spot-asg"}, tagged)
+	assert.Equal(t, []string{"spot-asg"}, suspended)
+
+	u.resumeScaling(groups)
+	assert.Equal(t, []string{"spot-asg"}, resumed)
+	assert.Equal(t, []string{"spot-asg"}, untagged)
+	assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+}
+
+func TestResumeLeftoverScaling(t *testing.T) {
+	var resumed []string
+	mockAutoScaling := MockAutoScaling{
+		DescribeAutoScalingGroupsFn: func(_ *autoscaling.DescribeAutoScalingGroupsInput) (*autoscaling.DescribeAutoScalingGroupsOutput, error) {
+			return &autoscaling.DescribeAutoScalingGroupsOutput{
+				AutoScalingGroups: []*autoscaling.Group{
+					{
+						AutoScalingGroupName: aws.String("spot-asg"),
+						SuspendedProcesses:   []*autoscaling.SuspendedProcess{{ProcessName: aws.String(scalingProcess)}},
+						Tags:                 []*autoscaling.TagDescription{{Key: aws.String(tagScalingSuspended), Value: aws.String("pass-0")}},
+					},
+					{
+						AutoScalingGroupName: aws.String("on-demand-asg"),
+						SuspendedProcesses:   []*autoscaling.SuspendedProcess{{ProcessName: aws.String(scalingProcess)}},
+					},
+				},
+			}, nil
+		},
+		ResumeProcessesFn: func(input *autoscaling.ScalingProcessQuery) (*autoscaling.ResumeProcessesOutput, error) {
+			resumed = append(resumed, aws.StringValue(input.AutoScalingGroupName))
+			return &autoscaling.ResumeProcessesOutput{}, nil
+		},
+		DeleteTagsFn: func(_ *autoscaling.DeleteTagsInput) (*autoscaling.DeleteTagsOutput, error) {
+			return nil, errors.New("access denied")
+		},
+	}
+	u := updater{
+		ecs:              scalingMockECS(t),
+		autoscaling:      mockAutoScaling,
+		scalingProviders: []string{"spot", "on-demand"},
+		warnings:         newPassWarnings(),
+	}
+	u.resumeLeftoverScaling()
+	assert.Equal(t, []string{"spot-asg"}, resumed, "should only resume groups suspended by the updater")
+	assert.Equal(t, 1, u.warnings.counts[warnDegraded], "should warn when the tag can't be removed")
+}
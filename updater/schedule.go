@@ -0,0 +1,195 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"strconv"
+	"strings"
+	"time"
+
+	// The updater image has no time zone database, so embed one for
+	// -update-window-timezone.
+	_ "time/tzdata"
+)
+
+// maxUpdateWindowDuration is the longest an update window can stay open.
+const maxUpdateWindowDuration = 7 * 24 * time.Hour
+
+// cronSchedule is a cron expression with the five standard fields: minute,
+// hour, day of month, month and day of week. Each field holds the values it
+// matches as bits.
+type cronSchedule struct {
+	minutes  uint64
+	hours    uint64
+	days     uint64
+	months   uint64
+	weekdays uint64
+	// anyDay and anyWeekday are set when the day of month or the day of week
+	// field starts with *. As in cron, a time matches when both day fields
+	// match if either starts with *, and when either matches otherwise.
+	anyDay     bool
+	anyWeekday bool
+}
+
+// parseCron parses a cron expression such as "0 2 * * 6" or "30 1-4/2 * * 1-5".
+// Fields are *, numbers, ranges and steps, separated by commas; 0 and 7 are
+// both Sunday.
+func parseCron(expr string) (cronSchedule, error) {
+	fields := strings.Fields(expr)
+	if len(fields) != 5 {
+		return cronSchedule{}, fmt.Errorf("cron expression %q must have 5 fields: minute, hour, day of month, month and day of week", expr)
+	}
+	var schedule cronSchedule
+	var err error
+	bounds := []struct {
+		name        string
+		first, last int
+		bits        *uint64
+	}{
+		{name: "minute", first: 0, last: 59, bits: &schedule.minutes},
+		{name: "hour", first: 0, last: 23, bits: &schedule.hours},
+		{name: "day of month", first: 1, last: 31, bits: &schedule.days},
+		{name: "month", first: 1, last: 12, bits: &schedule.months},
+		{name: "day of week", first: 0, last: 7, bits: &schedule.weekdays},
+	}
+	for n, b := range bounds {
+		if *b.bits, err = parseCronField(fields[n], b.first, b.last); err != nil {
+			return cronSchedule{}, fmt.Errorf("invalid %s in cron expression %q: %w", b.name, expr, err)
+		}
+	}
+	if schedule.weekdays&(1<<7) != 0 {
+		schedule.weekdays |= 1
+	}
+	schedule.anyDay = strings.HasPrefix(fields[2], "*")
+	schedule.anyWeekday = strings.HasPrefix(fields[4], "*")
+	return schedule, nil
+}
+
+// parseCronField returns the values from first to last a cron field matches,
+// as bits.
+func parseCronField(field string, first, last int) (uint64, error) {
+	var bits uint64
+	for _, part := range strings.Split(field, ",") {
+		values, stepText, hasStep := strings.Cut(part, "/")
+		step := 1
+		if hasStep {
+			var err error
+			if step, err = strconv.Atoi(stepText); err != nil || step < 1 {
+				return 0, fmt.Errorf("invalid step %q", stepText)
+			}
+		}
+		var lo, hi int
+		if values == "*" {
+			lo, hi = first, last
+		} else {
+			from, to, isRange := strings.Cut(values, "-")
+			var err error
+			if lo, err = strconv.Atoi(from); err != nil {
+				return 0, fmt.Errorf("invalid value %q", from)
+			}
+			hi = lo
+			if isRange {
+				if hi, err = strconv.Atoi(to); err != nil {
+					return 0, fmt.Errorf("invalid value %q", to)
+				}
+			} else if hasStep {
+				hi = last
+			}
+		}
+		if lo < first || hi > last || lo > hi {
+			return 0, fmt.Errorf("%q is not within %d-%d", part, first, last)
+		}
+		for v := lo; v <= hi; v += step {
+			bits |= 1 << v
+		}
+	}
+	return bits, nil
+}
+
+// matchesDay reports whether the schedule matches the day of t.
+func (c cronSchedule) matchesDay(t time.Time) bool {
+	day := c.days&(1<<t.Day()) != 0
+	weekday := c.weekdays&(1<<int(t.Weekday())) != 0
+	if c.anyDay || c.anyWeekday {
+		return day && weekday
+	}
+	return day || weekday
+}
+
+// matches reports whether the schedule matches the minute of t.
+func (c cronSchedule) matches(t time.Time) bool {
+	return c.months&(1<<int(t.Month())) != 0 && c.matchesDay(t) &&
+		c.hours&(1<<t.Hour()) != 0 && c.minutes&(1<<t.Minute()) != 0
+}
+
+// updateWindow is the window of -update-window: it opens at the times its
+// schedule matches in its time zone and stays open for its duration. Passes
+// check for updates at any time, but only drain and update instances while
+// the window is open.
+type updateWindow struct {
+	spec     string
+	schedule cronSchedule
+	duration time.Duration
+	location *time.Location
+}
+
+// newUpdateWindow returns the window opening at the times the cron expression
+// spec matches in the time zone, e.g. America/New_York, for duration.
+func newUpdateWindow(spec string, duration time.Duration, timezone string) (*updateWindow, error) {
+	schedule, err := parseCron(spec)
+	if err != nil {
+		return nil, err
+	}
+	if duration <= 0 || duration > maxUpdateWindowDuration {
+		return nil, fmt.Errorf("update window duration must be more than 0 and at most %s", maxUpdateWindowDuration)
+	}
+	location, err := time.LoadLocation(timezone)
+	if err != nil {
+		return nil, fmt.Errorf("invalid update window time zone %q: %w", timezone, err)
+	}
+	w := &updateWindow{spec: spec, schedule: schedule, duration: duration, location: location}
+	if _, ok := w.nextOpen(time.Now()); !ok {
+		return nil, errors.New("update window never opens")
+	}
+	return w, nil
+}
+
+// String describes the window, e.g. "0 2 * * 6 (America/New_York) for 4h0m0s".
+func (w *updateWindow) String() string {
+	return fmt.Sprintf("%s (%s) for %s", w.spec, w.location, w.duration)
+}
+
+// openUntil returns when the window closes and true while it is open at now.
+func (w *updateWindow) openUntil(now time.Time) (time.Time, bool) {
+	now = now.In(w.location)
+	for start := now.Truncate(time.Minute); now.Sub(start) < w.duration; start = start.Add(-time.Minute) {
+		if w.schedule.matches(start) {
+			return start.Add(w.duration), true
+		}
+	}
+	return time.Time{}, false
+}
+
+// nextOpen returns when the window next opens after now, and false when it
+// does not open in the next five years. Months, days and hours the schedule
+// does not match are skipped at once.
+func (w *updateWindow) nextOpen(now time.Time) (time.Time, bool) {
+	t := now.In(w.location).Truncate(time.Minute).Add(time.Minute)
+	limit := t.AddDate(5, 0, 0)
+	for t.Before(limit) {
+		year, month, day := t.Date()
+		switch {
+		case w.schedule.months&(1<<int(month)) == 0:
+			t = time.Date(year, month+1, 1, 0, 0, 0, 0, w.location)
+		case !w.schedule.matchesDay(t):
+			t = time.Date(year, month, day+1, 0, 0, 0, 0, w.location)
+		case w.schedule.hours&(1<<t.Hour()) == 0:
+			t = time.Date(year, month, day, t.Hour()+1, 0, 0, 0, w.location)
+		case w.schedule.minutes&(1<<t.Minute()) == 0:
+			t = t.Add(time.Minute)
+		default:
+			return t, true
+		}
+	}
+	return time.Time{}, false
+}
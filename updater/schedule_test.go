@@ -0,0 +1,103 @@
+package main
+
+import (
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseCron(t *testing.T) {
+	schedule, err := parseCron("0,30 1-5/2 * * 7")
+	require.NoError(t, err)
+	assert.Equal(t, uint64(1|1<<30), schedule.minutes)
+	assert.Equal(t, uint64(1<<1|1<<3|1<<5), schedule.hours)
+	assert.Equal(t, uint64(1<<0|1<<7), schedule.weekdays, "7 should be Sunday like 0")
+	assert.True(t, schedule.anyDay)
+	assert.False(t, schedule.anyWeekday)
+
+	schedule, err = parseCron("*/15 22/1 1 */3 *")
+	require.NoError(t, err)
+	assert.Equal(t, uint64(1|1<<15|1<<30|1<<45), schedule.minutes)
+	assert.Equal(t, uint64(1<<22|1<<23), schedule.hours)
+	assert.Equal(t, uint64(1<<1|1<<4|1<<7|1<<10), schedule.months)
+
+	for _, expr := range []string{"", "0 2 * *", "0 2 * * * *", "60 2 * * *", "0 24 * * *", "0 2 0 * *", "0 2 * 13 *", "0 2 * * 8", "0 5-2 * * *", "0 2/0 * * *", "0 two * * *", "0 2 * * MON"} {
+		_, err := parseCron(expr)
+		assert.Error(t, err, expr)
+	}
+}
+
+func TestCronMatches(t *testing.T) {
+	saturday := time.Date(2024, 3, 2, 2, 0, 0, 0, time.UTC)
+	cases := []struct {
+		expr     string
+		expected bool
+	}{
+		{expr: "0 2 * * 6", expected: true},
+		{expr: "0 2 * * 0", expected: false},
+		{expr: "1 2 * * 6", expected: false},
+		{expr: "0 3 * * 6", expected: false},
+		{expr: "0 2 2 3 *", expected: true},
+		{expr: "0 2 2 4 *", expected: false},
+		{expr: "0 2 15 * 6", expected: true},
+		{expr: "0 2 2 * 0", expected: true},
+		{expr: "0 2 15 * 0", expected: false},
+		{expr: "0 2 */2 * 6", expected: false},
+	}
+	for _, tc := range cases {
+		schedule, err := parseCron(tc.expr)
+		require.NoError(t, err, tc.expr)
+		assert.Equal(t, tc.expected, schedule.matches(saturday), tc.expr)
+	}
+}
+
+func TestUpdateWindow(t *testing.T) {
+	w, err := newUpdateWindow("0 2 * * 6", 4*time.Hour, "America/New_York")
+	require.NoError(t, err)
+	assert.Equal(t, "0 2 * * 6 (America/New_York) for 4h0m0s", w.String())
+
+	// Saturday, March 2 2024, 02:00 in New York is 07:00 UTC.
+	opens := time.Date(2024, 3, 2, 7, 0, 0, 0, time.UTC)
+	for _, minutes := range []int{-1, 0, 239, 240} {
+		end, open := w.openUntil(opens.Add(time.Duration(minutes) * time.Minute))
+		assert.Equal(t, minutes >= 0 && minutes < 240, open, "%d minute(s) after the window opens", minutes)
+		if open {
+			assert.True(t, end.Equal(opens.Add(4*time.Hour)), end)
+		}
+	}
+
+	next, ok := w.nextOpen(opens.Add(-time.Hour))
+	require.True(t, ok)
+	assert.True(t, next.Equal(opens), next)
+	next, ok = w.nextOpen(opens)
+	require.True(t, ok)
+	assert.True(t, next.Equal(opens.AddDate(0, 0, 7)), "the window should next open a week later, not %s", next)
+	// New York switches to daylight saving time on March 10, 2024.
+	next, ok = w.nextOpen(opens.AddDate(0, 0, 7))
+	require.True(t, ok)
+	assert.True(t, next.Equal(time.Date(2024, 3, 16, 6, 0, 0, 0, time.UTC)), next)
+
+	leap, err := newUpdateWindow("30 23 29 2 *", time.Hour, "UTC")
+	require.NoError(t, err)
+	next, ok = leap.nextOpen(time.Date(2024, 3, 1, 0, 0, 0, 0, time.UTC))
+	require.True(t, ok)
+	assert.True(t, next.Equal(time.Date(2028, 2, 29, 23, 30, 0, 0, time.UTC)), next)
+
+	invalid := []struct {
+		expr     string
+		duration time.Duration
+		timezone string
+	}{
+		{expr: "0 2 * *", duration: time.Hour, timezone: "UTC"},
+		{expr: "0 2 * * 6", duration: 0, timezone: "UTC"},
+		{expr: "0 2 * * 6", duration: 8 * 24 * time.Hour, timezone: "UTC"},
+		{expr: "0 2 * * 6", duration: time.Hour, timezone: "Mars/Olympus_Mons"},
+		{expr: "0 0 30 2 *", duration: time.Hour, timezone: "UTC"},
+	}
+	for _, tc := range invalid {
+		_, err := newUpdateWindow(tc.expr, tc.duration, tc.timezone)
+		assert.Error(t, err, tc.expr)
+	}
+}
@@ -0,0 +1,110 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"os"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/secretsmanager"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+// secretsProvider retrieves the value of a secret by name.
+type secretsProvider interface {
+	getSecret(name string) (string, error)
+}
+
+// envSecrets reads secrets from environment variables; unset variables are
+// empty.
+type envSecrets struct{}
+
+func (envSecrets) getSecret(name string) (string, error) {
+	return os.Getenv(name), nil
+}
+
+// secretsManagerSecrets reads secrets from AWS Secrets Manager, by secret name
+// or ARN.
+type secretsManagerSecrets struct {
+	api SecretsManagerAPI
+}
+
+func (p secretsManagerSecrets) getSecret(name string) (string, error) {
+	resp, err := p.api.GetSecretValue(&secretsmanager.GetSecretValueInput{SecretId: aws.String(name)})
+	if err != nil {
+		return "", fmt.Errorf("failed to get secret %q: %w", name, err)
+	}
+	if resp.SecretString == nil {
+		return "", fmt.Errorf("secret %q has no string value", name)
+	}
+	return aws.StringValue(resp.SecretString), nil
+}
+
+// ssmSecrets reads secrets from SSM parameters, decrypting SecureString
+// parameters.
+type ssmSecrets struct {
+	api SSMReadAPI
+}
+
+func (p ssmSecrets) getSecret(name string) (string, error) {
+	resp, err := p.api.GetParameter(&ssm.GetParameterInput{Name: aws.String(name), WithDecryption: aws.Bool(true)})
+	if err != nil {
+		return "", fmt.Errorf("failed to get parameter %q: %w", name, err)
+	}
+	if resp.Parameter == nil {
+		return "", fmt.Errorf("parameter %q has no value", name)
+	}
+	return aws.StringValue(resp.Parameter.Value), nil
+}
+
+// secret is a credential, such as the Authorization header of the failure
+// webhook, read from a secretsProvider. It is read again once it is refresh
+// old, so that rotated credentials are picked up during long passes.
+type secret struct {
+	provider secretsProvider
+	name     string
+	refresh  time.Duration
+	value    string
+	read     time.Time
+}
+
+// newSecret returns the secret a reference names: secretsmanager:<name or
+// ARN>, ssm:<parameter name> or env:<variable>.
+func newSecret(ref string, refresh time.Duration, secretsManager SecretsManagerAPI, ssmAPI SSMReadAPI) (*secret, error) {
+	kind, name, _ := strings.Cut(ref, ":")
+	s := &secret{name: name, refresh: refresh}
+	switch kind {
+	case "secretsmanager":
+		s.provider = secretsManagerSecrets{api: secretsManager}
+	case "ssm":
+		s.provider = ssmSecrets{api: ssmAPI}
+	case "env":
+		s.provider = envSecrets{}
+	}
+	if s.provider == nil || name == "" {
+		return nil, fmt.Errorf("invalid secret reference %q: expected secretsmanager:<name>, ssm:<name> or env:<name>", ref)
+	}
+	return s, nil
+}
+
+// get returns the value of the secret, reading it when it was not read yet,
+// or was read refresh or longer before now. A failed refresh is logged, and
+// the value read before is returned, so that a pass can still escalate while
+// the provider is unavailable.
+func (s *secret) get(now time.Time) (string, error) {
+	if !s.read.IsZero() && (s.refresh <= 0 || now.Sub(s.read) < s.refresh) {
+		return s.value, nil
+	}
+	value, err := s.provider.getSecret(s.name)
+	if err != nil {
+		if s.read.IsZero() {
+			return "", err
+		}
+		log.Printf("Failed to refresh secret %q, therefore using the value read at %s: %v", s.name, s.read.Format(time.RFC3339), err)
+		return s.value, nil
+	}
+	s.value, s.read = value, now
+	return value, nil
+}
@@ -0,0 +1,89 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/secretsmanager"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestNewSecret(t *testing.T) {
+	const secretARN = "arn:aws:secretsmanager:us-west-2:000000000000:secret:webhook-AbCdEf"
+	secretsManager := MockSecretsManager{
+		GetSecretValueFn: func(input *secretsmanager.GetSecretValueInput) (*secretsmanager.GetSecretValueOutput, error) {
+			assert.Equal(t, secretARN, aws.StringValue(input.SecretId))
+			return &secretsmanager.GetSecretValueOutput{SecretString: aws.String("Bearer from-secrets-manager")}, nil
+		},
+	}
+	mockSSM := MockSSM{
+		GetParameterFn: func(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error) {
+			assert.Equal(t, "/updater/webhook", aws.StringValue(input.Name))
+			assert.True(t, aws.BoolValue(input.WithDecryption))
+			return &ssm.GetParameterOutput{Parameter: &ssm.Parameter{Value: aws.String("Bearer from-ssm")}}, nil
+		},
+	}
+	t.Setenv("WEBHOOK_TOKEN", "Bearer from-env")
+
+	cases := map[string]string{
+		"secretsmanager:" + secretARN: "Bearer from-secrets-manager",
+		"ssm:/updater/webhook":        "Bearer from-ssm",
+		"env:WEBHOOK_TOKEN":           "Bearer from-env",
+	}
+	for ref, expected := range cases {
+		s, err := newSecret(ref, time.Minute, secretsManager, mockSSM)
+		require.NoError(t, err, ref)
+		value, err := s.get(time.Now())
+		require.NoError(t, err, ref)
+		assert.Equal(t, expected, value, ref)
+	}
+
+	for _, ref := range []string{"", "vault:webhook", "ssm:", "/updater/webhook"} {
+		_, err := newSecret(ref, time.Minute, secretsManager, mockSSM)
+		assert.Error(t, err, ref)
+	}
+}
+
+type fakeSecrets struct {
+	values []string
+	err    error
+	reads  int
+}
+
+func (f *fakeSecrets) getSecret(_ string) (string, error) {
+	f.reads++
+	if f.err != nil {
+		return "", f.err
+	}
+	return f.values[f.reads-1], nil
+}
+
+func TestSecretRefresh(t *testing.T) {
+	now := time.Date(2024, 5, 1, 12, 0, 0, 0, time.UTC)
+	provider := &fakeSecrets{values: []string{"Bearer first", "Bearer rotated"}}
+	s := &secret{provider: provider, name: "webhook", refresh: 15 * time.Minute}
+
+	value, err := s.get(now)
+	require.NoError(t, err)
+	assert.Equal(t, "Bearer first", value)
+	value, err = s.get(now.Add(10 * time.Minute))
+	require.NoError(t, err)
+	assert.Equal(t, "Bearer first", value)
+	assert.Equal(t, 1, provider.reads, "should not read the secret again before it is refresh old")
+
+	value, err = s.get(now.Add(15 * time.Minute))
+	require.NoError(t, err)
+	assert.Equal(t, "Bearer rotated", value)
+
+	provider.err = errors.New("secret unavailable")
+	value, err = s.get(now.Add(time.Hour))
+	require.NoError(t, err)
+	assert.Equal(t, "Bearer rotated", value, "a failed refresh should keep the value read before")
+
+	_, err = (&secret{provider: provider, name: "webhook"}).get(now)
+	assert.ErrorIs(t, err, provider.err, "the first read should fail")
+}
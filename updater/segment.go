@@ -0,0 +1,192 @@
+package main
+
+import (
+	"fmt"
+	"sort"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+const (
+	// untaggedSegment stands in for the value of a segment tag that neither the
+	// instance nor the cluster has.
+	untaggedSegment = "untagged"
+	// maxSegmentTags is the most segment tags; with the Cluster dimension, it
+	// is the most dimensions CloudWatch allows in a metric.
+	maxSegmentTags = 29
+)
+
+// reservedSegmentTags are the names of the properties of segment metric events
+// that segment tags would overwrite.
+var reservedSegmentTags = map[string]bool{
+	"_aws":             true,
+	"Cluster":          true,
+	"SegmentInstances": true,
+	"SegmentUpdated":   true,
+	"SegmentFailed":    true,
+	"SegmentSkipped":   true,
+}
+
+// parseSegmentTags parses the comma-separated tag keys of -segment-tags.
+func parseSegmentTags(spec string) ([]string, error) {
+	var keys []string
+	seen := make(map[string]bool)
+	for _, key := range strings.Split(spec, ",") {
+		key = strings.TrimSpace(key)
+		if key == "" {
+			continue
+		}
+		if reservedSegmentTags[key] {
+			return nil, fmt.Errorf("segment-tags must not include %q, which names a property of segment metrics", key)
+		}
+		if seen[key] {
+			return nil, fmt.Errorf("segment-tags lists %q more than once", key)
+		}
+		seen[key] = true
+		keys = append(keys, key)
+	}
+	if len(keys) > maxSegmentTags {
+		return nil, fmt.Errorf("segment-tags must list at most %d tag keys", maxSegmentTags)
+	}
+	return keys, nil
+}
+
+// tagValues maps the keys of ECS tags to their values.
+func tagValues(tags []*ecs.Tag) map[string]string {
+	values := make(map[string]string, len(tags))
+	for _, tag := range tags {
+		values[aws.StringValue(tag.Key)] = aws.StringValue(tag.Value)
+	}
+	return values
+}
+
+// clusterTags returns the tags of the updater's cluster.
+func (u *updater) clusterTags() (map[string]string, error) {
+	resp, err := u.ecs.DescribeClusters(&ecs.DescribeClustersInput{
+		Clusters: aws.StringSlice([]string{u.cluster}),
+		Include:  aws.StringSlice([]string{ecs.ClusterFieldTags}),
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe cluster %q: %w", u.cluster, err)
+	}
+	if len(resp.Clusters) == 0 {
+		return nil, fmt.Errorf("cluster %q not found: %v", u.cluster, resp.Failures)
+	}
+	return tagValues(resp.Clusters[0].Tags), nil
+}
+
+// segmentOf returns the value of each segment tag for an instance: the value
+// of the instance's tag, else the value of the cluster's tag, else untagged.
+func segmentOf(keys []string, clusterTags map[string]string, instanceTags map[string]string) map[string]string {
+	segment := make(map[string]string, len(keys))
+	for _, key := range keys {
+		value, ok := instanceTags[key]
+		if !ok {
+			value, ok = clusterTags[key]
+		}
+		if !ok || value == "" {
+			value = untaggedSegment
+		}
+		segment[key] = value
+	}
+	return segment
+}
+
+// segmentBy segments the pass by the tag keys, with the cluster's tags as the
+// fallback for instances without them. Instances the summary holds without a
+// recorded segment, e.g. because they could not be described, are in the
+// segment of the cluster's tags alone.
+func (s *passSummary) segmentBy(keys []string, clusterTags map[string]string) {
+	s.segmentTags = keys
+	s.clusterTags = clusterTags
+}
+
+// recordSegments records the segment of each instance from its tags.
+func (s *passSummary) recordSegments(instances []instance) {
+	if len(s.segmentTags) == 0 {
+		return
+	}
+	for _, inst := range instances {
+		s.segments[inst.instanceID] = segmentOf(s.segmentTags, s.clusterTags, inst.tags)
+	}
+}
+
+// segment returns the segment of the instance, or nil without segment tags.
+func (s *passSummary) segment(instanceID string) map[string]string {
+	if len(s.segmentTags) == 0 {
+		return nil
+	}
+	if segment, ok := s.segments[instanceID]; ok {
+		return segment
+	}
+	return segmentOf(s.segmentTags, s.clusterTags, nil)
+}
+
+// segmentCount is the number of instances of a segment and their outcomes.
+type segmentCount struct {
+	Segment   map[string]string `json:"segment"`
+	Instances int               `json:"instances"`
+	Updated   int               `json:"updated"`
+	Failed    int               `json:"failed"`
+	Skipped   int               `json:"skipped"`
+}
+
+// segmentCounts counts the instances of the pass and their outcomes per
+// segment, ordered by the values of the segment tags.
+func (s *passSummary) segmentCounts() []segmentCount {
+	if len(s.segmentTags) == 0 {
+		return nil
+	}
+	counts := make(map[string]*segmentCount)
+	seen := make(map[string]bool)
+	for _, ids := range []map[string]string{s.versionsBefore, s.results} {
+		for id := range ids {
+			if seen[id] {
+				continue
+			}
+			seen[id] = true
+			segment := s.segment(id)
+			values := make([]string, 0, len(s.segmentTags))
+			for _, key := range s.segmentTags {
+				values = append(values, segment[key])
+			}
+			name := strings.Join(values, "\x00")
+			count, ok := counts[name]
+			if !ok {
+				count = &segmentCount{Segment: segment}
+				counts[name] = count
+			}
+			count.Instances++
+			switch s.outcomes[id] {
+			case outcomeUpdated:
+				count.Updated++
+			case outcomeFailed:
+				count.Failed++
+			case outcomeSkipped:
+				count.Skipped++
+			}
+		}
+	}
+	names := make([]string, 0, len(counts))
+	for name := range counts {
+		names = append(names, name)
+	}
+	sort.Strings(names)
+	ordered := make([]segmentCount, 0, len(names))
+	for _, name := range names {
+		ordered = append(ordered, *counts[name])
+	}
+	return ordered
+}
+
+// name names the segment by its tags in the order of keys, e.g.
+// "team=payments, env=prod".
+func (c segmentCount) name(keys []string) string {
+	parts := make([]string, 0, len(keys))
+	for _, key := range keys {
+		parts = append(parts, key+"="+c.Segment[key])
+	}
+	return strings.Join(parts, ", ")
+}
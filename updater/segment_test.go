@@ -0,0 +1,78 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseSegmentTags(t *testing.T) {
+	keys, err := parseSegmentTags(" team, environment ,,")
+	require.NoError(t, err)
+	assert.Equal(t, []string{"team", "environment"}, keys)
+
+	keys, err = parseSegmentTags("")
+	require.NoError(t, err)
+	assert.Empty(t, keys)
+
+	_, err = parseSegmentTags("team,team")
+	assert.Error(t, err)
+	_, err = parseSegmentTags("team,Cluster")
+	assert.Error(t, err)
+	_, err = parseSegmentTags("SegmentFailed")
+	assert.Error(t, err)
+}
+
+func TestClusterTags(t *testing.T) {
+	u := &updater{cluster: "staging", ecs: MockECS{
+		DescribeClustersFn: func(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+			assert.Equal(t, []*string{aws.String(ecs.ClusterFieldTags)}, input.Include)
+			return lkgCluster(map[string]string{"team": "payments"}), nil
+		},
+	}}
+	tags, err := u.clusterTags()
+	require.NoError(t, err)
+	assert.Equal(t, map[string]string{"team": "payments"}, tags)
+
+	u.ecs = MockECS{
+		DescribeClustersFn: func(_ *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+			return nil, errors.New("failed to describe clusters")
+		},
+	}
+	_, err = u.clusterTags()
+	assert.Error(t, err)
+}
+
+func TestSegmentCounts(t *testing.T) {
+	summary := newPassSummary()
+	assert.Nil(t, summary.segmentCounts())
+	assert.Nil(t, summary.segment("inst-id-1"))
+
+	summary.segmentBy([]string{"team", "environment"}, map[string]string{"environment": "prod"})
+	instances := []instance{
+		{instanceID: "inst-id-1", bottlerocketVersion: "1.19.0", tags: map[string]string{"team": "payments"}},
+		{instanceID: "inst-id-2", bottlerocketVersion: "1.19.0", tags: map[string]string{"team": "payments", "environment": "staging"}},
+		{instanceID: "inst-id-3", bottlerocketVersion: "1.19.0", tags: map[string]string{"team": "search"}},
+		{instanceID: "inst-id-4", bottlerocketVersion: "1.19.0", tags: map[string]string{"team": "search"}},
+	}
+	summary.recordVersions(instances)
+	summary.recordSegments(instances)
+	summary.updatedInstance("inst-id-3", "Instance updated successfully")
+	summary.failedInstance("inst-id-4", "Update failed")
+	summary.skippedInstance("arn-unknown", skipStateUnknown, "State unknown: MISSING")
+
+	assert.Equal(t, map[string]string{"team": "payments", "environment": "staging"}, summary.segment("inst-id-2"))
+	assert.Equal(t, map[string]string{"team": untaggedSegment, "environment": "prod"}, summary.segment("arn-unknown"))
+	counts := summary.segmentCounts()
+	assert.Equal(t, []segmentCount{
+		{Segment: map[string]string{"team": "payments", "environment": "prod"}, Instances: 1},
+		{Segment: map[string]string{"team": "payments", "environment": "staging"}, Instances: 1},
+		{Segment: map[string]string{"team": "search", "environment": "prod"}, Instances: 2, Updated: 1, Failed: 1},
+		{Segment: map[string]string{"team": untaggedSegment, "environment": "prod"}, Instances: 1, Skipped: 1},
+	}, counts)
+	assert.Equal(t, "team=search, environment=prod", counts[2].name(summary.segmentTags))
+}
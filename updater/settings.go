@@ -0,0 +1,53 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"log"
+	"strings"
+)
+
+// settingsParameter is the parameter of the settings document that receives the
+// settings to apply, as JSON.
+const settingsParameter = "settings"
+
+// validateSettings checks that settings is a JSON object of Bottlerocket
+// settings, such as {"ecs": {"enable-spot-instance-draining": true}}. The
+// settings document quotes the settings with single quotes and its parameter
+// rejects them, which SSM would only report once an instance is drained and
+// updated, so they are rejected up front.
+func validateSettings(settings string) error {
+	if strings.Contains(settings, "'") {
+		return fmt.Errorf("settings must not contain single quotes")
+	}
+	var parsed map[string]interface{}
+	if err := json.Unmarshal([]byte(settings), &parsed); err != nil {
+		return fmt.Errorf("settings must be a JSON object: %w", err)
+	}
+	if len(parsed) == 0 {
+		return fmt.Errorf("settings must not be empty")
+	}
+	return nil
+}
+
+// applySettings applies the configured Bottlerocket settings to an instance.
+// Settings are applied after the update so the instance picks them up with
+// the reboot into the new version.
+func (u *updater) applySettings(inst instance) error {
+	if u.settingsDocument == "" || u.settings == "" {
+		return nil
+	}
+	log.Printf("Applying settings to instance %q before reboot", inst.instanceID)
+	parameters := map[string][]string{
+		settingsParameter: {u.settings},
+	}
+	if !apiclientSupports(inst.bottlerocketVersion, featureSetJSON) {
+		log.Printf("Instance %q runs Bottlerocket %s, whose apiclient lacks %s, therefore applying settings through the raw API", inst.instanceID, inst.bottlerocketVersion, featureSetJSON)
+		parameters[settingsSyntaxParameter] = []string{settingsSyntaxRaw}
+	}
+	_, err := u.sendCommandWithParameters([]string{inst.instanceID}, u.settingsDocument, parameters)
+	if err != nil {
+		return fmt.Errorf("failed to apply settings: %w", err)
+	}
+	return nil
+}
@@ -0,0 +1,115 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestValidateSettings(t *testing.T) {
+	cases := []struct {
+		name        string
+		settings    string
+		expectedErr string
+	}{
+		{
+			name:     "object",
+			settings: `{"ecs": {"enable-spot-instance-draining": true}}`,
+		},
+		{
+			name:        "not json",
+			settings:    `ecs.enable-spot-instance-draining=true`,
+			expectedErr: "settings must be a JSON object",
+		},
+		{
+			name:        "not an object",
+			settings:    `["ecs"]`,
+			expectedErr: "settings must be a JSON object",
+		},
+		{
+			name:        "empty object",
+			settings:    `{}`,
+			expectedErr: "settings must not be empty",
+		},
+		{
+			name:        "single quote",
+			settings:    `{"settings": {"motd": "it's updated"}}`,
+			expectedErr: "settings must not contain single quotes",
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			err := validateSettings(tc.settings)
+			if tc.expectedErr == "" {
+				assert.NoError(t, err)
+			} else {
+				require.Error(t, err)
+				assert.Contains(t, err.Error(), tc.expectedErr)
+			}
+		})
+	}
+}
+
+func TestApplySettings(t *testing.T) {
+	const settings = `{"ecs": {"enable-spot-instance-draining": true}}`
+	inst := instance{instanceID: "instance-id", containerInstanceID: "cont-inst-id"}
+
+	t.Run("not configured", func(t *testing.T) {
+		u := updater{ssm: MockSSM{}}
+		assert.NoError(t, u.applySettings(inst))
+	})
+	t.Run("success", func(t *testing.T) {
+		sendCommandCalls := 0
+		mockSSM := MockSSM{
+			SendCommandFn: func(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+				sendCommandCalls++
+				assert.Equal(t, "settings-document", aws.StringValue(input.DocumentName))
+				assert.Equal(t, []*string{aws.String("instance-id")}, input.InstanceIds)
+				assert.Equal(t, map[string][]*string{"settings": {aws.String(settings)}}, input.Parameters)
+				return &ssm.SendCommandOutput{Command: &ssm.Command{CommandId: aws.String("command-id")}}, nil
+			},
+			ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+				fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, "instance-id"), true)
+				return nil
+			},
+		}
+		u := updater{ssm: mockSSM, settingsDocument: "settings-document", settings: settings}
+		require.NoError(t, u.applySettings(inst))
+		assert.Equal(t, 1, sendCommandCalls)
+	})
+	t.Run("older apiclient", func(t *testing.T) {
+		mockSSM := MockSSM{
+			SendCommandFn: func(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+				assert.Equal(t, map[string][]*string{
+					"settings": {aws.String(settings)},
+					"syntax":   {aws.String("raw")},
+				}, input.Parameters)
+				return &ssm.SendCommandOutput{Command: &ssm.Command{CommandId: aws.String("command-id")}}, nil
+			},
+			ListCommandInvocationsPagesFn: func(_ *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+				fn(invocationsOutput(ssm.CommandInvocationStatusSuccess, "instance-id"), true)
+				return nil
+			},
+		}
+		u := updater{ssm: mockSSM, settingsDocument: "settings-document", settings: settings}
+		old := inst
+		old.bottlerocketVersion = "1.1.4"
+		require.NoError(t, u.applySettings(old))
+	})
+	t.Run("send err", func(t *testing.T) {
+		sendErr := errors.New("failed to send command")
+		mockSSM := MockSSM{
+			SendCommandFn: func(_ *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+				return nil, sendErr
+			},
+		}
+		u := updater{ssm: mockSSM, settingsDocument: "settings-document", settings: settings}
+		err := u.applySettings(inst)
+		require.Error(t, err)
+		assert.ErrorIs(t, err, sendErr)
+	})
+}
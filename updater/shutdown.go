@@ -0,0 +1,56 @@
+package main
+
+import (
+	"errors"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+)
+
+// shutdownExitCode is the exit status of an updater that shut down during a
+// pass, which tells a pass stopped early from a failed one.
+const shutdownExitCode = 3
+
+// errShutdown is returned by passes stopped early because the updater was
+// asked to shut down.
+var errShutdown = errors.New("pass stopped early because the updater is shutting down")
+
+// stopping reports whether the updater was asked to shut down, e.g. by ECS
+// stopping its task. A stopping updater starts no more updates, stops waiting
+// for drains, and re-activates the instance it drained before it exits.
+func (u *updater) stopping() bool {
+	return u.shutdown != nil && u.shutdown.Err() != nil
+}
+
+// shutdownContext returns the context of AWS waiters, which is canceled when
+// the updater is asked to shut down.
+func (u *updater) shutdownContext() aws.Context {
+	if u.shutdown == nil {
+		return aws.BackgroundContext()
+	}
+	return u.shutdown
+}
+
+// sleepContext pauses for d like sleep, but returns errShutdown as soon as ctx
+// is canceled. It sleeps with sleep, so that simulation mode and tests still
+// replace the wait.
+func sleepContext(ctx aws.Context, d time.Duration) error {
+	if ctx == nil {
+		sleep(d)
+		return nil
+	}
+	if ctx.Err() != nil {
+		return errShutdown
+	}
+	wait, slept := sleep, make(chan struct{})
+	go func() {
+		wait(d)
+		close(slept)
+	}()
+	select {
+	case <-ctx.Done():
+		return errShutdown
+	case <-slept:
+		return nil
+	}
+}
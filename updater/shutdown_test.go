@@ -0,0 +1,99 @@
+package main
+
+import (
+	"context"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/aws/aws-sdk-go/service/ec2"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestStopping(t *testing.T) {
+	u := updater{}
+	assert.False(t, u.stopping(), "an updater without a shutdown context never stops")
+	assert.NoError(t, u.shutdownContext().Err())
+
+	shutdown, cancel := context.WithCancel(context.Background())
+	u.shutdown = shutdown
+	assert.False(t, u.stopping())
+	cancel()
+	assert.True(t, u.stopping())
+	assert.Error(t, u.shutdownContext().Err())
+}
+
+func TestSleepContext(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	var slept []time.Duration
+	sleep = func(d time.Duration) { slept = append(slept, d) }
+
+	assert.NoError(t, sleepContext(nil, time.Second))
+	shutdown, cancel := context.WithCancel(context.Background())
+	assert.NoError(t, sleepContext(shutdown, time.Minute))
+	assert.Equal(t, []time.Duration{time.Second, time.Minute}, slept)
+
+	// A real wait that the updater is asked to shut down in the middle of.
+	sleep = time.Sleep
+	time.AfterFunc(10*time.Millisecond, cancel)
+	start := time.Now()
+	assert.ErrorIs(t, sleepContext(shutdown, time.Hour), errShutdown)
+	assert.Less(t, time.Since(start), time.Minute, "the wait should stop on shutdown")
+	assert.ErrorIs(t, sleepContext(shutdown, time.Hour), errShutdown, "a stopping updater should not wait")
+}
+
+func TestDrainInstanceShutdown(t *testing.T) {
+	t.Run("drain", func(t *testing.T) {
+		shutdown, cancel := context.WithCancel(context.Background())
+		stateChangeCalls := []string{}
+		mockECS := MockECS{
+			UpdateContainerInstancesStateFn: func(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+				stateChangeCalls = append(stateChangeCalls, aws.StringValue(input.Status))
+				return &ecs.UpdateContainerInstancesStateOutput{}, nil
+			},
+			ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+				return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-arn-1"})}, nil
+			},
+			WaitUntilTasksStoppedWithContextFn: func(ctx aws.Context, _ *ecs.DescribeTasksInput, _ ...request.WaiterOption) error {
+				// ECS stops the updater while it waits for the tasks to stop.
+				cancel()
+				return awserr.New(request.CanceledErrorCode, "waiter context canceled", ctx.Err())
+			},
+		}
+		u := updater{ecs: mockECS, cluster: "test-cluster", shutdown: shutdown}
+		err := u.drainInstance(instance{containerInstanceID: "cont-inst-id"}, nil)
+		require.Error(t, err)
+		assert.True(t, u.stopping())
+		assert.Equal(t, []string{"DRAINING", "ACTIVE"}, stateChangeCalls, "the drained instance should be re-activated")
+	})
+	t.Run("reboot", func(t *testing.T) {
+		originalSleep := sleep
+		defer func() { sleep = originalSleep }()
+		sleep = func(time.Duration) {}
+
+		shutdown, cancel := context.WithCancel(context.Background())
+		mockSSM := MockSSM{
+			SendCommandFn: func(_ *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+				return &ssm.SendCommandOutput{Command: &ssm.Command{CommandId: aws.String("reboot-id")}}, nil
+			},
+		}
+		mockEC2 := MockEC2{
+			WaitUntilInstanceStatusOkWithContextFn: func(ctx aws.Context, _ *ec2.DescribeInstanceStatusInput, _ ...request.WaiterOption) error {
+				assert.Equal(t, shutdown, ctx, "the reboot wait should stop on shutdown")
+				// ECS stops the updater while the instance reboots.
+				cancel()
+				return awserr.New(request.CanceledErrorCode, "waiter context canceled", ctx.Err())
+			},
+		}
+		u := updater{ssm: mockSSM, ec2: mockEC2, cluster: "test-cluster", rebootDocument: "reboot-document", shutdown: shutdown}
+		err := u.rebootInstance(instance{instanceID: "i-1", containerInstanceID: "cont-inst-id"})
+		require.Error(t, err)
+		assert.ErrorIs(t, err, errShutdown, "the pass should re-activate the instance and stop")
+	})
+}
@@ -0,0 +1,84 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"net/http"
+	"os"
+	"sort"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+// taskMetadataEnv is set by ECS to the URI of the task metadata endpoint
+// version 4 in every container of a task on Fargate or an up-to-date agent.
+const taskMetadataEnv = "ECS_CONTAINER_METADATA_URI_V4"
+
+var taskMetadataClient = &http.Client{Timeout: 5 * time.Second}
+
+// ownTaskARN returns the ARN of the updater's own ECS task from the task
+// metadata endpoint, or an empty ARN outside of ECS.
+func ownTaskARN() (string, error) {
+	uri := os.Getenv(taskMetadataEnv)
+	if uri == "" {
+		return "", nil
+	}
+	resp, err := taskMetadataClient.Get(uri + "/task")
+	if err != nil {
+		return "", fmt.Errorf("failed to read task metadata: %w", err)
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return "", fmt.Errorf("failed to read task metadata: %s", resp.Status)
+	}
+	var metadata struct {
+		TaskARN string `json:"TaskARN"`
+	}
+	if err := json.NewDecoder(resp.Body).Decode(&metadata); err != nil {
+		return "", fmt.Errorf("failed to parse task metadata: %w", err)
+	}
+	return metadata.TaskARN, nil
+}
+
+// checkTaskGroup is the task group of the updater tasks the stack's check
+// schedule starts. They only check, so update runs don't count them as
+// siblings.
+const checkTaskGroup = "bottlerocket-ecs-updater-check"
+
+// updaterTasks describes the running updater tasks, leaving out the tasks of
+// checkTaskGroup.
+func (u *updater) updaterTasks(taskARNs []*string) ([]*ecs.Task, error) {
+	resp, err := u.ecs.DescribeTasks(&ecs.DescribeTasksInput{
+		Cluster: &u.cluster,
+		Tasks:   taskARNs,
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe running updater tasks: %w", err)
+	}
+	if len(resp.Tasks) == 0 {
+		return nil, fmt.Errorf("running updater tasks not found: %v", resp.Failures)
+	}
+	tasks := make([]*ecs.Task, 0, len(resp.Tasks))
+	for _, task := range resp.Tasks {
+		if aws.StringValue(task.Group) != checkTaskGroup {
+			tasks = append(tasks, task)
+		}
+	}
+	return tasks, nil
+}
+
+// leaderTask returns the updater task that leads among sibling tasks: the one
+// created first, with ties broken by ARN, so that every sibling elects the
+// same leader without coordinating.
+func leaderTask(tasks []*ecs.Task) string {
+	sort.Slice(tasks, func(i, j int) bool {
+		a, b := aws.TimeValue(tasks[i].CreatedAt), aws.TimeValue(tasks[j].CreatedAt)
+		if !a.Equal(b) {
+			return a.Before(b)
+		}
+		return aws.StringValue(tasks[i].TaskArn) < aws.StringValue(tasks[j].TaskArn)
+	})
+	return aws.StringValue(tasks[0].TaskArn)
+}
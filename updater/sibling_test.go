@@ -0,0 +1,39 @@
+package main
+
+import (
+	"net/http"
+	"net/http/httptest"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestOwnTaskARN(t *testing.T) {
+	t.Run("outside of ECS", func(t *testing.T) {
+		t.Setenv(taskMetadataEnv, "")
+		self, err := ownTaskARN()
+		require.NoError(t, err)
+		assert.Empty(t, self)
+	})
+	t.Run("task metadata", func(t *testing.T) {
+		server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+			assert.Equal(t, "/v4/task", r.URL.Path)
+			_, _ = w.Write([]byte(`{"Cluster": "ecs-cluster", "TaskARN": "task-arn-1"}`))
+		}))
+		defer server.Close()
+		t.Setenv(taskMetadataEnv, server.URL+"/v4")
+		self, err := ownTaskARN()
+		require.NoError(t, err)
+		assert.Equal(t, "task-arn-1", self)
+	})
+	t.Run("endpoint error", func(t *testing.T) {
+		server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, _ *http.Request) {
+			w.WriteHeader(http.StatusInternalServerError)
+		}))
+		defer server.Close()
+		t.Setenv(taskMetadataEnv, server.URL)
+		_, err := ownTaskARN()
+		assert.Error(t, err)
+	})
+}
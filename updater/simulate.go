@@ -0,0 +1,1013 @@
+package main
+
+import (
+	"bytes"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"sort"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/aws/aws-sdk-go/service/ec2"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"gopkg.in/yaml.v3"
+)
+
+const (
+	simCheckDocument     = "simulated-check-document"
+	simApplyDocument     = "simulated-apply-document"
+	simRebootDocument    = "simulated-reboot-document"
+	simRegion            = "us-west-2"
+	simAccount           = "000000000000"
+	defaultSimCluster    = "simulated-cluster"
+	defaultSimDrainTime  = time.Minute
+	defaultSimRebootTime = 2 * time.Minute
+	defaultSimUpdateAge  = 30 * 24 * time.Hour
+	simArch              = "x86_64"
+	// defaultSimImageID and defaultSimInstanceType describe simulated
+	// instances of scenarios that do not set integ.ami_id and integ.instance_type.
+	defaultSimImageID      = "ami-00000000000000000"
+	defaultSimInstanceType = "m5.large"
+)
+
+// scenarioVersion is the version of the scenario format understood by this
+// build. Scenarios that leave out the version are assumed to be this version.
+const scenarioVersion = 1
+
+// Failures that can be injected into a simulated instance.
+const (
+	failDrain  = "drain"
+	failCheck  = "check"
+	failApply  = "apply"
+	failReboot = "reboot"
+	failDaemon = "daemon"
+)
+
+// scenario describes a synthetic cluster. The same file drives simulation mode
+// and the integration test setup in integ/setup.sh.
+type scenario struct {
+	Version   int                `yaml:"version"`
+	Cluster   string             `yaml:"cluster"`
+	Integ     scenarioInteg      `yaml:"integ"`
+	Instances []scenarioInstance `yaml:"instances"`
+	// DeferredServices lists the services tagged ecs-updater/defer=true.
+	DeferredServices []string `yaml:"deferred_services"`
+	// DaemonServices lists the services that use the DAEMON scheduling strategy.
+	DaemonServices []string `yaml:"daemon_services"`
+}
+
+// scenarioInteg holds the settings only used when a scenario is deployed as a
+// real cluster by the integration tests.
+type scenarioInteg struct {
+	// AMIID is the Bottlerocket ECS AMI launched for every Bottlerocket instance.
+	AMIID string `yaml:"ami_id"`
+	// InstanceType is the EC2 instance type of the launched instances.
+	InstanceType string `yaml:"instance_type"`
+}
+
+// scenarioInstance describes a container instance in a simulated cluster.
+type scenarioInstance struct {
+	// ID is the EC2 instance ID.
+	ID string `yaml:"id"`
+	// Variant is the Bottlerocket variant; instances without one are not running Bottlerocket.
+	Variant string `yaml:"variant"`
+	// Version is the Bottlerocket version the instance is running.
+	Version string `yaml:"version"`
+	// UpdateVersion is the version the instance updates to; no update is available when empty.
+	UpdateVersion string `yaml:"update_version"`
+	// UpdateAge is how long ago UpdateVersion was published.
+	UpdateAge time.Duration `yaml:"update_age"`
+	// Attributes are custom ECS attributes of the container instance.
+	Attributes map[string]string `yaml:"attributes"`
+	// Tags are the tags of the container instance, e.g. to quarantine it.
+	Tags map[string]string `yaml:"tags"`
+	// Services lists the service of each service task running on the instance.
+	Services []string `yaml:"services"`
+	// StandaloneTasks is the number of tasks not started by a service.
+	StandaloneTasks int `yaml:"standalone_tasks"`
+	// SingletonTasks lists the task definition family of each standalone
+	// task running on the instance that -singleton-families may name.
+	SingletonTasks []string `yaml:"singleton_tasks"`
+	// DrainTime is how long the instance's service tasks take to stop.
+	DrainTime time.Duration `yaml:"drain_time"`
+	// RebootTime is how long the instance takes to reach Ok status after a reboot.
+	RebootTime time.Duration `yaml:"reboot_time"`
+	// SSM describes how the instance's SSM agent behaves.
+	SSM scenarioSSM `yaml:"ssm"`
+	// Fail lists the steps that fail on the instance: drain, check, apply,
+	// reboot, or daemon, where the tasks of daemon services do not start
+	// again after the instance is reactivated.
+	Fail []string `yaml:"fail"`
+	// AgentDisconnected makes ECS report the instance's ECS agent as disconnected.
+	AgentDisconnected bool `yaml:"agent_disconnected"`
+	// ScheduledEvent is the code of an EC2 scheduled event of the instance,
+	// e.g. instance-retirement or system-reboot.
+	ScheduledEvent string `yaml:"scheduled_event"`
+	// ScheduledEventIn is how long after the start of the run the scheduled event is due.
+	ScheduledEventIn time.Duration `yaml:"scheduled_event_in"`
+}
+
+// scenarioSSM describes the behavior of a simulated instance's SSM agent.
+type scenarioSSM struct {
+	// Offline makes every command sent to the instance time out before delivery.
+	Offline bool `yaml:"offline"`
+	// CheckOutput replaces the output of the check document, e.g. to return malformed JSON.
+	CheckOutput string `yaml:"check_output"`
+	// Invalid makes SSM reject every command sent to the instance as invalid
+	// for it, as when the instance was terminated after it was listed.
+	Invalid bool `yaml:"invalid"`
+}
+
+// loadScenario reads a scenario from a YAML file.
+func loadScenario(path string) (scenario, error) {
+	sc := scenario{}
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return sc, fmt.Errorf("failed to read scenario: %w", err)
+	}
+	decoder := yaml.NewDecoder(bytes.NewReader(data))
+	decoder.KnownFields(true)
+	if err := decoder.Decode(&sc); err != nil && !errors.Is(err, io.EOF) {
+		return sc, fmt.Errorf("failed to parse scenario %q: %w", path, err)
+	}
+	if sc.Version == 0 {
+		sc.Version = scenarioVersion
+	}
+	if sc.Version != scenarioVersion {
+		return sc, fmt.Errorf("scenario %q has unsupported version %d, expected %d", path, sc.Version, scenarioVersion)
+	}
+	if sc.Cluster == "" {
+		sc.Cluster = defaultSimCluster
+	}
+	seen := make(map[string]bool)
+	for i, inst := range sc.Instances {
+		if inst.ID == "" {
+			return sc, fmt.Errorf("instance %d in scenario %q has no id", i, path)
+		}
+		if seen[inst.ID] {
+			return sc, fmt.Errorf("instance %q is defined more than once in scenario %q", inst.ID, path)
+		}
+		seen[inst.ID] = true
+		for _, step := range inst.Fail {
+			switch step {
+			case failDrain, failCheck, failApply, failReboot, failDaemon:
+			default:
+				return sc, fmt.Errorf("instance %q in scenario %q has unknown failure %q", inst.ID, path, step)
+			}
+		}
+	}
+	return sc, nil
+}
+
+// simTask is a task running on a simulated container instance.
+type simTask struct {
+	arn       string
+	startedBy string
+	group     string
+	instance  *simInstance
+}
+
+// taskDefinition returns the ARN of the task's task definition, whose family
+// is the task's group without its prefix.
+func (t *simTask) taskDefinition() string {
+	family := strings.TrimPrefix(strings.TrimPrefix(t.group, serviceGroupPrefix), "family:")
+	return fmt.Sprintf("arn:aws:ecs:%s:%s:task-definition/%s:1", simRegion, simAccount, family)
+}
+
+// simInstance holds the state of a simulated container instance.
+type simInstance struct {
+	scenarioInstance
+	arn         string
+	status      string
+	updateState string
+	tasks       []*simTask
+	tags        map[string]string
+}
+
+// fails reports whether the scenario injects a failure into the given step.
+func (i *simInstance) fails(step string) bool {
+	for _, s := range i.Fail {
+		if s == step {
+			return true
+		}
+	}
+	return false
+}
+
+// commandStatus returns the status and status details of a command sent to the instance.
+func (i *simInstance) commandStatus(document string) (string, string) {
+	switch {
+	case i.SSM.Offline:
+		return ssm.CommandInvocationStatusTimedOut, "DeliveryTimedOut"
+	case document == simCheckDocument && i.fails(failCheck),
+		document == simApplyDocument && i.fails(failApply):
+		return ssm.CommandInvocationStatusFailed, ssm.CommandInvocationStatusFailed
+	}
+	return ssm.CommandInvocationStatusSuccess, ssm.CommandInvocationStatusSuccess
+}
+
+// errWaiterTimeout mimics the error returned by AWS SDK waiters that give up.
+var errWaiterTimeout = awserr.New(request.WaiterResourceNotReadyErrorCode, "exceeded wait attempts", nil)
+
+// simCommand is an SSM command sent to simulated instances.
+type simCommand struct {
+	document    string
+	comment     string
+	instanceIDs []string
+}
+
+// simEvent is an entry in the timeline of a simulation.
+type simEvent struct {
+	at         time.Duration
+	instanceID string
+	message    string
+}
+
+// simulation runs the updater against a synthetic cluster instead of AWS. Time
+// in a simulation is only advanced by the updater's sleeps and by simulated
+// drains and reboots, so the resulting timeline is deterministic.
+type simulation struct {
+	mu        sync.Mutex
+	cluster   string
+	elapsed   time.Duration
+	instances []*simInstance
+	byARN     map[string]*simInstance
+	byID      map[string]*simInstance
+	tasks     map[string]*simTask
+	commands  map[string]simCommand
+	deferred  map[string]bool
+	daemons   map[string]bool
+	events    []simEvent
+	// parameters are the SSM parameters of the simulation, such as instance
+	// locks.
+	parameters map[string]string
+	// integ holds the AMI and instance type that EC2 reports for the instances.
+	integ scenarioInteg
+	// faults are injected into the simulated AWS calls.
+	faults *faultInjector
+}
+
+func newSimulation(sc scenario) *simulation {
+	s := &simulation{
+		cluster:    sc.Cluster,
+		byARN:      make(map[string]*simInstance),
+		byID:       make(map[string]*simInstance),
+		tasks:      make(map[string]*simTask),
+		commands:   make(map[string]simCommand),
+		deferred:   make(map[string]bool),
+		daemons:    make(map[string]bool),
+		parameters: make(map[string]string),
+		integ:      sc.Integ,
+	}
+	if s.integ.AMIID == "" {
+		s.integ.AMIID = defaultSimImageID
+	}
+	if s.integ.InstanceType == "" {
+		s.integ.InstanceType = defaultSimInstanceType
+	}
+	for _, service := range sc.DeferredServices {
+		s.deferred[service] = true
+	}
+	for _, service := range sc.DaemonServices {
+		s.daemons[service] = true
+	}
+	for _, scInst := range sc.Instances {
+		inst := &simInstance{
+			scenarioInstance: scInst,
+			arn:              fmt.Sprintf("arn:aws:ecs:%s:%s:container-instance/%s/%s", simRegion, simAccount, sc.Cluster, scInst.ID),
+			status:           ecs.ContainerInstanceStatusActive,
+			updateState:      updateStateIdle,
+			tags:             make(map[string]string),
+		}
+		for key, value := range scInst.Tags {
+			inst.tags[key] = value
+		}
+		if inst.UpdateVersion != "" {
+			inst.updateState = updateStateAvailable
+		}
+		if inst.DrainTime == 0 {
+			inst.DrainTime = defaultSimDrainTime
+		}
+		if inst.RebootTime == 0 {
+			inst.RebootTime = defaultSimRebootTime
+		}
+		if inst.UpdateAge == 0 {
+			inst.UpdateAge = defaultSimUpdateAge
+		}
+		for _, service := range inst.Services {
+			s.addTask(inst, "ecs-svc/"+service, serviceGroupPrefix+service)
+		}
+		for i := 0; i < inst.StandaloneTasks; i++ {
+			s.addTask(inst, "standalone", "family:standalone")
+		}
+		for _, family := range inst.SingletonTasks {
+			s.addTask(inst, "", "family:"+family)
+		}
+		s.instances = append(s.instances, inst)
+		s.byARN[inst.arn] = inst
+		s.byID[inst.ID] = inst
+	}
+	return s
+}
+
+func (s *simulation) addTask(inst *simInstance, startedBy string, group string) {
+	task := &simTask{
+		arn:       fmt.Sprintf("arn:aws:ecs:us-west-2:000000000000:task/%s/%s-%d", s.cluster, inst.ID, len(inst.tasks)),
+		startedBy: startedBy,
+		group:     group,
+		instance:  inst,
+	}
+	inst.tasks = append(inst.tasks, task)
+	s.tasks[task.arn] = task
+}
+
+// startDaemons starts a task of each daemon service of the instance that has
+// none running, as the ECS scheduler does when an instance becomes ACTIVE;
+// callers must hold the lock.
+func (s *simulation) startDaemons(inst *simInstance) {
+	if inst.fails(failDaemon) {
+		return
+	}
+	running := make(map[string]bool)
+	for _, task := range inst.tasks {
+		running[task.group] = true
+	}
+	for _, service := range inst.Services {
+		group := serviceGroupPrefix + service
+		if s.daemons[service] && !running[group] {
+			s.addTask(inst, "ecs-svc/"+service, group)
+			running[group] = true
+			s.record(inst.ID, "daemon task of service %s started", service)
+		}
+	}
+}
+
+// updater returns an updater that manages the simulated cluster.
+func (s *simulation) updater() *updater {
+	return &updater{
+		cluster:        s.cluster,
+		checkDocument:  simCheckDocument,
+		applyDocument:  simApplyDocument,
+		rebootDocument: simRebootDocument,
+		ecs:            simECS{s},
+		ssm:            simSSM{s},
+		ec2:            simEC2{s},
+		maxUnhealthy:   1,
+		region:         simRegion,
+		account:        simAccount,
+		readiness:      defaultReadinessWeights,
+	}
+}
+
+// sleep advances the simulated clock instead of sleeping.
+func (s *simulation) sleep(d time.Duration) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	s.elapsed += d
+}
+
+// fault returns the error to inject into a simulated call to the operation,
+// recording it in the timeline; callers must hold the lock. The simulation
+// does not retry calls the way the SDK does, so every injected fault reaches
+// the updater.
+func (s *simulation) fault(operation string, kinds ...string) error {
+	err := s.faults.fault(operation, kinds...)
+	if err != nil {
+		s.record("", "%v", err)
+	}
+	return err
+}
+
+// record adds an event to the timeline; callers must hold the lock.
+func (s *simulation) record(instanceID string, format string, args ...interface{}) {
+	s.events = append(s.events, simEvent{at: s.elapsed, instanceID: instanceID, message: fmt.Sprintf(format, args...)})
+}
+
+// printTimeline writes the timeline of the simulation.
+func (s *simulation) printTimeline(w io.Writer) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	fmt.Fprintf(w, "Simulated timeline for cluster %q:\n", s.cluster)
+	for _, event := range s.events {
+		fmt.Fprintf(w, "  +%-10s %-21s %s\n", event.at, event.instanceID, event.message)
+	}
+	fmt.Fprintf(w, "Simulated run took %s\n", s.elapsed)
+}
+
+// simECS implements ECSAPI for a simulation.
+type simECS struct {
+	sim *simulation
+}
+
+var _ ECSAPI = simECS{}
+
+func (e simECS) ListContainerInstancesPages(input *ecs.ListContainerInstancesInput, fn func(*ecs.ListContainerInstancesOutput, bool) bool) error {
+	e.sim.mu.Lock()
+	arns := make([]*string, 0)
+	for _, inst := range e.sim.instances {
+		if input.Status == nil || inst.status == aws.StringValue(input.Status) {
+			arns = append(arns, aws.String(inst.arn))
+		}
+	}
+	e.sim.mu.Unlock()
+	fn(&ecs.ListContainerInstancesOutput{ContainerInstanceArns: arns}, true)
+	return nil
+}
+
+func (e simECS) DescribeContainerInstances(input *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	if err := e.sim.fault("DescribeContainerInstances", faultFail, faultTimeout); err != nil {
+		return nil, err
+	}
+	out := &ecs.DescribeContainerInstancesOutput{}
+	for _, containerInstanceARN := range input.ContainerInstances {
+		inst, ok := e.sim.byARN[aws.StringValue(containerInstanceARN)]
+		if !ok {
+			out.Failures = append(out.Failures, &ecs.Failure{Arn: containerInstanceARN, Reason: aws.String("MISSING")})
+			continue
+		}
+		attributes := []*ecs.Attribute{}
+		if inst.Variant != "" {
+			attributes = append(attributes, &ecs.Attribute{Name: aws.String("bottlerocket.variant"), Value: aws.String(inst.Variant)})
+		}
+		attributes = append(attributes, &ecs.Attribute{Name: aws.String("ecs.cpu-architecture"), Value: aws.String(simArch)})
+		for name, value := range inst.Attributes {
+			attributes = append(attributes, &ecs.Attribute{Name: aws.String(name), Value: aws.String(value)})
+		}
+		tags := []*ecs.Tag{}
+		for key, value := range inst.tags {
+			tags = append(tags, &ecs.Tag{Key: aws.String(key), Value: aws.String(value)})
+		}
+		out.ContainerInstances = append(out.ContainerInstances, &ecs.ContainerInstance{
+			Attributes:           attributes,
+			AgentConnected:       aws.Bool(!inst.AgentDisconnected),
+			ContainerInstanceArn: aws.String(inst.arn),
+			Ec2InstanceId:        aws.String(inst.ID),
+			RunningTasksCount:    aws.Int64(int64(len(inst.tasks))),
+			Status:               aws.String(inst.status),
+			Tags:                 tags,
+		})
+	}
+	return out, nil
+}
+
+func (e simECS) UpdateContainerInstancesState(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	if err := e.sim.fault("UpdateContainerInstancesState", faultFail); err != nil {
+		return nil, err
+	}
+	out := &ecs.UpdateContainerInstancesStateOutput{}
+	for _, containerInstanceARN := range input.ContainerInstances {
+		inst, ok := e.sim.byARN[aws.StringValue(containerInstanceARN)]
+		if !ok {
+			out.Failures = append(out.Failures, &ecs.Failure{Arn: containerInstanceARN, Reason: aws.String("MISSING")})
+			continue
+		}
+		inst.status = aws.StringValue(input.Status)
+		e.sim.record(inst.ID, "container instance state changed to %s", inst.status)
+		if inst.status == ecs.ContainerInstanceStatusActive {
+			e.sim.startDaemons(inst)
+		}
+	}
+	if err := e.sim.fault("UpdateContainerInstancesState", faultTimeout); err != nil {
+		return nil, err
+	}
+	return out, nil
+}
+
+func (e simECS) ListTasks(input *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	if err := e.sim.fault("ListTasks", faultFail, faultTimeout); err != nil {
+		return nil, err
+	}
+	out := &ecs.ListTasksOutput{TaskArns: []*string{}}
+	if input.Family != nil {
+		arns := make([]string, 0)
+		for arn, task := range e.sim.tasks {
+			if taskFamily(task.taskDefinition()) == aws.StringValue(input.Family) {
+				arns = append(arns, arn)
+			}
+		}
+		sort.Strings(arns)
+		out.TaskArns = aws.StringSlice(arns)
+		return out, nil
+	}
+	if input.ContainerInstance == nil {
+		// The only tasks listed outside of a container instance are the updater's own.
+		out.TaskArns = append(out.TaskArns, aws.String("arn:aws:ecs:us-west-2:000000000000:task/"+e.sim.cluster+"/simulated-updater"))
+		return out, nil
+	}
+	inst, ok := e.sim.byARN[aws.StringValue(input.ContainerInstance)]
+	if !ok {
+		return nil, fmt.Errorf("container instance %q not found", aws.StringValue(input.ContainerInstance))
+	}
+	for _, task := range inst.tasks {
+		out.TaskArns = append(out.TaskArns, aws.String(task.arn))
+	}
+	return out, nil
+}
+
+func (e simECS) DescribeTasks(input *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	if err := e.sim.fault("DescribeTasks", faultFail, faultTimeout); err != nil {
+		return nil, err
+	}
+	out := &ecs.DescribeTasksOutput{}
+	for _, taskARN := range input.Tasks {
+		task, ok := e.sim.tasks[aws.StringValue(taskARN)]
+		if !ok {
+			out.Failures = append(out.Failures, &ecs.Failure{Arn: taskARN, Reason: aws.String("MISSING")})
+			continue
+		}
+		out.Tasks = append(out.Tasks, &ecs.Task{
+			TaskArn:              aws.String(task.arn),
+			TaskDefinitionArn:    aws.String(task.taskDefinition()),
+			ContainerInstanceArn: aws.String(task.instance.arn),
+			StartedBy:            aws.String(task.startedBy),
+			Group:                aws.String(task.group),
+			LastStatus:           aws.String("RUNNING"),
+		})
+	}
+	return out, nil
+}
+
+func (e simECS) DescribeServices(input *ecs.DescribeServicesInput) (*ecs.DescribeServicesOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	out := &ecs.DescribeServicesOutput{}
+	for _, name := range input.Services {
+		service := &ecs.Service{ServiceName: name, SchedulingStrategy: aws.String(ecs.SchedulingStrategyReplica)}
+		if e.sim.daemons[aws.StringValue(name)] {
+			service.SchedulingStrategy = aws.String(ecs.SchedulingStrategyDaemon)
+		}
+		if e.sim.deferred[aws.StringValue(name)] {
+			service.Tags = []*ecs.Tag{{Key: aws.String(deferTagKey), Value: aws.String("true")}}
+		}
+		out.Services = append(out.Services, service)
+	}
+	return out, nil
+}
+
+func (e simECS) WaitUntilTasksStoppedWithContext(_ aws.Context, input *ecs.DescribeTasksInput, _ ...request.WaiterOption) error {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	drained := make(map[*simInstance]bool)
+	for _, taskARN := range input.Tasks {
+		task, ok := e.sim.tasks[aws.StringValue(taskARN)]
+		if !ok {
+			continue
+		}
+		drained[task.instance] = true
+		if !task.instance.fails(failDrain) {
+			delete(e.sim.tasks, task.arn)
+		}
+	}
+	var err error
+	for _, inst := range e.sim.instances {
+		if !drained[inst] {
+			continue
+		}
+		e.sim.elapsed += inst.DrainTime
+		if inst.fails(failDrain) {
+			e.sim.record(inst.ID, "tasks still running after %s", inst.DrainTime)
+			err = errWaiterTimeout
+			continue
+		}
+		remaining := make([]*simTask, 0)
+		for _, task := range inst.tasks {
+			if _, ok := e.sim.tasks[task.arn]; ok {
+				remaining = append(remaining, task)
+			}
+		}
+		inst.tasks = remaining
+		e.sim.record(inst.ID, "tasks stopped after %s", inst.DrainTime)
+	}
+	return err
+}
+
+func (e simECS) WaitUntilTasksRunningWithContext(_ aws.Context, _ *ecs.DescribeTasksInput, _ ...request.WaiterOption) error {
+	return nil
+}
+
+func (e simECS) DescribeClusters(_ *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+	return &ecs.DescribeClustersOutput{
+		Clusters: []*ecs.Cluster{{
+			ClusterArn:  aws.String("arn:aws:ecs:us-west-2:000000000000:cluster/" + e.sim.cluster),
+			ClusterName: aws.String(e.sim.cluster),
+		}},
+	}, nil
+}
+
+func (e simECS) DescribeCapacityProviders(_ *ecs.DescribeCapacityProvidersInput) (*ecs.DescribeCapacityProvidersOutput, error) {
+	return &ecs.DescribeCapacityProvidersOutput{}, nil
+}
+
+func (e simECS) TagResource(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	if inst, ok := e.sim.byARN[aws.StringValue(input.ResourceArn)]; ok {
+		for _, tag := range input.Tags {
+			inst.tags[aws.StringValue(tag.Key)] = aws.StringValue(tag.Value)
+			e.sim.record(inst.ID, "container instance tagged %s=%s", aws.StringValue(tag.Key), aws.StringValue(tag.Value))
+		}
+		return &ecs.TagResourceOutput{}, nil
+	}
+	for _, tag := range input.Tags {
+		e.sim.record(e.sim.cluster, "cluster tagged %s=%s", aws.StringValue(tag.Key), aws.StringValue(tag.Value))
+	}
+	return &ecs.TagResourceOutput{}, nil
+}
+
+func (e simECS) UntagResource(input *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	inst, ok := e.sim.byARN[aws.StringValue(input.ResourceArn)]
+	if !ok {
+		return nil, fmt.Errorf("resource %q not found", aws.StringValue(input.ResourceArn))
+	}
+	for _, key := range input.TagKeys {
+		delete(inst.tags, aws.StringValue(key))
+		e.sim.record(inst.ID, "container instance untagged %s", aws.StringValue(key))
+	}
+	return &ecs.UntagResourceOutput{}, nil
+}
+
+func (e simECS) StopTask(input *ecs.StopTaskInput) (*ecs.StopTaskOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	task, ok := e.sim.tasks[aws.StringValue(input.Task)]
+	if !ok {
+		return nil, fmt.Errorf("task %q not found", aws.StringValue(input.Task))
+	}
+	delete(e.sim.tasks, task.arn)
+	remaining := make([]*simTask, 0)
+	for _, t := range task.instance.tasks {
+		if t != task {
+			remaining = append(remaining, t)
+		}
+	}
+	task.instance.tasks = remaining
+	e.sim.record(task.instance.ID, "task %s stopped: %s", task.arn, aws.StringValue(input.Reason))
+	return &ecs.StopTaskOutput{}, nil
+}
+
+func (e simECS) StartTask(input *ecs.StartTaskInput) (*ecs.StartTaskOutput, error) {
+	e.sim.mu.Lock()
+	defer e.sim.mu.Unlock()
+	out := &ecs.StartTaskOutput{}
+	for _, containerInstanceARN := range input.ContainerInstances {
+		inst, ok := e.sim.byARN[aws.StringValue(containerInstanceARN)]
+		if !ok || inst.status != ecs.ContainerInstanceStatusActive {
+			out.Failures = append(out.Failures, &ecs.Failure{Arn: containerInstanceARN, Reason: aws.String("INACTIVE")})
+			continue
+		}
+		group := aws.StringValue(input.Group)
+		if group == "" {
+			group = "family:" + taskFamily(aws.StringValue(input.TaskDefinition))
+		}
+		e.sim.addTask(inst, aws.StringValue(input.StartedBy), group)
+		task := inst.tasks[len(inst.tasks)-1]
+		e.sim.record(inst.ID, "task %s started", task.arn)
+		out.Tasks = append(out.Tasks, &ecs.Task{TaskArn: aws.String(task.arn)})
+	}
+	return out, nil
+}
+
+// simSSM implements SSMAPI for a simulation.
+type simSSM struct {
+	sim *simulation
+}
+
+var _ SSMAPI = simSSM{}
+
+func (m simSSM) SendCommand(input *ssm.SendCommandInput) (*ssm.SendCommandOutput, error) {
+	m.sim.mu.Lock()
+	defer m.sim.mu.Unlock()
+	if err := m.sim.fault("SendCommand", faultFail); err != nil {
+		return nil, err
+	}
+	document := aws.StringValue(input.DocumentName)
+	invalid := make([]string, 0)
+	for _, instanceID := range aws.StringValueSlice(input.InstanceIds) {
+		if inst, ok := m.sim.byID[instanceID]; ok && inst.SSM.Invalid {
+			invalid = append(invalid, instanceID)
+		}
+	}
+	if len(invalid) > 0 {
+		return nil, awserr.New(ssm.ErrCodeInvalidInstanceId, fmt.Sprintf("Instances [[%s]] not in a valid state for account 000000000000", strings.Join(invalid, ", ")), nil)
+	}
+	commandID := fmt.Sprintf("simulated-command-%d", len(m.sim.commands)+1)
+	m.sim.commands[commandID] = simCommand{
+		document:    document,
+		comment:     aws.StringValue(input.Comment),
+		instanceIDs: aws.StringValueSlice(input.InstanceIds),
+	}
+	for _, instanceID := range input.InstanceIds {
+		inst, ok := m.sim.byID[aws.StringValue(instanceID)]
+		if !ok {
+			return nil, fmt.Errorf("invalid instance id %q", aws.StringValue(instanceID))
+		}
+		if status, details := inst.commandStatus(document); status != ssm.CommandInvocationStatusSuccess {
+			m.sim.record(inst.ID, "%s: %s", document, details)
+			continue
+		}
+		switch document {
+		case simCheckDocument:
+			m.sim.record(inst.ID, "update check: %s", inst.updateState)
+		case simApplyDocument:
+			if inst.updateState == updateStateAvailable {
+				inst.updateState = updateStateReady
+			}
+			m.sim.record(inst.ID, "update to %s applied", inst.UpdateVersion)
+		case simRebootDocument:
+			if inst.fails(failReboot) {
+				m.sim.record(inst.ID, "rebooting; instance will not pass status checks")
+				continue
+			}
+			if inst.updateState == updateStateReady {
+				inst.Version = inst.UpdateVersion
+				inst.UpdateVersion = ""
+				inst.updateState = updateStateIdle
+			}
+			m.sim.record(inst.ID, "rebooting into %s", inst.Version)
+		}
+	}
+	if err := m.sim.fault("SendCommand", faultTimeout); err != nil {
+		return nil, err
+	}
+	return &ssm.SendCommandOutput{
+		Command: &ssm.Command{
+			CommandId:    aws.String(commandID),
+			DocumentName: aws.String(document),
+		},
+	}, nil
+}
+
+func (m simSSM) ListCommandsPages(input *ssm.ListCommandsInput, fn func(*ssm.ListCommandsOutput, bool) bool) error {
+	m.sim.mu.Lock()
+	document := ""
+	for _, filter := range input.Filters {
+		if aws.StringValue(filter.Key) == ssm.CommandFilterKeyDocumentName {
+			document = aws.StringValue(filter.Value)
+		}
+	}
+	out := &ssm.ListCommandsOutput{}
+	for commandID, command := range m.sim.commands {
+		if document != "" && command.document != document {
+			continue
+		}
+		out.Commands = append(out.Commands, &ssm.Command{
+			CommandId:    aws.String(commandID),
+			Comment:      aws.String(command.comment),
+			DocumentName: aws.String(command.document),
+			InstanceIds:  aws.StringSlice(command.instanceIDs),
+		})
+	}
+	m.sim.mu.Unlock()
+	fn(out, true)
+	return nil
+}
+
+func (m simSSM) ListCommandInvocationsPages(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+	m.sim.mu.Lock()
+	if err := m.sim.fault("ListCommandInvocations", faultFail, faultTimeout); err != nil {
+		m.sim.mu.Unlock()
+		return err
+	}
+	if input.CommandId == nil {
+		// Invocations are only listed by instance to look for SSM activity of
+		// others, and there is none on the simulated fleet.
+		m.sim.mu.Unlock()
+		fn(&ssm.ListCommandInvocationsOutput{}, true)
+		return nil
+	}
+	command, ok := m.sim.commands[aws.StringValue(input.CommandId)]
+	if !ok {
+		m.sim.mu.Unlock()
+		return fmt.Errorf("command %q does not exist", aws.StringValue(input.CommandId))
+	}
+	out := &ssm.ListCommandInvocationsOutput{}
+	for _, instanceID := range command.instanceIDs {
+		inst, ok := m.sim.byID[instanceID]
+		if !ok {
+			continue
+		}
+		status, details := inst.commandStatus(command.document)
+		out.CommandInvocations = append(out.CommandInvocations, &ssm.CommandInvocation{
+			CommandId:     input.CommandId,
+			DocumentName:  aws.String(command.document),
+			InstanceId:    aws.String(instanceID),
+			Status:        aws.String(status),
+			StatusDetails: aws.String(details),
+		})
+	}
+	m.sim.mu.Unlock()
+	fn(out, true)
+	return nil
+}
+
+func (m simSSM) GetCommandInvocation(input *ssm.GetCommandInvocationInput) (*ssm.GetCommandInvocationOutput, error) {
+	m.sim.mu.Lock()
+	defer m.sim.mu.Unlock()
+	if err := m.sim.fault("GetCommandInvocation", faultFail, faultTimeout); err != nil {
+		return nil, err
+	}
+	command, ok := m.sim.commands[aws.StringValue(input.CommandId)]
+	if !ok {
+		return nil, fmt.Errorf("invocation for command %q does not exist", aws.StringValue(input.CommandId))
+	}
+	inst, ok := m.sim.byID[aws.StringValue(input.InstanceId)]
+	if !ok {
+		return nil, fmt.Errorf("invocation for instance %q does not exist", aws.StringValue(input.InstanceId))
+	}
+	status, details := inst.commandStatus(command.document)
+	out := &ssm.GetCommandInvocationOutput{
+		CommandId:     input.CommandId,
+		InstanceId:    input.InstanceId,
+		Status:        aws.String(status),
+		StatusDetails: aws.String(details),
+	}
+	if command.document != simCheckDocument || aws.StringValue(out.Status) != ssm.CommandInvocationStatusSuccess {
+		return out, nil
+	}
+	if inst.SSM.CheckOutput != "" {
+		out.StandardOutputContent = aws.String(inst.SSM.CheckOutput)
+		return out, nil
+	}
+	output := checkOutput{UpdateState: inst.updateState}
+	output.ActivePartition.Image.Version = inst.Version
+	if inst.updateState == updateStateAvailable {
+		output.ChosenUpdate = &release{Arch: simArch, Version: inst.UpdateVersion, Variant: inst.Variant}
+	}
+	content, err := json.Marshal(output)
+	if err != nil {
+		return nil, err
+	}
+	out.StandardOutputContent = aws.String(string(content))
+	return out, nil
+}
+
+// GetParameter returns the public parameter of a release that a simulated
+// instance can update to, last modified when the release was published.
+func (m simSSM) GetParameter(input *ssm.GetParameterInput) (*ssm.GetParameterOutput, error) {
+	m.sim.mu.Lock()
+	defer m.sim.mu.Unlock()
+	if value, ok := m.sim.parameters[aws.StringValue(input.Name)]; ok {
+		return &ssm.GetParameterOutput{Parameter: &ssm.Parameter{Name: input.Name, Value: aws.String(value)}}, nil
+	}
+	for _, inst := range m.sim.instances {
+		if inst.UpdateVersion == "" {
+			continue
+		}
+		r := release{Arch: simArch, Version: inst.UpdateVersion, Variant: inst.Variant}
+		if r.parameter() == aws.StringValue(input.Name) {
+			return &ssm.GetParameterOutput{Parameter: &ssm.Parameter{
+				Name:             input.Name,
+				LastModifiedDate: aws.Time(time.Now().Add(-inst.UpdateAge)),
+			}}, nil
+		}
+	}
+	return nil, awserr.New(ssm.ErrCodeParameterNotFound, fmt.Sprintf("parameter %q not found", aws.StringValue(input.Name)), nil)
+}
+
+// PutParameter creates a parameter, such as an instance lock.
+func (m simSSM) PutParameter(input *ssm.PutParameterInput) (*ssm.PutParameterOutput, error) {
+	m.sim.mu.Lock()
+	defer m.sim.mu.Unlock()
+	name := aws.StringValue(input.Name)
+	if _, ok := m.sim.parameters[name]; ok && !aws.BoolValue(input.Overwrite) {
+		return nil, awserr.New(ssm.ErrCodeParameterAlreadyExists, fmt.Sprintf("parameter %q already exists", name), nil)
+	}
+	m.sim.parameters[name] = aws.StringValue(input.Value)
+	m.sim.record(m.sim.cluster, "parameter %s created", name)
+	return &ssm.PutParameterOutput{Version: aws.Int64(1)}, nil
+}
+
+// DeleteParameter deletes a parameter created with PutParameter.
+func (m simSSM) DeleteParameter(input *ssm.DeleteParameterInput) (*ssm.DeleteParameterOutput, error) {
+	m.sim.mu.Lock()
+	defer m.sim.mu.Unlock()
+	name := aws.StringValue(input.Name)
+	if _, ok := m.sim.parameters[name]; !ok {
+		return nil, awserr.New(ssm.ErrCodeParameterNotFound, fmt.Sprintf("parameter %q not found", name), nil)
+	}
+	delete(m.sim.parameters, name)
+	m.sim.record(m.sim.cluster, "parameter %s deleted", name)
+	return &ssm.DeleteParameterOutput{}, nil
+}
+
+// simWindowExecution is the execution of the simulated maintenance window,
+// which is always open.
+const simWindowExecution = "00000000-0000-4000-8000-000000000000"
+
+func (m simSSM) GetMaintenanceWindow(input *ssm.GetMaintenanceWindowInput) (*ssm.GetMaintenanceWindowOutput, error) {
+	return &ssm.GetMaintenanceWindowOutput{WindowId: input.WindowId, Name: aws.String("simulated"), Enabled: aws.Bool(true)}, nil
+}
+
+func (m simSSM) DescribeMaintenanceWindowExecutions(input *ssm.DescribeMaintenanceWindowExecutionsInput) (*ssm.DescribeMaintenanceWindowExecutionsOutput, error) {
+	return &ssm.DescribeMaintenanceWindowExecutionsOutput{WindowExecutions: []*ssm.MaintenanceWindowExecution{{
+		WindowId:          input.WindowId,
+		WindowExecutionId: aws.String(simWindowExecution),
+		Status:            aws.String(ssm.MaintenanceWindowExecutionStatusInProgress),
+	}}}, nil
+}
+
+// DescribeAutomationExecutions returns no executions: nothing but the updater
+// runs on the simulated fleet.
+func (m simSSM) DescribeAutomationExecutions(_ *ssm.DescribeAutomationExecutionsInput) (*ssm.DescribeAutomationExecutionsOutput, error) {
+	return &ssm.DescribeAutomationExecutionsOutput{}, nil
+}
+
+func (m simSSM) AddTagsToResource(input *ssm.AddTagsToResourceInput) (*ssm.AddTagsToResourceOutput, error) {
+	m.sim.mu.Lock()
+	defer m.sim.mu.Unlock()
+	for _, tag := range input.Tags {
+		m.sim.record(m.sim.cluster, "%s tagged %s=%s", aws.StringValue(input.ResourceId), aws.StringValue(tag.Key), aws.StringValue(tag.Value))
+	}
+	return &ssm.AddTagsToResourceOutput{}, nil
+}
+
+// simEC2 implements EC2API for a simulation.
+type simEC2 struct {
+	sim *simulation
+}
+
+var _ EC2API = simEC2{}
+
+func (c simEC2) WaitUntilInstanceStatusOkWithContext(_ aws.Context, input *ec2.DescribeInstanceStatusInput, _ ...request.WaiterOption) error {
+	c.sim.mu.Lock()
+	defer c.sim.mu.Unlock()
+	for _, instanceID := range input.InstanceIds {
+		inst, ok := c.sim.byID[aws.StringValue(instanceID)]
+		if !ok {
+			return fmt.Errorf("instance %q not found", aws.StringValue(instanceID))
+		}
+		c.sim.elapsed += inst.RebootTime
+		if inst.fails(failReboot) {
+			c.sim.record(inst.ID, "instance status checks still failing after %s", inst.RebootTime)
+			return errWaiterTimeout
+		}
+		c.sim.record(inst.ID, "instance status Ok after %s", inst.RebootTime)
+	}
+	return nil
+}
+
+func (c simEC2) DescribeInstancesPages(input *ec2.DescribeInstancesInput, fn func(*ec2.DescribeInstancesOutput, bool) bool) error {
+	c.sim.mu.Lock()
+	reservation := &ec2.Reservation{}
+	for _, filter := range input.Filters {
+		if aws.StringValue(filter.Name) != "instance-id" {
+			continue
+		}
+		for _, instanceID := range filter.Values {
+			inst, ok := c.sim.byID[aws.StringValue(instanceID)]
+			if !ok {
+				continue
+			}
+			reservation.Instances = append(reservation.Instances, &ec2.Instance{
+				InstanceId:   aws.String(inst.ID),
+				ImageId:      aws.String(c.sim.integ.AMIID),
+				InstanceType: aws.String(c.sim.integ.InstanceType),
+				Architecture: aws.String(simArch),
+			})
+		}
+	}
+	c.sim.mu.Unlock()
+	fn(&ec2.DescribeInstancesOutput{Reservations: []*ec2.Reservation{reservation}}, true)
+	return nil
+}
+
+func (c simEC2) DescribeInstanceStatusPages(input *ec2.DescribeInstanceStatusInput, fn func(*ec2.DescribeInstanceStatusOutput, bool) bool) error {
+	c.sim.mu.Lock()
+	output := &ec2.DescribeInstanceStatusOutput{}
+	for _, instanceID := range input.InstanceIds {
+		inst, ok := c.sim.byID[aws.StringValue(instanceID)]
+		if !ok {
+			c.sim.mu.Unlock()
+			return fmt.Errorf("instance %q not found", aws.StringValue(instanceID))
+		}
+		status := &ec2.InstanceStatus{InstanceId: aws.String(inst.ID)}
+		if inst.ScheduledEvent != "" {
+			status.Events = []*ec2.InstanceStatusEvent{{
+				Code:      aws.String(inst.ScheduledEvent),
+				NotBefore: aws.Time(time.Now().Add(inst.ScheduledEventIn)),
+			}}
+		}
+		output.InstanceStatuses = append(output.InstanceStatuses, status)
+	}
+	c.sim.mu.Unlock()
+	fn(output, true)
+	return nil
+}
@@ -0,0 +1,845 @@
+package main
+
+import (
+	"bytes"
+	"os"
+	"path/filepath"
+	"sort"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+const testScenario = `
+cluster: sim-cluster
+instances:
+  - id: i-updatable
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [web, web, worker]
+    drain_time: 5m
+  - id: i-standalone
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    standalone_tasks: 1
+  - id: i-current
+    variant: aws-ecs-2
+    version: 1.20.0
+  - id: i-amazon-linux
+`
+
+func writeScenario(t *testing.T, content string) string {
+	path := filepath.Join(t.TempDir(), "scenario.yaml")
+	require.NoError(t, os.WriteFile(path, []byte(content), 0o600))
+	return path
+}
+
+func TestLoadScenario(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, testScenario))
+	require.NoError(t, err)
+	assert.Equal(t, "sim-cluster", sc.Cluster)
+	require.Len(t, sc.Instances, 4)
+	assert.Equal(t, []string{"web", "web", "worker"}, sc.Instances[0].Services)
+	assert.Equal(t, 5*time.Minute, sc.Instances[0].DrainTime)
+	assert.Equal(t, 1, sc.Instances[1].StandaloneTasks)
+	assert.Equal(t, "", sc.Instances[3].Variant)
+
+	t.Run("default cluster", func(t *testing.T) {
+		sc, err := loadScenario(writeScenario(t, "instances: [{id: i-1}]"))
+		require.NoError(t, err)
+		assert.Equal(t, defaultSimCluster, sc.Cluster)
+	})
+	t.Run("duplicate instance", func(t *testing.T) {
+		_, err := loadScenario(writeScenario(t, "instances: [{id: i-1}, {id: i-1}]"))
+		require.Error(t, err)
+		assert.Contains(t, err.Error(), "instance \"i-1\" is defined more than once")
+	})
+	t.Run("missing id", func(t *testing.T) {
+		_, err := loadScenario(writeScenario(t, "instances: [{variant: aws-ecs-2}]"))
+		require.Error(t, err)
+		assert.Contains(t, err.Error(), "has no id")
+	})
+	t.Run("explicit version", func(t *testing.T) {
+		sc, err := loadScenario(writeScenario(t, "version: 1\ninstances: [{id: i-1}]"))
+		require.NoError(t, err)
+		assert.Equal(t, scenarioVersion, sc.Version)
+	})
+	t.Run("unsupported version", func(t *testing.T) {
+		_, err := loadScenario(writeScenario(t, "version: 2\ninstances: [{id: i-1}]"))
+		require.Error(t, err)
+		assert.Contains(t, err.Error(), "unsupported version 2")
+	})
+	t.Run("unknown field", func(t *testing.T) {
+		_, err := loadScenario(writeScenario(t, "instances: [{id: i-1, drain: 5m}]"))
+		require.Error(t, err)
+		assert.Contains(t, err.Error(), "field drain not found")
+	})
+	t.Run("unknown failure", func(t *testing.T) {
+		_, err := loadScenario(writeScenario(t, "instances: [{id: i-1, fail: [explode]}]"))
+		require.Error(t, err)
+		assert.Contains(t, err.Error(), "unknown failure \"explode\"")
+	})
+	t.Run("integ and ssm settings", func(t *testing.T) {
+		sc, err := loadScenario(writeScenario(t, `
+integ:
+  ami_id: ami-0123456789abcdef0
+  instance_type: m5.large
+instances:
+  - id: i-1
+    ssm:
+      offline: true
+      check_output: not json
+    fail: [drain, reboot]
+`))
+		require.NoError(t, err)
+		assert.Equal(t, "ami-0123456789abcdef0", sc.Integ.AMIID)
+		assert.Equal(t, "m5.large", sc.Integ.InstanceType)
+		require.Len(t, sc.Instances, 1)
+		assert.True(t, sc.Instances[0].SSM.Offline)
+		assert.Equal(t, "not json", sc.Instances[0].SSM.CheckOutput)
+		assert.Equal(t, []string{failDrain, failReboot}, sc.Instances[0].Fail)
+	})
+	t.Run("empty file", func(t *testing.T) {
+		sc, err := loadScenario(writeScenario(t, ""))
+		require.NoError(t, err)
+		assert.Empty(t, sc.Instances)
+	})
+	t.Run("missing file", func(t *testing.T) {
+		_, err := loadScenario(filepath.Join(t.TempDir(), "missing.yaml"))
+		require.Error(t, err)
+	})
+}
+
+func TestSimulatedRun(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, testScenario))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	require.NoError(t, run(sim.updater()))
+
+	assert.Equal(t, "1.20.0", sim.byID["i-updatable"].Version)
+	assert.Equal(t, updateStateIdle, sim.byID["i-updatable"].updateState)
+	assert.Equal(t, "ACTIVE", sim.byID["i-updatable"].status)
+	assert.Empty(t, sim.byID["i-updatable"].tasks, "service tasks should have been drained")
+	assert.Equal(t, "1.19.0", sim.byID["i-standalone"].Version, "instances with standalone tasks should be skipped")
+	assert.Equal(t, "1.20.0", sim.byID["i-current"].Version)
+
+	var timeline bytes.Buffer
+	sim.printTimeline(&timeline)
+	assert.Contains(t, timeline.String(), "Simulated timeline for cluster \"sim-cluster\"")
+	assert.Contains(t, timeline.String(), "tasks stopped after 5m0s")
+	assert.Contains(t, timeline.String(), "rebooting into 1.20.0")
+	// 5m drain, 15s and 20s waits around the reboot, and the 2m default reboot time
+	assert.Contains(t, timeline.String(), "Simulated run took 7m35s")
+}
+
+func TestSimulatedFailures(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-drain
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [web]
+    fail: [drain]
+  - id: i-apply
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    fail: [apply]
+  - id: i-reboot
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    fail: [reboot]
+  - id: i-offline
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    ssm:
+      offline: true
+  - id: i-garbled
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    ssm:
+      check_output: not json
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	require.NoError(t, run(sim.updater()))
+	for _, id := range []string{"i-drain", "i-apply", "i-reboot", "i-offline", "i-garbled"} {
+		assert.Equal(t, "1.19.0", sim.byID[id].Version, "instance %s should not have been updated", id)
+		assert.Equal(t, "ACTIVE", sim.byID[id].status, "instance %s should have been re-activated", id)
+	}
+	assert.Len(t, sim.byID["i-drain"].tasks, 1, "tasks on an instance that fails to drain should keep running")
+
+	var timeline bytes.Buffer
+	sim.printTimeline(&timeline)
+	assert.Contains(t, timeline.String(), "tasks still running after 1m0s")
+	assert.Contains(t, timeline.String(), "simulated-apply-document: Failed")
+	assert.Contains(t, timeline.String(), "instance status checks still failing after 2m0s")
+	assert.Contains(t, timeline.String(), "simulated-check-document: DeliveryTimedOut")
+}
+
+func TestSimulatedInvalidTargets(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-updatable
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+  - id: i-terminated
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    ssm:
+      invalid: true
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	u := sim.updater()
+	require.NoError(t, run(u))
+	assert.Equal(t, "1.20.0", sim.byID["i-updatable"].Version, "instances SSM accepts should be updated")
+	assert.Equal(t, "1.19.0", sim.byID["i-terminated"].Version)
+	assert.Equal(t, 1, u.warnings.count(warnSkipped))
+}
+
+func TestSimulatedCheckCache(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-latest-1
+    variant: aws-ecs-2
+    version: 1.20.0
+  - id: i-latest-2
+    variant: aws-ecs-2
+    version: 1.20.0
+  - id: i-behind
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	sim.parameters[latestVersionParameter("aws-ecs-2", simArch)] = "1.20.0-fcf15d3a"
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+	checked := func() []string {
+		ids := make([]string, 0)
+		for _, command := range sim.commands {
+			if command.document == simCheckDocument {
+				ids = append(ids, command.instanceIDs...)
+			}
+		}
+		sort.Strings(ids)
+		return ids
+	}
+
+	u := sim.updater()
+	u.checkCacheTTL = 6 * time.Hour
+	require.NoError(t, run(u))
+	assert.Equal(t, []string{"i-behind", "i-latest-1", "i-latest-2"}, checked())
+	assert.Equal(t, "1.20.0", sim.byID["i-behind"].Version)
+
+	sim.commands = make(map[string]simCommand)
+	u = sim.updater()
+	u.checkCacheTTL = 6 * time.Hour
+	require.NoError(t, run(u))
+	assert.Equal(t, []string{"i-behind"}, checked(), "instances checked running the latest version should not be checked again")
+
+	sim.parameters[latestVersionParameter("aws-ecs-2", simArch)] = "1.21.0-0a1b2c3d"
+	sim.commands = make(map[string]simCommand)
+	u = sim.updater()
+	u.checkCacheTTL = 6 * time.Hour
+	require.NoError(t, run(u))
+	assert.Equal(t, []string{"i-behind", "i-latest-1", "i-latest-2"}, checked(), "instances should be checked once a newer version is released")
+}
+
+func TestSimulatedMaxUpdates(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-first
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+  - id: i-second
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+  - id: i-third
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	u := sim.updater()
+	u.maxUpdates = 2
+	require.NoError(t, run(u))
+	updated := 0
+	for _, id := range []string{"i-first", "i-second", "i-third"} {
+		if sim.byID[id].Version == "1.20.0" {
+			updated++
+		}
+	}
+	assert.Equal(t, 2, updated, "should update no more instances than the limit")
+}
+
+func TestSimulatedSettleTime(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	simulate := func(settleTime time.Duration) time.Duration {
+		sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-first
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+  - id: i-second
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+`))
+		require.NoError(t, err)
+		sim := newSimulation(sc)
+		sleep = sim.sleep
+		u := sim.updater()
+		u.settleTime = settleTime
+		require.NoError(t, run(u))
+		assert.Equal(t, "1.20.0", sim.byID["i-first"].Version)
+		assert.Equal(t, "1.20.0", sim.byID["i-second"].Version)
+		return sim.elapsed
+	}
+	// the pass waits once, between the two instances.
+	assert.Equal(t, 10*time.Minute, simulate(10*time.Minute)-simulate(0))
+}
+
+func TestSimulatedSingletonTasks(t *testing.T) {
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	simulate := func(singletonFamilies map[string]bool) *simulation {
+		sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-scheduler
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    singleton_tasks: [scheduler]
+  - id: i-spare
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+`))
+		require.NoError(t, err)
+		sim := newSimulation(sc)
+		sleep = sim.sleep
+		u := sim.updater()
+		u.singletonFamilies = singletonFamilies
+		require.NoError(t, run(u))
+		return sim
+	}
+
+	sim := simulate(nil)
+	assert.Equal(t, "1.19.0", sim.byID["i-scheduler"].Version, "instances with standalone tasks should not be updated")
+	assert.Equal(t, "1.20.0", sim.byID["i-spare"].Version)
+
+	sim = simulate(map[string]bool{"scheduler": true})
+	assert.Equal(t, "1.20.0", sim.byID["i-scheduler"].Version)
+	assert.Equal(t, "1.20.0", sim.byID["i-spare"].Version)
+	schedulers := 0
+	for _, task := range sim.tasks {
+		if task.group == "family:scheduler" {
+			schedulers++
+		}
+	}
+	assert.Equal(t, 1, schedulers, "the singleton task should keep running exactly once")
+	replaced := false
+	for _, event := range sim.events {
+		if event.instanceID == "i-spare" && strings.HasSuffix(event.message, " started") {
+			replaced = true
+		}
+		if event.instanceID == "i-scheduler" && event.message == "container instance state changed to DRAINING" {
+			assert.True(t, replaced, "the singleton task should be replaced before its instance is drained")
+			break
+		}
+	}
+}
+
+func TestSimulatedUnhealthyCluster(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-healthy
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+  - id: i-disconnected
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    agent_disconnected: true
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	u := sim.updater()
+	u.maxUnhealthy = 0.25
+	err = run(u)
+	require.Error(t, err)
+	assert.Contains(t, err.Error(), "1 of 2 container instance(s) are unhealthy")
+	assert.Equal(t, "1.19.0", sim.byID["i-healthy"].Version, "should not update instances of an unhealthy cluster")
+	assert.Empty(t, sim.commands, "should not send commands to an unhealthy cluster")
+
+	u = sim.updater()
+	u.maxUnhealthy = 0.25
+	u.makeReadOnly()
+	require.NoError(t, run(u), "check-only runs should not abort on an unhealthy cluster")
+	assert.Positive(t, u.warnings.counts[warnDegraded], "the unhealthy cluster should be warned about")
+	assert.Equal(t, "1.19.0", sim.byID["i-healthy"].Version)
+
+	u = sim.updater()
+	u.maxUnhealthy = 0.5
+	require.NoError(t, run(u))
+	assert.Equal(t, "1.20.0", sim.byID["i-healthy"].Version, "should update when the unhealthy share is within the limit")
+}
+
+func TestSimulatedQuarantine(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-quarantined
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    tags:
+      bottlerocket-ecs-updater:quarantine-until: "2999-01-01T00:00:00Z"
+      bottlerocket-ecs-updater:quarantine-reason: kernel panic under investigation
+  - id: i-expired
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    tags:
+      bottlerocket-ecs-updater:quarantine-until: "2000-01-01T00:00:00Z"
+      bottlerocket-ecs-updater:quarantine-reason: waiting for a fix
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	u := sim.updater()
+	require.NoError(t, run(u))
+	assert.Equal(t, "1.19.0", sim.byID["i-quarantined"].Version, "should not update a quarantined instance")
+	assert.Equal(t, "1.20.0", sim.byID["i-expired"].Version, "should update an instance whose quarantine expired")
+	assert.Empty(t, sim.byID["i-expired"].tags, "should clear an expired quarantine")
+	assert.Len(t, sim.byID["i-quarantined"].tags, 2)
+	assert.Equal(t, 1, u.warnings.counts[warnQuarantineExpired])
+}
+
+func TestSimulatedScheduledEvents(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-retiring
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    scheduled_event: instance-retirement
+    scheduled_event_in: 72h
+  - id: i-plain
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+  - id: i-reboot
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [web]
+    scheduled_event: instance-reboot
+    scheduled_event_in: 24h
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	require.NoError(t, run(sim.updater()))
+	assert.Equal(t, "1.19.0", sim.byID["i-retiring"].Version, "should not update an instance scheduled for retirement")
+	assert.Equal(t, "1.20.0", sim.byID["i-plain"].Version)
+	assert.Equal(t, "1.20.0", sim.byID["i-reboot"].Version)
+	var drained []string
+	for _, event := range sim.events {
+		if event.message == "container instance state changed to "+ecs.ContainerInstanceStatusDraining {
+			drained = append(drained, event.instanceID)
+		}
+	}
+	assert.Equal(t, []string{"i-reboot", "i-plain"}, drained, "should update the instance with a scheduled reboot first")
+}
+
+func TestSimulatedRebootFleet(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, testScenario))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	u := sim.updater()
+	u.rebootOnly = true
+	require.NoError(t, run(u))
+	assert.Equal(t, "1.19.0", sim.byID["i-updatable"].Version, "should not apply updates")
+	assert.Equal(t, updateStateAvailable, sim.byID["i-updatable"].updateState)
+	assert.Equal(t, "ACTIVE", sim.byID["i-updatable"].status)
+	assert.Equal(t, "1.20.0", sim.byID["i-current"].Version)
+	var rebooted []string
+	for _, event := range sim.events {
+		assert.NotContains(t, event.message, "applied")
+		if strings.HasPrefix(event.message, "rebooting into ") {
+			rebooted = append(rebooted, event.instanceID)
+		}
+	}
+	assert.ElementsMatch(t, []string{"i-updatable", "i-current"}, rebooted, "should reboot instances with and without updates, but not instances with standalone tasks")
+}
+
+func TestSimulatedInstanceLocks(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-locked
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+  - id: i-free
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+	sim.parameters["/host-locks/i-locked"] = "kernel debugging"
+
+	u := sim.updater()
+	u.lockPrefix = "/host-locks"
+	u.lockTTL = time.Hour
+	require.NoError(t, run(u))
+	assert.Equal(t, "1.19.0", sim.byID["i-locked"].Version, "should not update an instance locked by others")
+	assert.Equal(t, "1.20.0", sim.byID["i-free"].Version)
+	assert.Equal(t, map[string]string{"/host-locks/i-locked": "kernel debugging"}, sim.parameters, "should release its own locks only")
+}
+
+func TestSimulatedReadOnly(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-1
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	u := sim.updater()
+	u.makeReadOnly()
+	require.NoError(t, run(u))
+	assert.Equal(t, "1.19.0", sim.byID["i-1"].Version, "should not update instances in read-only mode")
+	var timeline bytes.Buffer
+	sim.printTimeline(&timeline)
+	assert.Contains(t, timeline.String(), "update check:", "should still check for updates")
+	assert.NotContains(t, timeline.String(), "container instance state changed")
+}
+
+func TestSimulatedDaemonServices(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+daemon_services: [log-router]
+instances:
+  - id: i-daemon
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [log-router, web]
+  - id: i-stuck-daemon
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [log-router]
+    fail: [daemon]
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	require.NoError(t, run(sim.updater()))
+	require.Len(t, sim.byID["i-daemon"].tasks, 1, "only the daemon task should be back on the updated instance")
+	assert.Equal(t, "service:log-router", sim.byID["i-daemon"].tasks[0].group)
+	assert.Empty(t, sim.byID["i-stuck-daemon"].tasks)
+
+	var timeline bytes.Buffer
+	sim.printTimeline(&timeline)
+	assert.Contains(t, timeline.String(), "daemon task of service log-router started")
+	assert.GreaterOrEqual(t, sim.elapsed, daemonWaitTimeout, "should wait for the daemon task that does not start")
+}
+
+func TestSimulatedIntegDaemonScenario(t *testing.T) {
+	sc, err := loadScenario("../integ/scenarios/daemon.yaml")
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	require.NoError(t, run(sim.updater()))
+	for _, inst := range sim.instances {
+		assert.Equal(t, inst.UpdateVersion, inst.Version, "instance %s should have been updated", inst.ID)
+		require.Len(t, inst.tasks, 1, "instance %s should run its daemon task again", inst.ID)
+		assert.Equal(t, "service:integ-daemon", inst.tasks[0].group)
+	}
+}
+
+// unavailablePeaks replays the timeline of a simulation and returns the most
+// container instances that were out of service at once, in total and within
+// a single availability zone. An instance is out of service from when it is
+// set to DRAINING until it is ACTIVE again.
+func unavailablePeaks(sim *simulation) (int, int) {
+	unavailable := make(map[string]bool)
+	peak, peakInZone := 0, 0
+	for _, event := range sim.events {
+		switch event.message {
+		case "container instance state changed to " + ecs.ContainerInstanceStatusDraining:
+			unavailable[event.instanceID] = true
+		case "container instance state changed to " + ecs.ContainerInstanceStatusActive:
+			delete(unavailable, event.instanceID)
+		default:
+			continue
+		}
+		if len(unavailable) > peak {
+			peak = len(unavailable)
+		}
+		inZone := make(map[string]int)
+		for id := range unavailable {
+			zone := sim.byID[id].Attributes["ecs.availability-zone"]
+			inZone[zone]++
+			if inZone[zone] > peakInZone {
+				peakInZone = inZone[zone]
+			}
+		}
+	}
+	return peak, peakInZone
+}
+
+func TestSimulatedIntegMultiAZScenario(t *testing.T) {
+	sc, err := loadScenario("../integ/scenarios/multi-az.yaml")
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	require.NoError(t, run(sim.updater()))
+	peak, peakInZone := unavailablePeaks(sim)
+	assert.Equal(t, 1, peak, "should take one instance out of service at a time")
+	assert.Equal(t, 1, peakInZone, "should never have two instances of a zone out of service at once")
+	for _, inst := range sim.instances {
+		assert.Equal(t, ecs.ContainerInstanceStatusActive, inst.status, "instance %s should be back in service", inst.ID)
+	}
+}
+
+func TestSimulatedMinReleaseAge(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+instances:
+  - id: i-old-release
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    update_age: 72h
+  - id: i-new-release
+    variant: aws-ecs-2
+    version: 1.20.0
+    update_version: 1.21.0
+    update_age: 1h
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	u := sim.updater()
+	u.minReleaseAge = 48 * time.Hour
+	require.NoError(t, run(u))
+	assert.Equal(t, "1.20.0", sim.byID["i-old-release"].Version)
+	assert.Equal(t, "1.20.0", sim.byID["i-new-release"].Version, "should not update to a release younger than the minimum age")
+}
+
+func TestSimulatedDeferredServices(t *testing.T) {
+	sc, err := loadScenario(writeScenario(t, `
+deferred_services: [database]
+instances:
+  - id: i-database
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [database]
+  - id: i-web
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [web]
+`))
+	require.NoError(t, err)
+	sim := newSimulation(sc)
+	originalSleep := sleep
+	defer func() { sleep = originalSleep }()
+	sleep = sim.sleep
+
+	require.NoError(t, run(sim.updater()))
+	assert.Equal(t, "1.20.0", sim.byID["i-database"].Version, "deferred instances should still be updated")
+	var drained []string
+	for _, event := range sim.events {
+		if event.message == "container instance state changed to DRAINING" {
+			drained = append(drained, event.instanceID)
+		}
+	}
+	assert.Equal(t, []string{"i-web", "i-database"}, drained, "instances of deferred services should be updated last")
+}
+
+func TestSimulatedStages(t *testing.T) {
+	scenarioYAML := `
+instances:
+  - id: i-prod
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    attributes: {env: prod}
+  - id: i-staging
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    attributes: {env: staging}
+`
+	stages := []rolloutStage{{value: "staging", soak: time.Hour}, {value: "prod"}}
+
+	t.Run("soak", func(t *testing.T) {
+		sc, err := loadScenario(writeScenario(t, scenarioYAML))
+		require.NoError(t, err)
+		sim := newSimulation(sc)
+		originalSleep := sleep
+		defer func() { sleep = originalSleep }()
+		sleep = sim.sleep
+
+		u := sim.updater()
+		u.stageAttribute = "env"
+		u.stages = stages
+		require.NoError(t, run(u))
+		assert.Equal(t, "1.20.0", sim.byID["i-staging"].Version)
+		assert.Equal(t, "1.20.0", sim.byID["i-prod"].Version)
+		var drained []string
+		for _, event := range sim.events {
+			if event.message == "container instance state changed to DRAINING" {
+				drained = append(drained, event.instanceID)
+			}
+		}
+		assert.Equal(t, []string{"i-staging", "i-prod"}, drained)
+		assert.GreaterOrEqual(t, sim.elapsed, time.Hour, "should soak after the staging stage")
+	})
+	t.Run("failed stage", func(t *testing.T) {
+		sc, err := loadScenario(writeScenario(t, scenarioYAML+"    fail: [apply]\n"))
+		require.NoError(t, err)
+		sim := newSimulation(sc)
+		originalSleep := sleep
+		defer func() { sleep = originalSleep }()
+		sleep = sim.sleep
+
+		u := sim.updater()
+		u.stageAttribute = "env"
+		u.stages = stages
+		require.NoError(t, run(u))
+		assert.Equal(t, "1.19.0", sim.byID["i-staging"].Version)
+		assert.Equal(t, "1.19.0", sim.byID["i-prod"].Version, "later stages should not start after a failed stage")
+		assert.Less(t, sim.elapsed, time.Hour, "should not soak a failed stage")
+	})
+}
+
+func TestSimulatedFaults(t *testing.T) {
+	scenarioYAML := `
+instances:
+  - id: i-first
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [web]
+  - id: i-second
+    variant: aws-ecs-2
+    version: 1.19.0
+    update_version: 1.20.0
+    services: [web]
+`
+	simulate := func(t *testing.T, spec string) *simulation {
+		sc, err := loadScenario(writeScenario(t, scenarioYAML))
+		require.NoError(t, err)
+		sim := newSimulation(sc)
+		sim.faults, err = parseFaults(spec, 1)
+		require.NoError(t, err)
+		originalSleep := sleep
+		defer func() { sleep = originalSleep }()
+		sleep = sim.sleep
+		require.NoError(t, run(sim.updater()))
+		return sim
+	}
+
+	t.Run("lost command responses", func(t *testing.T) {
+		sim := simulate(t, "SendCommand:timeout=1")
+		applied := make(map[string]int)
+		for _, event := range sim.events {
+			if event.message == "update to 1.20.0 applied" {
+				applied[event.instanceID]++
+			}
+		}
+		assert.Equal(t, map[string]int{"i-first": 1, "i-second": 1}, applied, "each update should be applied exactly once")
+		for _, id := range []string{"i-first", "i-second"} {
+			assert.Equal(t, "1.20.0", sim.byID[id].Version)
+			assert.Equal(t, "ACTIVE", sim.byID[id].status)
+		}
+		var timeline bytes.Buffer
+		sim.printTimeline(&timeline)
+		assert.Contains(t, timeline.String(), "injected fault: response to SendCommand lost")
+	})
+	t.Run("failed drains", func(t *testing.T) {
+		sim := simulate(t, "UpdateContainerInstancesState=1")
+		for _, id := range []string{"i-first", "i-second"} {
+			assert.Equal(t, "1.19.0", sim.byID[id].Version, "instance %s should not be updated without draining", id)
+			assert.Equal(t, "ACTIVE", sim.byID[id].status)
+			assert.Len(t, sim.byID[id].tasks, 1, "tasks on instance %s should keep running", id)
+		}
+	})
+}
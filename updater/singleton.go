@@ -0,0 +1,199 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/arn"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+// parseFamilies parses comma-separated task definition families into a set,
+// which is nil when there are none.
+func parseFamilies(spec string) map[string]bool {
+	var families map[string]bool
+	for _, family := range strings.Split(spec, ",") {
+		family = strings.TrimSpace(family)
+		if family == "" {
+			continue
+		}
+		if families == nil {
+			families = make(map[string]bool)
+		}
+		families[family] = true
+	}
+	return families
+}
+
+// taskFamily returns the family of a task definition ARN, e.g. "web" for
+// arn:aws:ecs:us-west-2:123456789012:task-definition/web:3, or an empty
+// string when the ARN is invalid.
+func taskFamily(taskDefinitionARN string) string {
+	parsed, err := arn.Parse(taskDefinitionARN)
+	if err != nil || !strings.HasPrefix(parsed.Resource, "task-definition/") {
+		return ""
+	}
+	return strings.SplitN(strings.TrimPrefix(parsed.Resource, "task-definition/"), ":", 2)[0]
+}
+
+// isSingleton reports whether the task is a standalone task of one of the
+// -singleton-families, which the updater replaces on another instance before
+// draining the instance it runs on. Draining never stops standalone tasks,
+// and nothing starts them again once they are stopped.
+func (u *updater) isSingleton(task *ecs.Task) bool {
+	return !strings.HasPrefix(aws.StringValue(task.StartedBy), "ecs-svc/") &&
+		u.singletonFamilies[taskFamily(aws.StringValue(task.TaskDefinitionArn))]
+}
+
+// usesAwsvpc reports whether the task runs in awsvpc network mode, with an
+// elastic network interface of its own. StartTask needs the network
+// configuration of such tasks, which can't be copied from the task, so they
+// can't be replaced.
+func usesAwsvpc(task *ecs.Task) bool {
+	for _, attachment := range task.Attachments {
+		if aws.StringValue(attachment.Type) == "ElasticNetworkInterface" {
+			return true
+		}
+	}
+	return false
+}
+
+// familyRunsElsewhere reports whether tasks of the family run on container
+// instances of the cluster other than the given one, which keep the family
+// running while the instance drains.
+func (u *updater) familyRunsElsewhere(family string, containerInstance string) (bool, error) {
+	list, err := u.ecs.ListTasks(&ecs.ListTasksInput{
+		Cluster:       &u.cluster,
+		Family:        aws.String(family),
+		DesiredStatus: aws.String(ecs.DesiredStatusRunning),
+	})
+	if err != nil {
+		return false, fmt.Errorf("failed to list tasks of family %q: %w", family, err)
+	}
+	if len(list.TaskArns) == 0 {
+		return false, nil
+	}
+	desc, err := u.ecs.DescribeTasks(&ecs.DescribeTasksInput{
+		Cluster: &u.cluster,
+		Tasks:   list.TaskArns,
+	})
+	if err != nil {
+		return false, fmt.Errorf("failed to describe tasks of family %q: %w", family, err)
+	}
+	for _, task := range desc.Tasks {
+		if aws.StringValue(task.ContainerInstanceArn) != containerInstance {
+			return true, nil
+		}
+	}
+	return false, nil
+}
+
+// replaceSingletons replaces each singleton task on the instance before it is
+// drained, when its family runs on no other instance: it starts a copy of the
+// task on another of the instances, waits for the copy to be RUNNING, and
+// only then stops the task on the instance, so that the family never stops
+// running. Other instances are tried in order of their task count until one
+// has the capacity for the copy. If a copy can't be started, the instance is
+// not drained.
+func (u *updater) replaceSingletons(inst instance, instances []instance) error {
+	list, err := u.ecs.ListTasks(&ecs.ListTasksInput{
+		Cluster:           &u.cluster,
+		ContainerInstance: aws.String(inst.containerInstanceID),
+	})
+	if err != nil {
+		return fmt.Errorf("failed to list tasks: %w", err)
+	}
+	if len(list.TaskArns) == 0 {
+		return nil
+	}
+	desc, err := u.ecs.DescribeTasks(&ecs.DescribeTasksInput{
+		Cluster: &u.cluster,
+		Tasks:   list.TaskArns,
+	})
+	if err != nil {
+		return fmt.Errorf("failed to describe tasks: %w", err)
+	}
+	targets := make([]instance, 0, len(instances))
+	for _, other := range instances {
+		if other.containerInstanceID != inst.containerInstanceID {
+			targets = append(targets, other)
+		}
+	}
+	sort.SliceStable(targets, func(i, j int) bool {
+		return targets[i].taskCount < targets[j].taskCount
+	})
+	for _, task := range desc.Tasks {
+		if !u.isSingleton(task) {
+			continue
+		}
+		family := taskFamily(aws.StringValue(task.TaskDefinitionArn))
+		elsewhere, err := u.familyRunsElsewhere(family, inst.containerInstanceID)
+		if err != nil {
+			return err
+		}
+		if elsewhere {
+			log.Printf("Singleton task %q of instance %#q is not replaced, since family %q runs on other instances", aws.StringValue(task.TaskArn), inst, family)
+			continue
+		}
+		replacement, err := u.startReplacement(task, targets)
+		if err != nil {
+			return err
+		}
+		_, err = u.ecs.StopTask(&ecs.StopTaskInput{
+			Cluster: &u.cluster,
+			Task:    task.TaskArn,
+			Reason:  aws.String(fmt.Sprintf("Replaced by task %s before bottlerocket-ecs-updater drained the instance", replacement)),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to stop singleton task %q after starting its replacement %q: %w", aws.StringValue(task.TaskArn), replacement, err)
+		}
+		log.Printf("Replaced singleton task %q of instance %#q with task %q", aws.StringValue(task.TaskArn), inst, replacement)
+	}
+	return nil
+}
+
+// startReplacement starts a copy of the task on the first of the target
+// instances with the capacity for it, and waits for the copy to be RUNNING.
+func (u *updater) startReplacement(task *ecs.Task, targets []instance) (string, error) {
+	input := &ecs.StartTaskInput{
+		Cluster:        &u.cluster,
+		TaskDefinition: task.TaskDefinitionArn,
+		Overrides:      task.Overrides,
+		Group:          task.Group,
+	}
+	if aws.StringValue(task.StartedBy) != "" {
+		input.StartedBy = task.StartedBy
+	}
+	var reasons []string
+	for _, target := range targets {
+		input.ContainerInstances = aws.StringSlice([]string{target.containerInstanceID})
+		resp, err := u.ecs.StartTask(input)
+		if err != nil {
+			return "", fmt.Errorf("failed to start a replacement of singleton task %q: %w", aws.StringValue(task.TaskArn), err)
+		}
+		if len(resp.Tasks) == 0 {
+			for _, failure := range resp.Failures {
+				reasons = append(reasons, fmt.Sprintf("%s: %s", target.instanceID, aws.StringValue(failure.Reason)))
+			}
+			continue
+		}
+		replacement := aws.StringValue(resp.Tasks[0].TaskArn)
+		log.Printf("Started task %q on instance %#q to replace singleton task %q", replacement, target, aws.StringValue(task.TaskArn))
+		err = u.ecs.WaitUntilTasksRunningWithContext(u.shutdownContext(), &ecs.DescribeTasksInput{
+			Cluster: &u.cluster,
+			Tasks:   aws.StringSlice([]string{replacement}),
+		},
+			request.WithWaiterMaxAttempts(waiterMaxAttempts),
+			request.WithWaiterDelay(request.ConstantWaiterDelay(waiterDelay)),
+		)
+		if err != nil {
+			return "", fmt.Errorf("replacement %q of singleton task %q is not running: %w", replacement, aws.StringValue(task.TaskArn), err)
+		}
+		return replacement, nil
+	}
+	return "", fmt.Errorf("no other instance can run a replacement of singleton task %q: %s", aws.StringValue(task.TaskArn), strings.Join(reasons, "; "))
+}
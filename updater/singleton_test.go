@@ -0,0 +1,157 @@
+package main
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+const schedulerTaskDefinition = "arn:aws:ecs:us-west-2:000000000000:task-definition/scheduler:3"
+
+func TestParseFamilies(t *testing.T) {
+	assert.Equal(t, map[string]bool{"scheduler": true, "leader": true}, parseFamilies(" scheduler, leader ,,"))
+	assert.Nil(t, parseFamilies(""))
+}
+
+func TestTaskFamily(t *testing.T) {
+	assert.Equal(t, "scheduler", taskFamily(schedulerTaskDefinition))
+	assert.Equal(t, "scheduler", taskFamily("arn:aws:ecs:us-west-2:000000000000:task-definition/scheduler"))
+	assert.Equal(t, "", taskFamily("arn:aws:ecs:us-west-2:000000000000:task/cluster/scheduler"))
+	assert.Equal(t, "", taskFamily("scheduler:3"))
+}
+
+func TestEligibleSingleton(t *testing.T) {
+	tasks := []*ecs.Task{{
+		StartedBy:         aws.String("ecs-svc/svc-id"),
+		TaskDefinitionArn: aws.String("arn:aws:ecs:us-west-2:000000000000:task-definition/web:1"),
+	}, {
+		TaskDefinitionArn: aws.String(schedulerTaskDefinition),
+	}}
+	u := updater{cluster: "test-cluster", ecs: MockECS{
+		ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+			return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-arn-1", "task-arn-2"})}, nil
+		},
+		DescribeTasksFn: func(_ *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+			return &ecs.DescribeTasksOutput{Tasks: tasks}, nil
+		},
+	}}
+	ok, err := u.eligible("cont-inst-id")
+	require.NoError(t, err)
+	assert.False(t, ok, "standalone tasks should make the instance ineligible")
+
+	u.singletonFamilies = map[string]bool{"scheduler": true}
+	ok, err = u.eligible("cont-inst-id")
+	require.NoError(t, err)
+	assert.True(t, ok, "singleton tasks should not make the instance ineligible")
+
+	tasks[1].Attachments = []*ecs.Attachment{{Type: aws.String("ElasticNetworkInterface")}}
+	ok, err = u.eligible("cont-inst-id")
+	require.NoError(t, err)
+	assert.False(t, ok, "singleton tasks in awsvpc network mode can't be replaced")
+}
+
+func TestReplaceSingletons(t *testing.T) {
+	source := instance{instanceID: "inst-id-1", containerInstanceID: "cont-inst-1"}
+	instances := []instance{
+		source,
+		{instanceID: "inst-id-2", containerInstanceID: "cont-inst-2", taskCount: 5},
+		{instanceID: "inst-id-3", containerInstanceID: "cont-inst-3", taskCount: 1},
+	}
+	// familyTasks are the tasks of the scheduler family running in the cluster.
+	familyTasks := []*ecs.Task{{TaskArn: aws.String("scheduler-task"), ContainerInstanceArn: aws.String("cont-inst-1")}}
+	mockECS := func(start func(input *ecs.StartTaskInput) (*ecs.StartTaskOutput, error), stopped *[]string) MockECS {
+		return MockECS{
+			ListTasksFn: func(input *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+				if input.Family != nil {
+					assert.Equal(t, "scheduler", aws.StringValue(input.Family))
+					return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"family-tasks"})}, nil
+				}
+				assert.Equal(t, "cont-inst-1", aws.StringValue(input.ContainerInstance))
+				return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"service-task", "scheduler-task"})}, nil
+			},
+			DescribeTasksFn: func(input *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+				if aws.StringValue(input.Tasks[0]) == "family-tasks" {
+					return &ecs.DescribeTasksOutput{Tasks: familyTasks}, nil
+				}
+				return &ecs.DescribeTasksOutput{Tasks: []*ecs.Task{{
+					TaskArn:   aws.String("service-task"),
+					StartedBy: aws.String("ecs-svc/svc-id"),
+				}, {
+					TaskArn:           aws.String("scheduler-task"),
+					TaskDefinitionArn: aws.String(schedulerTaskDefinition),
+					Group:             aws.String("family:scheduler"),
+				}}}, nil
+			},
+			StartTaskFn: start,
+			WaitUntilTasksRunningWithContextFn: func(_ aws.Context, input *ecs.DescribeTasksInput, _ ...request.WaiterOption) error {
+				assert.Equal(t, []string{"replacement-task"}, aws.StringValueSlice(input.Tasks))
+				return nil
+			},
+			StopTaskFn: func(input *ecs.StopTaskInput) (*ecs.StopTaskOutput, error) {
+				*stopped = append(*stopped, aws.StringValue(input.Task))
+				return &ecs.StopTaskOutput{}, nil
+			},
+		}
+	}
+
+	t.Run("replaced on the least loaded instance with capacity", func(t *testing.T) {
+		var tried, stopped []string
+		u := updater{cluster: "test-cluster", singletonFamilies: map[string]bool{"scheduler": true}}
+		u.ecs = mockECS(func(input *ecs.StartTaskInput) (*ecs.StartTaskOutput, error) {
+			assert.Equal(t, schedulerTaskDefinition, aws.StringValue(input.TaskDefinition))
+			assert.Equal(t, "family:scheduler", aws.StringValue(input.Group))
+			assert.Nil(t, input.StartedBy)
+			target := aws.StringValue(input.ContainerInstances[0])
+			tried = append(tried, target)
+			if target == "cont-inst-3" {
+				return &ecs.StartTaskOutput{Failures: []*ecs.Failure{{Reason: aws.String("RESOURCE:MEMORY")}}}, nil
+			}
+			return &ecs.StartTaskOutput{Tasks: []*ecs.Task{{TaskArn: aws.String("replacement-task")}}}, nil
+		}, &stopped)
+		require.NoError(t, u.replaceSingletons(source, instances))
+		assert.Equal(t, []string{"cont-inst-3", "cont-inst-2"}, tried)
+		assert.Equal(t, []string{"scheduler-task"}, stopped, "only the singleton task should be stopped")
+	})
+
+	t.Run("no capacity", func(t *testing.T) {
+		var stopped []string
+		u := updater{cluster: "test-cluster", singletonFamilies: map[string]bool{"scheduler": true}}
+		u.ecs = mockECS(func(_ *ecs.StartTaskInput) (*ecs.StartTaskOutput, error) {
+			return &ecs.StartTaskOutput{Failures: []*ecs.Failure{{Reason: aws.String("RESOURCE:CPU")}}}, nil
+		}, &stopped)
+		err := u.replaceSingletons(source, instances)
+		require.Error(t, err)
+		assert.Contains(t, err.Error(), "inst-id-3: RESOURCE:CPU")
+		assert.Empty(t, stopped, "the singleton task should keep running without a replacement")
+	})
+
+	t.Run("start task err", func(t *testing.T) {
+		var stopped []string
+		startErr := errors.New("failed to start task")
+		u := updater{cluster: "test-cluster", singletonFamilies: map[string]bool{"scheduler": true}}
+		u.ecs = mockECS(func(_ *ecs.StartTaskInput) (*ecs.StartTaskOutput, error) {
+			return nil, startErr
+		}, &stopped)
+		err := u.replaceSingletons(source, instances)
+		assert.ErrorIs(t, err, startErr)
+		assert.Empty(t, stopped)
+	})
+
+	t.Run("family runs elsewhere", func(t *testing.T) {
+		var stopped []string
+		familyTasks = append(familyTasks, &ecs.Task{TaskArn: aws.String("other-scheduler-task"), ContainerInstanceArn: aws.String("cont-inst-2")})
+		defer func() { familyTasks = familyTasks[:1] }()
+		u := updater{cluster: "test-cluster", singletonFamilies: map[string]bool{"scheduler": true}}
+		u.ecs = mockECS(func(_ *ecs.StartTaskInput) (*ecs.StartTaskOutput, error) {
+			t.Error("a family running on other instances should not be replaced")
+			return nil, errors.New("unexpected StartTask")
+		}, &stopped)
+		require.NoError(t, u.replaceSingletons(source, instances))
+		assert.Empty(t, stopped, "draining stops the task")
+	})
+}
@@ -0,0 +1,207 @@
+package main
+
+import (
+	"log"
+	"sort"
+)
+
+// Reasons instances are skipped. The summary and the report count the
+// instances skipped for each reason, with what fleet owners can do about it.
+const (
+	skipForeign         = "foreign"
+	skipStateUnknown    = "state-unknown"
+	skipStale           = "stale-registration"
+	skipUnsupported     = "unsupported-variant"
+	skipNotSSMTarget    = "not-ssm-target"
+	skipWindowClosed    = "maintenance-window-closed"
+	skipOutsideWindow   = "update-window-closed"
+	skipUpdateLimit     = "update-limit"
+	skipStandaloneTasks = "standalone-tasks"
+	skipLocked          = "locked"
+	skipLockFailed      = "lock-failed"
+	skipStageFailed     = "stage-failed"
+	skipScheduledEvent  = "scheduled-event"
+	skipQuarantined     = "quarantined"
+	skipReleaseUnknown  = "release-unknown"
+	skipReleaseAge      = "release-age"
+	skipNotProven       = "not-last-known-good"
+	skipPromotion       = "promotion"
+	skipShutdown        = "shutdown"
+	skipBudget          = "max-unavailable"
+	skipExcluded        = "excluded-service"
+	skipSSMActivity     = "ssm-activity"
+)
+
+// readmeURL is the README, whose sections document what to do about each
+// skip reason.
+const readmeURL = "https://github.com/bottlerocket-os/bottlerocket-ecs-updater"
+
+// skipRemedy describes a skip reason and what fleet owners can do to have the
+// instances it skips updated. section is the anchor of the README section
+// documenting it.
+type skipRemedy struct {
+	description string
+	remedy      string
+	section     string
+}
+
+// skipRemedies describes every skip reason.
+var skipRemedies = map[string]skipRemedy{
+	skipForeign: {
+		description: "registered from another account or region",
+		remedy:      "update them with an updater in their own account and region",
+		section:     "why-were-instances-skipped",
+	},
+	skipStateUnknown: {
+		description: "ECS could not describe the container instance",
+		remedy:      "usually transient; check the updater's ecs:DescribeContainerInstances permission if it persists",
+		section:     "why-were-instances-skipped",
+	},
+	skipStale: {
+		description: "stale registration of an instance registered more than once",
+		remedy:      "deregister the stale container instance",
+		section:     "why-is-a-container-instance-listed-as-a-stale-registration",
+	},
+	skipUnsupported: {
+		description: "variant not in -supported-variants",
+		remedy:      "add the variant to SupportedVariants once it is tested with the updater",
+		section:     "supported-variants",
+	},
+	skipNotSSMTarget: {
+		description: "SSM agent offline or instance terminated",
+		remedy:      "check that the SSM agent runs and the instance role allows SSM",
+		section:     "why-is-an-instance-listed-as-not-a-valid-ssm-target",
+	},
+	skipWindowClosed: {
+		description: "maintenance window not open",
+		remedy:      "none; instances are updated in the next execution of the window",
+		section:     "updating-in-ssm-maintenance-windows",
+	},
+	skipOutsideWindow: {
+		description: "update window not open",
+		remedy:      "none; instances are updated once the update window opens",
+		section:     "restricting-updates-to-a-schedule",
+	},
+	skipUpdateLimit: {
+		description: "limit of updates per pass reached",
+		remedy:      "raise MaxUpdatesPerPass or run the updater more often",
+		section:     "check-and-update-schedules",
+	},
+	skipStandaloneTasks: {
+		description: "standalone tasks",
+		remedy:      "run the tasks as services, or list their families in SingletonFamilies",
+		section:     "replacing-singleton-tasks",
+	},
+	skipLocked: {
+		description: "locked by other automation",
+		remedy:      "none; instances are updated once the lock is released or expires",
+		section:     "coordinating-with-other-automation",
+	},
+	skipLockFailed: {
+		description: "instance could not be locked",
+		remedy:      "check the updater's permission to tag container instances",
+		section:     "coordinating-with-other-automation",
+	},
+	skipStageFailed: {
+		description: "an earlier stage or wave had too many failures",
+		remedy:      "fix the failed updates of the earlier stage or wave, or raise MaxWaveFailures",
+		section:     "staged-rollouts",
+	},
+	skipScheduledEvent: {
+		description: "scheduled EC2 event",
+		remedy:      "none; instances are updated after the event",
+		section:     "scheduled-ec2-events",
+	},
+	skipQuarantined: {
+		description: "quarantined",
+		remedy:      "none; instances are updated once their quarantine expires",
+		section:     "quarantining-instances",
+	},
+	skipReleaseUnknown: {
+		description: "release of the update unknown",
+		remedy:      "usually transient; check the updater's ssm:GetParameter permission if it persists",
+		section:     "waiting-for-new-releases-to-age",
+	},
+	skipReleaseAge: {
+		description: "release younger than MinReleaseAge",
+		remedy:      "none; instances are updated once the release is old enough",
+		section:     "waiting-for-new-releases-to-age",
+	},
+	skipNotProven: {
+		description: "update is not the last known good version",
+		remedy:      "update the cluster given by -last-known-good-cluster to the version first",
+		section:     "following-a-proven-version",
+	},
+	skipPromotion: {
+		description: "waiting for other clusters to prove the version",
+		remedy:      "none; instances are updated once the promotion policy allows it",
+		section:     "promoting-versions-between-clusters",
+	},
+	skipShutdown: {
+		description: "updater shut down during the pass",
+		remedy:      "none; instances are updated in the next pass",
+		section:     "stopping-the-updater",
+	},
+	skipBudget: {
+		description: "too many instances unavailable for MaxUnavailable",
+		remedy:      "recover the unavailable instances, or raise MaxUnavailable",
+		section:     "limiting-unavailable-instances",
+	},
+	skipExcluded: {
+		description: "requires manual update: runs tasks of ExcludeServices",
+		remedy:      "update the instances manually, e.g. after moving the tasks away, or remove the services from ExcludeServices",
+		section:     "excluding-services",
+	},
+	skipSSMActivity: {
+		description: "SSM commands or Automation executions of others in progress",
+		remedy:      "none; instances are updated in a later pass once the other SSM activity finished",
+		section:     "deferring-to-other-ssm-activity",
+	},
+}
+
+// skipCount is the number of instances skipped for a reason, with what to do
+// about them.
+type skipCount struct {
+	Reason      string `json:"reason"`
+	Description string `json:"description"`
+	Instances   int    `json:"instances"`
+	Remedy      string `json:"remedy"`
+	Link        string `json:"link"`
+}
+
+// skipCounts counts the skipped instances of the pass per reason, most
+// skipped first.
+func (s *passSummary) skipCounts() []skipCount {
+	counts := make(map[string]int)
+	for id, reason := range s.skipReasons {
+		if s.outcomes[id] == outcomeSkipped {
+			counts[reason]++
+		}
+	}
+	skips := make([]skipCount, 0, len(counts))
+	for reason, n := range counts {
+		r := skipRemedies[reason]
+		skips = append(skips, skipCount{
+			Reason:      reason,
+			Description: r.description,
+			Instances:   n,
+			Remedy:      r.remedy,
+			Link:        readmeURL + "#" + r.section,
+		})
+	}
+	sort.Slice(skips, func(i, j int) bool {
+		if skips[i].Instances != skips[j].Instances {
+			return skips[i].Instances > skips[j].Instances
+		}
+		return skips[i].Reason < skips[j].Reason
+	})
+	return skips
+}
+
+// logSkips writes the skipped instances per reason to the log, e.g.
+// "7 skipped: SSM agent offline or instance terminated; check that ...".
+func (s *passSummary) logSkips() {
+	for _, skip := range s.skipCounts() {
+		log.Printf("%d skipped: %s; %s (%s)", skip.Instances, skip.Description, skip.Remedy, skip.Link)
+	}
+}
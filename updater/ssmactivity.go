@@ -0,0 +1,153 @@
+package main
+
+import (
+	"fmt"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+// ssmActivityLookback is how long before now commands are looked for when
+// checking an instance for SSM activity: SSM commands run for at most 48 hours.
+const ssmActivityLookback = 48 * time.Hour
+
+// activeCommandStatuses are the statuses of command invocations that have not
+// finished.
+var activeCommandStatuses = map[string]bool{
+	ssm.CommandInvocationStatusPending:    true,
+	ssm.CommandInvocationStatusInProgress: true,
+	ssm.CommandInvocationStatusDelayed:    true,
+	ssm.CommandInvocationStatusCancelling: true,
+}
+
+// activeAutomationStatuses are the statuses of Automation executions that
+// have not finished.
+var activeAutomationStatuses = []string{
+	ssm.AutomationExecutionStatusPending,
+	ssm.AutomationExecutionStatusInProgress,
+	ssm.AutomationExecutionStatusWaiting,
+	ssm.AutomationExecutionStatusCancelling,
+}
+
+// checkSSMActivity returns an error when SSM commands or Automation executions
+// of others, such as patching or debugging sessions, are in progress on the
+// instance, or when they can't be determined, so that the updater does not
+// drain and reboot the instance in the middle of them. Commands sent by this
+// pass are the updater's own and are ignored.
+func (u *updater) checkSSMActivity(inst instance, now time.Time) error {
+	if !u.deferToSSMActivity {
+		return nil
+	}
+	activity, err := u.activeCommands(inst.instanceID, now)
+	if err != nil {
+		return fmt.Errorf("SSM activity unknown: %w", err)
+	}
+	automations, err := u.activeAutomations(inst.instanceID)
+	if err != nil {
+		return fmt.Errorf("SSM activity unknown: %w", err)
+	}
+	activity = append(activity, automations...)
+	if len(activity) > 0 {
+		return fmt.Errorf("SSM activity in progress: %s", strings.Join(activity, ", "))
+	}
+	return nil
+}
+
+// activeCommands describes the command invocations on the instance that have
+// not finished and were not sent by this pass.
+func (u *updater) activeCommands(instanceID string, now time.Time) ([]string, error) {
+	active := make([]string, 0)
+	err := u.ssm.ListCommandInvocationsPages(&ssm.ListCommandInvocationsInput{
+		InstanceId: aws.String(instanceID),
+		Filters: []*ssm.CommandFilter{
+			{Key: aws.String(ssm.CommandFilterKeyInvokedAfter), Value: aws.String(now.Add(-ssmActivityLookback).UTC().Format(time.RFC3339))},
+		},
+	}, func(page *ssm.ListCommandInvocationsOutput, _ bool) bool {
+		for _, invocation := range page.CommandInvocations {
+			status := aws.StringValue(invocation.Status)
+			if !activeCommandStatuses[status] || u.sentByPass(aws.StringValue(invocation.Comment)) {
+				continue
+			}
+			active = append(active, fmt.Sprintf("command %s of %s (%s)",
+				aws.StringValue(invocation.CommandId), aws.StringValue(invocation.DocumentName), status))
+		}
+		return true
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to list command invocations: %w", err)
+	}
+	return active, nil
+}
+
+// sentByPass reports whether an SSM command with the comment was sent by this
+// pass (see commandComment).
+func (u *updater) sentByPass(comment string) bool {
+	if u.passID == "" {
+		return false
+	}
+	for _, field := range strings.Fields(comment) {
+		if field == "pass="+shortCorrelationID(u.passID) {
+			return true
+		}
+	}
+	return false
+}
+
+// activeAutomations describes the Automation executions targeting the
+// instance that have not finished. Executions are matched by their targets and
+// resolved targets; executions given the instance only as a parameter are not
+// found, since listing executions does not return their parameters.
+func (u *updater) activeAutomations(instanceID string) ([]string, error) {
+	active := make([]string, 0)
+	input := &ssm.DescribeAutomationExecutionsInput{
+		Filters: []*ssm.AutomationExecutionFilter{{
+			Key:    aws.String(ssm.AutomationExecutionFilterKeyExecutionStatus),
+			Values: aws.StringSlice(activeAutomationStatuses),
+		}},
+	}
+	for {
+		resp, err := u.ssm.DescribeAutomationExecutions(input)
+		if err != nil {
+			return nil, fmt.Errorf("failed to describe Automation executions: %w", err)
+		}
+		for _, execution := range resp.AutomationExecutionMetadataList {
+			if automationTargets(execution, instanceID) {
+				active = append(active, fmt.Sprintf("automation %s of %s (%s)",
+					aws.StringValue(execution.AutomationExecutionId), aws.StringValue(execution.DocumentName), aws.StringValue(execution.AutomationExecutionStatus)))
+			}
+		}
+		if aws.StringValue(resp.NextToken) == "" {
+			return active, nil
+		}
+		input.NextToken = resp.NextToken
+	}
+}
+
+// automationTargets reports whether the Automation execution targets the
+// instance, by ID in its targets or among its resolved targets.
+func automationTargets(execution *ssm.AutomationExecutionMetadata, instanceID string) bool {
+	if aws.StringValue(execution.Target) == instanceID {
+		return true
+	}
+	for _, target := range execution.Targets {
+		key := aws.StringValue(target.Key)
+		if key != "InstanceIds" && key != "ParameterValues" {
+			continue
+		}
+		for _, value := range target.Values {
+			if aws.StringValue(value) == instanceID {
+				return true
+			}
+		}
+	}
+	if execution.ResolvedTargets != nil {
+		for _, value := range execution.ResolvedTargets.ParameterValues {
+			if aws.StringValue(value) == instanceID {
+				return true
+			}
+		}
+	}
+	return false
+}
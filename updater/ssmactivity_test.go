@@ -0,0 +1,90 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestCheckSSMActivity(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	inst := instance{instanceID: "i-1", containerInstanceID: "cont-inst-1"}
+	var invocations []*ssm.CommandInvocation
+	var executions [][]*ssm.AutomationExecutionMetadata
+	var describeErr error
+	mockSSM := MockSSM{
+		ListCommandInvocationsPagesFn: func(input *ssm.ListCommandInvocationsInput, fn func(*ssm.ListCommandInvocationsOutput, bool) bool) error {
+			assert.Equal(t, "i-1", aws.StringValue(input.InstanceId))
+			require.Len(t, input.Filters, 1)
+			assert.Equal(t, ssm.CommandFilterKeyInvokedAfter, aws.StringValue(input.Filters[0].Key))
+			assert.Equal(t, "2024-02-28T12:00:00Z", aws.StringValue(input.Filters[0].Value))
+			fn(&ssm.ListCommandInvocationsOutput{CommandInvocations: invocations}, true)
+			return nil
+		},
+		DescribeAutomationExecutionsFn: func(input *ssm.DescribeAutomationExecutionsInput) (*ssm.DescribeAutomationExecutionsOutput, error) {
+			require.Len(t, input.Filters, 1)
+			assert.Equal(t, activeAutomationStatuses, aws.StringValueSlice(input.Filters[0].Values))
+			if describeErr != nil {
+				return nil, describeErr
+			}
+			page := 0
+			if input.NextToken != nil {
+				page = 1
+			}
+			out := &ssm.DescribeAutomationExecutionsOutput{AutomationExecutionMetadataList: executions[page]}
+			if page+1 < len(executions) {
+				out.NextToken = aws.String("next")
+			}
+			return out, nil
+		},
+	}
+	invocation := func(id string, status string, comment string) *ssm.CommandInvocation {
+		return &ssm.CommandInvocation{CommandId: aws.String(id), DocumentName: aws.String("AWS-RunPatchBaseline"), Status: aws.String(status), Comment: aws.String(comment)}
+	}
+
+	u := updater{ssm: mockSSM, passID: "pass-1"}
+	assert.NoError(t, u.checkSSMActivity(inst, now), "SSM activity should only be looked for with -defer-to-ssm-activity")
+
+	u.deferToSSMActivity = true
+	invocations = []*ssm.CommandInvocation{
+		invocation("finished", ssm.CommandInvocationStatusSuccess, ""),
+		invocation("own", ssm.CommandInvocationStatusInProgress, "updater=v1 cluster=my-cluster pass=pass-1 update=update-1"),
+	}
+	executions = [][]*ssm.AutomationExecutionMetadata{{
+		{AutomationExecutionId: aws.String("other-instance"), Targets: []*ssm.Target{{Key: aws.String("InstanceIds"), Values: aws.StringSlice([]string{"i-2"})}}},
+	}}
+	assert.NoError(t, u.checkSSMActivity(inst, now), "finished and own commands should be ignored")
+
+	invocations = append(invocations, invocation("patch", ssm.CommandInvocationStatusInProgress, "updater=v1 cluster=my-cluster pass=pass-0"))
+	executions = append(executions, []*ssm.AutomationExecutionMetadata{{
+		AutomationExecutionId:     aws.String("restart"),
+		DocumentName:              aws.String("AWS-RestartEC2Instance"),
+		AutomationExecutionStatus: aws.String(ssm.AutomationExecutionStatusInProgress),
+		ResolvedTargets:           &ssm.ResolvedTargets{ParameterValues: aws.StringSlice([]string{"i-1"})},
+	}})
+	err := u.checkSSMActivity(inst, now)
+	require.Error(t, err)
+	assert.Equal(t, "SSM activity in progress: command patch of AWS-RunPatchBaseline (InProgress), automation restart of AWS-RestartEC2Instance (InProgress)", err.Error())
+
+	describeErr = errors.New("access denied")
+	err = u.checkSSMActivity(inst, now)
+	require.Error(t, err)
+	assert.ErrorIs(t, err, describeErr)
+	assert.Contains(t, err.Error(), "SSM activity unknown")
+}
+
+func TestAutomationTargets(t *testing.T) {
+	assert.True(t, automationTargets(&ssm.AutomationExecutionMetadata{Target: aws.String("i-1")}, "i-1"))
+	assert.True(t, automationTargets(&ssm.AutomationExecutionMetadata{
+		Targets: []*ssm.Target{{Key: aws.String("ParameterValues"), Values: aws.StringSlice([]string{"i-2", "i-1"})}},
+	}, "i-1"))
+	assert.False(t, automationTargets(&ssm.AutomationExecutionMetadata{
+		Targets: []*ssm.Target{{Key: aws.String("tag:team"), Values: aws.StringSlice([]string{"i-1"})}},
+	}, "i-1"), "only targets by instance ID should match")
+	assert.False(t, automationTargets(&ssm.AutomationExecutionMetadata{}, "i-1"))
+}
@@ -0,0 +1,100 @@
+package main
+
+import (
+	"fmt"
+	"strings"
+	"time"
+)
+
+// rolloutStage is a stage of a staged rollout. Instances whose stage attribute
+// has the stage's value are updated together, and the updater waits for the
+// soak time after the stage before moving on to the next one.
+type rolloutStage struct {
+	value string
+	soak  time.Duration
+}
+
+// parseStages parses stages given as comma-separated attribute values in
+// rollout order, each optionally followed by a soak time, e.g. "staging:1h,prod".
+func parseStages(spec string) ([]rolloutStage, error) {
+	stages := make([]rolloutStage, 0)
+	seen := make(map[string]bool)
+	for _, part := range strings.Split(spec, ",") {
+		part = strings.TrimSpace(part)
+		stage := rolloutStage{value: part}
+		if i := strings.LastIndex(part, ":"); i >= 0 {
+			soak, err := time.ParseDuration(part[i+1:])
+			if err != nil {
+				return nil, fmt.Errorf("invalid soak time for stage %q: %w", part[:i], err)
+			}
+			if soak < 0 {
+				return nil, fmt.Errorf("soak time for stage %q must not be negative", part[:i])
+			}
+			stage = rolloutStage{value: part[:i], soak: soak}
+		}
+		if stage.value == "" {
+			return nil, fmt.Errorf("stages %q contain an empty stage", spec)
+		}
+		if seen[stage.value] {
+			return nil, fmt.Errorf("stage %q is listed more than once", stage.value)
+		}
+		seen[stage.value] = true
+		stages = append(stages, stage)
+	}
+	return stages, nil
+}
+
+// stagedInstances are the candidates updated in one stage of a pass.
+type stagedInstances struct {
+	rolloutStage
+	// name describes the stage in logs and the after action summary.
+	name      string
+	instances []instance
+}
+
+// stageCandidates splits the candidates into the configured rollout stages,
+// keeping their order within each stage. Candidates whose attribute value is
+// not one of the stages are updated in a final stage of their own. With
+// waves, the candidates are split into the waves instead (see waves.go).
+// Without either, all candidates are updated in a single stage.
+func (u *updater) stageCandidates(candidates []instance) []stagedInstances {
+	if len(u.waves) > 0 {
+		return waveCandidates(u.waves, candidates)
+	}
+	if u.stageAttribute == "" {
+		return []stagedInstances{{name: "all instances", instances: candidates}}
+	}
+	stages := make([]stagedInstances, 0, len(u.stages)+1)
+	index := make(map[string]int)
+	for i, stage := range u.stages {
+		stages = append(stages, stagedInstances{
+			rolloutStage: stage,
+			name:         fmt.Sprintf("%s=%s", u.stageAttribute, stage.value),
+		})
+		index[stage.value] = i
+	}
+	stages = append(stages, stagedInstances{name: fmt.Sprintf("instances outside the %s stages", u.stageAttribute)})
+	for _, inst := range candidates {
+		i, ok := index[inst.stage]
+		if !ok {
+			i = len(stages) - 1
+		}
+		stages[i].instances = append(stages[i].instances, inst)
+	}
+	nonEmpty := make([]stagedInstances, 0, len(stages))
+	for _, stage := range stages {
+		if len(stage.instances) > 0 {
+			nonEmpty = append(nonEmpty, stage)
+		}
+	}
+	return nonEmpty
+}
+
+// stagedOrder returns the instances of all stages in the order they are updated.
+func stagedOrder(stages []stagedInstances) []instance {
+	ordered := make([]instance, 0)
+	for _, stage := range stages {
+		ordered = append(ordered, stage.instances...)
+	}
+	return ordered
+}
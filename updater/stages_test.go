@@ -0,0 +1,91 @@
+package main
+
+import (
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseStages(t *testing.T) {
+	cases := []struct {
+		name        string
+		spec        string
+		expected    []rolloutStage
+		expectedErr string
+	}{
+		{
+			name:     "values",
+			spec:     "staging,prod",
+			expected: []rolloutStage{{value: "staging"}, {value: "prod"}},
+		},
+		{
+			name:     "soak times",
+			spec:     "canary:30m, staging:1h,prod",
+			expected: []rolloutStage{{value: "canary", soak: 30 * time.Minute}, {value: "staging", soak: time.Hour}, {value: "prod"}},
+		},
+		{
+			name:        "invalid soak time",
+			spec:        "staging:soon,prod",
+			expectedErr: `invalid soak time for stage "staging"`,
+		},
+		{
+			name:        "negative soak time",
+			spec:        "staging:-1h,prod",
+			expectedErr: `soak time for stage "staging" must not be negative`,
+		},
+		{
+			name:        "empty stage",
+			spec:        "staging,,prod",
+			expectedErr: "contain an empty stage",
+		},
+		{
+			name:        "duplicate stage",
+			spec:        "staging,prod,staging:1h",
+			expectedErr: `stage "staging" is listed more than once`,
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			stages, err := parseStages(tc.spec)
+			if tc.expectedErr != "" {
+				require.Error(t, err)
+				assert.Contains(t, err.Error(), tc.expectedErr)
+				return
+			}
+			require.NoError(t, err)
+			assert.Equal(t, tc.expected, stages)
+		})
+	}
+}
+
+func TestStageCandidates(t *testing.T) {
+	candidates := []instance{
+		{instanceID: "inst-prod-1", stage: "prod"},
+		{instanceID: "inst-staging", stage: "staging"},
+		{instanceID: "inst-none"},
+		{instanceID: "inst-prod-2", stage: "prod"},
+	}
+
+	t.Run("unstaged", func(t *testing.T) {
+		u := updater{}
+		stages := u.stageCandidates(candidates)
+		require.Len(t, stages, 1)
+		assert.Equal(t, candidates, stages[0].instances)
+		assert.Equal(t, candidates, stagedOrder(stages))
+	})
+	t.Run("staged", func(t *testing.T) {
+		u := updater{
+			stageAttribute: "env",
+			stages:         []rolloutStage{{value: "canary"}, {value: "staging", soak: time.Hour}, {value: "prod"}},
+		}
+		stages := u.stageCandidates(candidates)
+		require.Len(t, stages, 3, "empty stages should be left out")
+		assert.Equal(t, "env=staging", stages[0].name)
+		assert.Equal(t, time.Hour, stages[0].soak)
+		assert.Equal(t, "env=prod", stages[1].name)
+		assert.Equal(t, "instances outside the env stages", stages[2].name)
+		assert.Equal(t, []instance{candidates[1], candidates[0], candidates[3], candidates[2]}, stagedOrder(stages))
+	})
+}
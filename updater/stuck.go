@@ -0,0 +1,200 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+)
+
+const (
+	// tagDrainingSince records when the updater set a container instance to
+	// DRAINING, in RFC 3339 format, so that a later pass can tell how long
+	// the instance has been draining, e.g. after the updater crashed.
+	tagDrainingSince = "bottlerocket-ecs-updater:draining-since"
+	// stuckDrainAfter is how long an instance the updater drained may stay
+	// DRAINING before its drain counts as stuck: twice the drain timeout.
+	stuckDrainAfter = 2 * waiterDelay * waiterMaxAttempts
+)
+
+// Policies for stuck drains, chosen with -stuck-drain-policy.
+const (
+	// stuckDrainAlert only warns about stuck drains.
+	stuckDrainAlert = "alert"
+	// stuckDrainActivate returns instances with stuck drains to ACTIVE.
+	stuckDrainActivate = "activate"
+	// stuckDrainComplete stops the service tasks still running on instances
+	// with stuck drains, which completes their drain; the instances stay
+	// DRAINING, e.g. to be replaced.
+	stuckDrainComplete = "complete"
+)
+
+// stuckDrain is a container instance the updater drained that has been
+// DRAINING for longer than stuckDrainAfter.
+type stuckDrain struct {
+	containerInstanceID string
+	instanceID          string
+	since               time.Time
+}
+
+// drainingSince returns when the updater drained the container instance
+// according to its tags, and false when it has no valid draining-since tag.
+func drainingSince(tags []*ecs.Tag) (time.Time, bool) {
+	for _, tag := range tags {
+		if aws.StringValue(tag.Key) != tagDrainingSince {
+			continue
+		}
+		since, err := time.Parse(time.RFC3339, aws.StringValue(tag.Value))
+		if err != nil {
+			return time.Time{}, false
+		}
+		return since, true
+	}
+	return time.Time{}, false
+}
+
+// recordDrainStart tags the container instance with the time it was drained.
+func (u *updater) recordDrainStart(containerInstance string, now time.Time) {
+	_, err := u.ecs.TagResource(&ecs.TagResourceInput{
+		ResourceArn: aws.String(containerInstance),
+		Tags:        []*ecs.Tag{{Key: aws.String(tagDrainingSince), Value: aws.String(now.UTC().Format(time.RFC3339))}},
+	})
+	if err != nil {
+		log.Printf("Failed to record when container instance %q was drained: %v", containerInstance, err)
+		u.warnings.add(warnDegraded, "drain of container instance %q is not tracked: %v", containerInstance, err)
+	}
+}
+
+// clearDrainStart removes the draining-since tag of the container instance once
+// it is no longer drained by the updater, so that a later drain by others,
+// e.g. by managed instance draining, is not mistaken for a stuck one.
+func (u *updater) clearDrainStart(containerInstance string) {
+	_, err := u.ecs.UntagResource(&ecs.UntagResourceInput{
+		ResourceArn: aws.String(containerInstance),
+		TagKeys:     aws.StringSlice([]string{tagDrainingSince}),
+	})
+	if err != nil {
+		log.Printf("Failed to clear when container instance %q was drained: %v", containerInstance, err)
+		u.warnings.add(warnDegraded, "drain of container instance %q was not cleared: %v", containerInstance, err)
+	}
+}
+
+// findStuckDrains returns the DRAINING container instances of the cluster that
+// the updater drained more than stuckDrainAfter before now. Instances drained
+// by others have no draining-since tag and are left alone.
+func (u *updater) findStuckDrains(now time.Time) ([]stuckDrain, error) {
+	var arns []*string
+	err := u.ecs.ListContainerInstancesPages(&ecs.ListContainerInstancesInput{
+		Cluster: &u.cluster,
+		Status:  aws.String(ecs.ContainerInstanceStatusDraining),
+	}, func(output *ecs.ListContainerInstancesOutput, _ bool) bool {
+		arns = append(arns, output.ContainerInstanceArns...)
+		return true
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to list draining container instances: %w", err)
+	}
+	stuck := make([]stuckDrain, 0)
+	_, err = eachPage(len(arns), ecsPageSize, func(start, stop int) error {
+		resp, err := u.ecs.DescribeContainerInstances(&ecs.DescribeContainerInstancesInput{
+			Cluster:            &u.cluster,
+			ContainerInstances: arns[start:stop],
+			Include:            aws.StringSlice([]string{ecs.ContainerInstanceFieldTags}),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to describe draining container instances: %w", err)
+		}
+		for _, containerInstance := range resp.ContainerInstances {
+			if aws.StringValue(containerInstance.Status) != ecs.ContainerInstanceStatusDraining {
+				continue
+			}
+			since, ok := drainingSince(containerInstance.Tags)
+			if !ok || now.Sub(since) < stuckDrainAfter {
+				continue
+			}
+			stuck = append(stuck, stuckDrain{
+				containerInstanceID: aws.StringValue(containerInstance.ContainerInstanceArn),
+				instanceID:          aws.StringValue(containerInstance.Ec2InstanceId),
+				since:               since,
+			})
+		}
+		return nil
+	})
+	if err != nil {
+		return nil, err
+	}
+	return stuck, nil
+}
+
+// recoverStuckDrains warns about each stuck drain and recovers it according to
+// the stuck drain policy. In check-only mode, stuck drains are only warned about.
+func (u *updater) recoverStuckDrains(stuck []stuckDrain, now time.Time, checkOnly bool) {
+	for _, d := range stuck {
+		age := now.Sub(d.since).Round(time.Minute)
+		log.Printf("Instance %q has been DRAINING for %s since the updater drained it, more than twice the drain timeout", d.instanceID, age)
+		u.warnings.add(warnStuckDrain, "instance %q has been draining for %s", d.instanceID, age)
+		if checkOnly || u.stuckDrainPolicy == stuckDrainAlert {
+			continue
+		}
+		var err error
+		switch u.stuckDrainPolicy {
+		case stuckDrainActivate:
+			err = u.activateInstance(d.containerInstanceID)
+		case stuckDrainComplete:
+			err = u.stopServiceTasks(d.containerInstanceID)
+		}
+		if err != nil {
+			log.Printf("Failed to recover the stuck drain of instance %q: %v", d.instanceID, err)
+			u.warnings.add(warnDegraded, "stuck drain of instance %q was not recovered: %v", d.instanceID, err)
+			continue
+		}
+		if u.stuckDrainPolicy == stuckDrainComplete {
+			// activateInstance clears the tag itself.
+			u.clearDrainStart(d.containerInstanceID)
+			log.Printf("Completed the stuck drain of instance %q; it stays DRAINING", d.instanceID)
+		}
+	}
+}
+
+// stopServiceTasks stops the service tasks running on the container instance;
+// the services start replacements on other instances. It returns an error if
+// tasks not started by a service run on the instance, which are never stopped.
+func (u *updater) stopServiceTasks(containerInstance string) error {
+	list, err := u.ecs.ListTasks(&ecs.ListTasksInput{
+		Cluster:           &u.cluster,
+		ContainerInstance: aws.String(containerInstance),
+	})
+	if err != nil {
+		return fmt.Errorf("failed to list tasks: %w", err)
+	}
+	if len(list.TaskArns) == 0 {
+		return nil
+	}
+	desc, err := u.ecs.DescribeTasks(&ecs.DescribeTasksInput{
+		Cluster: &u.cluster,
+		Tasks:   list.TaskArns,
+	})
+	if err != nil {
+		return fmt.Errorf("failed to describe tasks: %w", err)
+	}
+	for _, task := range desc.Tasks {
+		if !strings.HasPrefix(aws.StringValue(task.StartedBy), "ecs-svc/") {
+			return fmt.Errorf("non-service task %q is running", aws.StringValue(task.TaskArn))
+		}
+	}
+	for _, task := range desc.Tasks {
+		_, err := u.ecs.StopTask(&ecs.StopTaskInput{
+			Cluster: &u.cluster,
+			Task:    task.TaskArn,
+			Reason:  aws.String("Stuck drain completed by bottlerocket-ecs-updater"),
+		})
+		if err != nil {
+			return fmt.Errorf("failed to stop task %q: %w", aws.StringValue(task.TaskArn), err)
+		}
+		log.Printf("Stopped task %q of stuck drain", aws.StringValue(task.TaskArn))
+	}
+	return nil
+}
@@ -0,0 +1,123 @@
+package main
+
+import (
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestFindStuckDrains(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	drainedAt := func(d time.Duration) []*ecs.Tag {
+		return []*ecs.Tag{{Key: aws.String(tagDrainingSince), Value: aws.String(now.Add(-d).Format(time.RFC3339))}}
+	}
+	mockECS := MockECS{
+		ListContainerInstancesPagesFn: func(input *ecs.ListContainerInstancesInput, fn func(*ecs.ListContainerInstancesOutput, bool) bool) error {
+			assert.Equal(t, ecs.ContainerInstanceStatusDraining, aws.StringValue(input.Status))
+			fn(&ecs.ListContainerInstancesOutput{ContainerInstanceArns: aws.StringSlice([]string{"cont-inst-1", "cont-inst-2", "cont-inst-3", "cont-inst-4"})}, true)
+			return nil
+		},
+		DescribeContainerInstancesFn: func(input *ecs.DescribeContainerInstancesInput) (*ecs.DescribeContainerInstancesOutput, error) {
+			assert.Equal(t, []string{ecs.ContainerInstanceFieldTags}, aws.StringValueSlice(input.Include))
+			return &ecs.DescribeContainerInstancesOutput{ContainerInstances: []*ecs.ContainerInstance{
+				{ContainerInstanceArn: aws.String("cont-inst-1"), Ec2InstanceId: aws.String("i-1"), Status: aws.String("DRAINING"), Tags: drainedAt(2 * time.Hour)},
+				{ContainerInstanceArn: aws.String("cont-inst-2"), Ec2InstanceId: aws.String("i-2"), Status: aws.String("DRAINING"), Tags: drainedAt(10 * time.Minute)},
+				{ContainerInstanceArn: aws.String("cont-inst-3"), Ec2InstanceId: aws.String("i-3"), Status: aws.String("DRAINING")},
+				{ContainerInstanceArn: aws.String("cont-inst-4"), Ec2InstanceId: aws.String("i-4"), Status: aws.String("ACTIVE"), Tags: drainedAt(2 * time.Hour)},
+			}}, nil
+		},
+	}
+	u := updater{ecs: mockECS, cluster: "test-cluster"}
+	stuck, err := u.findStuckDrains(now)
+	require.NoError(t, err)
+	assert.Equal(t, []stuckDrain{{containerInstanceID: "cont-inst-1", instanceID: "i-1", since: now.Add(-2 * time.Hour)}}, stuck,
+		"should only find instances the updater drained more than twice the drain timeout ago")
+}
+
+func TestRecoverStuckDrains(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	stuck := []stuckDrain{{containerInstanceID: "cont-inst-1", instanceID: "i-1", since: now.Add(-2 * time.Hour)}}
+	newMockECS := func(calls *[]string) MockECS {
+		return MockECS{
+			UpdateContainerInstancesStateFn: func(input *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+				*calls = append(*calls, "state "+aws.StringValue(input.Status))
+				return &ecs.UpdateContainerInstancesStateOutput{}, nil
+			},
+			UntagResourceFn: func(input *ecs.UntagResourceInput) (*ecs.UntagResourceOutput, error) {
+				assert.Equal(t, []string{tagDrainingSince}, aws.StringValueSlice(input.TagKeys))
+				*calls = append(*calls, "untag")
+				return &ecs.UntagResourceOutput{}, nil
+			},
+			ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+				return &ecs.ListTasksOutput{TaskArns: aws.StringSlice([]string{"task-1"})}, nil
+			},
+			DescribeTasksFn: func(_ *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+				return &ecs.DescribeTasksOutput{Tasks: []*ecs.Task{{TaskArn: aws.String("task-1"), StartedBy: aws.String("ecs-svc/123")}}}, nil
+			},
+			StopTaskFn: func(input *ecs.StopTaskInput) (*ecs.StopTaskOutput, error) {
+				*calls = append(*calls, "stop "+aws.StringValue(input.Task))
+				return &ecs.StopTaskOutput{}, nil
+			},
+		}
+	}
+
+	cases := []struct {
+		name      string
+		policy    string
+		checkOnly bool
+		expected  []string
+	}{
+		{name: "alert", policy: stuckDrainAlert, expected: []string{}},
+		{name: "activate", policy: stuckDrainActivate, expected: []string{"state ACTIVE", "untag"}},
+		{name: "complete", policy: stuckDrainComplete, expected: []string{"stop task-1", "untag"}},
+		{name: "check-only", policy: stuckDrainActivate, checkOnly: true, expected: []string{}},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			calls := make([]string, 0)
+			u := updater{ecs: newMockECS(&calls), cluster: "test-cluster", stuckDrainPolicy: tc.policy, warnings: newPassWarnings()}
+			u.recoverStuckDrains(stuck, now, tc.checkOnly)
+			assert.Equal(t, tc.expected, calls)
+			assert.Equal(t, 1, u.warnings.counts[warnStuckDrain])
+			assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+		})
+	}
+	t.Run("non-service task", func(t *testing.T) {
+		calls := make([]string, 0)
+		mockECS := newMockECS(&calls)
+		mockECS.DescribeTasksFn = func(_ *ecs.DescribeTasksInput) (*ecs.DescribeTasksOutput, error) {
+			return &ecs.DescribeTasksOutput{Tasks: []*ecs.Task{{TaskArn: aws.String("task-1"), StartedBy: aws.String("batch")}}}, nil
+		}
+		u := updater{ecs: mockECS, cluster: "test-cluster", stuckDrainPolicy: stuckDrainComplete, warnings: newPassWarnings()}
+		u.recoverStuckDrains(stuck, now, false)
+		assert.Empty(t, calls, "should not stop non-service tasks")
+		assert.Equal(t, 1, u.warnings.counts[warnDegraded])
+	})
+}
+
+func TestDrainInstanceRecordsDrainStart(t *testing.T) {
+	var tags []*ecs.Tag
+	mockECS := MockECS{
+		UpdateContainerInstancesStateFn: func(_ *ecs.UpdateContainerInstancesStateInput) (*ecs.UpdateContainerInstancesStateOutput, error) {
+			return &ecs.UpdateContainerInstancesStateOutput{}, nil
+		},
+		TagResourceFn: func(input *ecs.TagResourceInput) (*ecs.TagResourceOutput, error) {
+			assert.Equal(t, "cont-inst-id", aws.StringValue(input.ResourceArn))
+			tags = input.Tags
+			return &ecs.TagResourceOutput{}, nil
+		},
+		ListTasksFn: func(_ *ecs.ListTasksInput) (*ecs.ListTasksOutput, error) {
+			return &ecs.ListTasksOutput{}, nil
+		},
+	}
+	u := updater{ecs: mockECS, cluster: "test-cluster", stuckDrainPolicy: stuckDrainAlert, warnings: newPassWarnings()}
+	require.NoError(t, u.drainInstance(instance{containerInstanceID: "cont-inst-id"}, nil))
+	require.Len(t, tags, 1)
+	assert.Equal(t, tagDrainingSince, aws.StringValue(tags[0].Key))
+	_, ok := drainingSince(tags)
+	assert.True(t, ok)
+}
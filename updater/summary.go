@@ -0,0 +1,225 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+)
+
+// unknownVersion stands in for the version of an instance that could not be checked.
+const unknownVersion = "unknown"
+
+// Outcomes of the instances handled during a pass.
+const (
+	outcomeUpdated     = "updated"
+	outcomeFailed      = "failed"
+	outcomeSkipped     = "skipped"
+	outcomeUnparseable = "unparseable"
+)
+
+// maxRawOutput is the most bytes of unparseable output kept for the report.
+const maxRawOutput = 4096
+
+// passSummary records the outcome for each instance handled during an updater run.
+type passSummary struct {
+	results map[string]string
+	// outcomes maps each instance with a result to whether it was updated, failed or skipped.
+	outcomes map[string]string
+	updated  int
+	failed   int
+	skipped  int
+	// failures lists the instances whose update failed, in the order they failed.
+	failures []string
+	// unparseable counts the instances whose update check output could not be
+	// parsed, and rawOutputs holds their output with account IDs masked.
+	unparseable int
+	rawOutputs  map[string]string
+	// passID and identity attribute the summary to a pass and the IAM identity
+	// that ran it, and changeRef to the change-management ticket of the pass.
+	passID    string
+	identity  string
+	changeRef string
+	// skipReasons map each skipped instance to the reason it was skipped (see
+	// skip.go).
+	skipReasons map[string]string
+	// updateIDs map each instance whose update started to the ID of the update.
+	updateIDs map[string]string
+	// services map each candidate to the services with tasks on it at the
+	// start of the pass, for failure escalations.
+	services map[string][]string
+	// intents are the unresolved intents found at the start of the pass (see
+	// intent.go).
+	intents []reportIntent
+	// versionsBefore and versionsAfter map each Bottlerocket instance to the
+	// version it ran before and after the pass.
+	versionsBefore map[string]string
+	versionsAfter  map[string]string
+	// segmentTags are the tag keys of -segment-tags, and segments map each
+	// instance to its value of each key (see segment.go).
+	segmentTags []string
+	clusterTags map[string]string
+	segments    map[string]map[string]string
+	// warnings are the soft problems of the pass, reported apart from failures.
+	warnings *passWarnings
+	// commands are the SSM commands sent during the pass.
+	commands *commandLog
+	// timings are the durations of the phases of the pass.
+	timings *passTimings
+}
+
+func newPassSummary() *passSummary {
+	return &passSummary{
+		results:        make(map[string]string),
+		outcomes:       make(map[string]string),
+		rawOutputs:     make(map[string]string),
+		skipReasons:    make(map[string]string),
+		updateIDs:      make(map[string]string),
+		services:       make(map[string][]string),
+		versionsBefore: make(map[string]string),
+		versionsAfter:  make(map[string]string),
+		segments:       make(map[string]map[string]string),
+	}
+}
+
+// recordVersions records the versions Bottlerocket instances run at the start
+// of the pass. Until an instance is updated, its version after the pass is
+// assumed to be the same.
+func (s *passSummary) recordVersions(instances []instance) {
+	for _, inst := range instances {
+		version := inst.bottlerocketVersion
+		if version == "" {
+			version = unknownVersion
+		}
+		s.versionsBefore[inst.instanceID] = version
+		s.versionsAfter[inst.instanceID] = version
+	}
+}
+
+// recordServices records the services running on each candidate at the
+// start of the pass, from the candidates' workloads by instance ID.
+func (s *passSummary) recordServices(workloads map[string]workload) {
+	for id, w := range workloads {
+		s.services[id] = w.services()
+	}
+}
+
+// updatedVersion records the version an instance runs after it was updated.
+func (s *passSummary) updatedVersion(instanceID string, version string) {
+	if version == "" {
+		version = unknownVersion
+	}
+	s.versionsAfter[instanceID] = version
+}
+
+// updatedInstance records an instance that was updated successfully.
+func (s *passSummary) updatedInstance(instanceID string, msg string) {
+	s.results[instanceID] = msg
+	s.outcomes[instanceID] = outcomeUpdated
+	s.updated++
+}
+
+// failedInstance records an instance for which the update failed.
+func (s *passSummary) failedInstance(instanceID string, msg string) {
+	s.results[instanceID] = msg
+	s.outcomes[instanceID] = outcomeFailed
+	s.failed++
+	s.failures = append(s.failures, instanceID)
+}
+
+// skippedInstance records an instance that was intentionally not updated, for
+// one of the reasons of skip.go.
+func (s *passSummary) skippedInstance(instanceID string, reason string, msg string) {
+	s.results[instanceID] = msg
+	s.outcomes[instanceID] = outcomeSkipped
+	s.skipReasons[instanceID] = reason
+	s.skipped++
+}
+
+// unparseableInstance records an instance that was not updated because the
+// output of its update check could not be parsed. The output is kept, with
+// account IDs masked, so that changes in its format can be diagnosed.
+func (s *passSummary) unparseableInstance(instanceID string, msg string, output string) {
+	s.results[instanceID] = msg
+	s.outcomes[instanceID] = outcomeUnparseable
+	s.unparseable++
+	if len(output) > maxRawOutput {
+		output = output[:maxRawOutput]
+	}
+	s.rawOutputs[instanceID] = string(redact([]byte(output)))
+}
+
+// log writes the after action summary to the log.
+func (s *passSummary) log() {
+	log.Printf("After action summary (%s):", versionString())
+	if s.passID != "" {
+		log.Printf("Pass: %s", s.passID)
+	}
+	if s.identity != "" {
+		log.Printf("Run as: %s", s.identity)
+	}
+	if s.changeRef != "" {
+		log.Printf("Change: %s", s.changeRef)
+	}
+	instanceIDs := make([]string, 0, len(s.results))
+	for k := range s.results {
+		instanceIDs = append(instanceIDs, k)
+	}
+	sort.Strings(instanceIDs)
+	for _, k := range instanceIDs {
+		log.Printf("%s: %s", k, s.results[k])
+	}
+	log.Printf("%d instance(s) updated, %d failed, %d skipped", s.updated, s.failed, s.skipped)
+	s.logSkips()
+	if s.unparseable > 0 {
+		log.Printf("%d instance(s) not updated because their update check output could not be parsed", s.unparseable)
+	}
+	if proven := s.provenVersion(); proven != "" {
+		log.Printf("All Bottlerocket instances run %s after a pass without failures, making it the cluster's last known good version", proven)
+	}
+	s.warnings.log()
+	s.commands.log()
+	if timings := s.timings.String(); timings != "" {
+		log.Printf("Phase durations: %s", timings)
+	}
+	if len(s.versionsBefore) > 0 {
+		log.Printf("Bottlerocket versions before the pass: %s", histogram(s.versionsBefore))
+		log.Printf("Bottlerocket versions after the pass: %s", histogram(s.versionsAfter))
+	}
+	for _, count := range s.segmentCounts() {
+		log.Printf("Segment %s: %d instance(s), %d updated, %d failed, %d skipped",
+			count.name(s.segmentTags), count.Instances, count.Updated, count.Failed, count.Skipped)
+	}
+}
+
+// versionHistogram counts instances per Bottlerocket version.
+type versionHistogram map[string]int
+
+// histogram counts the instances in versions, which maps instances to the
+// version they run, per version.
+func histogram(versions map[string]string) versionHistogram {
+	h := make(versionHistogram)
+	for _, version := range versions {
+		h[version]++
+	}
+	return h
+}
+
+// versions returns the versions in the histogram in order.
+func (h versionHistogram) versions() []string {
+	versions := make([]string, 0, len(h))
+	for version := range h {
+		versions = append(versions, version)
+	}
+	sort.Strings(versions)
+	return versions
+}
+
+// String lists the number of instances per version, e.g. "1.11.0: 2, 1.12.0: 1".
+func (h versionHistogram) String() string {
+	parts := make([]string, 0, len(h))
+	for _, version := range h.versions() {
+		parts = append(parts, fmt.Sprintf("%s: %d", version, h[version]))
+	}
+	return strings.Join(parts, ", ")
+}
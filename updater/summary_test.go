@@ -0,0 +1,105 @@
+package main
+
+import (
+	"os"
+	"strings"
+	"testing"
+	"unicode"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestPassSummary(t *testing.T) {
+	summary := newPassSummary()
+	summary.updatedInstance("inst-id-1", "Instance updated successfully")
+	summary.failedInstance("inst-id-2", "Update failed")
+	summary.skippedInstance("inst-id-3", skipStandaloneTasks, "Instance is not eligible for updates because it contains non-service task(s)")
+	summary.updatedInstance("inst-id-4", "Instance updated successfully")
+
+	assert.Equal(t, 2, summary.updated)
+	assert.Equal(t, 1, summary.failed)
+	assert.Equal(t, 1, summary.skipped)
+	assert.Equal(t, map[string]string{
+		"inst-id-1": "Instance updated successfully",
+		"inst-id-2": "Update failed",
+		"inst-id-3": "Instance is not eligible for updates because it contains non-service task(s)",
+		"inst-id-4": "Instance updated successfully",
+	}, summary.results)
+}
+
+func TestPassSummaryUnparseable(t *testing.T) {
+	summary := newPassSummary()
+	summary.unparseableInstance("inst-id-1", "Update check output could not be parsed", `{"error": "arn:aws:ssm:us-west-2:123456789012:document/check"}`)
+	summary.unparseableInstance("inst-id-2", "Update check output could not be parsed", strings.Repeat("x", maxRawOutput+1))
+
+	assert.Equal(t, 2, summary.unparseable)
+	assert.Equal(t, 0, summary.skipped)
+	assert.Equal(t, outcomeUnparseable, summary.outcomes["inst-id-1"])
+	assert.Equal(t, `{"error": "arn:aws:ssm:us-west-2:************:document/check"}`, summary.rawOutputs["inst-id-1"], "should mask account IDs")
+	assert.Len(t, summary.rawOutputs["inst-id-2"], maxRawOutput)
+}
+
+func TestPassSummaryVersions(t *testing.T) {
+	summary := newPassSummary()
+	summary.recordVersions([]instance{
+		{instanceID: "inst-id-1", bottlerocketVersion: "1.11.0"},
+		{instanceID: "inst-id-2", bottlerocketVersion: "1.11.0"},
+		{instanceID: "inst-id-3", bottlerocketVersion: "1.12.0"},
+		{instanceID: "inst-id-4"},
+	})
+	summary.updatedVersion("inst-id-1", "1.12.0")
+	summary.updatedVersion("inst-id-2", "")
+
+	before := histogram(summary.versionsBefore)
+	after := histogram(summary.versionsAfter)
+	assert.Equal(t, versionHistogram{"1.11.0": 2, "1.12.0": 1, "unknown": 1}, before)
+	assert.Equal(t, versionHistogram{"1.12.0": 2, "unknown": 2}, after)
+	assert.Equal(t, "1.11.0: 2, 1.12.0: 1, unknown: 1", before.String())
+}
+
+func TestPassSummarySkipCounts(t *testing.T) {
+	summary := newPassSummary()
+	summary.skippedInstance("inst-id-1", skipNotSSMTarget, "Not a valid SSM target, e.g. because it was terminated")
+	summary.skippedInstance("inst-id-2", skipStandaloneTasks, "Instance is not eligible for updates because it contains non-service task(s)")
+	summary.skippedInstance("inst-id-3", skipNotSSMTarget, "Not a valid SSM target, e.g. because it was terminated")
+	summary.updatedInstance("inst-id-4", "Instance updated successfully")
+
+	skips := summary.skipCounts()
+	require.Len(t, skips, 2)
+	assert.Equal(t, skipNotSSMTarget, skips[0].Reason, "the most common reason should come first")
+	assert.Equal(t, 2, skips[0].Instances)
+	assert.Equal(t, "SSM agent offline or instance terminated", skips[0].Description)
+	assert.Equal(t, readmeURL+"#why-is-an-instance-listed-as-not-a-valid-ssm-target", skips[0].Link)
+	assert.Equal(t, skipStandaloneTasks, skips[1].Reason)
+	assert.Equal(t, 1, skips[1].Instances)
+
+	// Every reason documents what to do about it in an existing README section.
+	readme, err := os.ReadFile("../README.md")
+	require.NoError(t, err)
+	anchors := make(map[string]bool)
+	for _, line := range strings.Split(string(readme), "\n") {
+		if strings.HasPrefix(line, "#") {
+			anchors[readmeAnchor(strings.TrimLeft(line, "# "))] = true
+		}
+	}
+	for reason, remedy := range skipRemedies {
+		assert.NotEmpty(t, remedy.description, reason)
+		assert.NotEmpty(t, remedy.remedy, reason)
+		assert.True(t, anchors[remedy.section], "README has no section %q for %s", remedy.section, reason)
+	}
+}
+
+// readmeAnchor returns the anchor GitHub gives a heading.
+func readmeAnchor(heading string) string {
+	var b strings.Builder
+	for _, r := range strings.ToLower(heading) {
+		switch {
+		case r == ' ':
+			b.WriteRune('-')
+		case r == '-' || r == '_' || unicode.IsLetter(r) || unicode.IsDigit(r):
+			b.WriteRune(r)
+		}
+	}
+	return b.String()
+}
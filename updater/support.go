@@ -0,0 +1,199 @@
+package main
+
+import (
+	"archive/tar"
+	"bytes"
+	"compress/gzip"
+	"encoding/json"
+	"flag"
+	"fmt"
+	"os"
+	"strings"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/cloudwatchlogs"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/sts"
+)
+
+const (
+	// supportBundleLogWindow is how far back the logs in a support bundle go.
+	supportBundleLogWindow = 7 * 24 * time.Hour
+	// maxSupportBundleLogEvents limits the log events in a support bundle.
+	maxSupportBundleLogEvents = 20000
+)
+
+// bundleFile is a file of a support bundle.
+type bundleFile struct {
+	name    string
+	content []byte
+}
+
+// supportBundle gathers what maintainers need to triage a problem reported
+// with the updater of a cluster. Everything in the bundle is best effort: a
+// part that can't be collected is noted in errors.txt instead.
+type supportBundle struct {
+	ecs  ECSReadAPI
+	sts  STSAPI
+	logs CloudWatchLogsAPI
+	// cluster is the cluster the updater manages.
+	cluster string
+	// logGroup is the log group of the updater; logs are left out when empty.
+	logGroup string
+	// reportPath is the -report of the last pass; left out when empty.
+	reportPath string
+	// sensitive keeps AWS account IDs in the bundle instead of masking them.
+	sensitive bool
+}
+
+// collect returns the files of the bundle.
+func (b supportBundle) collect(now time.Time) []bundleFile {
+	files := []bundleFile{
+		{name: "version.txt", content: []byte(verboseVersionString())},
+		{name: "flags.txt", content: effectiveFlags()},
+	}
+	var errs []string
+	add := func(name string, content []byte, err error) {
+		if err != nil {
+			errs = append(errs, fmt.Sprintf("%s: %v", name, err))
+			return
+		}
+		files = append(files, bundleFile{name: name, content: content})
+	}
+	identity, err := b.identity()
+	add("identity.txt", identity, err)
+	cluster, err := b.clusterSummary()
+	add("cluster.json", cluster, err)
+	if b.reportPath != "" {
+		report, err := os.ReadFile(b.reportPath)
+		add("report.json", report, err)
+	}
+	if b.logGroup != "" {
+		logs, err := b.recentLogs(now)
+		add("logs.txt", logs, err)
+	}
+	if len(errs) > 0 {
+		files = append(files, bundleFile{name: "errors.txt", content: []byte(strings.Join(errs, "\n") + "\n")})
+	}
+	if !b.sensitive {
+		for i := range files {
+			files[i].content = redact(files[i].content)
+		}
+	}
+	return files
+}
+
+// secretFlags are the flags whose values may hold credentials, which are left
+// out of support bundles. Settings may hold credentials too, e.g. for
+// registries, and are masked in logs as well.
+var secretFlags = map[string]bool{
+	"failure-webhook":               true,
+	"failure-webhook-authorization": true,
+	"settings":                      true,
+}
+
+// effectiveFlags lists the value of every flag, including defaults. The values
+// of secretFlags are only listed as set or not.
+func effectiveFlags() []byte {
+	var out bytes.Buffer
+	flag.VisitAll(func(f *flag.Flag) {
+		value := f.Value.String()
+		if secretFlags[f.Name] && value != "" {
+			value = "(set)"
+		}
+		fmt.Fprintf(&out, "-%s=%s\n", f.Name, value)
+	})
+	return out.Bytes()
+}
+
+// identity returns the IAM identity the bundle is collected as.
+func (b supportBundle) identity() ([]byte, error) {
+	resp, err := b.sts.GetCallerIdentity(&sts.GetCallerIdentityInput{})
+	if err != nil {
+		return nil, fmt.Errorf("failed to get caller identity: %w", err)
+	}
+	return []byte(aws.StringValue(resp.Arn) + "\n"), nil
+}
+
+// clusterSummary returns the cluster with its tags, which hold the summary of
+// the last run and the last known good version.
+func (b supportBundle) clusterSummary() ([]byte, error) {
+	resp, err := b.ecs.DescribeClusters(&ecs.DescribeClustersInput{
+		Clusters: aws.StringSlice([]string{b.cluster}),
+		Include:  aws.StringSlice([]string{ecs.ClusterFieldTags}),
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to describe cluster: %w", err)
+	}
+	if len(resp.Clusters) == 0 {
+		return nil, fmt.Errorf("cluster %q not found", b.cluster)
+	}
+	return json.MarshalIndent(resp.Clusters[0], "", "  ")
+}
+
+// recentLogs returns the updater's log events for the cluster from the last
+// supportBundleLogWindow, oldest first.
+func (b supportBundle) recentLogs(now time.Time) ([]byte, error) {
+	var out bytes.Buffer
+	count := 0
+	err := b.logs.FilterLogEventsPages(&cloudwatchlogs.FilterLogEventsInput{
+		LogGroupName:        aws.String(b.logGroup),
+		LogStreamNamePrefix: aws.String(logStreamPrefix(b.cluster)),
+		StartTime:           aws.Int64(now.Add(-supportBundleLogWindow).UnixNano() / int64(time.Millisecond)),
+	}, func(output *cloudwatchlogs.FilterLogEventsOutput, _ bool) bool {
+		for _, event := range output.Events {
+			if count >= maxSupportBundleLogEvents {
+				return false
+			}
+			fmt.Fprintf(&out, "%s %s\n", aws.StringValue(event.LogStreamName), strings.TrimRight(aws.StringValue(event.Message), "\n"))
+			count++
+		}
+		return true
+	})
+	if err != nil {
+		return nil, fmt.Errorf("failed to get log events: %w", err)
+	}
+	return out.Bytes(), nil
+}
+
+// logStreamPrefix returns the prefix of the updater's log streams for the
+// cluster, as set up by the CloudFormation template.
+func logStreamPrefix(cluster string) string {
+	name := cluster
+	if i := strings.LastIndex(name, "/"); i >= 0 {
+		name = name[i+1:]
+	}
+	return "/ecs/bottlerocket-updater/" + name
+}
+
+// writeSupportBundle writes the files to path as a gzipped tarball.
+func writeSupportBundle(path string, files []bundleFile, now time.Time) error {
+	var buf bytes.Buffer
+	gz := gzip.NewWriter(&buf)
+	tw := tar.NewWriter(gz)
+	for _, file := range files {
+		header := &tar.Header{
+			Name:    "support-bundle/" + file.name,
+			Mode:    0o644,
+			Size:    int64(len(file.content)),
+			ModTime: now,
+		}
+		if err := tw.WriteHeader(header); err != nil {
+			return fmt.Errorf("failed to write support bundle: %w", err)
+		}
+		if _, err := tw.Write(file.content); err != nil {
+			return fmt.Errorf("failed to write support bundle: %w", err)
+		}
+	}
+	if err := tw.Close(); err != nil {
+		return fmt.Errorf("failed to write support bundle: %w", err)
+	}
+	if err := gz.Close(); err != nil {
+		return fmt.Errorf("failed to write support bundle: %w", err)
+	}
+	if err := os.WriteFile(path, buf.Bytes(), 0o644); err != nil {
+		return fmt.Errorf("failed to write support bundle: %w", err)
+	}
+	return nil
+}
@@ -0,0 +1,99 @@
+package main
+
+import (
+	"archive/tar"
+	"compress/gzip"
+	"errors"
+	"flag"
+	"io"
+	"os"
+	"path/filepath"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/cloudwatchlogs"
+	"github.com/aws/aws-sdk-go/service/ecs"
+	"github.com/aws/aws-sdk-go/service/sts"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestEffectiveFlagsMasksSecrets(t *testing.T) {
+	secrets := []struct{ name, value string }{
+		{name: "settings", value: `{"container-registry": {"credentials": [{"registry": "docker.io", "auth": "c2VjcmV0"}]}}`},
+		{name: "failure-webhook", value: "https://hooks.example.com/services/T0/B0/secret"},
+		{name: "failure-webhook-authorization", value: "secretsmanager:webhook-token"},
+	}
+	for _, s := range secrets {
+		old := flag.Lookup(s.name).Value.String()
+		require.NoError(t, flag.Set(s.name, s.value))
+		name := s.name
+		t.Cleanup(func() { _ = flag.Set(name, old) })
+	}
+	flags := string(effectiveFlags())
+	for _, s := range secrets {
+		assert.NotContains(t, flags, s.value)
+		assert.Contains(t, flags, "-"+s.name+"=(set)\n")
+	}
+}
+
+func TestSupportBundle(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	reportPath := filepath.Join(t.TempDir(), "report.json")
+	require.NoError(t, os.WriteFile(reportPath, []byte(`{"passId": "pass-1"}`), 0o600))
+	bundle := supportBundle{
+		sts: MockSTS{
+			GetCallerIdentityFn: func(_ *sts.GetCallerIdentityInput) (*sts.GetCallerIdentityOutput, error) {
+				return &sts.GetCallerIdentityOutput{Arn: aws.String("arn:aws:sts::123456789012:assumed-role/updater/task")}, nil
+			},
+		},
+		ecs: MockECS{
+			DescribeClustersFn: func(input *ecs.DescribeClustersInput) (*ecs.DescribeClustersOutput, error) {
+				assert.Equal(t, []string{ecs.ClusterFieldTags}, aws.StringValueSlice(input.Include))
+				return nil, errors.New("access denied")
+			},
+		},
+		logs: MockCloudWatchLogs{
+			FilterLogEventsPagesFn: func(input *cloudwatchlogs.FilterLogEventsInput, fn func(*cloudwatchlogs.FilterLogEventsOutput, bool) bool) error {
+				assert.Equal(t, "/ecs/bottlerocket-updater/test-cluster", aws.StringValue(input.LogStreamNamePrefix))
+				assert.Equal(t, now.Add(-supportBundleLogWindow).UnixNano()/int64(time.Millisecond), aws.Int64Value(input.StartTime))
+				fn(&cloudwatchlogs.FilterLogEventsOutput{Events: []*cloudwatchlogs.FilteredLogEvent{
+					{LogStreamName: aws.String("stream"), Message: aws.String("Starting pass pass-1 over cluster \"test-cluster\"\n")},
+				}}, true)
+				return nil
+			},
+		},
+		cluster:    "arn:aws:ecs:us-west-2:123456789012:cluster/test-cluster",
+		logGroup:   "updater-logs",
+		reportPath: reportPath,
+	}
+	files := make(map[string]string)
+	for _, file := range bundle.collect(now) {
+		files[file.name] = string(file.content)
+	}
+	assert.Contains(t, files, "version.txt")
+	assert.Contains(t, files["flags.txt"], "-cluster=")
+	assert.Equal(t, "arn:aws:sts::************:assumed-role/updater/task\n", files["identity.txt"], "should mask account IDs")
+	assert.Equal(t, `{"passId": "pass-1"}`, files["report.json"])
+	assert.Equal(t, "stream Starting pass pass-1 over cluster \"test-cluster\"\n", files["logs.txt"])
+	assert.NotContains(t, files, "cluster.json")
+	assert.Contains(t, files["errors.txt"], "cluster.json: failed to describe cluster: access denied")
+
+	path := filepath.Join(t.TempDir(), "bundle.tar.gz")
+	require.NoError(t, writeSupportBundle(path, []bundleFile{{name: "version.txt", content: []byte("v1.0.0\n")}}, now))
+	f, err := os.Open(path)
+	require.NoError(t, err)
+	defer f.Close()
+	gz, err := gzip.NewReader(f)
+	require.NoError(t, err)
+	tr := tar.NewReader(gz)
+	header, err := tr.Next()
+	require.NoError(t, err)
+	assert.Equal(t, "support-bundle/version.txt", header.Name)
+	content, err := io.ReadAll(tr)
+	require.NoError(t, err)
+	assert.Equal(t, "v1.0.0\n", string(content))
+	_, err = tr.Next()
+	assert.Equal(t, io.EOF, err)
+}
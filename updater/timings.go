@@ -0,0 +1,80 @@
+package main
+
+import (
+	"fmt"
+	"strings"
+	"sync"
+	"time"
+)
+
+// Phases of a pass whose durations are recorded.
+const (
+	// phaseDiscover lists and describes the cluster's container instances.
+	phaseDiscover = "discover"
+	// phaseCheck checks the Bottlerocket instances for available updates.
+	phaseCheck = "check"
+	// phasePlan filters, orders and stages the instances to update.
+	phasePlan = "plan"
+	// phaseDrain drains instances, for all instances updated in the pass.
+	phaseDrain = "drain"
+	// phaseUpdate applies updates and reboots instances into them.
+	phaseUpdate = "update"
+	// phaseVerify checks the version and daemon tasks of updated instances.
+	phaseVerify = "verify"
+)
+
+// phases lists every phase in the order they run.
+var phases = []string{phaseDiscover, phaseCheck, phasePlan, phaseDrain, phaseUpdate, phaseVerify}
+
+// passTimings accumulates how long each phase of a pass took, so slow
+// phases, such as an update check that polls instances one at a time, show
+// up in the summary and metrics. A nil passTimings discards timings.
+type passTimings struct {
+	mu        sync.Mutex
+	durations map[string]time.Duration
+}
+
+func newPassTimings() *passTimings {
+	return &passTimings{durations: make(map[string]time.Duration)}
+}
+
+// record adds the time since start to the duration of the phase.
+func (t *passTimings) record(phase string, start time.Time) {
+	t.add(phase, time.Since(start))
+}
+
+// add adds d to the duration of the phase.
+func (t *passTimings) add(phase string, d time.Duration) {
+	if t == nil {
+		return
+	}
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	t.durations[phase] += d
+}
+
+// recorded returns the duration of each phase that ran, by phase.
+func (t *passTimings) recorded() map[string]time.Duration {
+	durations := make(map[string]time.Duration)
+	if t == nil {
+		return durations
+	}
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	for phase, d := range t.durations {
+		durations[phase] = d
+	}
+	return durations
+}
+
+// String lists the durations of the phases that ran in order, e.g. "discover: 1.2s, check: 40s".
+func (t *passTimings) String() string {
+	durations := t.recorded()
+	parts := make([]string, 0, len(durations))
+	for _, phase := range phases {
+		if d, ok := durations[phase]; ok {
+			parts = append(parts, fmt.Sprintf("%s: %s", phase, d.Round(100*time.Millisecond)))
+		}
+	}
+	return strings.Join(parts, ", ")
+}
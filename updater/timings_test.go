@@ -0,0 +1,27 @@
+package main
+
+import (
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+)
+
+func TestPassTimings(t *testing.T) {
+	timings := newPassTimings()
+	assert.Equal(t, "", timings.String())
+
+	timings.add(phaseDrain, 30*time.Second)
+	timings.add(phaseCheck, 1500*time.Millisecond)
+	timings.add(phaseDrain, 45*time.Second)
+	assert.Equal(t, "check: 1.5s, drain: 1m15s", timings.String(), "should list phases in the order they run")
+	assert.Equal(t, map[string]time.Duration{phaseCheck: 1500 * time.Millisecond, phaseDrain: 75 * time.Second}, timings.recorded())
+
+	timings.record(phaseVerify, time.Now().Add(-time.Minute))
+	assert.GreaterOrEqual(t, timings.recorded()[phaseVerify], time.Minute)
+
+	var nilTimings *passTimings
+	nilTimings.record(phaseCheck, time.Now())
+	assert.Empty(t, nilTimings.recorded())
+	assert.Equal(t, "", nilTimings.String())
+}
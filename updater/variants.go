@@ -0,0 +1,43 @@
+package main
+
+import (
+	"sort"
+	"strings"
+)
+
+// defaultSupportedVariants lists the Bottlerocket ECS variants the updater is
+// known to work with.
+const defaultSupportedVariants = "aws-ecs-1,aws-ecs-1-nvidia,aws-ecs-2,aws-ecs-2-nvidia"
+
+// parseVariants parses a comma-separated list of Bottlerocket variants. It
+// returns nil for an empty list, which supports every variant.
+func parseVariants(spec string) map[string]bool {
+	var variants map[string]bool
+	for _, variant := range strings.Split(spec, ",") {
+		variant = strings.TrimSpace(variant)
+		if variant == "" {
+			continue
+		}
+		if variants == nil {
+			variants = make(map[string]bool)
+		}
+		variants[variant] = true
+	}
+	return variants
+}
+
+// formatVariants returns the variants as a sorted, comma-separated list.
+func formatVariants(variants map[string]bool) string {
+	list := make([]string, 0, len(variants))
+	for variant := range variants {
+		list = append(list, variant)
+	}
+	sort.Strings(list)
+	return strings.Join(list, ",")
+}
+
+// supportsVariant reports whether the updater is configured to update
+// instances of the Bottlerocket variant.
+func (u *updater) supportsVariant(variant string) bool {
+	return u.variants == nil || u.variants[variant]
+}
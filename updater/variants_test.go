@@ -0,0 +1,25 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+)
+
+func TestParseVariants(t *testing.T) {
+	assert.Nil(t, parseVariants(""))
+	assert.Nil(t, parseVariants(" , "))
+	assert.Equal(t, map[string]bool{"aws-ecs-1": true, "aws-ecs-2": true}, parseVariants("aws-ecs-1, aws-ecs-2,"))
+	assert.Equal(t, defaultSupportedVariants, formatVariants(parseVariants(defaultSupportedVariants)))
+}
+
+func TestSupportsVariant(t *testing.T) {
+	u := updater{}
+	assert.True(t, u.supportsVariant("aws-ecs-3"), "should support every variant without a list")
+
+	u.variants = parseVariants(defaultSupportedVariants)
+	assert.True(t, u.supportsVariant("aws-ecs-2"))
+	assert.True(t, u.supportsVariant("aws-ecs-1-nvidia"))
+	assert.False(t, u.supportsVariant("aws-ecs-3"))
+	assert.False(t, u.supportsVariant(""))
+}
@@ -0,0 +1,70 @@
+package main
+
+import (
+	"fmt"
+	"runtime"
+	"runtime/debug"
+	"strings"
+)
+
+// Build metadata, set at build time through
+// -ldflags "-X main.version=... -X main.gitCommit=... -X main.buildDate=...".
+var (
+	version   = "dev"
+	gitCommit = ""
+	buildDate = "unknown"
+)
+
+// commit returns the git commit the updater was built from, falling back to the
+// VCS information embedded by the Go toolchain when it was not set at build time.
+func commit() string {
+	if gitCommit != "" {
+		return gitCommit
+	}
+	if setting, ok := buildSetting("vcs.revision"); ok {
+		return setting
+	}
+	return "unknown"
+}
+
+// buildSetting looks up a key in the build settings embedded by the Go toolchain.
+func buildSetting(key string) (string, bool) {
+	info, ok := debug.ReadBuildInfo()
+	if !ok {
+		return "", false
+	}
+	for _, setting := range info.Settings {
+		if setting.Key == key {
+			return setting.Value, true
+		}
+	}
+	return "", false
+}
+
+// versionString returns a short, single line description of the updater build.
+func versionString() string {
+	return fmt.Sprintf("bottlerocket-ecs-updater %s (commit %s, built %s)", version, commit(), buildDate)
+}
+
+// verboseVersionString returns the full build metadata for the updater including
+// the toolchain, enabled build tags, and the module dependencies compiled in.
+func verboseVersionString() string {
+	var b strings.Builder
+	fmt.Fprintf(&b, "version:    %s\n", version)
+	fmt.Fprintf(&b, "commit:     %s\n", commit())
+	fmt.Fprintf(&b, "build date: %s\n", buildDate)
+	fmt.Fprintf(&b, "go:         %s\n", runtime.Version())
+	fmt.Fprintf(&b, "platform:   %s/%s\n", runtime.GOOS, runtime.GOARCH)
+	tags, _ := buildSetting("-tags")
+	if tags == "" {
+		tags = "none"
+	}
+	fmt.Fprintf(&b, "build tags: %s\n", tags)
+	if info, ok := debug.ReadBuildInfo(); ok {
+		b.WriteString("dependencies:\n")
+		for _, dep := range info.Deps {
+			fmt.Fprintf(&b, "  %s %s\n", dep.Path, dep.Version)
+		}
+	}
+	return b.String()
+}
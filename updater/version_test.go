@@ -0,0 +1,23 @@
+package main
+
+import (
+	"runtime"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+)
+
+func TestVersionString(t *testing.T) {
+	originalVersion, originalCommit, originalDate := version, gitCommit, buildDate
+	defer func() { version, gitCommit, buildDate = originalVersion, originalCommit, originalDate }()
+	version, gitCommit, buildDate = "v0.2.2", "abc1234", "2024-02-14T00:00:00Z"
+
+	assert.Equal(t, "bottlerocket-ecs-updater v0.2.2 (commit abc1234, built 2024-02-14T00:00:00Z)", versionString())
+
+	verbose := verboseVersionString()
+	assert.Contains(t, verbose, "version:    v0.2.2\n")
+	assert.Contains(t, verbose, "commit:     abc1234\n")
+	assert.Contains(t, verbose, "build date: 2024-02-14T00:00:00Z\n")
+	assert.Contains(t, verbose, "go:         "+runtime.Version()+"\n")
+	assert.Contains(t, verbose, "build tags: ")
+}
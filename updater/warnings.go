@@ -0,0 +1,223 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"sort"
+	"strings"
+	"sync"
+
+	"github.com/aws/aws-sdk-go/aws/request"
+)
+
+// Categories of warnings.
+const (
+	// warnSkipped is an instance left out of the pass because its state could
+	// not be determined, as opposed to being skipped by policy.
+	warnSkipped = "skipped"
+	// warnTruncated is command output that was truncated and therefore ignored.
+	warnTruncated = "truncated"
+	// warnThrottled is an AWS call that was throttled.
+	warnThrottled = "throttled"
+	// warnRetried is an AWS call that was retried after a transient failure.
+	warnRetried = "retried"
+	// warnDegraded is a best-effort step, such as deferring instances, that
+	// could not be carried out.
+	warnDegraded = "degraded"
+	// warnUnsupported is a Bottlerocket instance of a variant the updater is
+	// not configured to update.
+	warnUnsupported = "unsupported"
+	// warnQuarantineExpired is an instance whose quarantine expired, so it is
+	// updated again.
+	warnQuarantineExpired = "quarantine-expired"
+	// warnUnparseable is update check output that could not be parsed, which
+	// usually means the output of apiclient changed.
+	warnUnparseable = "unparseable"
+	// warnStuckDrain is an instance the updater drained that has been
+	// draining for more than twice the drain timeout.
+	warnStuckDrain = "stuck-drain"
+	// warnSibling is another updater task running for the same cluster, which
+	// this task leads.
+	warnSibling = "sibling-updater"
+	// warnBelowMinimum is a Bottlerocket instance running a version older than
+	// -minimum-version.
+	warnBelowMinimum = "below-minimum-version"
+	// warnIntent is an action the updater recorded before taking it on an
+	// instance and did not resolve, e.g. because it crashed (see intent.go).
+	warnIntent = "unresolved-intent"
+)
+
+// warningCategories lists every category, so metrics report zero warnings too.
+var warningCategories = []string{warnSkipped, warnTruncated, warnThrottled, warnRetried, warnDegraded, warnUnsupported, warnQuarantineExpired, warnUnparseable, warnStuckDrain, warnSibling, warnBelowMinimum, warnIntent}
+
+// maxWarningMessages limits the warning messages kept for the summary; the
+// warnings beyond it are only counted.
+const maxWarningMessages = 20
+
+// passWarnings accumulates the soft problems of a pass: things that did not
+// fail an update but that operators should see before they turn into
+// failures. A nil passWarnings discards warnings.
+type passWarnings struct {
+	mu       sync.Mutex
+	counts   map[string]int
+	messages []string
+	// awsErrors counts the failed AWS calls by the class of their error (see
+	// classifyAWSError), and awsErrorCalls holds the calls that failed with
+	// each class, e.g. "ssm:GetParameter".
+	awsErrors     map[string]int
+	awsErrorCalls map[string]map[string]bool
+}
+
+func newPassWarnings() *passWarnings {
+	return &passWarnings{
+		counts:        make(map[string]int),
+		awsErrors:     make(map[string]int),
+		awsErrorCalls: make(map[string]map[string]bool),
+	}
+}
+
+// add records a warning of the category.
+func (w *passWarnings) add(category string, format string, args ...interface{}) {
+	if w == nil {
+		return
+	}
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	w.counts[category]++
+	if len(w.messages) < maxWarningMessages {
+		w.messages = append(w.messages, fmt.Sprintf("%s: %s", category, fmt.Sprintf(format, args...)))
+	}
+}
+
+// addAWSError records a failed AWS call.
+func (w *passWarnings) addAWSError(class string, call string) {
+	if w == nil {
+		return
+	}
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	w.awsErrors[class]++
+	if w.awsErrorCalls[class] == nil {
+		w.awsErrorCalls[class] = make(map[string]bool)
+	}
+	w.awsErrorCalls[class][call] = true
+}
+
+// reset discards the warnings and AWS errors recorded so far, for the next
+// pass of an updater that runs passes every -check-interval.
+func (w *passWarnings) reset() {
+	if w == nil {
+		return
+	}
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	w.counts = make(map[string]int)
+	w.messages = nil
+	w.awsErrors = make(map[string]int)
+	w.awsErrorCalls = make(map[string]map[string]bool)
+}
+
+// awsErrorCount returns the number of AWS calls that failed with the class.
+func (w *passWarnings) awsErrorCount(class string) int {
+	if w == nil {
+		return 0
+	}
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	return w.awsErrors[class]
+}
+
+// count returns the number of warnings of the category.
+func (w *passWarnings) count(category string) int {
+	if w == nil {
+		return 0
+	}
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	return w.counts[category]
+}
+
+// total returns the number of warnings of all categories.
+func (w *passWarnings) total() int {
+	if w == nil {
+		return 0
+	}
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	total := 0
+	for _, count := range w.counts {
+		total += count
+	}
+	return total
+}
+
+// String counts the warnings per category, e.g. "retried: 2, throttled: 5".
+func (w *passWarnings) String() string {
+	if w == nil {
+		return ""
+	}
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	return w.countsString()
+}
+
+// countsString is String with the lock held.
+func (w *passWarnings) countsString() string {
+	categories := make([]string, 0, len(w.counts))
+	for category := range w.counts {
+		categories = append(categories, category)
+	}
+	sort.Strings(categories)
+	parts := make([]string, 0, len(categories))
+	for _, category := range categories {
+		parts = append(parts, fmt.Sprintf("%s: %d", category, w.counts[category]))
+	}
+	return strings.Join(parts, ", ")
+}
+
+// log writes the warnings, and the failed AWS calls by class, to the log.
+func (w *passWarnings) log() {
+	if w == nil {
+		return
+	}
+	total := w.total()
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	if total > 0 {
+		log.Printf("%d warning(s) (%s):", total, w.countsString())
+		for _, message := range w.messages {
+			log.Printf("Warning: %s", message)
+		}
+		if total > len(w.messages) {
+			log.Printf("... and %d more warning(s)", total-len(w.messages))
+		}
+	}
+	for _, class := range errorClasses {
+		if w.awsErrors[class] == 0 {
+			continue
+		}
+		calls := make([]string, 0, len(w.awsErrorCalls[class]))
+		for call := range w.awsErrorCalls[class] {
+			calls = append(calls, call)
+		}
+		sort.Strings(calls)
+		log.Printf("AWS errors: %s: %d (%s)", class, w.awsErrors[class], strings.Join(calls, ", "))
+	}
+}
+
+// install records a warning for every attempt of an AWS call that is
+// throttled, for the clients created with the handlers. The SDK retries
+// throttled calls on its own, so they would otherwise go unnoticed. It also
+// records every call that failed after its retries by the class of its error.
+func (w *passWarnings) install(handlers *request.Handlers) {
+	handlers.Retry.PushBack(func(r *request.Request) {
+		if request.IsErrorThrottle(r.Error) {
+			w.add(warnThrottled, "%s was throttled", r.Operation.Name)
+		}
+	})
+	handlers.Complete.PushBack(func(r *request.Request) {
+		if r.Error != nil {
+			w.addAWSError(classifyAWSError(r.Error), r.ClientInfo.ServiceName+":"+r.Operation.Name)
+		}
+	})
+}
@@ -0,0 +1,72 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/aws/aws-sdk-go/aws/awserr"
+	"github.com/aws/aws-sdk-go/aws/client/metadata"
+	"github.com/aws/aws-sdk-go/aws/request"
+	"github.com/stretchr/testify/assert"
+)
+
+func TestPassWarnings(t *testing.T) {
+	var none *passWarnings
+	none.add(warnRetried, "discarded")
+	assert.Equal(t, 0, none.total())
+
+	warnings := newPassWarnings()
+	for i := 0; i < maxWarningMessages+1; i++ {
+		warnings.add(warnThrottled, "call %d was throttled", i)
+	}
+	warnings.add(warnTruncated, "output of instance %q was truncated", "inst-id-1")
+	assert.Equal(t, maxWarningMessages+1, warnings.count(warnThrottled))
+	assert.Equal(t, 1, warnings.count(warnTruncated))
+	assert.Equal(t, 0, warnings.count(warnSkipped))
+	assert.Equal(t, maxWarningMessages+2, warnings.total())
+	assert.Len(t, warnings.messages, maxWarningMessages)
+	assert.Equal(t, "throttled: call 0 was throttled", warnings.messages[0])
+	assert.Equal(t, "throttled: 21, truncated: 1", warnings.String())
+
+	warnings.reset()
+	assert.Equal(t, 0, warnings.total())
+	assert.Empty(t, warnings.messages)
+	warnings.add(warnSkipped, "instance %q was skipped", "inst-id-1")
+	assert.Equal(t, 1, warnings.count(warnSkipped), "warnings should be recorded after a reset")
+}
+
+func TestPassWarningsThrottling(t *testing.T) {
+	warnings := newPassWarnings()
+	handlers := request.Handlers{}
+	warnings.install(&handlers)
+	handlers.Retry.Run(&request.Request{
+		Operation: &request.Operation{Name: "ListTasks"},
+		Error:     awserr.New("ThrottlingException", "rate exceeded", nil),
+	})
+	handlers.Retry.Run(&request.Request{
+		Operation: &request.Operation{Name: "ListTasks"},
+		Error:     awserr.New(request.ErrCodeRequestError, "connection reset", nil),
+	})
+	assert.Equal(t, 1, warnings.count(warnThrottled))
+	assert.Equal(t, 1, warnings.total())
+}
+
+func TestPassWarningsAWSErrors(t *testing.T) {
+	warnings := newPassWarnings()
+	handlers := request.Handlers{}
+	warnings.install(&handlers)
+	complete := func(operation string, err error) {
+		handlers.Complete.Run(&request.Request{
+			ClientInfo: metadata.ClientInfo{ServiceName: "ssm"},
+			Operation:  &request.Operation{Name: operation},
+			Error:      err,
+		})
+	}
+	complete("GetParameter", awserr.New("AccessDeniedException", "not authorized", nil))
+	complete("SendCommand", awserr.New("AccessDeniedException", "not authorized", nil))
+	complete("GetParameter", awserr.New("AccessDeniedException", "not authorized", nil))
+	complete("ListCommandInvocations", nil)
+	assert.Equal(t, 3, warnings.awsErrorCount(errClassAccessDenied))
+	assert.Equal(t, map[string]bool{"ssm:GetParameter": true, "ssm:SendCommand": true}, warnings.awsErrorCalls[errClassAccessDenied])
+	assert.Equal(t, 0, warnings.awsErrorCount(errClassOther), "successful calls should not be counted")
+	assert.Equal(t, 0, warnings.total(), "failed calls are not warnings themselves")
+}
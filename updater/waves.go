@@ -0,0 +1,62 @@
+package main
+
+import (
+	"fmt"
+	"strings"
+	"time"
+)
+
+// parseWaves parses waves given as comma-separated sizes in rollout order,
+// each a number of instances or a percentage of the pass's candidates,
+// optionally followed by a bake time, e.g. "1:30m,10%:1h,100%". The size is
+// kept as the rolloutStage value and the bake time as its soak time.
+func parseWaves(spec string) ([]rolloutStage, error) {
+	waves := make([]rolloutStage, 0)
+	for _, part := range strings.Split(spec, ",") {
+		part = strings.TrimSpace(part)
+		wave := rolloutStage{value: part}
+		if i := strings.LastIndex(part, ":"); i >= 0 {
+			bake, err := time.ParseDuration(part[i+1:])
+			if err != nil {
+				return nil, fmt.Errorf("invalid bake time for wave %q: %w", part[:i], err)
+			}
+			if bake < 0 {
+				return nil, fmt.Errorf("bake time for wave %q must not be negative", part[:i])
+			}
+			wave = rolloutStage{value: part[:i], soak: bake}
+		}
+		if !instanceCountPattern.MatchString(wave.value) {
+			return nil, fmt.Errorf("wave %q must be a number of instances or a percentage such as 10%%", wave.value)
+		}
+		waves = append(waves, wave)
+	}
+	return waves, nil
+}
+
+// waveCandidates splits the candidates into the waves, keeping their order.
+// Each wave takes its number, or percentage of all the candidates, of the
+// instances the earlier waves left; the candidates left after the last wave
+// are updated in a final wave of their own.
+func waveCandidates(waves []rolloutStage, candidates []instance) []stagedInstances {
+	stages := make([]stagedInstances, 0, len(waves)+1)
+	rest := candidates
+	for n, wave := range waves {
+		if len(rest) == 0 {
+			break
+		}
+		size := instanceCount(wave.value, len(candidates))
+		if size > len(rest) {
+			size = len(rest)
+		}
+		stages = append(stages, stagedInstances{
+			rolloutStage: wave,
+			name:         fmt.Sprintf("wave %d (%s)", n+1, wave.value),
+			instances:    rest[:size],
+		})
+		rest = rest[size:]
+	}
+	if len(rest) > 0 {
+		stages = append(stages, stagedInstances{name: "instances after the waves", instances: rest})
+	}
+	return stages
+}
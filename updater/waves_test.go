@@ -0,0 +1,99 @@
+package main
+
+import (
+	"testing"
+	"time"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestParseWaves(t *testing.T) {
+	cases := []struct {
+		name        string
+		spec        string
+		expected    []rolloutStage
+		expectedErr string
+	}{
+		{
+			name:     "sizes",
+			spec:     "1,10%,100%",
+			expected: []rolloutStage{{value: "1"}, {value: "10%"}, {value: "100%"}},
+		},
+		{
+			name:     "bake times",
+			spec:     "1:30m, 10%:1h,100%",
+			expected: []rolloutStage{{value: "1", soak: 30 * time.Minute}, {value: "10%", soak: time.Hour}, {value: "100%"}},
+		},
+		{
+			name:        "invalid bake time",
+			spec:        "1:soon,100%",
+			expectedErr: `invalid bake time for wave "1"`,
+		},
+		{
+			name:        "negative bake time",
+			spec:        "1:-1h,100%",
+			expectedErr: `bake time for wave "1" must not be negative`,
+		},
+		{
+			name:        "invalid size",
+			spec:        "1,half",
+			expectedErr: `wave "half" must be a number of instances or a percentage`,
+		},
+		{
+			name:        "empty wave",
+			spec:        "1,,100%",
+			expectedErr: `wave "" must be a number of instances or a percentage`,
+		},
+	}
+	for _, tc := range cases {
+		t.Run(tc.name, func(t *testing.T) {
+			waves, err := parseWaves(tc.spec)
+			if tc.expectedErr != "" {
+				require.Error(t, err)
+				assert.Contains(t, err.Error(), tc.expectedErr)
+				return
+			}
+			require.NoError(t, err)
+			assert.Equal(t, tc.expected, waves)
+		})
+	}
+}
+
+func TestWaveCandidates(t *testing.T) {
+	candidates := make([]instance, 0, 10)
+	for i := 0; i < 10; i++ {
+		candidates = append(candidates, instance{instanceID: string(rune('a' + i))})
+	}
+
+	t.Run("waves", func(t *testing.T) {
+		u := updater{waves: []rolloutStage{{value: "1", soak: 30 * time.Minute}, {value: "20%", soak: time.Hour}, {value: "100%"}}}
+		stages := u.stageCandidates(candidates)
+		require.Len(t, stages, 3)
+		assert.Equal(t, "wave 1 (1)", stages[0].name)
+		assert.Equal(t, 30*time.Minute, stages[0].soak)
+		assert.Equal(t, candidates[:1], stages[0].instances)
+		assert.Equal(t, "wave 2 (20%)", stages[1].name)
+		assert.Equal(t, time.Hour, stages[1].soak)
+		assert.Equal(t, candidates[1:3], stages[1].instances)
+		assert.Equal(t, candidates[3:], stages[2].instances, "the last wave should take the instances left")
+		assert.Equal(t, candidates, stagedOrder(stages))
+	})
+	t.Run("instances after the waves", func(t *testing.T) {
+		stages := waveCandidates([]rolloutStage{{value: "2"}, {value: "10%"}}, candidates)
+		require.Len(t, stages, 3)
+		assert.Len(t, stages[0].instances, 2)
+		assert.Len(t, stages[1].instances, 1)
+		assert.Equal(t, "instances after the waves", stages[2].name)
+		assert.Equal(t, candidates[3:], stages[2].instances)
+	})
+	t.Run("fewer instances than waves", func(t *testing.T) {
+		stages := waveCandidates([]rolloutStage{{value: "1"}, {value: "5"}, {value: "100%"}}, candidates[:3])
+		require.Len(t, stages, 2, "waves without instances should be left out")
+		assert.Equal(t, candidates[:1], stages[0].instances)
+		assert.Equal(t, candidates[1:3], stages[1].instances)
+	})
+	t.Run("no candidates", func(t *testing.T) {
+		assert.Empty(t, waveCandidates([]rolloutStage{{value: "1"}}, nil))
+	})
+}
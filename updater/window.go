@@ -0,0 +1,87 @@
+package main
+
+import (
+	"fmt"
+	"log"
+	"regexp"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+)
+
+const (
+	// tagWindowPassPrefix prefixes the tag that records the last pass over a
+	// cluster on its maintenance window; the cluster name completes the key,
+	// so that clusters sharing a window keep their own records.
+	tagWindowPassPrefix = "bottlerocket-ecs-updater:"
+	// maxWindowDuration is the longest a maintenance window can be open, which
+	// bounds how far back executions are looked up.
+	maxWindowDuration = 24 * time.Hour
+)
+
+// maintenanceWindowPattern matches the IDs of SSM maintenance windows.
+var maintenanceWindowPattern = regexp.MustCompile(`^mw-[0-9a-f]{17}$`)
+
+// openWindowExecution returns the ID of the execution of the maintenance
+// window that is in progress, or "" when the window is not open. A disabled
+// window is never open.
+func (u *updater) openWindowExecution(now time.Time) (string, error) {
+	window, err := u.ssm.GetMaintenanceWindow(&ssm.GetMaintenanceWindowInput{
+		WindowId: aws.String(u.maintenanceWindow),
+	})
+	if err != nil {
+		return "", fmt.Errorf("failed to get maintenance window %q: %w", u.maintenanceWindow, err)
+	}
+	if !aws.BoolValue(window.Enabled) {
+		log.Printf("Maintenance window %q (%s) is disabled", u.maintenanceWindow, aws.StringValue(window.Name))
+		return "", nil
+	}
+	resp, err := u.ssm.DescribeMaintenanceWindowExecutions(&ssm.DescribeMaintenanceWindowExecutionsInput{
+		WindowId: aws.String(u.maintenanceWindow),
+		Filters: []*ssm.MaintenanceWindowFilter{{
+			Key:    aws.String("ExecutedAfter"),
+			Values: aws.StringSlice([]string{now.Add(-maxWindowDuration).UTC().Format(time.RFC3339)}),
+		}},
+	})
+	if err != nil {
+		return "", fmt.Errorf("failed to describe executions of maintenance window %q: %w", u.maintenanceWindow, err)
+	}
+	for _, execution := range resp.WindowExecutions {
+		if aws.StringValue(execution.Status) == ssm.MaintenanceWindowExecutionStatusInProgress {
+			return aws.StringValue(execution.WindowExecutionId), nil
+		}
+	}
+	return "", nil
+}
+
+// windowPassTag returns the tag that records the pass on the maintenance
+// window, e.g. "pass=<pass ID> execution=<execution ID> updated=2 failed=0 skipped=1".
+func (u *updater) windowPassTag(execution string, summary *passSummary) *ssm.Tag {
+	value := fmt.Sprintf("pass=%s execution=%s updated=%d failed=%d skipped=%d", u.passID, execution, summary.updated, summary.failed, summary.skipped)
+	if summary.changeRef != "" {
+		value += " change=" + summary.changeRef
+	}
+	return &ssm.Tag{
+		Key:   aws.String(tagWindowPassPrefix + u.clusterName()),
+		Value: aws.String(value),
+	}
+}
+
+// recordWindowPass records the pass and its outcome in a tag on the
+// maintenance window, so that the updater's activity shows up with the other
+// maintenance of the window.
+func (u *updater) recordWindowPass(execution string, summary *passSummary) {
+	tag := u.windowPassTag(execution, summary)
+	_, err := u.ssm.AddTagsToResource(&ssm.AddTagsToResourceInput{
+		ResourceType: aws.String(ssm.ResourceTypeForTaggingMaintenanceWindow),
+		ResourceId:   aws.String(u.maintenanceWindow),
+		Tags:         []*ssm.Tag{tag},
+	})
+	if err != nil {
+		log.Printf("Failed to record the pass on maintenance window %q: %v", u.maintenanceWindow, err)
+		u.warnings.add(warnDegraded, "pass was not recorded on maintenance window %q: %v", u.maintenanceWindow, err)
+		return
+	}
+	log.Printf("Recorded the pass on maintenance window %q: %s", u.maintenanceWindow, aws.StringValue(tag.Value))
+}
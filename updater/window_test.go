@@ -0,0 +1,91 @@
+package main
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/aws/aws-sdk-go/aws"
+	"github.com/aws/aws-sdk-go/service/ssm"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestOpenWindowExecution(t *testing.T) {
+	now := time.Date(2024, 3, 1, 12, 0, 0, 0, time.UTC)
+	windowMock := func(enabled bool, statuses ...string) MockSSM {
+		return MockSSM{
+			GetMaintenanceWindowFn: func(input *ssm.GetMaintenanceWindowInput) (*ssm.GetMaintenanceWindowOutput, error) {
+				assert.Equal(t, "mw-0123456789abcdef0", aws.StringValue(input.WindowId))
+				return &ssm.GetMaintenanceWindowOutput{Enabled: aws.Bool(enabled)}, nil
+			},
+			DescribeMaintenanceWindowExecutionsFn: func(input *ssm.DescribeMaintenanceWindowExecutionsInput) (*ssm.DescribeMaintenanceWindowExecutionsOutput, error) {
+				require.Len(t, input.Filters, 1)
+				assert.Equal(t, "ExecutedAfter", aws.StringValue(input.Filters[0].Key))
+				assert.Equal(t, []string{"2024-02-29T12:00:00Z"}, aws.StringValueSlice(input.Filters[0].Values))
+				executions := make([]*ssm.MaintenanceWindowExecution, 0)
+				for n, status := range statuses {
+					executions = append(executions, &ssm.MaintenanceWindowExecution{
+						WindowExecutionId: aws.String([]string{"execution-1", "execution-2"}[n]),
+						Status:            aws.String(status),
+					})
+				}
+				return &ssm.DescribeMaintenanceWindowExecutionsOutput{WindowExecutions: executions}, nil
+			},
+		}
+	}
+
+	u := updater{ssm: windowMock(true, ssm.MaintenanceWindowExecutionStatusSuccess, ssm.MaintenanceWindowExecutionStatusInProgress), maintenanceWindow: "mw-0123456789abcdef0"}
+	execution, err := u.openWindowExecution(now)
+	require.NoError(t, err)
+	assert.Equal(t, "execution-2", execution)
+
+	u = updater{ssm: windowMock(true, ssm.MaintenanceWindowExecutionStatusSuccess), maintenanceWindow: "mw-0123456789abcdef0"}
+	execution, err = u.openWindowExecution(now)
+	require.NoError(t, err)
+	assert.Empty(t, execution, "should not be open without an execution in progress")
+
+	u = updater{ssm: windowMock(false, ssm.MaintenanceWindowExecutionStatusInProgress), maintenanceWindow: "mw-0123456789abcdef0"}
+	execution, err = u.openWindowExecution(now)
+	require.NoError(t, err)
+	assert.Empty(t, execution, "should not be open while disabled")
+
+	u = updater{ssm: MockSSM{
+		GetMaintenanceWindowFn: func(_ *ssm.GetMaintenanceWindowInput) (*ssm.GetMaintenanceWindowOutput, error) {
+			return nil, errors.New("access denied")
+		},
+	}, maintenanceWindow: "mw-0123456789abcdef0"}
+	_, err = u.openWindowExecution(now)
+	assert.Error(t, err)
+}
+
+func TestRecordWindowPass(t *testing.T) {
+	summary := newPassSummary()
+	summary.changeRef = "CHG-1234"
+	summary.updatedInstance("inst-1", "Instance updated successfully")
+	summary.skippedInstance("inst-2", skipUpdateLimit, "Limit of 1 update(s) per pass reached")
+
+	var tags []*ssm.Tag
+	mockSSM := MockSSM{
+		AddTagsToResourceFn: func(input *ssm.AddTagsToResourceInput) (*ssm.AddTagsToResourceOutput, error) {
+			assert.Equal(t, ssm.ResourceTypeForTaggingMaintenanceWindow, aws.StringValue(input.ResourceType))
+			assert.Equal(t, "mw-0123456789abcdef0", aws.StringValue(input.ResourceId))
+			tags = input.Tags
+			return &ssm.AddTagsToResourceOutput{}, nil
+		},
+	}
+	u := updater{ssm: mockSSM, cluster: "arn:aws:ecs:us-west-2:123456789012:cluster/prod", passID: "pass-1", maintenanceWindow: "mw-0123456789abcdef0", warnings: newPassWarnings()}
+	u.recordWindowPass("execution-1", summary)
+	assert.Equal(t, []*ssm.Tag{{
+		Key:   aws.String("bottlerocket-ecs-updater:prod"),
+		Value: aws.String("pass=pass-1 execution=execution-1 updated=1 failed=0 skipped=1 change=CHG-1234"),
+	}}, tags)
+	assert.Equal(t, 0, u.warnings.counts[warnDegraded])
+
+	mockSSM.AddTagsToResourceFn = func(_ *ssm.AddTagsToResourceInput) (*ssm.AddTagsToResourceOutput, error) {
+		return nil, errors.New("access denied")
+	}
+	u.ssm = mockSSM
+	u.recordWindowPass("execution-1", summary)
+	assert.Equal(t, 1, u.warnings.counts[warnDegraded])
+}